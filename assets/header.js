@@ -56,6 +56,8 @@
           code: extractDefaultValue(modelRoot, "default-code"),
           ainput: extractDefaultValue(modelRoot, "default-ainput"),
           rinput: extractDefaultValue(modelRoot, "default-rinput"),
+          sourceCompiler: modelRoot.dataset.sourceCompiler || "",
+          source: extractDefaultValue(modelRoot, "default-source"),
           bundleBaseUrl,
           assetBaseUrl,
         });
@@ -8,8 +8,12 @@
 //   # a table with optional header and rows. each cell can be a block.
 // - container: { kind: "container", children: [block], orientation: "vertical" | "horizontal", display: "inline" | "block"}
 //   # a flat displayed container for grouping blocks. orientation defaults to vertical, display defaults to block.
+// - graph: { kind: "graph", nodes: [{ id, inner?: block, ports?: [string], group?, className? }],
+//            edges: [{ from, to, fromPort?, toPort?, label?, className? }],
+//            groups: [{ id, parent?, title?, className? }], orientation: "vertical" | "horizontal" }
+//   # nodes are placed in layers along the edges (top to bottom by default) and edges are drawn over them.
+//   # a group is a box around its nodes (and its child groups).
 // Unsupported (for now):
-// - graph: { kind: "graph", nodes: [{ id: number, inner: block }], edges: [{ from: number, to: number, inner: block }] }
 // - grid: { kind: "grid", cells: [[{ text }]] }
 // Blocks may include optional layout hints:
 // - className: string (extra CSS class)
@@ -55,6 +59,9 @@ export class Renderer {
       case "container":
         node = this.renderContainer(block);
         break;
+      case "graph":
+        node = this.renderGraph(block);
+        break;
       default:
         node = this.makeText(`(unknown block kind: ${kind ?? "?"})`);
         break;
@@ -147,6 +154,219 @@ export class Renderer {
     return wrapper;
   }
 
+  renderGraph(block) {
+    const wrapper = this.makeSection(block.title);
+    const graph = document.createElement("div");
+    graph.className = "wm-graph";
+    const grid = document.createElement("div");
+    grid.className = "wm-graph-grid";
+    graph.appendChild(grid);
+
+    const nodes = Array.isArray(block.nodes) ? block.nodes : [];
+    const edges = Array.isArray(block.edges) ? block.edges : [];
+    const groups = Array.isArray(block.groups) ? block.groups : [];
+    const horizontal = block.orientation === "horizontal";
+
+    // layer = BFS distance from a node without incoming edges
+    const ids = nodes.map(node => String(node?.id ?? ""));
+    const outgoing = new Map(ids.map(id => [id, []]));
+    const indegree = new Map(ids.map(id => [id, 0]));
+    edges.forEach(edge => {
+      const from = String(edge?.from ?? "");
+      const to = String(edge?.to ?? "");
+      if (outgoing.has(from) && indegree.has(to) && from !== to) {
+        outgoing.get(from).push(to);
+        indegree.set(to, indegree.get(to) + 1);
+      }
+    });
+    const layer = new Map();
+    const starts = ids.filter(id => indegree.get(id) === 0).concat(ids);
+    starts.forEach(start => {
+      if (layer.has(start)) return;
+      layer.set(start, 0);
+      const queue = [start];
+      while (queue.length) {
+        const id = queue.shift();
+        outgoing.get(id).forEach(next => {
+          if (!layer.has(next)) {
+            layer.set(next, layer.get(id) + 1);
+            queue.push(next);
+          }
+        });
+      }
+    });
+
+    const elements = new Map();
+    const counts = new Map();
+    nodes.forEach((node, i) => {
+      const id = ids[i];
+      const depth = layer.get(id) ?? 0;
+      const index = counts.get(depth) ?? 0;
+      counts.set(depth, index + 1);
+
+      const div = document.createElement("div");
+      div.className = "wm-graph-node";
+      if (typeof node?.className === "string" && node.className) {
+        node.className.split(/\s+/).forEach(cls => div.classList.add(cls));
+      }
+      div.style.gridRow = String((horizontal ? index : depth) + 1);
+      div.style.gridColumn = String((horizontal ? depth : index) + 1);
+      const content = node?.inner ? this.renderBlock(node.inner) : this.makeText(id);
+      if (content) {
+        div.appendChild(content);
+      }
+      const ports = new Map();
+      if (Array.isArray(node?.ports) && node.ports.length) {
+        const list = document.createElement("div");
+        list.className = "wm-graph-ports";
+        node.ports.forEach(port => {
+          const span = document.createElement("span");
+          span.className = "wm-graph-port";
+          span.textContent = String(port);
+          list.appendChild(span);
+          ports.set(String(port), span);
+        });
+        div.appendChild(list);
+      }
+      grid.appendChild(div);
+      elements.set(id, { div, ports, group: node?.group == null ? null : String(node.group) });
+    });
+
+    // edges and groups need the positions of the nodes, so they are drawn after layout
+    const svgNs = "http://www.w3.org/2000/svg";
+    const svg = document.createElementNS(svgNs, "svg");
+    svg.classList.add("wm-graph-overlay");
+    graph.appendChild(svg);
+    requestAnimationFrame(() => {
+      const base = graph.getBoundingClientRect();
+      svg.setAttribute("width", String(graph.scrollWidth));
+      svg.setAttribute("height", String(graph.scrollHeight));
+      const box = el => {
+        const r = el.getBoundingClientRect();
+        return { x: r.left - base.left, y: r.top - base.top, w: r.width, h: r.height };
+      };
+
+      const marker = document.createElementNS(svgNs, "marker");
+      const markerId = `wm-graph-arrow-${Math.random().toString(36).slice(2)}`;
+      marker.setAttribute("id", markerId);
+      marker.setAttribute("viewBox", "0 0 10 10");
+      marker.setAttribute("refX", "10");
+      marker.setAttribute("refY", "5");
+      marker.setAttribute("markerWidth", "8");
+      marker.setAttribute("markerHeight", "8");
+      marker.setAttribute("orient", "auto-start-reverse");
+      const tip = document.createElementNS(svgNs, "path");
+      tip.setAttribute("d", "M 0 0 L 10 5 L 0 10 z");
+      marker.appendChild(tip);
+      const defs = document.createElementNS(svgNs, "defs");
+      defs.appendChild(marker);
+      svg.appendChild(defs);
+
+      // a group covers its nodes and child groups, with more padding for outer groups
+      const parentOf = new Map(groups.map(g => [String(g?.id ?? ""), g?.parent == null ? null : String(g.parent)]));
+      const depthOf = id => {
+        let depth = 0;
+        for (let p = parentOf.get(id); p != null && depth < groups.length; p = parentOf.get(p)) depth++;
+        return depth;
+      };
+      const maxDepth = Math.max(0, ...groups.map(g => depthOf(String(g?.id ?? ""))));
+      groups.forEach(g => {
+        const id = String(g?.id ?? "");
+        const inside = groupId => {
+          for (let p = groupId, n = 0; p != null && n <= groups.length; p = parentOf.get(p), n++) {
+            if (p === id) return true;
+          }
+          return false;
+        };
+        const members = [...elements.values()].filter(e => e.group != null && inside(e.group)).map(e => box(e.div));
+        if (!members.length) return;
+        const pad = 6 + 8 * (maxDepth - depthOf(id));
+        const x = Math.min(...members.map(b => b.x)) - pad;
+        const y = Math.min(...members.map(b => b.y)) - pad;
+        const w = Math.max(...members.map(b => b.x + b.w)) - x + pad;
+        const h = Math.max(...members.map(b => b.y + b.h)) - y + pad;
+        const rect = document.createElementNS(svgNs, "rect");
+        rect.classList.add("wm-graph-group");
+        if (typeof g?.className === "string" && g.className) {
+          g.className.split(/\s+/).forEach(cls => rect.classList.add(cls));
+        }
+        rect.setAttribute("x", String(x));
+        rect.setAttribute("y", String(y));
+        rect.setAttribute("width", String(w));
+        rect.setAttribute("height", String(h));
+        svg.appendChild(rect);
+        if (g?.title) {
+          const text = document.createElementNS(svgNs, "text");
+          text.classList.add("wm-graph-group-title");
+          text.setAttribute("x", String(x + 4));
+          text.setAttribute("y", String(y + 12));
+          text.textContent = String(g.title);
+          svg.appendChild(text);
+        }
+      });
+
+      // endpoint on the border of the node (or the port) facing the other end
+      const anchor = (b, toward) => {
+        const cx = b.x + b.w / 2;
+        const cy = b.y + b.h / 2;
+        const dx = toward.x - cx;
+        const dy = toward.y - cy;
+        if (dx === 0 && dy === 0) return { x: cx, y: cy };
+        const scale = Math.min(
+          dx === 0 ? Infinity : b.w / 2 / Math.abs(dx),
+          dy === 0 ? Infinity : b.h / 2 / Math.abs(dy),
+        );
+        return { x: cx + dx * scale, y: cy + dy * scale };
+      };
+      const center = b => ({ x: b.x + b.w / 2, y: b.y + b.h / 2 });
+      edges.forEach(edge => {
+        const from = elements.get(String(edge?.from ?? ""));
+        const to = elements.get(String(edge?.to ?? ""));
+        if (!from || !to) return;
+        const fromBox = box(from.ports.get(String(edge.fromPort)) ?? from.div);
+        const toBox = box(to.ports.get(String(edge.toPort)) ?? to.div);
+        const path = document.createElementNS(svgNs, "path");
+        path.classList.add("wm-graph-edge");
+        if (typeof edge.className === "string" && edge.className) {
+          edge.className.split(/\s+/).forEach(cls => path.classList.add(cls));
+        }
+        let mid;
+        if (from === to) {
+          // self loop above the node
+          const b = box(from.div);
+          const x = b.x + b.w / 2;
+          path.setAttribute("d", `M ${x - 10} ${b.y} C ${x - 30} ${b.y - 40}, ${x + 30} ${b.y - 40}, ${x + 10} ${b.y}`);
+          mid = { x, y: b.y - 32 };
+        } else {
+          const start = anchor(fromBox, center(toBox));
+          const end = anchor(toBox, center(fromBox));
+          // bend a little so that edges in both directions do not overlap
+          const mx = (start.x + end.x) / 2 + (end.y - start.y) * 0.1;
+          const my = (start.y + end.y) / 2 - (end.x - start.x) * 0.1;
+          path.setAttribute("d", `M ${start.x} ${start.y} Q ${mx} ${my} ${end.x} ${end.y}`);
+          mid = { x: (start.x + 2 * mx + end.x) / 4, y: (start.y + 2 * my + end.y) / 4 };
+        }
+        path.setAttribute("marker-end", `url(#${markerId})`);
+        svg.appendChild(path);
+        if (edge.label != null && edge.label !== "") {
+          const text = document.createElementNS(svgNs, "text");
+          text.classList.add("wm-graph-edge-label");
+          if (typeof edge.className === "string" && edge.className) {
+            edge.className.split(/\s+/).forEach(cls => text.classList.add(cls));
+          }
+          text.setAttribute("x", String(mid.x));
+          text.setAttribute("y", String(mid.y));
+          text.setAttribute("text-anchor", "middle");
+          text.textContent = String(edge.label);
+          svg.appendChild(text);
+        }
+      });
+    });
+
+    wrapper.appendChild(graph);
+    return wrapper;
+  }
+
   makeSection(title) {
     const section = document.createElement("section");
     section.className = "wm-section";
@@ -1,4 +1,6 @@
 const OUTPUT_PLACEHOLDER = "\u00a0";
+// steps of one `stepN` call of the Run button
+const RUN_STEPS = 100000;
 let nextInstanceId = 0;
 
 export function ensureStyleSheet(options = {}) {
@@ -28,6 +30,10 @@ export async function mountModel(element, options = {}) {
     renderCompilerMount(element, spec.model, module, spec);
     return;
   }
+  if (kind === "interpreter") {
+    renderCompilerMount(element, spec.model, module, spec, "interpreter");
+    return;
+  }
   throw new Error(`unknown component kind for ${spec.model}`);
 }
 
@@ -44,6 +50,9 @@ function normalizeMountSpec(options) {
     code: options.code || "",
     ainput: options.ainput || "",
     rinput: options.rinput || "",
+    // a compiler into the model whose source map highlights the source line of the current state
+    sourceCompiler: (options.sourceCompiler || "").trim(),
+    source: options.source || "",
     instanceId: options.instanceId || `wm-${nextInstanceId++}`,
   };
 }
@@ -74,6 +83,43 @@ function setOutputText(el, value) {
   el.textContent = text === "" ? OUTPUT_PLACEHOLDER : text;
 }
 
+// errors thrown by component calls carry a `diagnostic` record as payload
+// if its span points into one of `fields` (by argument name), the range is selected there
+function describeError(error, fields = {}) {
+  const diagnostic = error?.payload;
+  if (!diagnostic || typeof diagnostic.message !== "string") {
+    return `${error}`;
+  }
+  const span = diagnostic.span;
+  if (!span) {
+    return diagnostic.message;
+  }
+  const field = fields[diagnostic.origin];
+  if (field) {
+    field.focus();
+    field.setSelectionRange(
+      byteToIndex(field.value, span.start),
+      byteToIndex(field.value, Math.max(span.end, span.start + 1)),
+    );
+  }
+  return `${diagnostic.origin}:${span.line}:${span.column}: ${diagnostic.message}`;
+}
+
+// spans are byte offsets of UTF-8 but textarea uses UTF-16 indices
+function byteToIndex(text, byte) {
+  const encoder = new TextEncoder();
+  let bytes = 0;
+  let index = 0;
+  for (const ch of text) {
+    if (bytes >= byte) {
+      break;
+    }
+    bytes += encoder.encode(ch).length;
+    index += ch.length;
+  }
+  return index;
+}
+
 function parseJsonString(value, name) {
   if (typeof value !== "string") {
     throw new Error(`${name} returned non-string value`);
@@ -136,18 +182,31 @@ function detectComponentKind(module) {
     "decodeRoutput",
     "decodeFoutput",
   ];
-  const hasModel = modelFns.every(name => typeof module[name] === "function");
-  const hasCompiler = compilerFns.every(name => typeof module[name] === "function");
-  if (hasModel && !hasCompiler) {
-    return "model";
+  const interpreterFns = [
+    "interpreterCode",
+    "encodeAinput",
+    "encodeRinput",
+    "decodeRoutput",
+    "decodeFoutput",
+  ];
+  const hasAll = fns => fns.every(name => typeof module[name] === "function");
+  const kinds = [];
+  if (hasAll(modelFns)) {
+    kinds.push("model");
+  }
+  if (hasAll(compilerFns)) {
+    kinds.push("compiler");
   }
-  if (hasCompiler && !hasModel) {
-    return "compiler";
+  if (hasAll(interpreterFns)) {
+    kinds.push("interpreter");
   }
-  if (hasModel && hasCompiler) {
-    throw new Error("module exports both model and compiler APIs");
+  if (kinds.length === 1) {
+    return kinds[0];
   }
-  throw new Error("module does not match model/compiler APIs");
+  if (kinds.length > 1) {
+    throw new Error(`module exports multiple APIs: ${kinds.join(", ")}`);
+  }
+  throw new Error("module does not match model/compiler/interpreter APIs");
 }
 
 async function drawCurrentSnapshot(module, renderer) {
@@ -192,6 +251,29 @@ async function renderModelMount(root, name, module, defaults, options) {
   stepBtn.className = "wm-model-step-button";
   stepBtn.textContent = "Step";
   transitionGrid.appendChild(stepBtn);
+  const runBtn = document.createElement("button");
+  runBtn.className = "wm-model-run-button";
+  runBtn.textContent = `Run ${RUN_STEPS} steps`;
+  // components built before the batch exports can only step one at a time
+  if (typeof module.stepN === "function") {
+    transitionGrid.appendChild(runBtn);
+  }
+  // components built before the history exports have no back/forward
+  const hasHistory = ["rewind", "historyLen", "historyPosition"].every(
+    fn => typeof module[fn] === "function",
+  );
+  const historyBar = document.createElement("div");
+  historyBar.className = "wm-model-history";
+  const backBtn = document.createElement("button");
+  backBtn.textContent = "Back";
+  const forwardBtn = document.createElement("button");
+  forwardBtn.textContent = "Forward";
+  const historyLabel = document.createElement("span");
+  historyLabel.className = "wm-model-history-label";
+  historyBar.append(backBtn, forwardBtn, historyLabel);
+  if (hasHistory) {
+    transitionGrid.appendChild(historyBar);
+  }
 
   const snapshotPanel = createPanel(root, "wm-panel-state", "Snapshot");
   const snapshotContainer = document.createElement("div");
@@ -202,22 +284,66 @@ async function renderModelMount(root, name, module, defaults, options) {
   const renderer = new Renderer(snapshotContainer);
   let machineReady = false;
 
+  // with a source compiler, the code can be compiled from a source whose statements are followed
+  const source = await createSourceView(root, name, module, defaults, code, options);
+  const redraw = async () => {
+    await drawCurrentSnapshot(module, renderer);
+    await source.showLine();
+  };
+
   const setStatus = (message, isError = false) => {
     status.style.color = isError ? "red" : "";
     setOutputText(status, message || "");
   };
 
+  const updateHistory = async () => {
+    if (!hasHistory) return;
+    const position = await module.historyPosition();
+    const length = await module.historyLen();
+    historyLabel.textContent = `step ${position} / ${length}`;
+    backBtn.disabled = position === 0;
+    forwardBtn.disabled = position >= length;
+  };
+
+  // position after the halting step, which has no snapshot
+  let haltedAt = null;
+  const moveInHistory = async delta => {
+    try {
+      const position = (await module.historyPosition()) + delta;
+      await module.rewind(position);
+      setOutputText(routput, "");
+      if (position === haltedAt) {
+        machineReady = false;
+        source.clearLine();
+        setStatus("halted");
+      } else {
+        machineReady = true;
+        await redraw();
+        setOutputText(foutput, "");
+        setStatus("");
+      }
+      await updateHistory();
+    } catch (error) {
+      setStatus(`rewind failed: ${describeError(error, {})}`, true);
+    }
+  };
+  backBtn.addEventListener("click", () => moveInHistory(-1));
+  forwardBtn.addEventListener("click", () => moveInHistory(1));
+
   createBtn.addEventListener("click", async () => {
     try {
       await module.make(code.value, ainput.value);
-      await drawCurrentSnapshot(module, renderer);
+      source.codeCreated(code.value);
+      await redraw();
+      await updateHistory();
+      haltedAt = null;
       machineReady = true;
       setOutputText(routput, "");
       setOutputText(foutput, "");
       setStatus("");
     } catch (error) {
       machineReady = false;
-      setStatus(`create failed: ${error}`, true);
+      setStatus(`create failed: ${describeError(error, {code, ainput})}`, true);
     }
   });
 
@@ -228,8 +354,10 @@ async function renderModelMount(root, name, module, defaults, options) {
     }
     try {
       const result = parseJsonString(await module.step(rinput.value), "step");
+      await updateHistory();
       if (result.kind === "continue") {
-        await drawCurrentSnapshot(module, renderer);
+        haltedAt = null;
+        await redraw();
         setOutputText(routput, result.routput ?? "");
         setOutputText(foutput, "");
         setStatus("");
@@ -237,24 +365,135 @@ async function renderModelMount(root, name, module, defaults, options) {
       }
       if (result.kind === "halt") {
         machineReady = false;
+        haltedAt = hasHistory ? await module.historyPosition() : null;
         setOutputText(foutput, result.foutput ?? "");
+        source.clearLine();
         setStatus("halted");
         return;
       }
       throw new Error(`unknown step result kind: ${result.kind}`);
     } catch (error) {
-      setStatus(`step failed: ${error}`, true);
+      setStatus(`step failed: ${describeError(error, {rinput})}`, true);
+    }
+  });
+
+  // the steps of `stepN` are recorded as one history entry, Back inside it replays them
+  runBtn.addEventListener("click", async () => {
+    if (!machineReady) {
+      setStatus("machine not initialized; run Create first", true);
+      return;
+    }
+    try {
+      const batch = await module.stepN(RUN_STEPS, rinput.value);
+      await updateHistory();
+      setOutputText(routput, batch.lastRoutput ?? "");
+      if (batch.end.tag === "halted") {
+        machineReady = false;
+        haltedAt = hasHistory ? await module.historyPosition() : null;
+        setOutputText(foutput, batch.end.val);
+        source.clearLine();
+        setStatus(`halted after ${batch.steps} steps`);
+        return;
+      }
+      haltedAt = null;
+      await redraw();
+      setOutputText(foutput, "");
+      if (batch.end.tag === "failed") {
+        const message = describeError({payload: batch.end.val}, {rinput});
+        setStatus(`step failed after ${batch.steps} steps: ${message}`, true);
+        return;
+      }
+      setStatus(`${batch.steps} steps`);
+    } catch (error) {
+      setStatus(`run failed: ${describeError(error, {rinput})}`, true);
+    }
+  });
+}
+
+// the Source panel of a model mount with `sourceCompiler`: Compile fills the Code field,
+// and while the machine steps the statement of its `location` is marked in the source
+async function createSourceView(root, name, module, defaults, code, options) {
+  const none = {showLine: async () => {}, clearLine: () => {}, codeCreated: () => {}};
+  if (!defaults.sourceCompiler || typeof module.location !== "function") {
+    return none;
+  }
+  const compiler = await loadComponentModule(
+    defaults.sourceCompiler,
+    `${defaults.instanceId}-source`,
+    options.bundleBaseUrl,
+  );
+  if (typeof compiler.sourceMap !== "function") {
+    return none;
+  }
+  // the panel goes before the Boundary panel, whose Code field it fills
+  const boundary = root.querySelector(".wm-panel-boundary");
+  const panel = createPanel(root, "wm-panel-source", `Source: ${defaults.sourceCompiler}`);
+  root.insertBefore(panel.panel, boundary);
+  const field = createField(panel.body, "", "wm-field-code", defaults.source, 8);
+  const compileBtn = document.createElement("button");
+  compileBtn.textContent = `Compile into ${name}`;
+  panel.body.appendChild(compileBtn);
+  const view = document.createElement("pre");
+  view.className = "wm-source-view";
+  panel.body.appendChild(view);
+
+  // mappings of the compiled code, dropped when a different code is created
+  let compiled = null;
+  let mappings = [];
+  const drawLines = current => {
+    const lines = (mappings.length > 0 ? compiled.source : field.value).split("\n");
+    view.replaceChildren(...lines.map((line, index) => {
+      const row = document.createElement("div");
+      row.textContent = line || OUTPUT_PLACEHOLDER;
+      if (index + 1 === current) {
+        row.className = "wm-source-current";
+      }
+      return row;
+    }));
+  };
+  compileBtn.addEventListener("click", async () => {
+    try {
+      const text = field.value;
+      code.value = await compiler.compileCode(text);
+      compiled = {source: text, code: code.value, mappings: await compiler.sourceMap(text)};
+      drawLines(null);
+    } catch (error) {
+      setOutputText(view, `compile failed: ${describeError(error, {code: field})}`);
     }
   });
+  drawLines(null);
+  return {
+    codeCreated: text => {
+      mappings = compiled && compiled.code === text ? compiled.mappings : [];
+    },
+    showLine: async () => {
+      const location = mappings.length > 0 ? await module.location() : undefined;
+      const mapping = mappings.find(({target}) => target === location);
+      drawLines(mapping ? mapping.span.line : null);
+      if (mapping) {
+        view.querySelector(".wm-source-current")?.scrollIntoView({block: "nearest"});
+      }
+    },
+    clearLine: () => drawLines(null),
+  };
 }
 
-function renderCompilerMount(root, name, module, defaults) {
+// an interpreter is shown like a compiler whose target side is the host machine
+function renderCompilerMount(root, name, module, defaults, kind = "compiler") {
+  const isInterpreter = kind === "interpreter";
+  const [sourceLabel, targetLabel] = isInterpreter ? ["Guest", "Host"] : ["Source", "Target"];
   root.replaceChildren();
-  const statusPanel = createPanel(root, "wm-panel-controller-status", `Compiler: ${name}`);
+  const statusPanel = createPanel(
+    root,
+    "wm-panel-controller-status",
+    `${isInterpreter ? "Interpreter" : "Compiler"}: ${name}`,
+  );
   const status = createOutput(statusPanel.body, "Status", "wm-controller-status-box");
   setOutputText(status, "");
 
-  const panel = createPanel(root, "wm-panel-boundary", "Compiler IO");
+  const panel = createPanel(root, "wm-panel-boundary", `${isInterpreter ? "Interpreter" : "Compiler"} IO`);
+  // source-side inputs by argument name, to point diagnostics into
+  const fields = {};
   const addIoRow = (
     label,
     actionLabel,
@@ -277,7 +516,7 @@ function renderCompilerMount(root, name, module, defaults) {
     sourceWrap.className = "wm-compiler-side wm-compiler-source";
     const sourceTitle = document.createElement("div");
     sourceTitle.className = "wm-output-label";
-    sourceTitle.textContent = "(Source)";
+    sourceTitle.textContent = `(${sourceLabel})`;
     sourceWrap.appendChild(sourceTitle);
 
     const middle = document.createElement("div");
@@ -291,7 +530,7 @@ function renderCompilerMount(root, name, module, defaults) {
     targetWrap.className = "wm-compiler-side wm-compiler-target";
     const targetTitle = document.createElement("div");
     targetTitle.className = "wm-output-label";
-    targetTitle.textContent = "(Target)";
+    targetTitle.textContent = `(${targetLabel})`;
     targetWrap.appendChild(targetTitle);
 
     let sourceInput = null;
@@ -301,6 +540,7 @@ function renderCompilerMount(root, name, module, defaults) {
 
     if (direction === "source-to-target") {
       sourceInput = createField(sourceWrap, "", className, defaultValue, rows);
+      fields[label.toLowerCase()] = sourceInput;
       const outPre = document.createElement("pre");
       outPre.className = "wm-output";
       const outCode = document.createElement("code");
@@ -328,12 +568,14 @@ function renderCompilerMount(root, name, module, defaults) {
         }
         setStatus("");
       } catch (error) {
-        setStatus(`${actionLabel} failed: ${error}`, true);
+        const pointed = direction === "source-to-target" ? fields : {output: targetInput};
+        setStatus(`${actionLabel} failed: ${describeError(error, pointed)}`, true);
       }
     });
 
     row.append(sourceWrap, middle, targetWrap);
     panel.body.appendChild(row);
+    return sourceInput;
   };
 
   const setStatus = (message, isError = false) => {
@@ -341,8 +583,23 @@ function renderCompilerMount(root, name, module, defaults) {
     setOutputText(status, message || "");
   };
 
-  addIoRow("Code", "compile", "wm-field-code", defaults.code, async value => module.compileCode(value), 8);
-  addIoRow("AInput", "encode", "wm-field-ainput", defaults.ainput, async value => module.encodeAinput(value), 6);
+  if (isInterpreter) {
+    // the guest code is not translated on its own; it is encoded together with the ainput
+    const code = addIoRow("Code", "interpreter", "wm-field-code", defaults.code, async () => module.interpreterCode(), 8);
+    addIoRow("AInput", "encode", "wm-field-ainput", defaults.ainput, async value => module.encodeAinput(code.value, value), 6);
+  } else {
+    addIoRow("Code", "compile", "wm-field-code", defaults.code, async value => module.compileCode(value), 8);
+    if (typeof module.sourceMap === "function") {
+      // each compiled location with the source line of its statement
+      addIoRow("Source map", "map", "wm-field-code", defaults.code, async value => {
+        const lines = value.split("\n");
+        return (await module.sourceMap(value))
+          .map(({target, span}) => `${target}\t${span.line}:${span.column}\t${lines[span.line - 1].trim()}`)
+          .join("\n");
+      }, 8);
+    }
+    addIoRow("AInput", "encode", "wm-field-ainput", defaults.ainput, async value => module.encodeAinput(value), 6);
+  }
   addIoRow("RInput", "encode", "wm-field-rinput", defaults.rinput, async value => module.encodeRinput(value), 4);
   addIoRow("ROutput", "decode", "wm-field-routput", "", async value => module.decodeRoutput(value), 4, "target-to-source");
   addIoRow("FOutput", "decode", "wm-field-foutput", "", async value => module.decodeFoutput(value), 4, "target-to-source");
@@ -1,13 +1,23 @@
 use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use clap::{Args, Parser, Subcommand};
-use cli::runtime::{CompilerHost, ModelHost};
+use cli::pipeline::{compile_and_run, Chain, Input};
+use cli::run::{Format, Limits};
+use cli::runtime::{terminal_style, Backend, CompilerHost, InterpreterHost, ModelHost};
+use cli::sandbox::WasmLimits;
 use serde_json::Value;
 
 #[derive(Parser, Debug)]
 #[command(about = "One-shot machine CLI over wasm components in wasm_bundle/")]
 struct Cli {
+    // `native` runs the models linked into the cli instead of the wasm components
+    #[arg(long, value_enum, default_value_t, global = true)]
+    backend: Backend,
+    #[command(flatten)]
+    wasm_limits: WasmLimits,
     #[command(subcommand)]
     command: Command,
 }
@@ -16,6 +26,36 @@ struct Cli {
 enum Command {
     Model(ModelArgs),
     Compiler(CompilerArgs),
+    Interpreter(InterpreterArgs),
+    // compile through a chain of compilers, run the target model and decode the outputs
+    CompileAndRun(CompileAndRunArgs),
+    // replay recorded sessions (see `repl-machine model --record`) and fail on any divergence
+    Replay(ReplayArgs),
+}
+
+#[derive(Args, Debug)]
+struct ReplayArgs {
+    // session files or directories of them, the golden sessions of every model crate if omitted
+    #[arg(value_name = "PATH")]
+    paths: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct CompileAndRunArgs {
+    // compiler names in the order they are applied
+    #[arg(long, value_delimiter = ',', required = true)]
+    chain: Vec<String>,
+    // model running the compiled code
+    #[arg(long)]
+    target: String,
+    // model of the source code, also run and compared with the decoded target outputs
+    #[arg(long)]
+    source: Option<String>,
+    // compare the (non-empty) routputs too, not only the foutput
+    #[arg(long)]
+    diff_routputs: bool,
+    #[command(flatten)]
+    run: RunArgs,
 }
 
 #[derive(Args, Debug)]
@@ -38,6 +78,53 @@ enum ModelCommand {
         #[arg(long)]
         rinput: String,
     },
+    // create the machine and step it until halt or a limit
+    Run(RunArgs),
+    // print the name, text formats and examples of the model as JSON
+    Describe,
+    // write the drawing of every step of a run as a standalone HTML page
+    Trace(TraceArgs),
+    // draw the snapshot on stdin, or print its render state JSON with `--json`
+    Render {
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Args, Debug)]
+struct RunArgs {
+    #[arg(long)]
+    code: String,
+    #[arg(long, default_value = "")]
+    ainput: String,
+    // file with one rinput per line, applied in order
+    #[arg(long, value_name = "FILE")]
+    rinputs: Option<PathBuf>,
+    // rinput repeated after the script (or from the start without one), empty by default
+    #[arg(long)]
+    rinput: Option<String>,
+    #[arg(long, default_value_t = cli::run::DEFAULT_FUEL)]
+    fuel: usize,
+    #[arg(long, value_name = "MS")]
+    time_limit: Option<u64>,
+    #[arg(long, value_enum, default_value_t)]
+    format: Format,
+}
+
+#[derive(Args, Debug)]
+struct TraceArgs {
+    #[arg(long)]
+    code: String,
+    #[arg(long, default_value = "")]
+    ainput: String,
+    #[arg(long, value_name = "FILE")]
+    rinputs: Option<PathBuf>,
+    #[arg(long)]
+    rinput: Option<String>,
+    #[arg(long, default_value_t = cli::trace::TRACE_FUEL)]
+    fuel: usize,
+    #[arg(long, short, value_name = "FILE")]
+    output: PathBuf,
 }
 
 #[derive(Args, Debug)]
@@ -48,13 +135,54 @@ struct CompilerArgs {
 
 #[derive(Subcommand, Debug)]
 enum CompilerCommand {
+    // print the name and the source/target models of the compiler as JSON
+    Describe(DescribeArgs),
     CompileCode(CompileCodeArgs),
+    // print the compiled locations with the source statements they come from as JSON
+    SourceMap(CompileCodeArgs),
     CompileAinput(CompileAinputArgs),
     CompileRinput(CompileRinputArgs),
     DecodeRoutput(DecodeRoutputArgs),
     DecodeFoutput(DecodeFoutputArgs),
 }
 
+#[derive(Args, Debug)]
+struct InterpreterArgs {
+    #[command(subcommand)]
+    command: InterpreterCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum InterpreterCommand {
+    InterpreterCode(InterpreterCodeArgs),
+    EncodeAinput(EncodeAinputArgs),
+    EncodeRinput(CompileRinputArgs),
+    DecodeRoutput(DecodeRoutputArgs),
+    DecodeFoutput(DecodeFoutputArgs),
+}
+
+#[derive(Args, Debug)]
+struct DescribeArgs {
+    #[arg(value_name = "NAME")]
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct InterpreterCodeArgs {
+    #[arg(value_name = "NAME")]
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct EncodeAinputArgs {
+    #[arg(value_name = "NAME")]
+    name: String,
+    #[arg(long)]
+    code: String,
+    #[arg(long, default_value = "")]
+    ainput: String,
+}
+
 #[derive(Args, Debug)]
 struct CompileCodeArgs {
     #[arg(value_name = "NAME")]
@@ -107,18 +235,29 @@ fn read_stdin_snapshot() -> Result<Value> {
     serde_json::from_str(trimmed).context("stdin is not valid JSON")
 }
 
-fn run_model_create(name: &str, code: &str, ainput: &str) -> Result<()> {
-    let mut model = ModelHost::load(name)?;
+fn run_model_create(
+    name: &str,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+    code: &str,
+    ainput: &str,
+) -> Result<()> {
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
     model.create(code, ainput)?;
     println!("{}", model.checkpoint()?);
     Ok(())
 }
 
-fn run_model_step(name: &str, rinput: &str) -> Result<()> {
+fn run_model_step(
+    name: &str,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+    rinput: &str,
+) -> Result<()> {
     let snapshot = read_stdin_snapshot()?;
     let snapshot_str = serde_json::to_string(&snapshot)?;
 
-    let mut model = ModelHost::load(name)?;
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
     model.restore(&snapshot_str)?;
     let step_raw = model.step(rinput)?;
     let step: Value = serde_json::from_str(&step_raw).context("invalid step result JSON")?;
@@ -148,18 +287,173 @@ fn run_model_step(name: &str, rinput: &str) -> Result<()> {
     Ok(())
 }
 
-fn run_compiler(args: &CompilerArgs) -> Result<()> {
+fn run_model_run(
+    name: &str,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+    args: &RunArgs,
+) -> Result<()> {
+    let script = read_script(args.rinputs.as_ref())?;
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
+    let rinputs = cli::run::rinputs(&mut model, script.as_deref(), args.rinput.as_deref())?;
+    model.create(&args.code, &args.ainput)?;
+    let report = cli::run::run(&mut model, rinputs, limits(args))?;
+    report.print(args.format)?;
+    if !report.is_halted() {
+        anyhow::bail!("machine did not halt");
+    }
+    Ok(())
+}
+
+fn run_model_describe(name: &str, backend: Backend, wasm_limits: WasmLimits) -> Result<()> {
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
+    println!("{}", serde_json::to_string_pretty(&model.describe()?)?);
+    Ok(())
+}
+
+fn run_model_trace(
+    name: &str,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+    args: &TraceArgs,
+) -> Result<()> {
+    let script = read_script(args.rinputs.as_ref())?;
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
+    let rinputs = cli::run::rinputs(&mut model, script.as_deref(), args.rinput.as_deref())?;
+    model.create(&args.code, &args.ainput)?;
+    let trace = cli::trace::record(&mut model, name, rinputs, args.fuel)?;
+    std::fs::write(&args.output, trace.to_html())
+        .with_context(|| format!("failed to write {}", args.output.display()))?;
+    eprintln!(
+        "{}: {} steps written to {}",
+        trace.stop.name(),
+        trace.frames.len() - 1,
+        args.output.display()
+    );
+    Ok(())
+}
+
+fn run_model_render(
+    name: &str,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+    json: bool,
+) -> Result<()> {
+    let snapshot = serde_json::to_string(&read_stdin_snapshot()?)?;
+    let mut model = ModelHost::load_with_limits(name, backend, wasm_limits)?;
+    if json {
+        println!("{}", model.render(&snapshot)?);
+    } else {
+        print!("{}", model.render_terminal(&snapshot, terminal_style())?);
+    }
+    Ok(())
+}
+
+fn read_script(rinputs: Option<&PathBuf>) -> Result<Option<String>> {
+    rinputs
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read rinputs: {}", path.display()))
+        })
+        .transpose()
+}
+
+fn limits(args: &RunArgs) -> Limits {
+    Limits {
+        fuel: args.fuel,
+        time: args.time_limit.map(Duration::from_millis),
+    }
+}
+
+fn run_compile_and_run(
+    args: &CompileAndRunArgs,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+) -> Result<()> {
+    let script = read_script(args.run.rinputs.as_ref())?;
+    let mut chain = Chain::load_with_limits(&args.chain, backend, wasm_limits)?;
+    let mut target = ModelHost::load_with_limits(&args.target, backend, wasm_limits)?;
+    let mut source = match &args.source {
+        Some(name) => Some(ModelHost::load_with_limits(name, backend, wasm_limits)?),
+        None => None,
+    };
+    let input = Input {
+        code: &args.run.code,
+        ainput: &args.run.ainput,
+        script: script.as_deref(),
+        repeat: args.run.rinput.as_deref(),
+        limits: limits(&args.run),
+    };
+    let report = compile_and_run(
+        &mut chain,
+        &mut target,
+        source.as_mut(),
+        &input,
+        args.diff_routputs,
+    )?;
+    report.print(args.run.format)?;
+    if let Some(mismatch) = &report.mismatch {
+        anyhow::bail!("{mismatch}");
+    }
+    if !report.target.is_halted() {
+        anyhow::bail!("machine did not halt");
+    }
+    Ok(())
+}
+
+fn run_replay(args: &ReplayArgs, backend: Backend, wasm_limits: WasmLimits) -> Result<()> {
+    let files = if args.paths.is_empty() {
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .parent()
+            .expect("cli has workspace parent");
+        cli::session::golden_files(workspace)?
+    } else {
+        let mut files = vec![];
+        for path in &args.paths {
+            files.extend(cli::session::session_files(path)?);
+        }
+        files
+    };
+    let mut failed = 0;
+    for file in &files {
+        let replayed = cli::session::read(file)
+            .and_then(|events| cli::session::replay(&events, backend, wasm_limits));
+        match replayed {
+            Ok(steps) => println!("ok {}: {steps} steps", file.display()),
+            Err(err) => {
+                failed += 1;
+                println!("FAILED {}: {err:#}", file.display());
+            }
+        }
+    }
+    println!("{} passed, {failed} failed", files.len() - failed);
+    if failed > 0 {
+        anyhow::bail!("{failed} sessions failed");
+    }
+    Ok(())
+}
+
+fn run_compiler(args: &CompilerArgs, backend: Backend, wasm_limits: WasmLimits) -> Result<()> {
     let name = match &args.command {
+        CompilerCommand::Describe(cmd) => &cmd.name,
         CompilerCommand::CompileCode(cmd) => &cmd.name,
+        CompilerCommand::SourceMap(cmd) => &cmd.name,
         CompilerCommand::CompileAinput(cmd) => &cmd.name,
         CompilerCommand::CompileRinput(cmd) => &cmd.name,
         CompilerCommand::DecodeRoutput(cmd) => &cmd.name,
         CompilerCommand::DecodeFoutput(cmd) => &cmd.name,
     };
-    let mut compiler = CompilerHost::load(name)?;
+    let mut compiler = CompilerHost::load_with_limits(name, backend, wasm_limits)?;
 
     match &args.command {
+        CompilerCommand::Describe(_) => {
+            println!("{}", serde_json::to_string_pretty(&compiler.describe()?)?)
+        }
         CompilerCommand::CompileCode(cmd) => println!("{}", compiler.compile_code(&cmd.code)?),
+        CompilerCommand::SourceMap(cmd) => {
+            let mappings = source_map_json(&mut compiler, &cmd.code)?;
+            println!("{}", serde_json::to_string_pretty(&mappings)?)
+        }
         CompilerCommand::CompileAinput(cmd) => {
             println!("{}", compiler.compile_ainput(&cmd.ainput)?)
         }
@@ -176,13 +470,80 @@ fn run_compiler(args: &CompilerArgs) -> Result<()> {
     Ok(())
 }
 
+// `[{target, start, end, line, column, text}]` with the text of the source statement
+fn source_map_json(compiler: &mut CompilerHost, code: &str) -> Result<Value> {
+    let mappings = compiler
+        .source_map(code)?
+        .into_iter()
+        .map(|mapping| {
+            let (line, column, _) = mapping.line(code);
+            serde_json::json!({
+                "target": mapping.target,
+                "start": mapping.span.start,
+                "end": mapping.span.end,
+                "line": line,
+                "column": column,
+                "text": &code[mapping.span.start..mapping.span.end],
+            })
+        })
+        .collect();
+    Ok(Value::Array(mappings))
+}
+
+fn run_interpreter(
+    args: &InterpreterArgs,
+    backend: Backend,
+    wasm_limits: WasmLimits,
+) -> Result<()> {
+    let name = match &args.command {
+        InterpreterCommand::InterpreterCode(cmd) => &cmd.name,
+        InterpreterCommand::EncodeAinput(cmd) => &cmd.name,
+        InterpreterCommand::EncodeRinput(cmd) => &cmd.name,
+        InterpreterCommand::DecodeRoutput(cmd) => &cmd.name,
+        InterpreterCommand::DecodeFoutput(cmd) => &cmd.name,
+    };
+    let mut interpreter = InterpreterHost::load_with_limits(name, backend, wasm_limits)?;
+
+    match &args.command {
+        InterpreterCommand::InterpreterCode(_) => println!("{}", interpreter.interpreter_code()?),
+        InterpreterCommand::EncodeAinput(cmd) => {
+            println!("{}", interpreter.encode_ainput(&cmd.code, &cmd.ainput)?)
+        }
+        InterpreterCommand::EncodeRinput(cmd) => {
+            println!("{}", interpreter.encode_rinput(&cmd.rinput)?)
+        }
+        InterpreterCommand::DecodeRoutput(cmd) => {
+            println!("{}", interpreter.decode_routput(&cmd.routput)?)
+        }
+        InterpreterCommand::DecodeFoutput(cmd) => {
+            println!("{}", interpreter.decode_foutput(&cmd.foutput)?)
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
         Command::Model(args) => match args.command {
-            ModelCommand::Create { code, ainput } => run_model_create(&args.name, &code, &ainput),
-            ModelCommand::Step { rinput } => run_model_step(&args.name, &rinput),
+            ModelCommand::Create { code, ainput } => {
+                run_model_create(&args.name, cli.backend, cli.wasm_limits, &code, &ainput)
+            }
+            ModelCommand::Step { rinput } => {
+                run_model_step(&args.name, cli.backend, cli.wasm_limits, &rinput)
+            }
+            ModelCommand::Run(run) => run_model_run(&args.name, cli.backend, cli.wasm_limits, &run),
+            ModelCommand::Describe => run_model_describe(&args.name, cli.backend, cli.wasm_limits),
+            ModelCommand::Trace(trace) => {
+                run_model_trace(&args.name, cli.backend, cli.wasm_limits, &trace)
+            }
+            ModelCommand::Render { json } => {
+                run_model_render(&args.name, cli.backend, cli.wasm_limits, json)
+            }
         },
-        Command::Compiler(args) => run_compiler(&args),
+        Command::Compiler(args) => run_compiler(&args, cli.backend, cli.wasm_limits),
+        Command::Interpreter(args) => run_interpreter(&args, cli.backend, cli.wasm_limits),
+        Command::CompileAndRun(args) => run_compile_and_run(&args, cli.backend, cli.wasm_limits),
+        Command::Replay(args) => run_replay(&args, cli.backend, cli.wasm_limits),
     }
 }
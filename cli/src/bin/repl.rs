@@ -1,10 +1,19 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::{Args, Parser, Subcommand};
-use cli::runtime::CompilerHost;
+use cli::repl_model::{Options, SourceView};
+use cli::runtime::{Backend, CompilerHost, InterpreterHost};
+use cli::sandbox::WasmLimits;
 
 #[derive(Parser, Debug)]
 #[command(about = "Load and call wasm component from wasm_bundle/<name>.component.wasm")]
 struct Cli {
+    // `native` runs the models linked into the cli instead of the wasm components
+    #[arg(long, value_enum, default_value_t, global = true)]
+    backend: Backend,
+    #[command(flatten)]
+    limits: WasmLimits,
     #[command(subcommand)]
     command: Command,
 }
@@ -13,6 +22,7 @@ struct Cli {
 enum Command {
     Model(ModelArgs),
     Compiler(CompilerArgs),
+    Interpreter(InterpreterArgs),
 }
 
 #[derive(Args, Debug)]
@@ -23,6 +33,18 @@ struct ModelArgs {
     code: Option<String>,
     #[arg(long)]
     ainput: Option<String>,
+    // compiler whose source map shows the `--source` statement of each step
+    // the code defaults to the compiled source
+    #[arg(long, value_name = "COMPILER", requires = "source")]
+    source_map: Option<String>,
+    #[arg(long, requires = "source_map")]
+    source: Option<String>,
+    // draw the render state after every step
+    #[arg(long)]
+    render: bool,
+    // write the session to FILE, to be replayed with `pipe-machine replay`
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
 }
 
 #[derive(Args, Debug)]
@@ -40,6 +62,37 @@ enum CompilerCommand {
     DecodeFoutput(DecodeFoutputArgs),
 }
 
+#[derive(Args, Debug)]
+struct InterpreterArgs {
+    #[command(subcommand)]
+    command: InterpreterCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum InterpreterCommand {
+    InterpreterCode(InterpreterCodeArgs),
+    EncodeAinput(EncodeAinputArgs),
+    EncodeRinput(CompileRinputArgs),
+    DecodeRoutput(DecodeRoutputArgs),
+    DecodeFoutput(DecodeFoutputArgs),
+}
+
+#[derive(Args, Debug)]
+struct InterpreterCodeArgs {
+    #[arg(value_name = "NAME")]
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct EncodeAinputArgs {
+    #[arg(value_name = "NAME")]
+    name: String,
+    #[arg(long)]
+    code: String,
+    #[arg(long, default_value = "")]
+    ainput: String,
+}
+
 #[derive(Args, Debug)]
 struct CompileCodeArgs {
     #[arg(value_name = "NAME")]
@@ -80,8 +133,13 @@ struct DecodeFoutputArgs {
     foutput: String,
 }
 
-fn run_as_compiler(name: &str, args: &CompilerArgs) -> Result<()> {
-    let mut compiler = CompilerHost::load(name)?;
+fn run_as_compiler(
+    name: &str,
+    backend: Backend,
+    limits: WasmLimits,
+    args: &CompilerArgs,
+) -> Result<()> {
+    let mut compiler = CompilerHost::load_with_limits(name, backend, limits)?;
     println!("world=compiler");
 
     match &args.command {
@@ -113,11 +171,72 @@ fn run_as_compiler(name: &str, args: &CompilerArgs) -> Result<()> {
     }
 }
 
+fn run_as_interpreter(
+    name: &str,
+    backend: Backend,
+    limits: WasmLimits,
+    args: &InterpreterArgs,
+) -> Result<()> {
+    let mut interpreter = InterpreterHost::load_with_limits(name, backend, limits)?;
+    println!("world=interpreter");
+
+    match &args.command {
+        InterpreterCommand::InterpreterCode(_) => {
+            let out = interpreter.interpreter_code()?;
+            println!("interpreter-code={out}");
+            Ok(())
+        }
+        InterpreterCommand::EncodeAinput(cmd) => {
+            let out = interpreter.encode_ainput(&cmd.code, &cmd.ainput)?;
+            println!("encode-ainput={out}");
+            Ok(())
+        }
+        InterpreterCommand::EncodeRinput(cmd) => {
+            let out = interpreter.encode_rinput(&cmd.rinput)?;
+            println!("encode-rinput={out}");
+            Ok(())
+        }
+        InterpreterCommand::DecodeRoutput(cmd) => {
+            let out = interpreter.decode_routput(&cmd.routput)?;
+            println!("decode-routput={out}");
+            Ok(())
+        }
+        InterpreterCommand::DecodeFoutput(cmd) => {
+            let out = interpreter.decode_foutput(&cmd.foutput)?;
+            println!("decode-foutput={out}");
+            Ok(())
+        }
+    }
+}
+
 fn main() -> Result<()> {
     let cli = Cli::parse();
     match cli.command {
         Command::Model(args) => {
-            cli::repl_model::run(&args.name, args.code.as_deref(), args.ainput.as_deref())
+            let mut code = args.code.clone();
+            let source = match (&args.source_map, &args.source) {
+                (Some(compiler), Some(source)) => {
+                    let mut compiler =
+                        CompilerHost::load_with_limits(compiler, cli.backend, cli.limits)?;
+                    if code.is_none() {
+                        code = Some(compiler.compile_code(source)?);
+                    }
+                    Some(SourceView::new(&mut compiler, source)?)
+                }
+                _ => None,
+            };
+            cli::repl_model::run(
+                &args.name,
+                cli.backend,
+                cli.limits,
+                code.as_deref(),
+                args.ainput.as_deref(),
+                Options {
+                    source: source.as_ref(),
+                    render: args.render,
+                    record: args.record.as_deref(),
+                },
+            )
         }
         Command::Compiler(args) => {
             let name = match &args.command {
@@ -127,7 +246,17 @@ fn main() -> Result<()> {
                 CompilerCommand::DecodeRoutput(cmd) => &cmd.name,
                 CompilerCommand::DecodeFoutput(cmd) => &cmd.name,
             };
-            run_as_compiler(name, &args)
+            run_as_compiler(name, cli.backend, cli.limits, &args)
+        }
+        Command::Interpreter(args) => {
+            let name = match &args.command {
+                InterpreterCommand::InterpreterCode(cmd) => &cmd.name,
+                InterpreterCommand::EncodeAinput(cmd) => &cmd.name,
+                InterpreterCommand::EncodeRinput(cmd) => &cmd.name,
+                InterpreterCommand::DecodeRoutput(cmd) => &cmd.name,
+                InterpreterCommand::DecodeFoutput(cmd) => &cmd.name,
+            };
+            run_as_interpreter(name, cli.backend, cli.limits, &args)
         }
     }
 }
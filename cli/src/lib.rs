@@ -1,2 +1,9 @@
+pub mod line_editor;
+pub mod pipeline;
+pub mod registry;
 pub mod repl_model;
+pub mod run;
 pub mod runtime;
+pub mod sandbox;
+pub mod session;
+pub mod trace;
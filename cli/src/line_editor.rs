@@ -0,0 +1,355 @@
+use std::io::{self, BufRead, Read, Write};
+
+use utils::render_terminal::char_width;
+
+// readline-style prompt of the REPL: the line can be edited with the arrow keys and the usual
+// emacs bindings (C-a C-e C-b C-f C-k C-u C-w), and up/down (C-p C-n) walk through the history
+// when stdin is not a terminal lines are read as they are
+#[derive(Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    KillToEnd,
+    KillToStart,
+    KillWord,
+    // C-c, the line is dropped
+    Cancel,
+    // C-d, end of input on an empty line
+    Eof,
+    Ignored,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // `None` at the end of input
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let line = match raw::RawMode::enter() {
+            Some(_raw) => self.edit(&mut io::stdin().lock(), &mut io::stdout(), prompt)?,
+            None => {
+                print!("{prompt}");
+                io::stdout().flush()?;
+                let mut line = String::new();
+                if io::stdin().lock().read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                Some(line.trim_end_matches(['\n', '\r']).to_string())
+            }
+        };
+        if let Some(line) = &line {
+            self.add_history(line);
+        }
+        Ok(line)
+    }
+
+    fn add_history(&mut self, line: &str) {
+        if !line.trim().is_empty() && self.history.last().map(String::as_str) != Some(line) {
+            self.history.push(line.to_string());
+        }
+    }
+
+    // edit one line read key by key from `input`, echoing to `out`
+    fn edit(
+        &self,
+        input: &mut impl Read,
+        out: &mut impl Write,
+        prompt: &str,
+    ) -> io::Result<Option<String>> {
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        // position in the history, `history.len()` is the line being typed (kept in `draft`)
+        let mut entry = self.history.len();
+        let mut draft: Vec<char> = vec![];
+        redraw(out, prompt, &line, cursor)?;
+        loop {
+            let Some(key) = read_key(input)? else {
+                return Ok(None);
+            };
+            match key {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Eof if line.is_empty() => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(None);
+                }
+                Key::Eof if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left => cursor = cursor.saturating_sub(1),
+                Key::Right => cursor = (cursor + 1).min(line.len()),
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::KillToEnd => line.truncate(cursor),
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::KillWord => {
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1] == ' ' {
+                        start -= 1;
+                    }
+                    while start > 0 && line[start - 1] != ' ' {
+                        start -= 1;
+                    }
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Up | Key::Down => {
+                    let next = match key {
+                        Key::Up => entry.checked_sub(1),
+                        _ => Some(entry + 1).filter(|next| *next <= self.history.len()),
+                    };
+                    if let Some(next) = next {
+                        if entry == self.history.len() {
+                            draft = line.clone();
+                        }
+                        entry = next;
+                        line = match self.history.get(entry) {
+                            Some(text) => text.chars().collect(),
+                            None => draft.clone(),
+                        };
+                        cursor = line.len();
+                    }
+                }
+                Key::Cancel => {
+                    write!(out, "^C\r\n")?;
+                    line.clear();
+                    cursor = 0;
+                    entry = self.history.len();
+                }
+                _ => {}
+            }
+            redraw(out, prompt, &line, cursor)?;
+        }
+    }
+}
+
+fn redraw(out: &mut impl Write, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+    let text: String = line.iter().collect();
+    let before: usize = line[..cursor].iter().map(|c| char_width(*c)).sum();
+    write!(out, "\r{prompt}{text}\x1b[K\r")?;
+    let column = prompt.chars().map(char_width).sum::<usize>() + before;
+    if column > 0 {
+        write!(out, "\x1b[{column}C")?;
+    }
+    out.flush()
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    loop {
+        match input.read(&mut byte) {
+            Ok(0) => return Ok(None),
+            Ok(_) => return Ok(Some(byte[0])),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// `None` at the end of input
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let Some(byte) = read_byte(input)? else {
+        return Ok(None);
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Cancel,
+        0x04 => Key::Eof,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x0b => Key::KillToEnd,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillToStart,
+        0x17 => Key::KillWord,
+        0x1b => read_escape(input)?,
+        byte if byte < 0x20 => Key::Ignored,
+        byte => read_char(input, byte)?,
+    };
+    Ok(Some(key))
+}
+
+// `ESC [ A`, `ESC O H`, `ESC [ 3 ~`, ...
+fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+    let Some(kind @ (b'[' | b'O')) = read_byte(input)? else {
+        return Ok(Key::Ignored);
+    };
+    let mut params = String::new();
+    loop {
+        let Some(byte) = read_byte(input)? else {
+            return Ok(Key::Ignored);
+        };
+        let key = match (kind, byte) {
+            (_, b'0'..=b'9' | b';') => {
+                params.push(byte as char);
+                continue;
+            }
+            (_, b'A') => Key::Up,
+            (_, b'B') => Key::Down,
+            (_, b'C') => Key::Right,
+            (_, b'D') => Key::Left,
+            (_, b'H') => Key::Home,
+            (_, b'F') => Key::End,
+            (b'[', b'~') => match params.as_str() {
+                "1" | "7" => Key::Home,
+                "4" | "8" => Key::End,
+                "3" => Key::Delete,
+                _ => Key::Ignored,
+            },
+            _ => Key::Ignored,
+        };
+        return Ok(key);
+    }
+}
+
+// the rest of a UTF-8 character starting with `first`
+fn read_char(input: &mut impl Read, first: u8) -> io::Result<Key> {
+    let len = match first {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    let mut bytes = vec![first];
+    for _ in 1..len {
+        match read_byte(input)? {
+            Some(byte) => bytes.push(byte),
+            None => break,
+        }
+    }
+    Ok(std::str::from_utf8(&bytes)
+        .ok()
+        .and_then(|text| text.chars().next())
+        .map_or(Key::Ignored, Key::Char))
+}
+
+#[cfg(unix)]
+mod raw {
+    // the terminal in non-canonical mode without echo, restored on drop
+    pub struct RawMode(libc::termios);
+
+    impl RawMode {
+        // `None` if stdin is not a terminal
+        pub fn enter() -> Option<RawMode> {
+            unsafe {
+                if libc::isatty(libc::STDIN_FILENO) != 1 {
+                    return None;
+                }
+                let mut termios = std::mem::zeroed::<libc::termios>();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                    return None;
+                }
+                let original = termios;
+                termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+                termios.c_iflag &= !(libc::IXON | libc::ICRNL);
+                termios.c_cc[libc::VMIN] = 1;
+                termios.c_cc[libc::VTIME] = 0;
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) != 0 {
+                    return None;
+                }
+                Some(RawMode(original))
+            }
+        }
+    }
+
+    impl Drop for RawMode {
+        fn drop(&mut self) {
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod raw {
+    pub struct RawMode;
+
+    impl RawMode {
+        pub fn enter() -> Option<RawMode> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(editor: &LineEditor, keys: &str) -> Option<String> {
+        let mut out = vec![];
+        editor.edit(&mut keys.as_bytes(), &mut out, "> ").unwrap()
+    }
+
+    #[test]
+    fn keys_edit_the_line() {
+        let editor = LineEditor::new();
+        // left twice, insert, C-e, append
+        assert_eq!(
+            edit(&editor, "abc\x1b[D\x1b[DX\x05!\r").as_deref(),
+            Some("aXbc!")
+        );
+        // backspace, C-a and delete, C-w
+        assert_eq!(
+            edit(&editor, "step one\x7f\x01\x1b[3~\x05 two\x17x\r").as_deref(),
+            Some("tep on x")
+        );
+        // C-u and a multibyte character
+        assert_eq!(edit(&editor, "abc\x15ä\r").as_deref(), Some("ä"));
+        // C-d deletes, or ends the input on an empty line
+        assert_eq!(edit(&editor, "ab\x01\x04\r").as_deref(), Some("b"));
+        assert_eq!(edit(&editor, "\x04"), None);
+        assert_eq!(edit(&editor, ""), None);
+    }
+
+    #[test]
+    fn up_and_down_walk_the_history() {
+        let mut editor = LineEditor::new();
+        editor.add_history("first");
+        editor.add_history("second");
+        editor.add_history("second");
+        assert_eq!(editor.history(), ["first", "second"]);
+        assert_eq!(edit(&editor, "\x1b[A\r").as_deref(), Some("second"));
+        assert_eq!(edit(&editor, "\x10\x10\x10!\r").as_deref(), Some("first!"));
+        assert_eq!(edit(&editor, "new\x1b[A\x1b[B\r").as_deref(), Some("new"));
+    }
+}
@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::run::{self, Format, Limits, Report, Stop};
+use crate::runtime::{Backend, CompilerHost, ModelHost};
+use crate::sandbox::WasmLimits;
+
+// compilers applied in order, each target being the source of the next
+pub struct Chain {
+    compilers: Vec<(String, CompilerHost)>,
+}
+
+impl Chain {
+    pub fn load(names: &[String], backend: Backend) -> Result<Self> {
+        Self::load_with_limits(names, backend, WasmLimits::default())
+    }
+
+    pub fn load_with_limits(
+        names: &[String],
+        backend: Backend,
+        limits: WasmLimits,
+    ) -> Result<Self> {
+        if names.is_empty() {
+            anyhow::bail!("empty compiler chain");
+        }
+        let compilers = names
+            .iter()
+            .map(|name| {
+                Ok((
+                    name.clone(),
+                    CompilerHost::load_with_limits(name, backend, limits)?,
+                ))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Chain { compilers })
+    }
+
+    pub fn compile_code(&mut self, code: &str) -> Result<String> {
+        self.forward(code, CompilerHost::compile_code)
+    }
+
+    pub fn encode_ainput(&mut self, ainput: &str) -> Result<String> {
+        self.forward(ainput, CompilerHost::compile_ainput)
+    }
+
+    pub fn encode_rinput(&mut self, rinput: &str) -> Result<String> {
+        self.forward(rinput, CompilerHost::compile_rinput)
+    }
+
+    pub fn decode_routput(&mut self, output: &str) -> Result<String> {
+        self.backward(output, CompilerHost::decode_routput)
+    }
+
+    pub fn decode_foutput(&mut self, output: &str) -> Result<String> {
+        self.backward(output, CompilerHost::decode_foutput)
+    }
+
+    fn forward(
+        &mut self,
+        text: &str,
+        f: fn(&mut CompilerHost, &str) -> Result<String>,
+    ) -> Result<String> {
+        let mut text = text.to_string();
+        for (name, compiler) in &mut self.compilers {
+            text = f(compiler, &text).with_context(|| format!("in compiler {name}"))?;
+        }
+        Ok(text)
+    }
+
+    fn backward(
+        &mut self,
+        text: &str,
+        f: fn(&mut CompilerHost, &str) -> Result<String>,
+    ) -> Result<String> {
+        let mut text = text.to_string();
+        for (name, compiler) in self.compilers.iter_mut().rev() {
+            text = f(compiler, &text).with_context(|| format!("in compiler {name}"))?;
+        }
+        Ok(text)
+    }
+}
+
+pub struct Input<'a> {
+    pub code: &'a str,
+    pub ainput: &'a str,
+    // one rinput per line, see `run::rinputs`
+    pub script: Option<&'a str>,
+    pub repeat: Option<&'a str>,
+    pub limits: Limits,
+}
+
+pub struct PipelineReport {
+    // the code run by the target model
+    pub target_code: String,
+    // the target run with its routputs and foutput decoded back to the source
+    pub target: Report,
+    pub source: Option<Report>,
+    // why the decoded target run differs from the source run
+    pub mismatch: Option<String>,
+}
+
+impl PipelineReport {
+    pub fn is_ok(&self) -> bool {
+        self.target.is_halted() && self.mismatch.is_none()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "target_code": self.target_code,
+            "target": self.target.to_json(),
+            "source": self.source.as_ref().map(Report::to_json),
+            "mismatch": self.mismatch,
+        })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = self.target.to_text();
+        if let Some(source) = &self.source {
+            let summary = source.to_text();
+            let summary = summary.lines().last().unwrap_or_default();
+            out.push_str(&format!("source {summary}\n"));
+            match &self.mismatch {
+                None => out.push_str("diff: ok\n"),
+                Some(mismatch) => out.push_str(&format!("diff: {mismatch}\n")),
+            }
+        }
+        out
+    }
+
+    pub fn print(&self, format: Format) -> Result<()> {
+        match format {
+            Format::Text => print!("{}", self.to_text()),
+            Format::Json => println!("{}", serde_json::to_string(&self.to_json())?),
+        }
+        Ok(())
+    }
+}
+
+// compile through the chain, run the target model and decode its outputs
+// with a source model, also run it on the same input and compare the foutputs
+// (and the routputs printing as non-empty text, if `diff_routputs`)
+pub fn compile_and_run(
+    chain: &mut Chain,
+    target: &mut ModelHost,
+    mut source: Option<&mut ModelHost>,
+    input: &Input,
+    diff_routputs: bool,
+) -> Result<PipelineReport> {
+    let target_code = chain.compile_code(input.code)?;
+    let target_ainput = chain.encode_ainput(input.ainput)?;
+    let script = input
+        .script
+        .map(|script| {
+            script
+                .lines()
+                .map(|line| chain.encode_rinput(line))
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?
+        .map(|lines| lines.join("\n"));
+    let repeat = match (input.repeat, source.as_deref_mut()) {
+        (Some(rinput), _) => Some(chain.encode_rinput(rinput)?),
+        // the default rinput of the source model, as `run::rinputs` repeats without script
+        (None, Some(source)) if input.script.is_none() => {
+            Some(chain.encode_rinput(&run::default_rinput(source)?)?)
+        }
+        (None, _) => None,
+    };
+
+    // without a source model, the default rinput of the target
+    let rinputs = run::rinputs(target, script.as_deref(), repeat.as_deref())?;
+    target.create(&target_code, &target_ainput)?;
+    let mut target_report = run::run(target, rinputs, input.limits)?;
+    target_report.routputs = target_report
+        .routputs
+        .iter()
+        .map(|(step, output)| Ok((*step, chain.decode_routput(output)?)))
+        .collect::<Result<_>>()?;
+    if let Stop::Halted { foutput } = &target_report.stop {
+        target_report.stop = Stop::Halted {
+            foutput: chain.decode_foutput(foutput)?,
+        };
+    }
+
+    let Some(source) = source else {
+        return Ok(PipelineReport {
+            target_code,
+            target: target_report,
+            source: None,
+            mismatch: None,
+        });
+    };
+    source.create(input.code, input.ainput)?;
+    let rinputs = run::rinputs(source, input.script, input.repeat)?;
+    let source_report = run::run(source, rinputs, input.limits)?;
+    let mismatch = mismatch(&source_report, &target_report, diff_routputs);
+    Ok(PipelineReport {
+        target_code,
+        target: target_report,
+        source: Some(source_report),
+        mismatch,
+    })
+}
+
+fn mismatch(source: &Report, target: &Report, diff_routputs: bool) -> Option<String> {
+    let (
+        Stop::Halted {
+            foutput: source_foutput,
+        },
+        Stop::Halted {
+            foutput: target_foutput,
+        },
+    ) = (&source.stop, &target.stop)
+    else {
+        return (source.stop != target.stop).then(|| {
+            format!(
+                "source stopped with {:?}, target with {:?}",
+                source.stop, target.stop
+            )
+        });
+    };
+    if source_foutput != target_foutput {
+        return Some(format!(
+            "foutput differs: source `{source_foutput}`, target `{target_foutput}`"
+        ));
+    }
+    // the steps differ between the models, only the texts are compared
+    let observable = |report: &Report| -> Vec<String> {
+        report
+            .routputs
+            .iter()
+            .map(|(_, text)| text)
+            .filter(|text| !text.trim().is_empty())
+            .cloned()
+            .collect()
+    };
+    let (source_routputs, target_routputs) = (observable(source), observable(target));
+    (diff_routputs && source_routputs != target_routputs).then(|| {
+        format!(
+            "routputs differ: source [{}], target [{}]",
+            source_routputs.join(", "),
+            target_routputs.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(fuel: usize) -> Limits {
+        Limits { fuel, time: None }
+    }
+
+    #[test]
+    fn recursive_function_through_turing_machine() {
+        let names = [
+            "recursive_function-rec_tm_ir",
+            "rec_tm_ir-rec_tm_ir_jump",
+            "rec_tm_ir_jump-turing_machine",
+        ]
+        .map(String::from);
+        let mut chain = Chain::load(&names, Backend::Native).unwrap();
+        let mut target = ModelHost::load("turing_machine", Backend::Native).unwrap();
+        let mut source = ModelHost::load("recursive_function", Backend::Native).unwrap();
+        let input = Input {
+            code: "SUCC",
+            ainput: "(2)",
+            script: None,
+            repeat: None,
+            limits: limits(100_000),
+        };
+        let report =
+            compile_and_run(&mut chain, &mut target, Some(&mut source), &input, false).unwrap();
+        assert!(report.is_ok(), "{}", report.to_text());
+        let Stop::Halted { foutput } = &report.target.stop else {
+            unreachable!()
+        };
+        assert_eq!(foutput, "3");
+    }
+
+    #[test]
+    fn identity_compiler_agrees_with_the_source() {
+        let names = ["example_counter-example_counter".to_string()];
+        let mut chain = Chain::load(&names, Backend::Native).unwrap();
+        let mut target = ModelHost::load("example_counter", Backend::Native).unwrap();
+        let mut source = ModelHost::load("example_counter", Backend::Native).unwrap();
+        let input = Input {
+            code: "8",
+            ainput: "",
+            script: None,
+            repeat: Some("inc"),
+            limits: limits(3),
+        };
+        let report =
+            compile_and_run(&mut chain, &mut target, Some(&mut source), &input, true).unwrap();
+        assert!(report.is_ok(), "{}", report.to_text());
+        assert_eq!(report.target.routputs, [(1, "inc".into())]);
+        assert!(report.to_text().ends_with("diff: ok\n"));
+    }
+}
@@ -0,0 +1,259 @@
+use utils::dynamic::{self, DynCompiler, DynInterpreter, DynMachine};
+
+// every model linked natively, under the name of its wasm component (the bin target)
+
+type Entry<T> = (&'static str, fn() -> Box<T>);
+
+const MACHINES: &[Entry<dyn DynMachine>] = &[
+    (
+        "coroutine",
+        dynamic::machine::<async_await::coroutine::CoroutineMachine>,
+    ),
+    (
+        "example_counter",
+        dynamic::machine::<example_counter::Counter>,
+    ),
+    (
+        "flow_ir",
+        dynamic::machine::<flow_ir::flow_ir::FlowIrMachine>,
+    ),
+    ("goto_lang", dynamic::machine::<goto_lang::machine::Program>),
+    (
+        "lambda_calculus",
+        dynamic::machine::<lambda_calculus::machine::LambdaTerm>,
+    ),
+    (
+        "logic_circuit",
+        dynamic::machine::<logic_circuit::LogicCircuit>,
+    ),
+    (
+        "pcf-expr",
+        dynamic::machine::<pcf::expr_machine::ExprMachine>,
+    ),
+    ("pcf-cek", dynamic::machine::<pcf::cek_machine::CekMachine>),
+    (
+        "pcf-secd",
+        dynamic::machine::<pcf::secd_machine::SecdMachine>,
+    ),
+    (
+        "pcf-expr-stmt-cek",
+        dynamic::machine::<pcf::expr_stmt_machine::ExprStmtMachine>,
+    ),
+    (
+        "rec_tm_ir",
+        dynamic::machine::<recursive_function_turing_machine::rec_tm_ir::RecTmIrMachine>,
+    ),
+    (
+        "rec_tm_ir_jump",
+        dynamic::machine::<recursive_function_turing_machine::rec_tm_ir_jump::RecTmIrJumpMachine>,
+    ),
+    (
+        "recursive_function",
+        dynamic::machine::<recursive_function::machine::Program>,
+    ),
+    (
+        "proc_lang",
+        dynamic::machine::<structured_lang::proc_lang::ProcLangMachine>,
+    ),
+    (
+        "internal_ctrl",
+        dynamic::machine::<structured_lang::internal_ctrl::InternalCtrlMachine>,
+    ),
+    (
+        "expr_lang",
+        dynamic::machine::<structured_lang::expr_lang::ExprLangMachine>,
+    ),
+    (
+        "mini_prog",
+        dynamic::machine::<structured_lang::mini_prog_machine::MiniProgMachine>,
+    ),
+    (
+        "fn_ptr",
+        dynamic::machine::<structured_lang::fn_ptr_machine::FnPtrMachine>,
+    ),
+    (
+        "tiny_isa",
+        dynamic::machine::<tiny_isa::tiny_isa::TinyIsaMachine>,
+    ),
+    (
+        "symbolic_asm",
+        dynamic::machine::<tiny_isa::symbolic_asm::SymbolicAsmMachine>,
+    ),
+    (
+        "cfg_vreg",
+        dynamic::machine::<tiny_isa::cfg_vreg::CfgVRegMachine>,
+    ),
+    (
+        "turing_machine",
+        dynamic::machine::<turing_machine::machine::TuringMachine>,
+    ),
+    (
+        "strarr",
+        dynamic::machine::<while_with_data::strarr::StrArrMachine>,
+    ),
+    (
+        "strtree",
+        dynamic::machine::<while_with_data::strtree::StrTreeMachine>,
+    ),
+];
+
+const COMPILERS: &[Entry<dyn DynCompiler>] = &[
+    (
+        "example_counter-example_counter",
+        dynamic::compiler::<example_counter::ExampleCounterCompiler>,
+    ),
+    (
+        "pcf-expr-cek",
+        dynamic::compiler::<pcf::compiler::ExprToCekCompiler>,
+    ),
+    (
+        "pcf-expr-secd",
+        dynamic::compiler::<pcf::compiler::ExprToSecdCompiler>,
+    ),
+    (
+        "recursive_function-goto_lang",
+        dynamic::compiler::<recursive_function_goto_lang::RecToGotoCompiler>,
+    ),
+    (
+        "recursive_function_lambda_calculus",
+        dynamic::compiler::<recursive_function_lambda_calculus::Rec2LamCompiler>,
+    ),
+    (
+        "recursive_function-rec_tm_ir",
+        dynamic::compiler::<recursive_function_turing_machine::rec_to_ir::RecToRecTmIrCompiler>,
+    ),
+    (
+        "rec_tm_ir-rec_tm_ir_jump",
+        dynamic::compiler::<recursive_function_turing_machine::rec_tm_ir::RecTmIrToJumpCompiler>,
+    ),
+    (
+        "rec_tm_ir_jump-turing_machine",
+        dynamic::compiler::<
+            recursive_function_turing_machine::rec_tm_ir_jump::RecTmIrJumpToTmCompiler,
+        >,
+    ),
+    (
+        "recursive_function-turing_machine",
+        dynamic::compiler::<recursive_function_turing_machine::RecToTmCompiler>,
+    ),
+    (
+        "internal_ctrl_to_flow_ir",
+        dynamic::compiler::<
+            structured_lang::internal_ctrl::internal_ctrl_compiler::InternalCtrlToFlowIrCompiler,
+        >,
+    ),
+    (
+        "expr_to_flow_ir",
+        dynamic::compiler::<structured_lang::expr_lang::expr_lang_compiler::ExprLangToFlowIrCompiler>,
+    ),
+];
+
+const INTERPRETERS: &[Entry<dyn DynInterpreter>] = &[(
+    "lambda_calculus-self_interpreter",
+    dynamic::interpreter::<lambda_calculus::self_interpreter::SelfInterpreter>,
+)];
+
+fn lookup<T: ?Sized>(entries: &[Entry<T>], name: &str) -> Option<Box<T>> {
+    entries
+        .iter()
+        .find(|(entry, _)| *entry == name)
+        .map(|(_, make)| make())
+}
+
+pub fn machine(name: &str) -> Option<Box<dyn DynMachine>> {
+    lookup(MACHINES, name)
+}
+
+pub fn compiler(name: &str) -> Option<Box<dyn DynCompiler>> {
+    lookup(COMPILERS, name)
+}
+
+pub fn interpreter(name: &str) -> Option<Box<dyn DynInterpreter>> {
+    lookup(INTERPRETERS, name)
+}
+
+// names of the registered models, compilers and interpreters
+pub fn names() -> impl Iterator<Item = &'static str> {
+    let machines = MACHINES.iter().map(|(name, _)| *name);
+    let compilers = COMPILERS.iter().map(|(name, _)| *name);
+    let interpreters = INTERPRETERS.iter().map(|(name, _)| *name);
+    machines.chain(compilers).chain(interpreters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::{Backend, CompilerHost, ModelHost};
+
+    #[test]
+    fn names_are_unique() {
+        let mut names: Vec<_> = names().collect();
+        let count = names.len();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), count);
+    }
+
+    #[test]
+    fn native_model_steps_and_restores() {
+        let mut model = ModelHost::load("example_counter", Backend::Native).unwrap();
+        model.create("5", "").unwrap();
+        let step = model.step("inc").unwrap();
+        assert!(step.contains("\"continue\""));
+        let snapshot = model.checkpoint().unwrap();
+        assert_eq!(model.history_len().unwrap(), 1);
+        assert_eq!(model.history_position().unwrap(), 1);
+
+        let mut restored = ModelHost::load("example_counter", Backend::Native).unwrap();
+        restored.restore(&snapshot).unwrap();
+        assert_eq!(restored.checkpoint().unwrap(), snapshot);
+        assert!(model.render(&snapshot).is_ok());
+
+        let err = model.step("jump").unwrap_err();
+        assert!(format!("{err:#}").starts_with("model step: "));
+        assert!(ModelHost::load("no_such_model", Backend::Native).is_err());
+    }
+
+    #[test]
+    fn descriptions_match_the_registry() {
+        for (name, _) in MACHINES {
+            let mut model = ModelHost::load(name, Backend::Native).unwrap();
+            let description = model.describe().unwrap();
+            assert_eq!(description.name, *name);
+            for example in &description.examples {
+                model
+                    .create(&example.code, &example.ainput)
+                    .unwrap_or_else(|err| panic!("{name} {}: {err:#}", example.name));
+            }
+        }
+        for (name, _) in COMPILERS {
+            let description = CompilerHost::load(name, Backend::Native)
+                .unwrap()
+                .describe()
+                .unwrap();
+            assert_eq!(description.name, *name);
+            assert!(machine(&description.source).is_some(), "{name}");
+            assert!(machine(&description.target).is_some(), "{name}");
+        }
+    }
+
+    #[test]
+    fn target_locations_lead_back_to_the_source() {
+        let source = "x := 1 ;\nwhile x < 3 [ x := x + 1 ]";
+        let mut compiler = CompilerHost::load("expr_to_flow_ir", Backend::Native).unwrap();
+        let mappings = compiler.source_map(source).unwrap();
+        let mut model = ModelHost::load("flow_ir", Backend::Native).unwrap();
+        model
+            .create(&compiler.compile_code(source).unwrap(), "")
+            .unwrap();
+        let mut lines = vec![];
+        while !model.step("").unwrap().contains("\"halt\"") {
+            let location = model.location().unwrap().unwrap();
+            if let Some(mapping) = utils::source_map::lookup(&mappings, &location) {
+                lines.push(mapping.line(source).0);
+            }
+        }
+        lines.dedup();
+        assert_eq!(lines, [1, 2]);
+    }
+}
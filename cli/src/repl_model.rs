@@ -1,9 +1,19 @@
 use std::io::{self, Write};
+use std::path::Path;
 
 use anyhow::{Context, Result};
 use serde_json::Value;
 
-use crate::runtime::ModelHost;
+use utils::breakpoint::{Breakpoints, Condition, JsonPath};
+use utils::source_map::{self, SourceMapping};
+
+use crate::line_editor::LineEditor;
+use crate::runtime::{terminal_style, Backend, CompilerHost, ModelHost};
+use crate::sandbox::WasmLimits;
+use crate::session::{step_result, Event, Recorder};
+
+// maximum number of steps of one `:continue`, and of `:run` without a count (as `COMMANDS` says)
+const CONTINUE_FUEL: usize = 100_000;
 
 fn read_multiline_until_eof(stdin: &io::Stdin) -> Result<String> {
     let mut out = String::new();
@@ -42,7 +52,79 @@ fn read_ainput(stdin: &io::Stdin) -> Result<String> {
     Ok(line.trim_end_matches(['\n', '\r']).to_string())
 }
 
-pub fn run(name: &str, code: Option<&str>, ainput: Option<&str>) -> Result<()> {
+// source code of the running (compiled) code, shown at the statement of the current location
+pub struct SourceView {
+    source: String,
+    mappings: Vec<SourceMapping>,
+}
+
+impl SourceView {
+    pub fn new(compiler: &mut CompilerHost, source: &str) -> Result<Self> {
+        Ok(SourceView {
+            source: source.to_string(),
+            mappings: compiler.source_map(source)?,
+        })
+    }
+
+    // `source=line:column text` of the statement, `source=<location>` for locations added by the compiler
+    fn print(&self, model: &mut ModelHost) -> Result<()> {
+        let Some(location) = model.location()? else {
+            return Ok(());
+        };
+        match source_map::lookup(&self.mappings, &location) {
+            Some(mapping) => {
+                let (line, column, text) = mapping.line(&self.source);
+                println!("source={line}:{column} {}", text.trim());
+            }
+            None => println!("source=<{location}>"),
+        }
+        Ok(())
+    }
+}
+
+// `:help`
+const COMMANDS: &str = "\
+:run [N [RINPUT]]   step N times with RINPUT (empty), up to 100000 steps until it halts if N is omitted
+:continue [RINPUT]  step with RINPUT until a breakpoint holds or it halts, up to 100000 steps (:c)
+:back [N]           go back N steps (1 if omitted) in the history
+:history            the current step and the number of recorded steps
+:reset              create the machine again from the code and ainput
+:save FILE          write the snapshot to FILE
+:load FILE          restore the snapshot in FILE, starting a new history
+:render [json]      draw the current state (the render state JSON with `json`)
+:break COND (:b), :delete N (:d), :watch PATH (:w), :unwatch PATH, :breaks
+:begin ... :end     a multi-line rinput
+:quit               leave (:q, :exit)";
+
+// what the REPL shows after each step and where it records the session
+pub struct Options<'a> {
+    pub source: Option<&'a SourceView>,
+    // draw the state after the snapshot
+    pub render: bool,
+    // session file (see `session`) of the make, steps, `:back`, `:reset` and `:load`
+    pub record: Option<&'a Path>,
+}
+
+// the machine of a REPL session with what is needed to print, reset and record it
+struct Session<'a> {
+    name: String,
+    model: ModelHost,
+    code: String,
+    ainput: String,
+    breakpoints: Breakpoints,
+    source: Option<&'a SourceView>,
+    render: bool,
+    recorder: Option<Recorder>,
+}
+
+pub fn run(
+    name: &str,
+    backend: Backend,
+    limits: WasmLimits,
+    code: Option<&str>,
+    ainput: Option<&str>,
+    options: Options,
+) -> Result<()> {
     let stdin = io::stdin();
     let code = match code {
         Some(code) => code.to_string(),
@@ -56,68 +138,263 @@ pub fn run(name: &str, code: Option<&str>, ainput: Option<&str>) -> Result<()> {
         None => read_ainput(&stdin)?,
     };
 
-    let mut model = ModelHost::load(name)?;
+    let recorder = options.record.map(Recorder::create).transpose()?;
+    let mut model = ModelHost::load_with_limits(name, backend, limits)?;
     model.create(&code, &ainput)?;
-    let snapshot = model.checkpoint()?;
 
     println!("world=model");
     println!("create=ok");
-    println!("snapshot={snapshot}");
-
-    let mut line = String::new();
-    let mut block = String::new();
-    loop {
-        print!("rinput> ");
-        io::stdout().flush().context("flush stdout failed")?;
-
-        line.clear();
-        let read = stdin
-            .read_line(&mut line)
-            .context("failed to read line from stdin")?;
-        if read == 0 {
-            break;
-        }
+    let mut session = Session {
+        name: name.to_string(),
+        model,
+        code,
+        ainput,
+        breakpoints: Breakpoints::new(),
+        source: options.source,
+        render: options.render,
+        recorder,
+    };
+    session.record_make()?;
+    session.show()?;
 
-        let mut rinput = line.trim_end_matches(['\n', '\r']).to_string();
-        if matches!(rinput.as_str(), ":q" | ":quit" | ":exit") {
+    let mut editor = LineEditor::new();
+    while let Some(line) = editor.read_line("rinput> ")? {
+        if matches!(line.as_str(), ":q" | ":quit" | ":exit") {
             break;
         }
-        if rinput == ":begin" {
-            block.clear();
-            loop {
-                print!("...> ");
-                io::stdout().flush().context("flush stdout failed")?;
-
-                line.clear();
-                let read = stdin
-                    .read_line(&mut line)
-                    .context("failed to read line from stdin")?;
-                if read == 0 {
-                    break;
-                }
-
-                let part = line.trim_end_matches(['\n', '\r']);
+        let rinput = if line == ":begin" {
+            let mut block = vec![];
+            while let Some(part) = editor.read_line("...> ")? {
                 if part == ":end" {
                     break;
                 }
-                if !block.is_empty() {
-                    block.push('\n');
-                }
-                block.push_str(part);
+                block.push(part);
+            }
+            block.join("\n")
+        } else if let Some(command) = line.strip_prefix(':') {
+            if let Err(err) = session.command(command) {
+                println!("error={err:#}");
             }
-            rinput = block.clone();
+            continue;
+        } else {
+            line
+        };
+        if let Err(err) = session.step(&rinput) {
+            println!("error={err:#}");
         }
+    }
+
+    Ok(())
+}
 
-        let step = model.step(&rinput)?;
+fn is_halt(step: &str) -> Result<bool> {
+    let parsed: Value = serde_json::from_str(step).context("invalid JSON from step")?;
+    Ok(parsed.get("kind").and_then(Value::as_str) == Some("halt"))
+}
+
+fn print_watches(breakpoints: &Breakpoints, snapshot: &str) -> Result<()> {
+    if breakpoints.watches().is_empty() {
+        return Ok(());
+    }
+    let snapshot: Value = serde_json::from_str(snapshot).context("invalid JSON from snapshot")?;
+    for (path, value) in breakpoints.watched(&snapshot) {
+        match value {
+            Some(value) => println!("watch {path}={value}"),
+            None => println!("watch {path}=<none>"),
+        }
+    }
+    Ok(())
+}
+
+// `N` of `:run N` and `:back N`
+fn count(arg: &str, default: usize) -> Result<usize> {
+    if arg.is_empty() {
+        return Ok(default);
+    }
+    arg.parse().context("expected a number of steps")
+}
+
+impl Session<'_> {
+    fn record(&mut self, event: Event) -> Result<()> {
+        match &mut self.recorder {
+            Some(recorder) => recorder.record(&event),
+            None => Ok(()),
+        }
+    }
+
+    fn record_make(&mut self) -> Result<()> {
+        self.record(Event::Make {
+            model: self.name.clone(),
+            code: self.code.clone(),
+            ainput: self.ainput.clone(),
+        })
+    }
+
+    // `model.step`, recorded with its result when it fails too
+    fn step_model(&mut self, rinput: &str) -> Result<String> {
+        let step = self.model.step(rinput);
+        if self.recorder.is_some() {
+            self.record(Event::Step {
+                rinput: rinput.to_string(),
+                result: step_result(&step),
+            })?;
+        }
+        step
+    }
+
+    // the snapshot, watched values, drawing and source line of the current state
+    fn show(&mut self) -> Result<()> {
+        let snapshot = self.model.checkpoint()?;
+        println!("snapshot={snapshot}");
+        print_watches(&self.breakpoints, &snapshot)?;
+        if self.render {
+            print!(
+                "{}",
+                self.model.render_terminal(&snapshot, terminal_style())?
+            );
+        }
+        if let Some(source) = self.source {
+            source.print(&mut self.model)?;
+        }
+        Ok(())
+    }
+
+    // one step recorded in the history, the machine stays halted until `:back` or `:reset`
+    fn step(&mut self, rinput: &str) -> Result<()> {
+        let step = self.step_model(rinput)?;
         println!("step={step}");
-        let parsed: Value = serde_json::from_str(&step).context("invalid JSON from step")?;
-        if parsed.get("kind").and_then(Value::as_str) == Some("halt") {
+        if is_halt(&step)? {
             println!("halted");
-            break;
+            return Ok(());
+        }
+        self.show()
+    }
+
+    // steps until the machine halts, `fuel` steps are taken or (with `stop_at_breaks`) a breakpoint holds
+    fn run(&mut self, rinput: &str, fuel: usize, stop_at_breaks: bool) -> Result<()> {
+        for steps in 1..=fuel {
+            let step = self.step_model(rinput)?;
+            if is_halt(&step)? {
+                println!("step={step}");
+                println!("halted after {steps} steps");
+                return Ok(());
+            }
+            if !stop_at_breaks {
+                continue;
+            }
+            let snapshot = self.model.checkpoint()?;
+            let parsed: Value =
+                serde_json::from_str(&snapshot).context("invalid JSON from snapshot")?;
+            let model = &mut self.model;
+            let fired = self
+                .breakpoints
+                .fired(&parsed, |name, arg| {
+                    model.hook(name, arg).map_err(|e| format!("{e:#}"))
+                })
+                .map_err(anyhow::Error::msg)?;
+            if !fired.is_empty() {
+                for number in fired {
+                    println!("hit breakpoint {number} after {steps} steps");
+                }
+                return self.show();
+            }
+        }
+        if stop_at_breaks {
+            println!("no breakpoint hit in {fuel} steps");
         } else {
-            println!("snapshot={}", model.checkpoint()?);
+            println!("ran {fuel} steps");
         }
+        self.show()
     }
 
-    Ok(())
+    // the debugger command after `:`
+    fn command(&mut self, command: &str) -> Result<()> {
+        let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+        let arg = arg.trim();
+        let breakpoints = &mut self.breakpoints;
+        match name {
+            "help" | "h" => println!("{COMMANDS}"),
+            "break" | "b" => {
+                let condition = Condition::parse(arg).map_err(anyhow::Error::msg)?;
+                let text = condition.to_string();
+                println!("breakpoint {}: {text}", breakpoints.add(condition));
+            }
+            "delete" | "d" => {
+                let number = arg.parse().context("expected a breakpoint number")?;
+                breakpoints.delete(number).map_err(anyhow::Error::msg)?;
+            }
+            "watch" | "w" => breakpoints.watch(JsonPath::parse(arg).map_err(anyhow::Error::msg)?),
+            "unwatch" => breakpoints.unwatch(&JsonPath::parse(arg).map_err(anyhow::Error::msg)?),
+            "breaks" => {
+                for (number, condition) in breakpoints.breakpoints() {
+                    println!("breakpoint {number}: {condition}");
+                }
+                for path in breakpoints.watches() {
+                    println!("watch {path}");
+                }
+            }
+            "continue" | "c" => self.run(arg, CONTINUE_FUEL, true)?,
+            "run" | "r" => {
+                let (steps, rinput) = arg.split_once(' ').unwrap_or((arg, ""));
+                self.run(rinput.trim(), count(steps, CONTINUE_FUEL)?, false)?
+            }
+            "back" => {
+                let position = self.model.history_position()? as usize;
+                let back = count(arg, 1)?;
+                let Some(target) = position.checked_sub(back) else {
+                    anyhow::bail!("only {position} steps to go back");
+                };
+                self.model.rewind(target as u32)?;
+                self.record(Event::Rewind {
+                    step: target as u32,
+                })?;
+                println!("back to step {target}");
+                self.show()?;
+            }
+            "history" => println!(
+                "history={}/{}",
+                self.model.history_position()?,
+                self.model.history_len()?
+            ),
+            "reset" => {
+                self.model.create(&self.code, &self.ainput)?;
+                self.record_make()?;
+                println!("create=ok");
+                self.show()?;
+            }
+            "save" => {
+                let snapshot = self.model.checkpoint()?;
+                std::fs::write(arg, format!("{snapshot}\n"))
+                    .with_context(|| format!("failed to write {arg}"))?;
+                println!("saved={arg}");
+            }
+            "load" => {
+                let snapshot = std::fs::read_to_string(arg)
+                    .with_context(|| format!("failed to read {arg}"))?;
+                self.model.restore(snapshot.trim())?;
+                let snapshot =
+                    serde_json::from_str(snapshot.trim()).context("invalid JSON from snapshot")?;
+                self.record(Event::Restore { snapshot })?;
+                println!("loaded={arg}");
+                self.show()?;
+            }
+            "render" => {
+                let snapshot = self.model.checkpoint()?;
+                match arg {
+                    "json" => {
+                        let render: Value = serde_json::from_str(&self.model.render(&snapshot)?)
+                            .context("invalid JSON from render")?;
+                        println!("{}", serde_json::to_string_pretty(&render)?);
+                    }
+                    "" => print!(
+                        "{}",
+                        self.model.render_terminal(&snapshot, terminal_style())?
+                    ),
+                    _ => anyhow::bail!("expected :render or :render json"),
+                }
+            }
+            _ => anyhow::bail!("unknown command :{name}, see :help"),
+        }
+        Ok(())
+    }
 }
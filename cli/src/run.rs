@@ -0,0 +1,339 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+
+use utils::history::BatchEnd;
+
+use crate::runtime::ModelHost;
+use crate::sandbox::LimitExceeded;
+
+// step budget of `run` when `--fuel` is not given
+pub const DEFAULT_FUEL: usize = 100_000;
+
+// steps of one `step-n` call, the time limit is checked between them
+// (no more than a batch returns routputs of, so none is dropped)
+const BATCH: usize = utils::wasm_util::BATCH_ROUTPUTS;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    // maximum number of `step` calls
+    pub fuel: usize,
+    // wall clock budget, checked between batches of steps
+    pub time: Option<Duration>,
+}
+
+// how a run ended, the text level version of `utils::run::RunOutcome`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Stop {
+    Halted { foutput: String },
+    OutOfFuel,
+    OutOfTime,
+    InputExhausted,
+    // the `step` call (0-indexed) failed
+    Error { step: usize, message: String },
+    // the `step` call (0-indexed) exceeded a limit of the wasm component
+    Exceeded { step: usize, limit: LimitExceeded },
+}
+
+impl Stop {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stop::Halted { .. } => "halted",
+            Stop::OutOfFuel => "out-of-fuel",
+            Stop::OutOfTime => "out-of-time",
+            Stop::InputExhausted => "input-exhausted",
+            Stop::Error { .. } => "error",
+            Stop::Exceeded { limit, .. } => limit.name(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub stop: Stop,
+    // number of successful steps (a halting step is counted)
+    pub steps: usize,
+    // routputs printing as non-empty text, with their 1-indexed step
+    pub routputs: Vec<(usize, String)>,
+    // the snapshot of the machine when it did not halt
+    pub snapshot: Option<String>,
+    pub elapsed: Duration,
+}
+
+impl Report {
+    pub fn is_halted(&self) -> bool {
+        matches!(self.stop, Stop::Halted { .. })
+    }
+
+    fn stop_name(&self) -> &'static str {
+        self.stop.name()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "stop": self.stop_name(),
+            "steps": self.steps,
+            "elapsed_ms": self.elapsed.as_millis() as u64,
+            "routputs": self
+                .routputs
+                .iter()
+                .map(|(step, routput)| json!({ "step": step, "routput": routput }))
+                .collect::<Vec<_>>(),
+        });
+        match &self.stop {
+            Stop::Halted { foutput } => value["foutput"] = json!(foutput),
+            Stop::Error { step, message } => {
+                value["error"] = json!({ "step": step, "message": message });
+            }
+            Stop::Exceeded { step, limit } => {
+                value["error"] = json!({ "step": step, "message": limit.to_string() });
+            }
+            _ => {}
+        }
+        if let Some(snapshot) = &self.snapshot {
+            value["snapshot"] = serde_json::from_str(snapshot).unwrap_or(json!(snapshot));
+        }
+        value
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (step, routput) in &self.routputs {
+            out.push_str(&format!("routput {step}: {routput}\n"));
+        }
+        match &self.stop {
+            Stop::Halted { foutput } => out.push_str(&format!("foutput: {foutput}\n")),
+            Stop::Error { step, message } => {
+                out.push_str(&format!("error at step {step}: {message}\n"))
+            }
+            Stop::Exceeded { step, limit } => {
+                out.push_str(&format!("error at step {step}: {limit}\n"))
+            }
+            _ => {}
+        }
+        if let Some(snapshot) = &self.snapshot {
+            out.push_str(&format!("snapshot: {snapshot}\n"));
+        }
+        out.push_str(&format!(
+            "{}: {} steps, {} ms\n",
+            self.stop_name(),
+            self.steps,
+            self.elapsed.as_millis()
+        ));
+        out
+    }
+
+    pub fn print(&self, format: Format) -> Result<()> {
+        match format {
+            Format::Text => print!("{}", self.to_text()),
+            Format::Json => println!("{}", serde_json::to_string(&self.to_json())?),
+        }
+        Ok(())
+    }
+}
+
+// the rinputs of `run`: the lines of a script, then `repeat` forever if given
+pub struct Rinputs {
+    script: Vec<String>,
+    repeat: Option<String>,
+}
+
+// without script and `repeat`, the default rinput of the model is repeated
+pub fn rinputs(
+    model: &mut ModelHost,
+    script: Option<&str>,
+    repeat: Option<&str>,
+) -> Result<Rinputs> {
+    let lines = script
+        .map(|script| script.lines().map(str::to_string).collect())
+        .unwrap_or_default();
+    let repeat = match (script, repeat) {
+        (_, Some(repeat)) => Some(repeat.to_string()),
+        (Some(_), None) => None,
+        (None, None) => Some(default_rinput(model)?),
+    };
+    Ok(Rinputs {
+        script: lines,
+        repeat,
+    })
+}
+
+// the rinput the model describes as its default, an error naming the rinput format without one
+pub fn default_rinput(model: &mut ModelHost) -> Result<String> {
+    let description = model.describe()?;
+    description.default_rinput.ok_or_else(|| {
+        anyhow!(
+            "model {} has no default rinput, give one with --rinput or --rinputs ({})",
+            description.name,
+            description.rinput_format
+        )
+    })
+}
+
+impl Rinputs {
+    // the rinputs in order, endless if there is a repeated one
+    pub fn lines(self) -> impl Iterator<Item = String> {
+        let repeat = self.repeat;
+        self.script
+            .into_iter()
+            .chain(std::iter::from_fn(move || repeat.clone()))
+    }
+}
+
+// step the created machine until it halts or a limit is reached
+// script lines are stepped one by one, the repeated rinput in `step-n` batches
+pub fn run(model: &mut ModelHost, rinputs: Rinputs, limits: Limits) -> Result<Report> {
+    let start = Instant::now();
+    let mut script = rinputs.script.into_iter();
+    let mut routputs = Vec::new();
+    let mut steps = 0;
+    let stop = loop {
+        if steps >= limits.fuel {
+            break Stop::OutOfFuel;
+        }
+        if limits.time.is_some_and(|time| start.elapsed() >= time) {
+            break Stop::OutOfTime;
+        }
+        let (rinput, count) = match (script.next(), &rinputs.repeat) {
+            (Some(line), _) => (line, 1),
+            (None, Some(repeat)) => (repeat.clone(), (limits.fuel - steps).min(BATCH)),
+            (None, None) => break Stop::InputExhausted,
+        };
+        let batch = match model.step_n(count as u32, &rinput) {
+            Ok(batch) => batch,
+            Err(err) => {
+                if let Some(limit) = err.downcast_ref::<LimitExceeded>() {
+                    break Stop::Exceeded {
+                        step: steps,
+                        limit: *limit,
+                    };
+                }
+                break Stop::Error {
+                    step: steps,
+                    message: format!("{err:#}"),
+                };
+            }
+        };
+        routputs.extend(
+            batch
+                .routputs
+                .into_iter()
+                .map(|(step, routput)| (steps + step, routput)),
+        );
+        steps += batch.steps;
+        match batch.end {
+            BatchEnd::Running => {}
+            BatchEnd::Halted(foutput) => break Stop::Halted { foutput },
+            BatchEnd::Failed(message) => {
+                break Stop::Error {
+                    step: steps,
+                    message: format!("model step: {message}"),
+                }
+            }
+        }
+    };
+    // the instance trapped when a limit was exceeded and can not take a snapshot
+    let snapshot = match stop {
+        Stop::Halted { .. } | Stop::Exceeded { .. } => None,
+        _ => Some(model.checkpoint()?),
+    };
+    Ok(Report {
+        stop,
+        steps,
+        routputs,
+        snapshot,
+        elapsed: start.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::Backend;
+
+    fn run_counter(
+        code: &str,
+        script: Option<&str>,
+        repeat: Option<&str>,
+        limits: Limits,
+    ) -> Report {
+        let mut model = ModelHost::load("example_counter", Backend::Native).unwrap();
+        model.create(code, "").unwrap();
+        let rinputs = rinputs(&mut model, script, repeat).unwrap();
+        run(&mut model, rinputs, limits).unwrap()
+    }
+
+    #[test]
+    fn runs_until_halt_with_repeated_rinput() {
+        let limits = Limits {
+            fuel: DEFAULT_FUEL,
+            time: None,
+        };
+        let report = run_counter("7", None, Some("inc"), limits);
+        assert_eq!(
+            report.stop,
+            Stop::Halted {
+                foutput: "End".into()
+            }
+        );
+        assert_eq!(report.steps, 3);
+        assert_eq!(report.routputs, [(1, "inc".into()), (2, "inc".into())]);
+        assert_eq!(report.to_json()["foutput"], "End");
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_script_or_the_fuel() {
+        let limits = Limits {
+            fuel: 2,
+            time: None,
+        };
+        let report = run_counter("5", Some("dec"), None, limits);
+        assert_eq!(report.stop, Stop::InputExhausted);
+        assert_eq!(report.snapshot.as_deref(), Some(r#"{"count":4}"#));
+
+        let report = run_counter("5", Some("inc\ndec\ninc"), None, limits);
+        assert_eq!(report.stop, Stop::OutOfFuel);
+        assert!(report.to_text().contains("out-of-fuel: 2 steps"));
+
+        let report = run_counter("0", None, Some("dec"), limits);
+        assert!(matches!(report.stop, Stop::Error { step: 0, .. }));
+    }
+
+    #[test]
+    fn batches_keep_counting_the_steps() {
+        let limits = Limits {
+            fuel: 25_000,
+            time: None,
+        };
+        // the second batch fails when the count reaches 0
+        let report = run_counter("12000", Some("dec\ndec"), Some("dec"), limits);
+        assert!(matches!(report.stop, Stop::Error { step: 12_000, .. }));
+        assert_eq!(report.steps, 12_000);
+        assert_eq!(report.routputs.len(), 12_000);
+        assert_eq!(report.routputs[2], (3, "dec".into()));
+        assert_eq!(report.snapshot.as_deref(), Some(r#"{"count":0}"#));
+    }
+
+    #[test]
+    fn repeats_the_default_rinput_of_the_model() {
+        let limits = Limits {
+            fuel: DEFAULT_FUEL,
+            time: None,
+        };
+        let report = run_counter("8", None, None, limits);
+        assert_eq!(report.steps, 2);
+
+        let mut model = ModelHost::load("coroutine", Backend::Native).unwrap();
+        let error = rinputs(&mut model, None, None).err().unwrap();
+        assert!(error.to_string().contains("no default rinput"));
+        assert!(rinputs(&mut model, Some("0"), None).is_ok());
+    }
+}
@@ -1,8 +1,18 @@
+use std::io::IsTerminal;
 use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
+use utils::diagnostic::{Diagnostic, Severity, Span};
+use utils::dynamic::{DynCompiler, DynInterpreter, DynMachine};
+use utils::history::BatchEnd;
+use utils::render_terminal::{render_terminal, TerminalStyle};
+use utils::source_map::SourceMapping;
+use utils::wasm_util::{Batch, BoundaryError};
+use utils::{describe::Example, CompilerDescription, Description, RenderState};
 use wasmtime::component::{Component, Linker};
-use wasmtime::{Config, Engine, Store};
+use wasmtime::Store;
+
+use crate::sandbox::{new_engine, GuardedStore, HostState, WasmLimits};
 
 mod model_bindings {
     wasmtime::component::bindgen!({
@@ -15,6 +25,19 @@ mod compiler_bindings {
     wasmtime::component::bindgen!({
         path: "../utils/wit",
         world: "compiler",
+        with: {
+            "computation:web/types": crate::runtime::model_bindings::computation::web::types,
+        },
+    });
+}
+
+mod interpreter_bindings {
+    wasmtime::component::bindgen!({
+        path: "../utils/wit",
+        world: "interpreter",
+        with: {
+            "computation:web/types": crate::runtime::model_bindings::computation::web::types,
+        },
     });
 }
 
@@ -31,130 +54,630 @@ fn component_directory(workspace: &Path) -> PathBuf {
         .unwrap_or_else(|| workspace.join(".repo").join("generated").join("build-wasm"))
 }
 
-fn new_engine() -> Result<Engine> {
-    let mut config = Config::new();
-    config.wasm_component_model(true);
-    Engine::new(&config).context("failed to initialize wasmtime engine")
+type WitDiagnostic = model_bindings::computation::web::types::Diagnostic;
+
+// `sources` are the arguments of the call, used to show the line a diagnostic points to
+fn expect_ok<T>(
+    label: &str,
+    value: std::result::Result<T, WitDiagnostic>,
+    sources: &[(&str, &str)],
+) -> Result<T> {
+    value.map_err(|e| anyhow::anyhow!("{label}: {}", describe_diagnostic(e, sources)))
 }
 
-fn expect_ok<T>(label: &str, value: std::result::Result<T, String>) -> Result<T> {
-    value.map_err(|e| anyhow::anyhow!("{label}: {e}"))
+fn describe_diagnostic(diagnostic: WitDiagnostic, sources: &[(&str, &str)]) -> String {
+    use model_bindings::computation::web::types::Severity as WitSeverity;
+    let line_col = diagnostic
+        .span
+        .map(|span| (span.line as usize, span.column as usize));
+    let diagnostic_origin = diagnostic.origin;
+    let diagnostic = Diagnostic {
+        severity: match diagnostic.severity {
+            WitSeverity::Error => Severity::Error,
+            WitSeverity::Warning => Severity::Warning,
+        },
+        message: diagnostic.message,
+        span: diagnostic
+            .span
+            .map(|span| Span::new(span.start as usize, span.end as usize)),
+    };
+    describe(diagnostic, &diagnostic_origin, line_col, sources)
+}
+
+// the same as `expect_ok` for the native backend
+fn native_ok<T>(
+    label: &str,
+    value: std::result::Result<T, BoundaryError>,
+    sources: &[(&str, &str)],
+) -> Result<T> {
+    value.map_err(|e| {
+        let description = describe(e.diagnostic, e.origin, e.line_col, sources);
+        anyhow::anyhow!("{label}: {description}")
+    })
+}
+
+fn describe(
+    diagnostic: Diagnostic,
+    origin: &str,
+    line_col: Option<(usize, usize)>,
+    sources: &[(&str, &str)],
+) -> String {
+    let (Some(_), Some((line, column))) = (diagnostic.span, line_col) else {
+        return diagnostic.message;
+    };
+    let source = sources
+        .iter()
+        .find(|(name, _)| *name == origin)
+        .map(|(_, source)| *source);
+    match source {
+        Some(source) => diagnostic.render_named(origin, source),
+        None => format!("{origin}:{line}:{column}: {}", diagnostic.message),
+    }
+}
+
+// where models, compilers and interpreters are loaded from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Backend {
+    // prebuilt components in the component directory
+    #[default]
+    Wasm,
+    // the model crates linked into the cli (see `registry`)
+    Native,
+}
+
+fn instantiate<T>(
+    name: &str,
+    limits: WasmLimits,
+    instantiate: impl FnOnce(
+        &mut Store<HostState>,
+        &Component,
+        &Linker<HostState>,
+    ) -> wasmtime::Result<T>,
+    world: &str,
+) -> Result<(GuardedStore, Box<T>)> {
+    let component_path = resolve_component_path(name);
+    if !component_path.exists() {
+        anyhow::bail!("component not found: {}", component_path.display());
+    }
+
+    let engine = new_engine(limits)?;
+    let component = Component::from_file(&engine, &component_path)
+        .with_context(|| format!("failed to load component: {}", component_path.display()))?;
+    let linker = Linker::new(&engine);
+    let mut store = GuardedStore::new(&engine, limits);
+    let instance = store
+        .call(|store| instantiate(store, &component, &linker))
+        .with_context(|| format!("instantiate as {world} failed"))?;
+    Ok((store, Box::new(instance)))
+}
+
+// colours and box drawing when stdout is a terminal and `NO_COLOR` is not set
+pub fn terminal_style() -> TerminalStyle {
+    if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+        TerminalStyle::Ansi
+    } else {
+        TerminalStyle::Plain
+    }
+}
+
+fn not_registered(name: &str) -> anyhow::Error {
+    anyhow::anyhow!("no native model named {name}")
 }
 
 pub struct ModelHost {
-    store: Store<()>,
-    instance: model_bindings::Model,
+    backend: ModelBackend,
+}
+
+enum ModelBackend {
+    Wasm {
+        store: GuardedStore,
+        instance: Box<model_bindings::Model>,
+    },
+    Native(Box<dyn DynMachine>),
 }
 
 impl ModelHost {
-    pub fn load(name: &str) -> Result<Self> {
-        let component_path = resolve_component_path(name);
-        if !component_path.exists() {
-            anyhow::bail!("component not found: {}", component_path.display());
-        }
+    pub fn load(name: &str, backend: Backend) -> Result<Self> {
+        Self::load_with_limits(name, backend, WasmLimits::default())
+    }
+
+    // `limits` bound every call into the wasm component
+    pub fn load_with_limits(name: &str, backend: Backend, limits: WasmLimits) -> Result<Self> {
+        let backend = match backend {
+            Backend::Wasm => {
+                let (store, instance) = instantiate(
+                    name,
+                    limits,
+                    |store, component, linker| {
+                        model_bindings::Model::instantiate(store, component, linker)
+                    },
+                    "model",
+                )?;
+                ModelBackend::Wasm { store, instance }
+            }
+            Backend::Native => ModelBackend::Native(
+                crate::registry::machine(name).ok_or_else(|| not_registered(name))?,
+            ),
+        };
+        Ok(Self { backend })
+    }
 
-        let engine = new_engine()?;
-        let component = Component::from_file(&engine, &component_path)
-            .with_context(|| format!("failed to load component: {}", component_path.display()))?;
-        let linker = Linker::new(&engine);
-        let mut store = Store::new(&engine, ());
-        let instance = model_bindings::Model::instantiate(&mut store, &component, &linker)
-            .context("instantiate as model failed")?;
-        Ok(Self { store, instance })
+    // the name, formats and examples the model reports about itself
+    pub fn describe(&mut self) -> Result<Description> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let description = store
+                    .call(|store| instance.call_describe(store))
+                    .context("model describe failed")?;
+                Ok(Description {
+                    name: description.name,
+                    code_format: description.code_format,
+                    ainput_format: description.ainput_format,
+                    rinput_format: description.rinput_format,
+                    default_rinput: description.default_rinput,
+                    examples: description
+                        .examples
+                        .into_iter()
+                        .map(|example| Example {
+                            name: example.name,
+                            code: example.code,
+                            ainput: example.ainput,
+                        })
+                        .collect(),
+                })
+            }
+            ModelBackend::Native(machine) => Ok(machine.describe()),
+        }
     }
 
     pub fn create(&mut self, code: &str, ainput: &str) -> Result<()> {
-        let out = self
-            .instance
-            .call_make(&mut self.store, code, ainput)
-            .context("model make failed")?;
-        expect_ok("model make", out)
+        let sources = [("code", code), ("ainput", ainput)];
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_make(store, code, ainput))
+                    .context("model make failed")?;
+                expect_ok("model make", out, &sources)
+            }
+            ModelBackend::Native(machine) => {
+                native_ok("model make", machine.make(code, ainput), &sources)
+            }
+        }
     }
 
     pub fn step(&mut self, rinput: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_step(&mut self.store, rinput)
-            .context("model step failed")?;
-        expect_ok("model step", out)
+        let sources = [("rinput", rinput)];
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_step(store, rinput))
+                    .context("model step failed")?;
+                expect_ok("model step", out, &sources)
+            }
+            ModelBackend::Native(machine) => {
+                native_ok("model step", machine.step(rinput), &sources)
+            }
+        }
+    }
+
+    // `count` steps with the same rinput in one call, recorded as one history entry
+    pub fn step_n(&mut self, count: u32, rinput: &str) -> Result<Batch> {
+        let sources = [("rinput", rinput)];
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                use model_bindings::computation::web::types::BatchEnd as WitBatchEnd;
+                let out = store
+                    .call(|store| instance.call_step_n(store, count, rinput))
+                    .context("model step-n failed")?;
+                let batch = expect_ok("model step-n", out, &sources)?;
+                Ok(Batch {
+                    end: match batch.end {
+                        WitBatchEnd::Running => BatchEnd::Running,
+                        WitBatchEnd::Halted(foutput) => BatchEnd::Halted(foutput),
+                        WitBatchEnd::Failed(diagnostic) => {
+                            BatchEnd::Failed(describe_diagnostic(diagnostic, &sources))
+                        }
+                    },
+                    steps: batch.steps as usize,
+                    routputs: batch
+                        .routputs
+                        .into_iter()
+                        .map(|(step, routput)| (step as usize, routput))
+                        .collect(),
+                    last_routput: batch.last_routput,
+                })
+            }
+            ModelBackend::Native(machine) => {
+                native_ok("model step-n", machine.step_n(count, rinput), &sources)
+            }
+        }
     }
 
     pub fn checkpoint(&mut self) -> Result<String> {
-        let out = self
-            .instance
-            .call_snapshot(&mut self.store)
-            .context("model snapshot failed")?;
-        expect_ok("model snapshot", out)
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_snapshot(store))
+                    .context("model snapshot failed")?;
+                expect_ok("model snapshot", out, &[])
+            }
+            ModelBackend::Native(machine) => native_ok("model snapshot", machine.snapshot(), &[]),
+        }
     }
 
     pub fn restore(&mut self, snapshot: &str) -> Result<()> {
-        let out = self
-            .instance
-            .call_restore(&mut self.store, snapshot)
-            .context("model restore failed")?;
-        expect_ok("model restore", out)
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_restore(store, snapshot))
+                    .context("model restore failed")?;
+                expect_ok("model restore", out, &[])
+            }
+            ModelBackend::Native(machine) => {
+                native_ok("model restore", machine.restore(snapshot), &[])
+            }
+        }
+    }
+
+    // the render state JSON of a snapshot
+    pub fn render(&mut self, snapshot: &str) -> Result<String> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_render(store, snapshot))
+                    .context("model render failed")?;
+                expect_ok("model render", out, &[])
+            }
+            ModelBackend::Native(machine) => {
+                native_ok("model render", machine.render(snapshot), &[])
+            }
+        }
+    }
+
+    // the render state of a snapshot laid out for the terminal
+    pub fn render_terminal(&mut self, snapshot: &str, style: TerminalStyle) -> Result<String> {
+        let state: RenderState =
+            serde_json::from_str(&self.render(snapshot)?).context("invalid render state JSON")?;
+        Ok(render_terminal(&state, style))
+    }
+
+    // move to the state after `step` steps of the recorded history
+    pub fn rewind(&mut self, step: u32) -> Result<()> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_rewind(store, step))
+                    .context("model rewind failed")?;
+                expect_ok("model rewind", out, &[])
+            }
+            ModelBackend::Native(machine) => native_ok("model rewind", machine.rewind(step), &[]),
+        }
+    }
+
+    pub fn history_len(&mut self) -> Result<u32> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => store
+                .call(|store| instance.call_history_len(store))
+                .context("model history-len failed"),
+            ModelBackend::Native(machine) => Ok(machine.history_len()),
+        }
+    }
+
+    pub fn history_position(&mut self) -> Result<u32> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => store
+                .call(|store| instance.call_history_position(store))
+                .context("model history-position failed"),
+            ModelBackend::Native(machine) => Ok(machine.history_position()),
+        }
+    }
+
+    // evaluate the model specific breakpoint condition `@name arg`
+    pub fn hook(&mut self, name: &str, arg: &str) -> Result<bool> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_hook(store, name, arg))
+                    .context("model hook failed")?;
+                expect_ok("model hook", out, &[])
+            }
+            ModelBackend::Native(machine) => native_ok("model hook", machine.hook(name, arg), &[]),
+        }
+    }
+
+    // the location of the current state named as in source maps, `None` if the model has none
+    pub fn location(&mut self) -> Result<Option<String>> {
+        match &mut self.backend {
+            ModelBackend::Wasm { store, instance } => store
+                .call(|store| instance.call_location(store))
+                .context("model location failed"),
+            ModelBackend::Native(machine) => Ok(machine.location()),
+        }
     }
 }
 
 pub struct CompilerHost {
-    store: Store<()>,
-    instance: compiler_bindings::Compiler,
+    backend: CompilerBackend,
+}
+
+enum CompilerBackend {
+    Wasm {
+        store: GuardedStore,
+        instance: Box<compiler_bindings::Compiler>,
+    },
+    Native(Box<dyn DynCompiler>),
 }
 
 impl CompilerHost {
-    pub fn load(name: &str) -> Result<Self> {
-        let component_path = resolve_component_path(name);
-        if !component_path.exists() {
-            anyhow::bail!("component not found: {}", component_path.display());
-        }
+    pub fn load(name: &str, backend: Backend) -> Result<Self> {
+        Self::load_with_limits(name, backend, WasmLimits::default())
+    }
 
-        let engine = new_engine()?;
-        let component = Component::from_file(&engine, &component_path)
-            .with_context(|| format!("failed to load component: {}", component_path.display()))?;
-        let linker = Linker::new(&engine);
-        let mut store = Store::new(&engine, ());
-        let instance = compiler_bindings::Compiler::instantiate(&mut store, &component, &linker)
-            .context("instantiate as compiler failed")?;
-        Ok(Self { store, instance })
+    // `limits` bound every call into the wasm component
+    pub fn load_with_limits(name: &str, backend: Backend, limits: WasmLimits) -> Result<Self> {
+        let backend = match backend {
+            Backend::Wasm => {
+                let (store, instance) = instantiate(
+                    name,
+                    limits,
+                    |store, component, linker| {
+                        compiler_bindings::Compiler::instantiate(store, component, linker)
+                    },
+                    "compiler",
+                )?;
+                CompilerBackend::Wasm { store, instance }
+            }
+            Backend::Native => CompilerBackend::Native(
+                crate::registry::compiler(name).ok_or_else(|| not_registered(name))?,
+            ),
+        };
+        Ok(Self { backend })
+    }
+
+    pub fn describe(&mut self) -> Result<CompilerDescription> {
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let description = store
+                    .call(|store| instance.call_describe(store))
+                    .context("compiler describe failed")?;
+                Ok(CompilerDescription {
+                    name: description.name,
+                    source: description.source,
+                    target: description.target,
+                })
+            }
+            CompilerBackend::Native(compiler) => Ok(compiler.describe()),
+        }
     }
 
     pub fn compile_code(&mut self, code: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_compile_code(&mut self.store, code)
-            .context("compiler compile-code failed")?;
-        expect_ok("compiler compile-code", out)
+        let sources = [("code", code)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_compile_code(store, code))
+                    .context("compiler compile-code failed")?;
+                expect_ok("compiler compile-code", out, &sources)
+            }
+            CompilerBackend::Native(compiler) => native_ok(
+                "compiler compile-code",
+                compiler.compile_code(code),
+                &sources,
+            ),
+        }
+    }
+
+    // locations of the compiled code with the spans of their statements in `code`
+    pub fn source_map(&mut self, code: &str) -> Result<Vec<SourceMapping>> {
+        let sources = [("code", code)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_source_map(store, code))
+                    .context("compiler source-map failed")?;
+                let mappings = expect_ok("compiler source-map", out, &sources)?;
+                Ok(mappings
+                    .into_iter()
+                    .map(|mapping| SourceMapping {
+                        target: mapping.target,
+                        span: Span::new(mapping.span.start as usize, mapping.span.end as usize),
+                    })
+                    .collect())
+            }
+            CompilerBackend::Native(compiler) => {
+                native_ok("compiler source-map", compiler.source_map(code), &sources)
+            }
+        }
     }
 
     pub fn compile_ainput(&mut self, ainput: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_encode_ainput(&mut self.store, ainput)
-            .context("compiler encode-ainput failed")?;
-        expect_ok("compiler encode-ainput", out)
+        let sources = [("ainput", ainput)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_encode_ainput(store, ainput))
+                    .context("compiler encode-ainput failed")?;
+                expect_ok("compiler encode-ainput", out, &sources)
+            }
+            CompilerBackend::Native(compiler) => native_ok(
+                "compiler encode-ainput",
+                compiler.encode_ainput(ainput),
+                &sources,
+            ),
+        }
     }
 
     pub fn compile_rinput(&mut self, rinput: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_encode_rinput(&mut self.store, rinput)
-            .context("compiler encode-rinput failed")?;
-        expect_ok("compiler encode-rinput", out)
+        let sources = [("rinput", rinput)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_encode_rinput(store, rinput))
+                    .context("compiler encode-rinput failed")?;
+                expect_ok("compiler encode-rinput", out, &sources)
+            }
+            CompilerBackend::Native(compiler) => native_ok(
+                "compiler encode-rinput",
+                compiler.encode_rinput(rinput),
+                &sources,
+            ),
+        }
     }
 
     pub fn decode_routput(&mut self, output: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_decode_routput(&mut self.store, output)
-            .context("compiler decode-routput failed")?;
-        expect_ok("compiler decode-routput", out)
+        let sources = [("output", output)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_decode_routput(store, output))
+                    .context("compiler decode-routput failed")?;
+                expect_ok("compiler decode-routput", out, &sources)
+            }
+            CompilerBackend::Native(compiler) => native_ok(
+                "compiler decode-routput",
+                compiler.decode_routput(output),
+                &sources,
+            ),
+        }
     }
 
     pub fn decode_foutput(&mut self, output: &str) -> Result<String> {
-        let out = self
-            .instance
-            .call_decode_foutput(&mut self.store, output)
-            .context("compiler decode-foutput failed")?;
-        expect_ok("compiler decode-foutput", out)
+        let sources = [("output", output)];
+        match &mut self.backend {
+            CompilerBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_decode_foutput(store, output))
+                    .context("compiler decode-foutput failed")?;
+                expect_ok("compiler decode-foutput", out, &sources)
+            }
+            CompilerBackend::Native(compiler) => native_ok(
+                "compiler decode-foutput",
+                compiler.decode_foutput(output),
+                &sources,
+            ),
+        }
+    }
+}
+
+pub struct InterpreterHost {
+    backend: InterpreterBackend,
+}
+
+enum InterpreterBackend {
+    Wasm {
+        store: GuardedStore,
+        instance: Box<interpreter_bindings::Interpreter>,
+    },
+    Native(Box<dyn DynInterpreter>),
+}
+
+impl InterpreterHost {
+    pub fn load(name: &str, backend: Backend) -> Result<Self> {
+        Self::load_with_limits(name, backend, WasmLimits::default())
+    }
+
+    // `limits` bound every call into the wasm component
+    pub fn load_with_limits(name: &str, backend: Backend, limits: WasmLimits) -> Result<Self> {
+        let backend = match backend {
+            Backend::Wasm => {
+                let (store, instance) = instantiate(
+                    name,
+                    limits,
+                    |store, component, linker| {
+                        interpreter_bindings::Interpreter::instantiate(store, component, linker)
+                    },
+                    "interpreter",
+                )?;
+                InterpreterBackend::Wasm { store, instance }
+            }
+            Backend::Native => InterpreterBackend::Native(
+                crate::registry::interpreter(name).ok_or_else(|| not_registered(name))?,
+            ),
+        };
+        Ok(Self { backend })
+    }
+
+    pub fn interpreter_code(&mut self) -> Result<String> {
+        match &mut self.backend {
+            InterpreterBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_interpreter_code(store))
+                    .context("interpreter interpreter-code failed")?;
+                expect_ok("interpreter interpreter-code", out, &[])
+            }
+            InterpreterBackend::Native(interpreter) => native_ok(
+                "interpreter interpreter-code",
+                interpreter.interpreter_code(),
+                &[],
+            ),
+        }
+    }
+
+    pub fn encode_ainput(&mut self, code: &str, ainput: &str) -> Result<String> {
+        let sources = [("code", code), ("ainput", ainput)];
+        match &mut self.backend {
+            InterpreterBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_encode_ainput(store, code, ainput))
+                    .context("interpreter encode-ainput failed")?;
+                expect_ok("interpreter encode-ainput", out, &sources)
+            }
+            InterpreterBackend::Native(interpreter) => native_ok(
+                "interpreter encode-ainput",
+                interpreter.encode_ainput(code, ainput),
+                &sources,
+            ),
+        }
+    }
+
+    pub fn encode_rinput(&mut self, rinput: &str) -> Result<String> {
+        let sources = [("rinput", rinput)];
+        match &mut self.backend {
+            InterpreterBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_encode_rinput(store, rinput))
+                    .context("interpreter encode-rinput failed")?;
+                expect_ok("interpreter encode-rinput", out, &sources)
+            }
+            InterpreterBackend::Native(interpreter) => native_ok(
+                "interpreter encode-rinput",
+                interpreter.encode_rinput(rinput),
+                &sources,
+            ),
+        }
+    }
+
+    pub fn decode_routput(&mut self, output: &str) -> Result<String> {
+        let sources = [("output", output)];
+        match &mut self.backend {
+            InterpreterBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_decode_routput(store, output))
+                    .context("interpreter decode-routput failed")?;
+                expect_ok("interpreter decode-routput", out, &sources)
+            }
+            InterpreterBackend::Native(interpreter) => native_ok(
+                "interpreter decode-routput",
+                interpreter.decode_routput(output),
+                &sources,
+            ),
+        }
+    }
+
+    pub fn decode_foutput(&mut self, output: &str) -> Result<String> {
+        let sources = [("output", output)];
+        match &mut self.backend {
+            InterpreterBackend::Wasm { store, instance } => {
+                let out = store
+                    .call(|store| instance.call_decode_foutput(store, output))
+                    .context("interpreter decode-foutput failed")?;
+                expect_ok("interpreter decode-foutput", out, &sources)
+            }
+            InterpreterBackend::Native(interpreter) => native_ok(
+                "interpreter decode-foutput",
+                interpreter.decode_foutput(output),
+                &sources,
+            ),
+        }
     }
 }
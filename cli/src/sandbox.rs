@@ -0,0 +1,272 @@
+use std::fmt;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use wasmtime::{Config, Engine, ResourceLimiter, Store, Trap};
+
+// limits of each call into a wasm component, `None` is unlimited
+// the native backend runs in the cli process and is not limited
+#[derive(clap::Args, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WasmLimits {
+    // fuel of one call, roughly the number of executed wasm instructions
+    #[arg(
+        id = "wasm_fuel",
+        long = "wasm-fuel",
+        value_name = "FUEL",
+        global = true
+    )]
+    pub fuel: Option<u64>,
+    // bytes of linear memory the instance may grow to
+    #[arg(
+        id = "wasm_memory",
+        long = "wasm-memory",
+        value_name = "BYTES",
+        global = true
+    )]
+    pub memory: Option<usize>,
+    // wall-clock time of one call
+    #[arg(id = "wasm_timeout", long = "wasm-timeout", value_name = "MS", value_parser = parse_millis, global = true)]
+    pub timeout: Option<Duration>,
+}
+
+fn parse_millis(text: &str) -> Result<Duration, String> {
+    text.parse()
+        .map(Duration::from_millis)
+        .map_err(|e| format!("{text} is not milliseconds: {e}"))
+}
+
+// why a call was stopped, found by `downcast_ref` on the error of the call
+// after a limit is exceeded the instance traps and can not be called again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    Fuel(u64),
+    Memory(usize),
+    Timeout(Duration),
+}
+
+impl LimitExceeded {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LimitExceeded::Fuel(_) => "wasm-fuel",
+            LimitExceeded::Memory(_) => "wasm-memory",
+            LimitExceeded::Timeout(_) => "wasm-timeout",
+        }
+    }
+}
+
+impl fmt::Display for LimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitExceeded::Fuel(fuel) => write!(f, "fuel limit of {fuel} exhausted"),
+            LimitExceeded::Memory(bytes) => write!(f, "memory limit of {bytes} bytes exceeded"),
+            LimitExceeded::Timeout(time) => {
+                write!(f, "time limit of {} ms exceeded", time.as_millis())
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+pub fn new_engine(limits: WasmLimits) -> Result<Engine> {
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    config.consume_fuel(limits.fuel.is_some());
+    config.epoch_interruption(limits.timeout.is_some());
+    Engine::new(&config).context("failed to initialize wasmtime engine")
+}
+
+pub struct HostState {
+    memory: Option<usize>,
+}
+
+impl ResourceLimiter for HostState {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        match self.memory {
+            Some(limit) if desired > limit => Err(LimitExceeded::Memory(limit).into()),
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> wasmtime::Result<bool> {
+        Ok(true)
+    }
+}
+
+// a store whose calls (instantiation included) run under the limits
+// the engine must come from `new_engine` with the same limits
+pub struct GuardedStore {
+    store: Store<HostState>,
+    limits: WasmLimits,
+}
+
+impl GuardedStore {
+    pub fn new(engine: &Engine, limits: WasmLimits) -> Self {
+        let mut store = Store::new(
+            engine,
+            HostState {
+                memory: limits.memory,
+            },
+        );
+        store.limiter(|state| state);
+        GuardedStore { store, limits }
+    }
+
+    pub fn call<R>(
+        &mut self,
+        f: impl FnOnce(&mut Store<HostState>) -> wasmtime::Result<R>,
+    ) -> Result<R> {
+        if let Some(fuel) = self.limits.fuel {
+            self.store.set_fuel(fuel)?;
+        }
+        let Some(timeout) = self.limits.timeout else {
+            return f(&mut self.store).map_err(|e| self.classify(e));
+        };
+        // the timer bumps the epoch unless the call ends first and drops `done`
+        self.store.set_epoch_deadline(1);
+        let engine = self.store.engine().clone();
+        let (done, finished) = mpsc::channel::<()>();
+        let timer = std::thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                engine.increment_epoch();
+            }
+        });
+        let out = f(&mut self.store);
+        drop(done);
+        let _ = timer.join();
+        out.map_err(|e| self.classify(e))
+    }
+
+    fn classify(&self, error: anyhow::Error) -> anyhow::Error {
+        let limit = match error.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => self.limits.fuel.map(LimitExceeded::Fuel),
+            Some(Trap::Interrupt) => self.limits.timeout.map(LimitExceeded::Timeout),
+            _ => None,
+        };
+        match limit {
+            Some(limit) => error.context(limit),
+            None => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::{Instance, Module};
+
+    const WAT: &str = r#"
+        (module
+          (memory 1)
+          (func (export "spin") (loop $l (br $l)))
+          (func (export "grow") (param i32) (result i32)
+            (memory.grow (local.get 0)))
+          (func (export "add") (param i32 i32) (result i32)
+            (i32.add (local.get 0) (local.get 1))))
+    "#;
+
+    fn instantiate(limits: WasmLimits) -> (GuardedStore, Instance) {
+        let engine = new_engine(limits).unwrap();
+        let module = Module::new(&engine, WAT).unwrap();
+        let mut store = GuardedStore::new(&engine, limits);
+        let instance = store
+            .call(|store| Instance::new(store, &module, &[]))
+            .unwrap();
+        (store, instance)
+    }
+
+    fn call<P, R>(limits: WasmLimits, name: &str, params: P) -> Result<R>
+    where
+        P: wasmtime::WasmParams,
+        R: wasmtime::WasmResults,
+    {
+        let (mut store, instance) = instantiate(limits);
+        let func = store.call(|store| instance.get_typed_func::<P, R>(store, name))?;
+        store.call(|store| func.call(store, params))
+    }
+
+    #[test]
+    fn flags_are_apart_from_the_run_options() {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Cli {
+            #[command(flatten)]
+            limits: WasmLimits,
+            #[arg(long)]
+            fuel: usize,
+        }
+        let cli = Cli::try_parse_from([
+            "cli",
+            "--fuel",
+            "3",
+            "--wasm-fuel",
+            "5",
+            "--wasm-timeout",
+            "7",
+        ])
+        .unwrap();
+        assert_eq!(cli.fuel, 3);
+        assert_eq!(
+            cli.limits,
+            WasmLimits {
+                fuel: Some(5),
+                memory: None,
+                timeout: Some(Duration::from_millis(7)),
+            }
+        );
+    }
+
+    fn limit(error: anyhow::Error) -> Option<LimitExceeded> {
+        error.downcast_ref::<LimitExceeded>().copied()
+    }
+
+    #[test]
+    fn calls_within_the_limits_succeed() {
+        let limits = WasmLimits {
+            fuel: Some(1_000),
+            memory: Some(4 << 16),
+            timeout: Some(Duration::from_secs(10)),
+        };
+        assert_eq!(call::<_, i32>(limits, "add", (2, 2)).unwrap(), 4);
+        assert_eq!(call::<_, i32>(limits, "grow", 1).unwrap(), 1);
+    }
+
+    #[test]
+    fn exceeded_limits_are_distinct_errors() {
+        let fuel = WasmLimits {
+            fuel: Some(1_000),
+            ..WasmLimits::default()
+        };
+        let error = call::<(), ()>(fuel, "spin", ()).unwrap_err();
+        assert_eq!(limit(error), Some(LimitExceeded::Fuel(1_000)));
+
+        let timeout = WasmLimits {
+            timeout: Some(Duration::from_millis(50)),
+            ..WasmLimits::default()
+        };
+        let error = call::<(), ()>(timeout, "spin", ()).unwrap_err();
+        assert_eq!(
+            limit(error),
+            Some(LimitExceeded::Timeout(Duration::from_millis(50)))
+        );
+
+        let memory = WasmLimits {
+            memory: Some(2 << 16),
+            ..WasmLimits::default()
+        };
+        let error = call::<_, i32>(memory, "grow", 4).unwrap_err();
+        assert_eq!(limit(error), Some(LimitExceeded::Memory(2 << 16)));
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::runtime::{Backend, ModelHost};
+use crate::sandbox::WasmLimits;
+
+// a recorded session is a JSON lines file, one event per line:
+//   {"event":"make","model":NAME,"code":CODE,"ainput":AINPUT}
+//   {"event":"step","rinput":RINPUT,"result":{"kind":"continue","routput":..}}
+//   {"event":"step","rinput":RINPUT,"error":MESSAGE}
+//   {"event":"rewind","step":N}
+//   {"event":"restore","snapshot":SNAPSHOT}
+// replaying does the same calls and fails on the first step whose result differs (any error matches an error)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Make {
+        model: String,
+        code: String,
+        ainput: String,
+    },
+    // the step JSON, or the message of the failed step
+    Step {
+        rinput: String,
+        result: Result<Value, String>,
+    },
+    Rewind {
+        step: u32,
+    },
+    Restore {
+        snapshot: Value,
+    },
+}
+
+impl Event {
+    pub fn to_json(&self) -> Value {
+        match self {
+            Event::Make {
+                model,
+                code,
+                ainput,
+            } => json!({ "event": "make", "model": model, "code": code, "ainput": ainput }),
+            Event::Step {
+                rinput,
+                result: Ok(result),
+            } => json!({ "event": "step", "rinput": rinput, "result": result }),
+            Event::Step {
+                rinput,
+                result: Err(message),
+            } => json!({ "event": "step", "rinput": rinput, "error": message }),
+            Event::Rewind { step } => json!({ "event": "rewind", "step": step }),
+            Event::Restore { snapshot } => json!({ "event": "restore", "snapshot": snapshot }),
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Result<Event> {
+        let text = |key: &str| -> Result<String> {
+            value
+                .get(key)
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .with_context(|| format!("missing string `{key}`"))
+        };
+        let event = value
+            .get("event")
+            .and_then(Value::as_str)
+            .context("missing `event`")?;
+        let event = match event {
+            "make" => Event::Make {
+                model: text("model")?,
+                code: text("code")?,
+                ainput: text("ainput")?,
+            },
+            "step" => Event::Step {
+                rinput: text("rinput")?,
+                result: match (value.get("result"), value.get("error")) {
+                    (Some(result), None) => Ok(result.clone()),
+                    (None, Some(_)) => Err(text("error")?),
+                    _ => anyhow::bail!("a step has either `result` or `error`"),
+                },
+            },
+            "rewind" => Event::Rewind {
+                step: value
+                    .get("step")
+                    .and_then(Value::as_u64)
+                    .and_then(|step| u32::try_from(step).ok())
+                    .context("missing number `step`")?,
+            },
+            "restore" => Event::Restore {
+                snapshot: value
+                    .get("snapshot")
+                    .cloned()
+                    .context("missing `snapshot`")?,
+            },
+            other => anyhow::bail!("unknown event {other}"),
+        };
+        Ok(event)
+    }
+}
+
+// the events of a session file, blank lines are skipped
+pub fn parse(text: &str) -> Result<Vec<Event>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            let value: Value = serde_json::from_str(line)
+                .with_context(|| format!("line {}: invalid JSON", index + 1))?;
+            Event::from_json(&value).with_context(|| format!("line {}", index + 1))
+        })
+        .collect()
+}
+
+pub fn read(path: &Path) -> Result<Vec<Event>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read session: {}", path.display()))?;
+    parse(&text).with_context(|| format!("invalid session: {}", path.display()))
+}
+
+// appends the events of a REPL session to a file, flushed line by line
+// so that the file is complete up to the last command when the REPL is killed
+pub struct Recorder {
+    out: BufWriter<File>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create session: {}", path.display()))?;
+        Ok(Recorder {
+            out: BufWriter::new(file),
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) -> Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(&event.to_json())?)?;
+        self.out.flush().context("failed to write session")
+    }
+}
+
+// the outcome of `step` as it is recorded
+pub fn step_result(step: &Result<String>) -> Result<Value, String> {
+    let step = step.as_ref().map_err(|err| format!("{err:#}"))?;
+    Ok(serde_json::from_str(step).unwrap_or_else(|_| Value::String(step.clone())))
+}
+
+// do the calls of `events` on a new machine and compare every step with the recorded one
+// returns the number of replayed steps
+pub fn replay(events: &[Event], backend: Backend, limits: WasmLimits) -> Result<usize> {
+    let mut model: Option<(String, ModelHost)> = None;
+    let mut steps = 0;
+    for (index, event) in events.iter().enumerate() {
+        let number = index + 1;
+        if let Event::Make {
+            model: name,
+            code,
+            ainput,
+        } = event
+        {
+            if model.as_ref().is_none_or(|(loaded, _)| loaded != name) {
+                model = Some((
+                    name.clone(),
+                    ModelHost::load_with_limits(name, backend, limits)?,
+                ));
+            }
+            let (_, host) = model.as_mut().expect("loaded above");
+            host.create(code, ainput)
+                .with_context(|| format!("event {number}: make failed"))?;
+            continue;
+        }
+        let Some((_, host)) = model.as_mut() else {
+            anyhow::bail!("event {number}: the session does not start with make");
+        };
+        match event {
+            Event::Make { .. } => unreachable!("handled above"),
+            Event::Step { rinput, result } => {
+                let actual = step_result(&host.step(rinput));
+                if !same_result(&actual, result) {
+                    anyhow::bail!(
+                        "event {number}: step {rinput:?} diverged\n  recorded: {}\n  replayed: {}",
+                        describe(result),
+                        describe(&actual)
+                    );
+                }
+                steps += 1;
+            }
+            Event::Rewind { step } => host
+                .rewind(*step)
+                .with_context(|| format!("event {number}: rewind failed"))?,
+            Event::Restore { snapshot } => host
+                .restore(&serde_json::to_string(snapshot)?)
+                .with_context(|| format!("event {number}: restore failed"))?,
+        }
+    }
+    Ok(steps)
+}
+
+// a failed step only has to fail again: error texts change with the models' wording
+fn same_result(actual: &Result<Value, String>, recorded: &Result<Value, String>) -> bool {
+    match (actual, recorded) {
+        (Ok(actual), Ok(recorded)) => actual == recorded,
+        (Err(_), Err(_)) => true,
+        _ => false,
+    }
+}
+
+fn describe(result: &Result<Value, String>) -> String {
+    match result {
+        Ok(result) => result.to_string(),
+        Err(message) => format!("error {message}"),
+    }
+}
+
+// the session files under `path`: the `*.jsonl` files of a directory in name order, or the file itself
+pub fn session_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path)
+        .with_context(|| format!("failed to read directory: {}", path.display()))?
+    {
+        let file = entry?.path();
+        if file
+            .extension()
+            .is_some_and(|extension| extension == "jsonl")
+        {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+// the golden sessions of every model crate, `models/*/golden/*.jsonl` in the workspace
+pub fn golden_files(workspace: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    let mut crates: Vec<_> = std::fs::read_dir(workspace.join("models"))
+        .context("failed to read the models directory")?
+        .map(|entry| entry.map(|entry| entry.path().join("golden")))
+        .collect::<std::io::Result<_>>()?;
+    crates.sort();
+    for golden in crates.into_iter().filter(|golden| golden.is_dir()) {
+        files.extend(session_files(&golden)?);
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter_session() -> Vec<Event> {
+        let mut model = ModelHost::load("example_counter", Backend::Native).unwrap();
+        let mut events = vec![Event::Make {
+            model: "example_counter".into(),
+            code: "1".into(),
+            ainput: "".into(),
+        }];
+        model.create("1", "").unwrap();
+        for rinput in ["inc", "jump", "inc", "inc"] {
+            events.push(Event::Step {
+                rinput: rinput.into(),
+                result: step_result(&model.step(rinput)),
+            });
+        }
+        events
+    }
+
+    #[test]
+    fn events_round_trip_through_the_file_format() {
+        let mut events = counter_session();
+        events.push(Event::Rewind { step: 1 });
+        events.push(Event::Restore {
+            snapshot: json!({ "count": 3 }),
+        });
+        let text: String = events
+            .iter()
+            .map(|event| format!("{}\n\n", event.to_json()))
+            .collect();
+        assert_eq!(parse(&text).unwrap(), events);
+        assert!(matches!(events[2], Event::Step { result: Err(_), .. }));
+
+        let error = parse("{\"event\":\"make\"}\n{}").unwrap_err();
+        assert!(format!("{error:#}").starts_with("line 1: missing string `model`"));
+    }
+
+    #[test]
+    fn replay_fails_on_divergence() {
+        let mut events = counter_session();
+        assert_eq!(
+            replay(&events, Backend::Native, WasmLimits::default()).unwrap(),
+            4
+        );
+        // the `jump` step failed: any error text matches, a success does not
+        events[2] = Event::Step {
+            rinput: "jump".into(),
+            result: Err("reworded".into()),
+        };
+        assert!(replay(&events, Backend::Native, WasmLimits::default()).is_ok());
+        let mut ok = events.clone();
+        ok[2] = Event::Step {
+            rinput: "jump".into(),
+            result: Ok(json!({ "kind": "continue", "routput": "" })),
+        };
+        assert!(replay(&ok, Backend::Native, WasmLimits::default()).is_err());
+
+        events.push(Event::Rewind { step: 1 });
+        events.push(Event::Step {
+            rinput: "inc".into(),
+            result: Ok(json!({ "kind": "continue", "routput": "dec" })),
+        });
+        let error = replay(&events, Backend::Native, WasmLimits::default()).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("event 7: step \"inc\" diverged"));
+    }
+
+    #[test]
+    fn golden_sessions_replay() {
+        let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+        let files = golden_files(workspace).unwrap();
+        assert!(!files.is_empty());
+        for file in files {
+            let events = read(&file).unwrap();
+            replay(&events, Backend::Native, WasmLimits::default())
+                .unwrap_or_else(|err| panic!("{}: {err:#}", file.display()));
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use utils::render::{RenderBlock, RenderState};
+
+use crate::run::{Rinputs, Stop};
+use crate::runtime::ModelHost;
+use crate::sandbox::LimitExceeded;
+
+// step budget of a trace when `--fuel` is not given, every step is kept in the page
+pub const TRACE_FUEL: usize = 1_000;
+
+// class of the blocks that differ from the previous frame
+const CHANGED: &str = "wm-changed";
+
+const STYLE: &str = include_str!("../../assets/style.css");
+const RENDERER: &str = include_str!("../../assets/renderer.js");
+
+// the drawing of the machine after `step` steps, with the step leading to it
+#[derive(Debug, Clone)]
+pub struct Frame {
+    pub step: usize,
+    pub rinput: Option<String>,
+    pub routput: Option<String>,
+    pub render: RenderState,
+}
+
+// a recorded run, written as a self-contained HTML page by `to_html`
+#[derive(Debug, Clone)]
+pub struct Trace {
+    pub model: String,
+    pub frames: Vec<Frame>,
+    pub stop: Stop,
+}
+
+// step the created machine with `rinputs` until it halts or `fuel` steps are taken,
+// rendering the state after every step
+pub fn record(model: &mut ModelHost, name: &str, rinputs: Rinputs, fuel: usize) -> Result<Trace> {
+    // the drawing of the last frame before `wm-changed` was added
+    let mut previous = render(model)?;
+    let mut frames = vec![Frame {
+        step: 0,
+        rinput: None,
+        routput: None,
+        render: previous.clone(),
+    }];
+    let mut rinputs = rinputs.lines();
+    let stop = loop {
+        let step = frames.len() - 1;
+        if step >= fuel {
+            break Stop::OutOfFuel;
+        }
+        let Some(rinput) = rinputs.next() else {
+            break Stop::InputExhausted;
+        };
+        let output = match model.step(&rinput) {
+            Ok(output) => output,
+            Err(err) => {
+                if let Some(limit) = err.downcast_ref::<LimitExceeded>() {
+                    break Stop::Exceeded {
+                        step,
+                        limit: *limit,
+                    };
+                }
+                break Stop::Error {
+                    step,
+                    message: format!("{err:#}"),
+                };
+            }
+        };
+        let output: Value = serde_json::from_str(&output).context("invalid step result JSON")?;
+        let text = |key: &str| output.get(key).and_then(Value::as_str).map(str::to_string);
+        if output.get("kind").and_then(Value::as_str) == Some("halt") {
+            break Stop::Halted {
+                foutput: text("foutput").unwrap_or_default(),
+            };
+        }
+        let current = render(model)?;
+        let mut render = current.clone();
+        mark_changes(&previous, &mut render);
+        previous = current;
+        frames.push(Frame {
+            step: step + 1,
+            rinput: Some(rinput),
+            routput: text("routput"),
+            render,
+        });
+    };
+    Ok(Trace {
+        model: name.to_string(),
+        frames,
+        stop,
+    })
+}
+
+fn render(model: &mut ModelHost) -> Result<RenderState> {
+    let snapshot = model.checkpoint()?;
+    serde_json::from_str(&model.render(&snapshot)?).context("invalid render state JSON")
+}
+
+// add `wm-changed` to the blocks of `current` that are new or differ from those of `previous`
+// blocks are paired by position (graph nodes by id), a changed class counts as a change
+fn mark_changes(previous: &[RenderBlock], current: &mut [RenderBlock]) {
+    for (index, block) in current.iter_mut().enumerate() {
+        match previous.get(index) {
+            Some(previous) => mark_block(previous, block),
+            None => add_changed(class_of(block)),
+        }
+    }
+}
+
+fn mark_block(previous: &RenderBlock, current: &mut RenderBlock) {
+    match (previous, current) {
+        (RenderBlock::Text(previous), RenderBlock::Text(current)) => {
+            if previous.text != current.text || previous.class_name != current.class_name {
+                add_changed(&mut current.class_name);
+            }
+        }
+        (RenderBlock::Table(previous), RenderBlock::Table(current)) => {
+            mark_changes(&previous.columns, &mut current.columns);
+            for (index, row) in current.rows.iter_mut().enumerate() {
+                match previous.rows.get(index) {
+                    Some(previous) => {
+                        if previous.class_name != row.class_name {
+                            add_changed(&mut row.class_name);
+                        }
+                        mark_changes(&previous.cells, &mut row.cells);
+                    }
+                    None => add_changed(&mut row.class_name),
+                }
+            }
+        }
+        (RenderBlock::Container(previous), RenderBlock::Container(current)) => {
+            mark_changes(&previous.children, &mut current.children);
+        }
+        (RenderBlock::Graph(previous), RenderBlock::Graph(current)) => {
+            for node in &mut current.nodes {
+                let Some(before) = previous.nodes.iter().find(|before| before.id == node.id) else {
+                    add_changed(&mut node.class_name);
+                    continue;
+                };
+                if before.class_name != node.class_name {
+                    add_changed(&mut node.class_name);
+                }
+                match (&before.inner, &mut node.inner) {
+                    (Some(before), Some(inner)) => mark_block(before, inner),
+                    (None, None) => {}
+                    _ => add_changed(&mut node.class_name),
+                }
+            }
+            for (index, edge) in current.edges.iter_mut().enumerate() {
+                if previous.edges.get(index) != Some(edge) {
+                    add_changed(&mut edge.class_name);
+                }
+            }
+        }
+        (_, current) => add_changed(class_of(current)),
+    }
+}
+
+fn class_of(block: &mut RenderBlock) -> &mut Option<String> {
+    match block {
+        RenderBlock::Text(block) => &mut block.class_name,
+        RenderBlock::Table(block) => &mut block.class_name,
+        RenderBlock::Container(block) => &mut block.class_name,
+        RenderBlock::Graph(block) => &mut block.class_name,
+    }
+}
+
+fn add_changed(class: &mut Option<String>) {
+    *class = Some(match class.take() {
+        Some(class) => format!("{class} {CHANGED}"),
+        None => CHANGED.to_string(),
+    });
+}
+
+impl Trace {
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "model": self.model,
+            "stop": self.stop.name(),
+            "frames": self
+                .frames
+                .iter()
+                .map(|frame| json!({
+                    "step": frame.step,
+                    "rinput": frame.rinput,
+                    "routput": frame.routput,
+                    "render": frame.render,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        match &self.stop {
+            Stop::Halted { foutput } => value["foutput"] = json!(foutput),
+            Stop::Error { step, message } => {
+                value["error"] = json!({ "step": step, "message": message });
+            }
+            Stop::Exceeded { step, limit } => {
+                value["error"] = json!({ "step": step, "message": limit.to_string() });
+            }
+            _ => {}
+        }
+        value
+    }
+
+    // a page with the frames, renderer.js and style.css inlined, viewed without a server
+    pub fn to_html(&self) -> String {
+        let title = format!("{} trace", self.model);
+        let mut html = String::new();
+        html.push_str("<!doctype html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!(
+            "<title>{}</title>\n<style>\n",
+            escape_html(&title)
+        ));
+        html.push_str(STYLE);
+        html.push_str(VIEWER_STYLE);
+        html.push_str("</style>\n</head>\n<body>\n");
+        html.push_str(&format!("<h3>{}</h3>\n", escape_html(&title)));
+        html.push_str(VIEWER_BODY);
+        html.push_str("<script type=\"application/json\" id=\"trace\">");
+        html.push_str(&escape_script(&self.to_json().to_string()));
+        html.push_str("</script>\n<script type=\"module\">\n");
+        html.push_str(&escape_script(RENDERER));
+        html.push_str(VIEWER_SCRIPT);
+        html.push_str("</script>\n</body>\n</html>\n");
+        html
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// `</` would end the script element, `<\/` means the same in JSON and JavaScript strings
+fn escape_script(text: &str) -> String {
+    text.replace("</", "<\\/")
+}
+
+const VIEWER_STYLE: &str = r#"
+.trace-controls {
+  position: sticky;
+  top: 0;
+  display: flex;
+  gap: 8px;
+  align-items: center;
+  padding: 6px 0;
+  background: #fff;
+  z-index: 1;
+}
+.trace-controls input[type="range"] {
+  flex: 1;
+}
+#trace-io {
+  font-family: monospace;
+  white-space: pre-wrap;
+  margin-bottom: 8px;
+}
+.wm-changed,
+.wm-table tr.wm-changed > td {
+  outline: 2px solid #e0a000;
+  background-color: #fff3c4;
+}
+body.trace-hide-changes .wm-changed,
+body.trace-hide-changes .wm-table tr.wm-changed > td {
+  outline: none;
+  background-color: inherit;
+}
+"#;
+
+const VIEWER_BODY: &str = r#"<div class="trace-controls">
+  <button id="trace-prev" title="previous step (left arrow)">&#9664;</button>
+  <button id="trace-play" title="play / pause (space)">play</button>
+  <button id="trace-next" title="next step (right arrow)">&#9654;</button>
+  <input id="trace-slider" type="range" min="0" value="0">
+  <span id="trace-position"></span>
+  <label><input id="trace-changes" type="checkbox" checked> changes</label>
+</div>
+<div id="trace-io"></div>
+<div id="trace-state"></div>
+"#;
+
+const VIEWER_SCRIPT: &str = r#"
+const trace = JSON.parse(document.getElementById("trace").textContent);
+const frames = trace.frames;
+const renderer = new Renderer(document.getElementById("trace-state"));
+const slider = document.getElementById("trace-slider");
+const play = document.getElementById("trace-play");
+const position = document.getElementById("trace-position");
+const io = document.getElementById("trace-io");
+slider.max = String(frames.length - 1);
+let current = 0;
+let timer = null;
+
+function show(index) {
+  current = Math.max(0, Math.min(frames.length - 1, index));
+  const frame = frames[current];
+  slider.value = String(current);
+  position.textContent = `step ${frame.step} / ${frames[frames.length - 1].step}`;
+  const lines = [];
+  if (frame.rinput !== null) lines.push(`rinput: ${frame.rinput}`);
+  if (frame.routput !== null) lines.push(`routput: ${frame.routput}`);
+  if (current === frames.length - 1) {
+    lines.push(`stop: ${trace.stop}`);
+    if (trace.foutput !== undefined) lines.push(`foutput: ${trace.foutput}`);
+    if (trace.error !== undefined) lines.push(`error at step ${trace.error.step}: ${trace.error.message}`);
+  }
+  io.textContent = lines.join("\n");
+  renderer.draw(frame.render);
+}
+
+function pause() {
+  clearInterval(timer);
+  timer = null;
+  play.textContent = "play";
+}
+
+function toggle() {
+  if (timer !== null) {
+    pause();
+    return;
+  }
+  if (current === frames.length - 1) show(0);
+  play.textContent = "pause";
+  timer = setInterval(() => {
+    if (current >= frames.length - 1) {
+      pause();
+    } else {
+      show(current + 1);
+    }
+  }, 300);
+}
+
+slider.addEventListener("input", () => show(Number(slider.value)));
+play.addEventListener("click", toggle);
+document.getElementById("trace-prev").addEventListener("click", () => show(current - 1));
+document.getElementById("trace-next").addEventListener("click", () => show(current + 1));
+document.getElementById("trace-changes").addEventListener("change", event => {
+  document.body.classList.toggle("trace-hide-changes", !event.target.checked);
+});
+document.addEventListener("keydown", event => {
+  if (event.target instanceof HTMLInputElement && event.target.type !== "range") return;
+  if (event.key === "ArrowLeft") show(current - 1);
+  else if (event.key === "ArrowRight") show(current + 1);
+  else if (event.key === " ") toggle();
+  else return;
+  event.preventDefault();
+});
+show(0);
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run::rinputs;
+    use crate::runtime::Backend;
+    use utils::{render_row, render_table, render_text};
+
+    #[test]
+    fn changed_cells_and_rows_are_marked() {
+        let table = |head: usize| {
+            let rows = (0..3)
+                .map(|index| {
+                    let cell = render_text!(format!("{index}"));
+                    if index == head {
+                        render_row!([cell], class: "highlight")
+                    } else {
+                        render_row!([cell])
+                    }
+                })
+                .collect();
+            render_table!(columns: vec![], rows: rows)
+        };
+        let previous = vec![table(0), render_text!("a".to_string())];
+        let mut current = vec![
+            table(1),
+            render_text!("a".to_string()),
+            render_text!("b".to_string()),
+        ];
+        mark_changes(&previous, &mut current);
+        let RenderBlock::Table(table) = &current[0] else {
+            panic!("expected a table");
+        };
+        let classes: Vec<_> = table
+            .rows
+            .iter()
+            .map(|row| row.class_name.as_deref())
+            .collect();
+        assert_eq!(classes, [Some(CHANGED), Some("highlight wm-changed"), None]);
+        assert_eq!(current[1], render_text!("a".to_string()));
+        assert_eq!(current[2], render_text!("b".to_string(), class: CHANGED));
+    }
+
+    #[test]
+    fn record_renders_every_step_into_the_page() {
+        let mut model = ModelHost::load("example_counter", Backend::Native).unwrap();
+        model.create("7", "").unwrap();
+        let rinputs = rinputs(&mut model, None, Some("inc")).unwrap();
+        let trace = record(&mut model, "example_counter", rinputs, 10).unwrap();
+        assert_eq!(trace.frames.len(), 3);
+        assert_eq!(
+            trace.stop,
+            Stop::Halted {
+                foutput: "End".into()
+            }
+        );
+        assert_eq!(trace.frames[1].rinput.as_deref(), Some("inc"));
+        // the count changes at every step, the title does not
+        for frame in &trace.frames[1..] {
+            let changed = serde_json::to_string(&frame.render).unwrap();
+            assert_eq!(changed.matches(CHANGED).count(), 1);
+        }
+        assert!(trace.to_html().contains("export class Renderer"));
+        assert!(!escape_script(&trace.to_json().to_string()).contains("</"));
+    }
+}
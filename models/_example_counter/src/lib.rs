@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use utils::{Compiler, Machine, StepResult, TextCodec};
+use utils::{Compiler, Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Counter {
@@ -93,6 +93,15 @@ impl Machine for Counter {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("example_counter")
+            .code("the initial count, a natural number (empty for 0)")
+            .ainput("empty")
+            .rinput("`inc` or `dec`, halts when the count reaches 10")
+            .default_rinput("inc")
+            .example("from seven", "7", "")
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         utils::render_state![utils::render_table!(
             columns: vec![utils::render_text!("field".to_string()), utils::render_text!("value".to_string())],
@@ -105,6 +114,12 @@ impl Machine for Counter {
     }
 }
 
+impl Metrics for Counter {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("count", self.count)]
+    }
+}
+
 pub struct ExampleCounterCompiler;
 
 impl Compiler for ExampleCounterCompiler {
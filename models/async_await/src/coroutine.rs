@@ -7,7 +7,7 @@ use std::collections::{BTreeMap, HashMap, VecDeque};
 
 use serde::{Deserialize, Serialize};
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, NondetMachine, StepResult};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CoroutineCode(pub Program);
@@ -132,7 +132,7 @@ impl CoroutineMachine {
                 Ok(match op {
                     ABinOp::Add => lhs + rhs,
                     ABinOp::Sub => lhs - rhs,
-                    ABinOp::Mul => Number::from(lhs.as_usize()? * rhs.as_usize()?),
+                    ABinOp::Mul => lhs * rhs,
                 })
             }
         }
@@ -340,11 +340,84 @@ impl Machine for CoroutineMachine {
         }
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("coroutine")
+            .code("`fn name { stmt; .. }` definitions, `main` runs first; `x <- a`, `if b goto +n`, `goto -n`, `call f`, `run f -> $t`, `yield`, with `done $t` in conditions")
+            .ainput("the number of agents, at least 1")
+            .rinput("the agent (0-indexed) to step")
+            .example(
+                "run and wait",
+                "fn main { run child -> $t; if done $t goto +2; yield; x <- 1 }\nfn child { y <- 1 }",
+                "1",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         coroutine_render::render_machine(snapshot)
     }
 }
 
+impl Metrics for CoroutineMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("tasks", self.tasks.len()),
+            ("frames", self.tasks.iter().map(Vec::len).sum()),
+            ("queue", self.queue.len()),
+            ("vars", self.env.vars.len()),
+        ]
+    }
+}
+
+// every runnable coroutine is a choice: the task of each busy agent, and the head of the queue
+// taken by the first idle agent; the choice is the agent as in `Machine::step`
+impl NondetMachine for CoroutineMachine {
+    type Code = CoroutineCode;
+    type AInput = usize;
+    type FOutput = GlobalEnv;
+    type Choice = usize;
+    type SnapShot = CoroutineMachine;
+
+    fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String> {
+        <Self as Machine>::make(code, ainput)
+    }
+
+    fn halted(&self) -> Option<Self::FOutput> {
+        (self.queue.is_empty() && self.workers.iter().all(Option::is_none))
+            .then(|| self.env.clone())
+    }
+
+    fn successors(&self) -> Result<Vec<(Self::Choice, Self)>, String> {
+        let busy = (0..self.workers.len()).filter(|&agent| self.workers[agent].is_some());
+        let idle = (0..self.workers.len())
+            .find(|&agent| self.workers[agent].is_none())
+            .filter(|_| !self.queue.is_empty());
+        let mut agents = busy.chain(idle).collect::<Vec<_>>();
+        agents.sort_unstable();
+        let mut successors = vec![];
+        for agent in agents {
+            match self.clone().step(agent)? {
+                StepResult::Continue { next, .. } => successors.push((agent, next)),
+                StepResult::Halt { .. } => {
+                    unreachable!("the agent has a task or the queue has one")
+                }
+            }
+        }
+        Ok(successors)
+    }
+
+    fn snapshot(&self) -> Self::SnapShot {
+        <Self as Machine>::snapshot(self)
+    }
+
+    fn restore(snapshot: Self::SnapShot) -> Self {
+        <Self as Machine>::restore(snapshot)
+    }
+
+    fn render(snapshot: Self::SnapShot) -> utils::RenderState {
+        <Self as Machine>::render(snapshot)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -356,7 +429,7 @@ mod tests {
             "fn main { run child -> $t; if done $t goto +2; yield; x <- 1 }\nfn child { y <- 1 }",
         )
         .unwrap();
-        let machine = CoroutineMachine::make(code, 1).unwrap();
+        let machine = <CoroutineMachine as Machine>::make(code, 1).unwrap();
 
         let mut machine = machine;
         let output = loop {
@@ -369,4 +442,32 @@ mod tests {
         assert_eq!(output.get_var("x"), Number::from(1usize));
         assert_eq!(output.get_var("y"), Number::from(1usize));
     }
+
+    #[test]
+    fn explore_finds_the_outputs_of_every_interleaving() {
+        use utils::nondet::{ExploreOptions, explore};
+        // with two agents the child can write x before or after main
+        let code = CoroutineCode::parse("fn main { run child -> $t; x <- 1 }\nfn child { x <- 2 }")
+            .unwrap();
+        let machine = <CoroutineMachine as NondetMachine>::make(code, 2).unwrap();
+        let result = explore(machine, ExploreOptions::bfs(1000));
+        assert!(result.is_complete());
+        let mut outputs = result
+            .halting_outputs()
+            .map(|(_, env)| env.get_var("x"))
+            .collect::<Vec<_>>();
+        outputs.sort();
+        outputs.dedup();
+        assert_eq!(outputs, vec![Number::from(1usize), Number::from(2usize)]);
+        assert!(result.cyclic_states().is_empty());
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        let code = CoroutineCode::parse(
+            "fn main { run child -> $t; if done $t goto +2; yield; x <- 1 }\nfn child { y <- 1 }",
+        )
+        .unwrap();
+        utils::pretty::check_roundtrip(&code).unwrap();
+    }
 }
@@ -516,8 +516,8 @@ fn lex_expr(text: &str) -> Result<Vec<Token>, String> {
             while i < chars.len() && chars[i].is_ascii_digit() {
                 i += 1;
             }
-            let number = text[start..i].parse::<usize>().map_err(|e| e.to_string())?;
-            tokens.push(Token::Number(Number::from(number)));
+            let number = Number::from_decimal_str(&text[start..i])?;
+            tokens.push(Token::Number(number));
             continue;
         }
         if ch.is_ascii_alphabetic() || ch == '_' {
@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, HashMap, HashSet};
 
 use serde::{Deserialize, Serialize};
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 pub type Vreg = String;
 
@@ -566,6 +566,63 @@ impl Machine for FlowIrMachine {
         snapshot
     }
 
+    // `@region :label`: control is in the region, `@block :label`: in the block of the current region
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, String> {
+        let label = arg.strip_prefix(':').unwrap_or(arg);
+        match name {
+            "region" => {
+                let region = self
+                    .names
+                    .region_label_to_id
+                    .get(label)
+                    .ok_or_else(|| format!("unknown region label :{label}"))?;
+                Ok(*region == self.current_region)
+            }
+            "block" => {
+                // a block of another region is valid, control is just not in it
+                if !self
+                    .names
+                    .block_labels_in_region
+                    .iter()
+                    .any(|labels| labels.contains_key(label))
+                {
+                    return Err(format!("unknown block label :{label}"));
+                }
+                Ok(self
+                    .names
+                    .block_labels_in_region
+                    .get(self.current_region)
+                    .and_then(|labels| labels.get(label))
+                    == Some(&self.current_block))
+            }
+            _ => Err(format!(
+                "unknown hook: @{name} (expected @region or @block)"
+            )),
+        }
+    }
+
+    // label of the current region, the unit of source maps into flow_ir
+    fn location(&self) -> Option<String> {
+        self.code
+            .0
+            .regions
+            .get(self.current_region)
+            .map(|region| region.label.clone())
+    }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("flow_ir")
+            .code("`@static value` lines, then regions `:name { :block { stmt; .. cont; } .. }`, `:main` runs first; %vreg := ld/st/ref/+/-, push, pop, halloc, print, input; blocks end in goto, enter or halt")
+            .ainput("initial statics, `x = 3` per line (the static `@x` must be declared)")
+            .rinput("the bytes read by `input`, otherwise ignored")
+            .default_rinput("")
+            .example(
+                "double",
+                "@x 0\n:main {\n  :entry {\n    %a := ld @x;\n    %b := %a + %a;\n    @x := st %b;\n    halt;\n  }\n}",
+                "x = 3",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let region = snapshot
             .code
@@ -639,11 +696,18 @@ impl Machine for FlowIrMachine {
             })
             .collect::<Vec<_>>();
 
+        let cfg = render_cfg(
+            &snapshot.code.0,
+            snapshot.current_region,
+            snapshot.current_block,
+        );
+
         utils::render_state![
             utils::render_text!(format!(":{}", region), title: "current_region"),
             utils::render_text!(format!(":{}", block), title: "current_block"),
             utils::render_text!(snapshot.current_line.to_string(), title: "current_line"),
             utils::render_text!(snapshot.halted.to_string(), title: "halted"),
+            cfg,
             utils::render_table!(
                 columns: vec![utils::render_text!("vreg".to_string()), utils::render_text!("value".to_string())],
                 rows: vreg_rows,
@@ -672,6 +736,90 @@ impl Machine for FlowIrMachine {
     }
 }
 
+// the control-flow graph of every region, one group per region
+// jumps to a computed label are not drawn, the current block is highlighted
+fn render_cfg(
+    program: &Program,
+    current_region: usize,
+    current_block: usize,
+) -> utils::RenderBlock {
+    let node_id = |region: &Region, block: &str| format!("{}:{}", region.label, block);
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    let mut groups = vec![];
+    for (region_idx, region) in program.regions.iter().enumerate() {
+        groups.push(utils::render_group!(region.label, title: format!(":{}", region.label)));
+        for (block_idx, block) in region.blocks.iter().enumerate() {
+            let id = node_id(region, &block.label);
+            let inner = utils::render_text!(format!(":{}", block.label));
+            if (region_idx, block_idx) == (current_region, current_block) {
+                nodes.push(
+                    utils::render_node!(id, inner: inner, group: region.label, class: "highlight"),
+                );
+            } else {
+                nodes.push(utils::render_node!(id, inner: inner, group: region.label));
+            }
+            for jump_if in block.cont.ifs() {
+                if let ValueExpr::CodeLabel(target) = &jump_if.target {
+                    edges.push(utils::render_edge!(id => node_id(region, target), label: "if"));
+                }
+            }
+            match &block.cont {
+                Cont::Go {
+                    target: ValueExpr::CodeLabel(target),
+                    ..
+                } => edges.push(utils::render_edge!(id => node_id(region, target))),
+                Cont::Enter {
+                    target: ValueExpr::CodeLabel(target),
+                    ..
+                } => {
+                    let entry = program
+                        .regions
+                        .iter()
+                        .find(|other| &other.label == target)
+                        .and_then(|other| {
+                            other
+                                .blocks
+                                .first()
+                                .map(|entry| node_id(other, &entry.label))
+                        });
+                    if let Some(entry) = entry {
+                        edges.push(utils::render_edge!(id => entry, label: "enter"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    utils::render_graph!(nodes: nodes, edges: edges, groups: groups, title: "cfg")
+}
+
+impl Metrics for FlowIrMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let heap = self.heap.values().map(Vec::len).sum();
+        vec![
+            ("stack", self.stack.len()),
+            ("heap", heap),
+            ("vregs", self.vregs.len()),
+        ]
+    }
+
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        let stmt = self
+            .code
+            .0
+            .regions
+            .get(self.current_region)
+            .and_then(|region| region.blocks.get(self.current_block))
+            .and_then(|block| block.stmts.get(self.current_line));
+        match stmt {
+            Some(Stmt::HAlloc { .. }) if !self.halted => vec![("alloc", 1)],
+            Some(Stmt::HFree { .. }) if !self.halted => vec![("free", 1)],
+            _ => vec![],
+        }
+    }
+}
+
 fn compile_name_mapping(program: &Program) -> Result<NameMapping, String> {
     let mut static_labels = HashSet::new();
     for s in &program.statics {
@@ -718,3 +866,67 @@ fn compile_name_mapping(program: &Program) -> Result<NameMapping, String> {
         entry_region,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::TextCodec;
+
+    #[test]
+    fn hooks_reject_unknown_labels() {
+        let code = FlowIrCode::parse(
+            ":main {\n  :entry {\n    enter :sub;\n  }\n}\n:sub {\n  :start {\n    halt;\n  }\n}",
+        )
+        .unwrap();
+        let machine = FlowIrMachine::make(code, StaticEnv::default()).unwrap();
+        assert_eq!(machine.hook("block", ":entry"), Ok(true));
+        assert_eq!(machine.hook("block", ":start"), Ok(false));
+        assert_eq!(
+            machine.hook("block", ":missing"),
+            Err("unknown block label :missing".to_string())
+        );
+        assert_eq!(
+            machine.hook("region", ":missing"),
+            Err("unknown region label :missing".to_string())
+        );
+    }
+
+    #[test]
+    fn render_draws_the_cfg_of_every_region() {
+        let code = FlowIrCode::parse(
+            ":main {\n  :entry {\n    if %x = #0 then :done;\n    enter :sub;\n  }\n  :done {\n    halt;\n  }\n}\n:sub {\n  :start {\n    goto %x;\n  }\n}",
+        )
+        .unwrap();
+        let machine = FlowIrMachine::make(code, StaticEnv::default()).unwrap();
+        let state = FlowIrMachine::render(machine);
+        let graph = state
+            .iter()
+            .find_map(|block| match block {
+                utils::RenderBlock::Graph(graph) => Some(graph),
+                _ => None,
+            })
+            .expect("expected a graph block");
+        let ids = graph
+            .nodes
+            .iter()
+            .map(|node| node.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["main:entry", "main:done", "sub:start"]);
+        assert_eq!(graph.nodes[0].class_name.as_deref(), Some("highlight"));
+        assert_eq!(graph.nodes[2].group.as_deref(), Some("sub"));
+        assert_eq!(graph.groups.len(), 2);
+        // the computed jump of :start has no edge
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.label.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ("main:entry", "main:done", Some("if")),
+                ("main:entry", "sub:start", Some("enter")),
+            ]
+        );
+    }
+}
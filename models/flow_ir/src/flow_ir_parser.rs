@@ -1,10 +1,15 @@
+use utils::diagnostic::{Diagnostic, Span, lines_with_span};
 use utils::number::Number;
-use utils::{TextCodec, Token as LexToken, lex};
+use utils::{SpannedToken, TextCodec, Token as LexToken, lex_spanned};
 
 use crate::flow_ir::*;
 
 impl TextCodec for FlowIrCode {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
         Ok(Self(parse_program(text)?))
     }
 
@@ -90,20 +95,24 @@ impl TextCodec for FlowValue {
 
 impl TextCodec for StaticEnv {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    // errors point to the whole line
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
         let mut entries = std::collections::BTreeMap::new();
-        for line in text.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
+        for (span, line) in lines_with_span(text) {
             let Some((left, right)) = line.split_once('=') else {
-                return Err(format!("Invalid static input line: {line}"));
+                return Err(Diagnostic::error(
+                    span,
+                    format!("Invalid static input line: {line}"),
+                ));
             };
             let key = left.trim().to_string();
             if key.is_empty() {
-                return Err("Empty static input key".to_string());
+                return Err(Diagnostic::error(span, "Empty static input key"));
             }
-            let value = FlowValue::parse(right.trim())?;
+            let value = FlowValue::parse(right.trim()).map_err(|e| Diagnostic::error(span, e))?;
             entries.insert(key, value);
         }
         Ok(StaticEnv { entries })
@@ -117,9 +126,9 @@ impl TextCodec for StaticEnv {
     }
 }
 
-fn parse_program(text: &str) -> Result<Program, String> {
-    let tokens = lex(text).map_err(|e| e.to_string())?;
-    let mut parser = Parser::new(tokens);
+fn parse_program(text: &str) -> Result<Program, Diagnostic> {
+    let tokens = lex_spanned(text)?;
+    let mut parser = Parser::new(tokens, text.len());
     let mut statics = Vec::new();
     let mut regions = Vec::new();
     while !parser.is_eof() {
@@ -138,17 +147,23 @@ fn parse_program(text: &str) -> Result<Program, String> {
 }
 
 struct Parser {
-    tokens: Vec<LexToken>,
+    tokens: Vec<SpannedToken>,
     pos: usize,
+    // byte length of the text, for errors at end of input
+    end: usize,
 }
 
 impl Parser {
-    fn new(tokens: Vec<LexToken>) -> Self {
+    fn new(tokens: Vec<SpannedToken>, end: usize) -> Self {
         let tokens = tokens
             .into_iter()
-            .filter(|token| !matches!(token, LexToken::Whitespace(_) | LexToken::Comment(_)))
+            .filter(|token| !matches!(token.token, LexToken::Whitespace(_) | LexToken::Comment(_)))
             .collect();
-        Self { tokens, pos: 0 }
+        Self {
+            tokens,
+            pos: 0,
+            end,
+        }
     }
 
     fn is_eof(&self) -> bool {
@@ -156,21 +171,26 @@ impl Parser {
     }
 
     fn peek(&self) -> Option<&LexToken> {
-        self.tokens.get(self.pos)
+        self.tokens.get(self.pos).map(|token| &token.token)
     }
 
-    fn next(&mut self) -> Option<LexToken> {
-        let token = self.tokens.get(self.pos).cloned();
-        if token.is_some() {
-            self.pos += 1;
-        }
-        token
+    // span of the current token (or of the end of input)
+    fn span_here(&self) -> Span {
+        self.tokens
+            .get(self.pos)
+            .map_or(Span::at(self.end), |token| token.span)
     }
 
-    fn error_here(&self, message: impl Into<String>) -> String {
+    fn error_here(&self, message: impl Into<String>) -> Diagnostic {
         match self.peek() {
-            Some(token) => format!("{} near {:?}", message.into(), token),
-            None => format!("{} at end of input", message.into()),
+            Some(token) => Diagnostic::error(
+                self.span_here(),
+                format!("{} near {:?}", message.into(), token),
+            ),
+            None => Diagnostic::error(
+                self.span_here(),
+                format!("{} at end of input", message.into()),
+            ),
         }
     }
 
@@ -187,7 +207,7 @@ impl Parser {
         }
     }
 
-    fn expect_symbol(&mut self, ch: char) -> Result<(), String> {
+    fn expect_symbol(&mut self, ch: char) -> Result<(), Diagnostic> {
         if self.eat_symbol(ch) {
             Ok(())
         } else {
@@ -208,7 +228,7 @@ impl Parser {
         }
     }
 
-    fn expect_ident(&mut self, word: &str) -> Result<(), String> {
+    fn expect_ident(&mut self, word: &str) -> Result<(), Diagnostic> {
         if self.eat_ident(word) {
             Ok(())
         } else {
@@ -216,21 +236,26 @@ impl Parser {
         }
     }
 
-    fn parse_name(&mut self, kind: &str) -> Result<String, String> {
-        match self.next() {
-            Some(LexToken::Ident(name)) | Some(LexToken::Number(name)) => Ok(name),
+    fn parse_name(&mut self, kind: &str) -> Result<String, Diagnostic> {
+        match self.peek() {
+            Some(LexToken::Ident(name)) | Some(LexToken::Number(name)) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
             _ => Err(self.error_here(format!("expected {kind}"))),
         }
     }
 
-    fn parse_static_def(&mut self) -> Result<(String, Number), String> {
+    fn parse_static_def(&mut self) -> Result<(String, Number), Diagnostic> {
         self.expect_symbol('@')?;
         let label = self.parse_name("static label")?;
         let value = self.parse_number_literal()?;
         Ok((label, value))
     }
 
-    fn parse_region(&mut self) -> Result<Region, String> {
+    fn parse_region(&mut self) -> Result<Region, Diagnostic> {
+        let header = self.span_here();
         self.expect_symbol(':')?;
         let label = self.parse_name("region label")?;
         self.expect_symbol('{')?;
@@ -242,12 +267,15 @@ impl Parser {
         self.expect_symbol('}')?;
 
         if blocks.is_empty() {
-            return Err(format!("Region :{} has no blocks", label));
+            return Err(Diagnostic::error(
+                header,
+                format!("Region :{} has no blocks", label),
+            ));
         }
         Ok(Region { label, blocks })
     }
 
-    fn parse_block(&mut self) -> Result<Block, String> {
+    fn parse_block(&mut self) -> Result<Block, Diagnostic> {
         self.expect_symbol(':')?;
         let label = self.parse_name("block label")?;
         self.expect_symbol('{')?;
@@ -268,14 +296,20 @@ impl Parser {
             }
 
             if cont.is_some() {
-                return Err(format!("Line exists after terminator in block :{}", label));
+                return Err(Diagnostic::error(
+                    self.span_here(),
+                    format!("Line exists after terminator in block :{}", label),
+                ));
             }
 
             stmts.push(self.parse_stmt()?);
         }
 
+        let close = self.span_here();
         self.expect_symbol('}')?;
-        let cont = cont.ok_or_else(|| format!("Missing terminator in block :{}", label))?;
+        let cont = cont.ok_or_else(|| {
+            Diagnostic::error(close, format!("Missing terminator in block :{}", label))
+        })?;
         Ok(Block { label, stmts, cont })
     }
 
@@ -283,7 +317,7 @@ impl Parser {
         self.peek_ident("goto") || self.peek_ident("enter") || self.peek_ident("halt")
     }
 
-    fn parse_stmt(&mut self) -> Result<Stmt, String> {
+    fn parse_stmt(&mut self) -> Result<Stmt, Diagnostic> {
         if self.eat_ident("nop") {
             self.expect_symbol(';')?;
             return Ok(Stmt::Nop);
@@ -373,7 +407,7 @@ impl Parser {
         Ok(Stmt::Store { place, src })
     }
 
-    fn parse_jump_if(&mut self) -> Result<JumpIf, String> {
+    fn parse_jump_if(&mut self) -> Result<JumpIf, Diagnostic> {
         self.expect_ident("if")?;
         let cond = self.parse_cond()?;
         self.expect_ident("then")?;
@@ -382,7 +416,7 @@ impl Parser {
         Ok(JumpIf { cond, target })
     }
 
-    fn parse_cont(&mut self, ifs: &[JumpIf]) -> Result<Cont, String> {
+    fn parse_cont(&mut self, ifs: &[JumpIf]) -> Result<Cont, Diagnostic> {
         if self.eat_ident("goto") {
             let target = self.parse_value_expr()?;
             self.expect_symbol(';')?;
@@ -406,7 +440,7 @@ impl Parser {
         Err(self.error_here("expected block terminator"))
     }
 
-    fn parse_cond(&mut self) -> Result<Cond, String> {
+    fn parse_cond(&mut self) -> Result<Cond, Diagnostic> {
         let lhs = self.parse_value_expr()?;
         if self.eat_symbol('<') {
             let rhs = self.parse_value_expr()?;
@@ -423,7 +457,7 @@ impl Parser {
         Err(self.error_here("expected relation operator"))
     }
 
-    fn parse_place_expr(&mut self) -> Result<PlaceExpr, String> {
+    fn parse_place_expr(&mut self) -> Result<PlaceExpr, Diagnostic> {
         if self.eat_symbol('@') {
             let label = self.parse_name("data label")?;
             return Ok(PlaceExpr::Label(label));
@@ -449,7 +483,7 @@ impl Parser {
         Err(self.error_here("expected place expression"))
     }
 
-    fn parse_value_expr(&mut self) -> Result<ValueExpr, String> {
+    fn parse_value_expr(&mut self) -> Result<ValueExpr, Diagnostic> {
         if self.eat_ident("ref") {
             return Ok(ValueExpr::Ref(self.parse_place_expr()?));
         }
@@ -469,14 +503,19 @@ impl Parser {
         Err(self.error_here("expected value expression"))
     }
 
-    fn parse_number_literal(&mut self) -> Result<Number, String> {
-        match self.next() {
-            Some(LexToken::Number(text)) => Number::parse(&text),
+    fn parse_number_literal(&mut self) -> Result<Number, Diagnostic> {
+        match self.peek() {
+            Some(LexToken::Number(text)) => {
+                let number =
+                    Number::parse(text).map_err(|e| Diagnostic::error(self.span_here(), e))?;
+                self.pos += 1;
+                Ok(number)
+            }
             _ => Err(self.error_here("expected number literal")),
         }
     }
 
-    fn parse_vreg(&mut self) -> Result<Vreg, String> {
+    fn parse_vreg(&mut self) -> Result<Vreg, Diagnostic> {
         self.expect_symbol('%')?;
         self.parse_name("virtual register")
     }
@@ -549,6 +588,7 @@ mod tests {
         let printed = parsed.print();
         let reparsed = FlowIrCode::parse(&printed).expect("reparse should succeed");
         assert_eq!(parsed, reparsed, "roundtrip failed:\n{printed}");
+        utils::pretty::check_roundtrip(&parsed).unwrap();
     }
 
     #[test]
@@ -631,6 +671,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn flow_ir_errors_point_into_the_code() {
+        let text = ":main {\n  :entry {\n    %x := ld;\n    halt;\n  }\n}\n";
+        let err = FlowIrCode::parse_spanned(text).unwrap_err();
+        assert_eq!(err.line_col(text), Some((3, 13)));
+
+        let text = ":main {\n  :entry {\n    nop;\n  }\n}\n";
+        let err = FlowIrCode::parse_spanned(text).unwrap_err();
+        assert_eq!(err.line_col(text), Some((4, 3)));
+
+        let err = StaticEnv::parse_spanned("x = 1\n  y 2\n").unwrap_err();
+        assert_eq!(err.span, Some(Span::new(8, 11)));
+    }
+
     #[test]
     fn flow_value_rejects_old_static_key_syntax() {
         let err = FlowValue::parse("@x").unwrap_err();
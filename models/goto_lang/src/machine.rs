@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
+use utils::diagnostic::summarize;
 use utils::identifier::Identifier;
 use utils::number::Number;
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Diagnostic, Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Environment {
@@ -31,7 +32,15 @@ impl Environment {
 
 impl TextCodec for Environment {
     fn parse(text: &str) -> Result<Self, String> {
-        let env = crate::manipulation::env_read_to_end(text).map_err(|e| e.to_string())?;
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        Self::parse_all(text).map_err(summarize)
+    }
+
+    fn parse_all(text: &str) -> Result<Self, Vec<Diagnostic>> {
+        let env = crate::manipulation::env_read_to_end(text)?;
         Ok(Environment { env })
     }
 
@@ -77,7 +86,15 @@ pub struct Code(pub Vec<Command>);
 
 impl TextCodec for Code {
     fn parse(text: &str) -> Result<Self, String> {
-        let code = crate::manipulation::program_read_to_end(text).map_err(|e| e.to_string())?;
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        Self::parse_all(text).map_err(summarize)
+    }
+
+    fn parse_all(text: &str) -> Result<Self, Vec<Diagnostic>> {
+        let code = crate::manipulation::program_read_to_end(text)?;
         Ok(Code(code))
     }
 
@@ -175,4 +192,37 @@ impl Machine for Program {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::goto_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("goto_lang")
+            .code("commands ending with `;`: `inc x`, `dec x`, `clr x`, `cpy x <- y`, `ifnz x : n` jumping to the n-th command (0-indexed)")
+            .ainput("initial variables, `x = 3` per line (others are 0)")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "multiply",
+                "cpy y2 <- y;\ninc z;\ndec y2;\nifnz y2 : 1;\ndec x;\nifnz x : 0;",
+                "x = 3\ny = 4",
+            )
+    }
+}
+
+// memory is the number of variables and the total bits of their values
+impl Metrics for Program {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let bits = self.env.env.iter().map(|(_, num)| num.bits()).sum();
+        vec![("vars", self.env.env.len()), ("bits", bits)]
+    }
+
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        let command = self
+            .pc
+            .as_usize()
+            .ok()
+            .and_then(|pc| self.commands.0.get(pc));
+        match command {
+            Some(Command::Ifnz(var, _)) if !self.env.get(var).is_zero() => vec![("jump", 1)],
+            _ => vec![],
+        }
+    }
 }
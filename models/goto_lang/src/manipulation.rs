@@ -1,151 +1,59 @@
 use crate::machine::Command;
+use utils::diagnostic::Diagnostic;
 use utils::identifier::Identifier as Var;
+use utils::lexer::LexerConfig;
 use utils::number::Number;
-use utils::{TextCodec, Token as LexToken, lex};
-
-pub fn program(code: &str) -> Result<Vec<Command>, String> {
-    let tokens = lex(code).map_err(|e| e.to_string())?;
-    let mut parser = Parser::new(tokens);
-    let mut statements = Vec::new();
-
-    while !parser.is_eof() {
-        statements.push(parser.parse_statement()?);
-        parser.expect_symbol(';')?;
-    }
-
-    Ok(statements)
+use utils::tree_parser::{PResult, TreeParser, parse_text, parse_text_with};
+
+// every statement is checked, so all the errors are reported at once
+pub fn program(code: &str) -> Result<Vec<Command>, Vec<Diagnostic>> {
+    let config = LexerConfig::new().operators(&["<-"]);
+    parse_text_with(code, &config, |parser| {
+        Ok(parser.terminated(';', parse_statement))
+    })
 }
 
-pub fn program_read_to_end(code: &str) -> Result<Vec<Command>, String> {
+pub fn program_read_to_end(code: &str) -> Result<Vec<Command>, Vec<Diagnostic>> {
     program(code)
 }
 
-pub fn env_read_to_end(code: &str) -> Result<Vec<(Var, Number)>, String> {
-    let tokens = lex(code).map_err(|e| e.to_string())?;
-    let mut parser = Parser::new(tokens);
-    let mut env = Vec::new();
-
-    while !parser.is_eof() {
-        let name = parser.parse_name()?;
-        parser.expect_symbol('=')?;
-        let number = parser.parse_number()?;
-        env.push((name, number));
-        parser.eat_symbol(';');
-    }
-
-    Ok(env)
-}
-
-struct Parser {
-    tokens: Vec<LexToken>,
-    pos: usize,
+pub fn env_read_to_end(code: &str) -> Result<Vec<(Var, Number)>, Vec<Diagnostic>> {
+    parse_text(code, |parser| {
+        let mut env = Vec::new();
+        while !parser.is_eof() {
+            let name = parser.ident()?;
+            parser.expect_symbol('=')?;
+            let number = parser.number()?;
+            env.push((name, number));
+            parser.eat_symbol(';');
+        }
+        Ok(env)
+    })
 }
 
-impl Parser {
-    fn new(tokens: Vec<LexToken>) -> Self {
-        let tokens = tokens
-            .into_iter()
-            .filter(|token| !matches!(token, LexToken::Whitespace(_) | LexToken::Comment(_)))
-            .collect();
-        Self { tokens, pos: 0 }
-    }
-
-    fn is_eof(&self) -> bool {
-        self.pos >= self.tokens.len()
-    }
-
-    fn peek(&self) -> Option<&LexToken> {
-        self.tokens.get(self.pos)
-    }
-
-    fn next(&mut self) -> Option<LexToken> {
-        let token = self.tokens.get(self.pos).cloned();
-        if token.is_some() {
-            self.pos += 1;
-        }
-        token
-    }
-
-    fn error_here(&self, message: impl Into<String>) -> String {
-        match self.peek() {
-            Some(token) => format!("{} near {:?}", message.into(), token),
-            None => format!("{} at end of input", message.into()),
-        }
-    }
-
-    fn peek_ident(&self, word: &str) -> bool {
-        matches!(self.peek(), Some(LexToken::Ident(found)) if found == word)
-    }
-
-    fn eat_ident(&mut self, word: &str) -> bool {
-        if self.peek_ident(word) {
-            self.pos += 1;
-            true
-        } else {
-            false
-        }
-    }
-
-    fn peek_symbol(&self, ch: char) -> bool {
-        matches!(self.peek(), Some(LexToken::Symbol(found)) if *found == ch)
+fn parse_statement(parser: &mut TreeParser<'_>) -> PResult<Command> {
+    if parser.eat_keyword("inc") {
+        return Ok(Command::Inc(parser.ident()?));
     }
-
-    fn eat_symbol(&mut self, ch: char) -> bool {
-        if self.peek_symbol(ch) {
-            self.pos += 1;
-            true
-        } else {
-            false
-        }
+    if parser.eat_keyword("dec") {
+        return Ok(Command::Dec(parser.ident()?));
     }
-
-    fn expect_symbol(&mut self, ch: char) -> Result<(), String> {
-        if self.eat_symbol(ch) {
-            Ok(())
-        } else {
-            Err(self.error_here(format!("expected symbol '{ch}'")))
-        }
+    if parser.eat_keyword("clr") {
+        return Ok(Command::Clr(parser.ident()?));
     }
-
-    fn parse_name(&mut self) -> Result<Var, String> {
-        match self.next() {
-            Some(LexToken::Ident(name)) => Var::new(name).map_err(|e| e.to_string()),
-            _ => Err(self.error_here("expected identifier")),
-        }
+    if parser.eat_keyword("cpy") {
+        let dst = parser.ident()?;
+        parser.expect_op("<-")?;
+        let src = parser.ident()?;
+        return Ok(Command::Cpy(dst, src));
     }
-
-    fn parse_number(&mut self) -> Result<Number, String> {
-        match self.next() {
-            Some(LexToken::Number(n)) => Number::parse(&n),
-            _ => Err(self.error_here("expected number")),
-        }
-    }
-
-    fn parse_statement(&mut self) -> Result<Command, String> {
-        if self.eat_ident("inc") {
-            return Ok(Command::Inc(self.parse_name()?));
-        }
-        if self.eat_ident("dec") {
-            return Ok(Command::Dec(self.parse_name()?));
-        }
-        if self.eat_ident("clr") {
-            return Ok(Command::Clr(self.parse_name()?));
-        }
-        if self.eat_ident("cpy") {
-            let dst = self.parse_name()?;
-            self.expect_symbol('<')?;
-            self.expect_symbol('-')?;
-            let src = self.parse_name()?;
-            return Ok(Command::Cpy(dst, src));
-        }
-        if self.eat_ident("ifnz") {
-            let var = self.parse_name()?;
-            self.expect_symbol(':')?;
-            let num = self.parse_number()?;
-            return Ok(Command::Ifnz(var, num));
-        }
-        Err(self.error_here("expected statement"))
+    if parser.eat_keyword("ifnz") {
+        let var = parser.ident()?;
+        parser.expect_symbol(':')?;
+        let num = parser.number()?;
+        return Ok(Command::Ifnz(var, num));
     }
+    Err(parser.expected("statement"))
 }
 
 #[cfg(test)]
@@ -163,6 +71,7 @@ mod tests {
     use crate::machine::Code;
 
     use super::*;
+    use utils::diagnostic::Span;
 
     #[test]
     fn test_parse_env() {
@@ -216,6 +125,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_errors_point_into_the_code() {
+        let code = "inc x;\ncpy y <- ;\n";
+        let err = &program_read_to_end(code).unwrap_err()[0];
+        assert_eq!(err.span, Some(Span::new(16, 17)));
+        assert_eq!(err.line_col(code), Some((2, 10)));
+
+        // missing ';' at the end
+        let code = "inc x";
+        let err = &program_read_to_end(code).unwrap_err()[0];
+        assert_eq!(err.span, Some(Span::at(5)));
+
+        let err = &env_read_to_end("x = 1; y = ?").unwrap_err()[0];
+        assert_eq!(err.span, Some(Span::new(11, 12)));
+    }
+
+    #[test]
+    fn parse_reports_all_statement_errors() {
+        let code = "inc 1;\ndec x;\nmov x;\nifnz x 0;\n";
+        let errs = program_read_to_end(code).unwrap_err();
+        let lines: Vec<_> = errs
+            .iter()
+            .map(|err| err.line_col(code).unwrap().0)
+            .collect();
+        assert_eq!(lines, vec![1, 3, 4]);
+        assert_eq!(errs[1].message, "expected statement, found 'mov'");
+    }
+
     #[test]
     fn test2() {
         let code = "
@@ -258,4 +195,10 @@ ifnz x : 0;
             }
         }
     }
+
+    #[test]
+    fn code_roundtrip() {
+        let code = Code::parse("inc x; dec y; clr z; cpy x <- y; ifnz z : 0;").unwrap();
+        utils::pretty::check_roundtrip(&code).unwrap();
+    }
 }
@@ -0,0 +1 @@
+utils::interpreter_entry!(lambda_calculus::self_interpreter::SelfInterpreter);
@@ -1,11 +1,13 @@
 pub mod machine;
 pub mod manipulation;
+pub mod self_interpreter;
 
 use crate::machine::{is_normal_form, LambdaTerm, MarkedTerm};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use utils::identifier::Var;
-use utils::{Machine, StepResult, TextCodec};
+use utils::pretty::Doc;
+use utils::{Machine, Metrics, NondetMachine, StepResult, TextCodec};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum SnapshotTerm {
@@ -68,19 +70,47 @@ impl TextCodec for LambdaTerm {
     }
 
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
-        match self {
-            LambdaTerm::Var(var) => write!(f, "{}", var.as_str()),
-            LambdaTerm::Abs(var, lambda_term) => {
-                write!(f, "\\{}. ", var.as_str())?;
-                lambda_term.write_fmt(f)
+        f.write_str(&self.print_pretty(usize::MAX))
+    }
+
+    fn to_doc(&self) -> Doc {
+        term_doc(self, true)
+    }
+}
+
+// application is left associative and an abstraction extends to the right as far as possible,
+// so only an abstraction followed by something (`!last`) and an argument application need parentheses
+fn term_doc(term: &LambdaTerm, last: bool) -> Doc {
+    let paren = |doc: Doc| Doc::text("(") + doc + Doc::text(")");
+    match term {
+        LambdaTerm::Var(var) => Doc::text(var.as_str()),
+        LambdaTerm::Abs(var, body) => {
+            let doc = (Doc::text(format!("\\{}.", var.as_str()))
+                + (Doc::line() + term_doc(body, true)).nest(2))
+            .group();
+            if last {
+                doc
+            } else {
+                paren(doc)
+            }
+        }
+        LambdaTerm::App(..) => {
+            let mut args = vec![];
+            let mut head = term;
+            while let LambdaTerm::App(lhs, rhs) = head {
+                args.push(rhs.as_ref());
+                head = lhs;
             }
-            LambdaTerm::App(lambda_term, lambda_term1) => {
-                write!(f, "(")?;
-                lambda_term.write_fmt(f)?;
-                write!(f, " ")?;
-                lambda_term1.write_fmt(f)?;
-                write!(f, ")")
+            args.reverse();
+            let mut rest = vec![];
+            for (i, arg) in args.iter().enumerate() {
+                let arg_doc = match arg {
+                    LambdaTerm::App(..) => paren(term_doc(arg, true)),
+                    _ => term_doc(arg, last && i + 1 == args.len()),
+                };
+                rest.push(Doc::line() + arg_doc);
             }
+            (term_doc(head, false) + Doc::concat(rest).nest(2)).group()
         }
     }
 }
@@ -155,6 +185,20 @@ impl Machine for LambdaTerm {
         decode_snapshot_term(&snapshot.term, &mut vars)
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("lambda_calculus")
+            .code("a lambda term: `x`, `\\x y. t` or `t t`")
+            .ainput("comma-separated terms the code is applied to (may be empty)")
+            .rinput("index of the redex to reduce, counted from 0 left to right")
+            .default_rinput("0")
+            .example("identity", "(\\x. x) y", "")
+            .example(
+                "successor of two",
+                "\\ x y z. (y ((x y) z))",
+                "\\s z. (s (s z))",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         fn term_block(term: MarkedTerm) -> utils::RenderBlock {
             match term {
@@ -186,9 +230,112 @@ impl Machine for LambdaTerm {
             }
         }
 
-        let restored = Self::restore(snapshot);
+        // the syntax tree, nodes are numbered in preorder and the redex is highlighted
+        fn tree(
+            term: &MarkedTerm,
+            nodes: &mut Vec<utils::RenderGraphNode>,
+            edges: &mut Vec<utils::RenderGraphEdge>,
+        ) -> String {
+            let id = nodes.len().to_string();
+            match term {
+                MarkedTerm::Var(var) => {
+                    nodes.push(utils::render_node!(id, inner: utils::render_text!(var.as_str().to_string())));
+                }
+                MarkedTerm::Abs(var, body) => {
+                    nodes.push(utils::render_node!(id, inner: utils::render_text!(format!("\\{}", var.as_str()))));
+                    let body = tree(body, nodes, edges);
+                    edges.push(utils::render_edge!(id => body));
+                }
+                MarkedTerm::App(lhs, rhs) => {
+                    nodes
+                        .push(utils::render_node!(id, inner: utils::render_text!("@".to_string())));
+                    let lhs = tree(lhs, nodes, edges);
+                    edges.push(utils::render_edge!(id => lhs));
+                    let rhs = tree(rhs, nodes, edges);
+                    edges.push(utils::render_edge!(id => rhs));
+                }
+                MarkedTerm::Red(var, abs_term, app_term) => {
+                    nodes.push(utils::render_node!(id, inner: utils::render_text!("@".to_string()), class: "highlight"));
+                    let abs = nodes.len().to_string();
+                    nodes.push(utils::render_node!(abs, inner: utils::render_text!(format!("\\{}", var.as_str())), class: "highlight"));
+                    edges.push(utils::render_edge!(id => abs, class: "highlight"));
+                    let body = tree(abs_term, nodes, edges);
+                    edges.push(utils::render_edge!(abs => body));
+                    let arg = tree(app_term, nodes, edges);
+                    edges.push(utils::render_edge!(id => arg, class: "highlight"));
+                }
+            }
+            id
+        }
+
+        let restored = <Self as Machine>::restore(snapshot);
         let marked = crate::machine::mark_redex(&restored);
-        utils::render_state![term_block(marked)]
+        let mut nodes = vec![];
+        let mut edges = vec![];
+        tree(&marked, &mut nodes, &mut edges);
+        utils::render_state![
+            term_block(marked),
+            utils::render_graph!(nodes: nodes, edges: edges, title: "tree")
+        ]
+    }
+}
+
+// every step is one beta reduction
+impl Metrics for LambdaTerm {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        fn size(term: &LambdaTerm) -> usize {
+            match term {
+                LambdaTerm::Var(_) => 1,
+                LambdaTerm::Abs(_, body) => 1 + size(body),
+                LambdaTerm::App(lhs, rhs) => 1 + size(lhs) + size(rhs),
+            }
+        }
+        vec![("term", size(self))]
+    }
+
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        vec![("beta", 1)]
+    }
+}
+
+// every redex is a choice, numbered as in `Machine::step`
+impl NondetMachine for LambdaTerm {
+    type Code = LambdaTerm;
+    type AInput = AInput;
+    type FOutput = LambdaTerm;
+    type Choice = usize;
+    type SnapShot = Snapshot;
+
+    fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String> {
+        <Self as Machine>::make(code, ainput)
+    }
+
+    fn halted(&self) -> Option<Self::FOutput> {
+        is_normal_form(self).then(|| self.clone())
+    }
+
+    fn successors(&self) -> Result<Vec<(Self::Choice, Self)>, String> {
+        let marked = crate::machine::mark_redex(self);
+        Ok((0..)
+            .map_while(|i| Some((i, crate::machine::step(&marked, i)?)))
+            .collect())
+    }
+
+    fn snapshot(&self) -> Self::SnapShot {
+        <Self as Machine>::snapshot(self)
+    }
+
+    fn restore(snapshot: Self::SnapShot) -> Self {
+        <Self as Machine>::restore(snapshot)
+    }
+
+    fn render(snapshot: Self::SnapShot) -> utils::RenderState {
+        <Self as Machine>::render(snapshot)
+    }
+
+    // the snapshot numbers variables by address, so the text is used instead
+    fn state_key(&self) -> String {
+        self.print()
     }
 }
 
@@ -203,6 +350,32 @@ mod tests {
         eprintln!("Parsed term: {}", e.print());
     }
 
+    #[test]
+    fn render_draws_the_tree_with_the_redex() {
+        let term = LambdaTerm::parse(r"z ((\x. x) y)").unwrap();
+        let state = <LambdaTerm as Machine>::render(Machine::snapshot(&term));
+        let utils::RenderBlock::Graph(graph) = &state[1] else {
+            panic!("expected a graph block");
+        };
+        let highlighted = graph
+            .nodes
+            .iter()
+            .filter(|node| node.class_name.is_some())
+            .map(|node| node.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(graph.nodes.len(), 6);
+        assert_eq!(highlighted, vec!["2", "3"]);
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![("0", "1"), ("2", "3"), ("3", "4"), ("2", "5"), ("0", "2")]
+        );
+    }
+
     #[test]
     fn parse_application_is_left_associative() {
         let code = "x y z";
@@ -220,10 +393,78 @@ mod tests {
         assert_eq!(parsed.print(), expected.print());
     }
 
+    #[test]
+    fn explore_finds_the_normal_form_and_the_loop() {
+        use utils::nondet::{explore, ExploreOptions};
+        // (\x. \y. y) ((\x. x x) (\x. x x)): reduce the outer redex, or loop on the argument
+        let code = r"(\x. \y. y) ((\x. x x) (\x. x x))";
+        let term = LambdaTerm::parse(code).unwrap();
+        let result = explore(term, ExploreOptions::bfs(100));
+        assert!(result.is_complete());
+        let outputs = result
+            .halting_outputs()
+            .map(|(_, term)| term.print())
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, vec![r"\y. y".to_string()]);
+        assert_eq!(result.cyclic_states(), vec![0]);
+    }
+
     #[test]
     fn parse_multi_binder_abstraction() {
         let code = r"\x y. x";
         let parsed = LambdaTerm::parse(code).unwrap();
         assert_eq!(parsed.print(), r"\x. \y. x");
     }
+
+    #[test]
+    fn print_is_minimally_parenthesized() {
+        let code = r"(\x. x) ((f a) (\y. y)) (\z. z)";
+        let parsed = LambdaTerm::parse(code).unwrap();
+        assert_eq!(parsed.print(), r"(\x. x) (f a \y. y) \z. z");
+        assert_eq!(
+            parsed.print_pretty(16),
+            "(\\x. x)\n  (f a \\y. y)\n  \\z. z"
+        );
+    }
+
+    // pseudo random well scoped terms, some of the names are free or shadowed
+    fn random_term(seed: &mut u64, depth: usize, scope: &mut Vec<Var>) -> LambdaTerm {
+        let mut next = |n: u64| {
+            *seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (*seed >> 33) % n
+        };
+        let name = ["x", "y", "f"][next(3) as usize];
+        match if depth == 0 { 0 } else { next(3) } {
+            0 => LambdaTerm::Var(
+                scope
+                    .iter()
+                    .rev()
+                    .find(|var| var.as_str() == name)
+                    .cloned()
+                    .unwrap_or_else(|| Var::new(name)),
+            ),
+            1 => {
+                let var = Var::new(name);
+                scope.push(var.clone());
+                let body = random_term(seed, depth - 1, scope);
+                scope.pop();
+                LambdaTerm::Abs(var, Box::new(body))
+            }
+            _ => LambdaTerm::App(
+                Box::new(random_term(seed, depth - 1, scope)),
+                Box::new(random_term(seed, depth - 1, scope)),
+            ),
+        }
+    }
+
+    #[test]
+    fn pretty_print_roundtrip_random_terms() {
+        let mut seed = 1;
+        for _ in 0..300 {
+            let term = random_term(&mut seed, 6, &mut vec![]);
+            utils::pretty::check_roundtrip(&term).unwrap();
+        }
+    }
 }
@@ -0,0 +1,161 @@
+// Mogensen's self-interpreter
+// terms are represented as higher-order abstract syntax
+// (binders of the guest are binders of the host)
+// - [x] = \q_var q_app q_abs. q_var x
+// - [M N] = \q_var q_app q_abs. q_app [M] [N]
+// - [\x. M] = \q_var q_app q_abs. q_abs (\x. [M])
+// E = Y (\e m. m (\x. x) (\m n. (e m) (e n)) (\m v. e (m v)))
+// E [M] is beta-equivalent to M, so normal forms agree up to the names of bound variables
+use std::collections::HashSet;
+
+use utils::identifier::Var;
+use utils::{Interpreter, Machine, TextCodec};
+
+use crate::machine::{assoc_app, LambdaTerm};
+use crate::AInput;
+
+const INTERPRETER: &str = r"(\f. (\x. f (x x)) (\x. f (x x)))
+    (\e m. m (\x. x) (\m n. (e m) (e n)) (\m v. e (m v)))";
+
+pub fn quote(term: &LambdaTerm) -> LambdaTerm {
+    let mut names = HashSet::new();
+    collect_names(term, &mut names);
+    // the quote is passed around as text, where a guest variable named like a binder would be captured
+    let binders = ["q_var", "q_app", "q_abs"].map(|base| {
+        (0..)
+            .map(|i| match i {
+                0 => base.to_string(),
+                i => format!("{base}{i}"),
+            })
+            .find(|name| !names.contains(name))
+            .unwrap()
+    });
+    quote_with(term, &binders)
+}
+
+fn collect_names(term: &LambdaTerm, names: &mut HashSet<String>) {
+    match term {
+        LambdaTerm::Var(x) => {
+            names.insert(x.as_str().to_string());
+        }
+        LambdaTerm::App(m, n) => {
+            collect_names(m, names);
+            collect_names(n, names);
+        }
+        LambdaTerm::Abs(x, m) => {
+            names.insert(x.as_str().to_string());
+            collect_names(m, names);
+        }
+    }
+}
+
+fn quote_with(term: &LambdaTerm, binders: &[String; 3]) -> LambdaTerm {
+    let [q_var, q_app, q_abs] = binders.each_ref().map(Var::from);
+    let body = match term {
+        LambdaTerm::Var(x) => assoc_app(q_var.clone().into(), vec![x.into()]),
+        LambdaTerm::App(m, n) => assoc_app(
+            q_app.clone().into(),
+            vec![quote_with(m, binders), quote_with(n, binders)],
+        ),
+        LambdaTerm::Abs(x, m) => assoc_app(
+            q_abs.clone().into(),
+            vec![LambdaTerm::Abs(x.clone(), Box::new(quote_with(m, binders)))],
+        ),
+    };
+    [q_var, q_app, q_abs]
+        .into_iter()
+        .rev()
+        .fold(body, |body, var| LambdaTerm::Abs(var, Box::new(body)))
+}
+
+pub struct SelfInterpreter;
+
+impl Interpreter for SelfInterpreter {
+    type Guest = LambdaTerm;
+    type Host = LambdaTerm;
+
+    fn interpreter() -> Result<<<Self as Interpreter>::Host as Machine>::Code, String> {
+        LambdaTerm::parse(INTERPRETER)
+    }
+
+    fn encode_ainput(
+        code: <<Self as Interpreter>::Guest as Machine>::Code,
+        ainput: <<Self as Interpreter>::Guest as Machine>::AInput,
+    ) -> Result<<<Self as Interpreter>::Host as Machine>::AInput, String> {
+        Ok(AInput(vec![quote(&assoc_app(code, ainput.0))]))
+    }
+
+    fn encode_rinput(
+        rinput: <<Self as Interpreter>::Guest as Machine>::RInput,
+    ) -> Result<<<Self as Interpreter>::Host as Machine>::RInput, String> {
+        let _: usize = rinput;
+        // redexes of the guest do not correspond to those of the host
+        // the host always reduces the leftmost outermost redex
+        Ok(0)
+    }
+
+    fn decode_routput(
+        output: <<Self as Interpreter>::Host as Machine>::ROutput,
+    ) -> Result<<<Self as Interpreter>::Guest as Machine>::ROutput, String> {
+        let _: () = output;
+        Ok(())
+    }
+
+    fn decode_foutput(
+        output: <<Self as Interpreter>::Host as Machine>::FOutput,
+    ) -> Result<<<Self as Interpreter>::Guest as Machine>::FOutput, String> {
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::differential::{assert_interpreter_agrees, DiffOptions};
+
+    #[test]
+    fn quote_survives_text_roundtrip() {
+        let term = LambdaTerm::parse(r"\x. (x q) \y. y").unwrap();
+        let quoted = quote(&term);
+        let reparsed = LambdaTerm::parse(&quoted.print()).unwrap();
+        assert_eq!(reparsed.print(), quoted.print());
+    }
+
+    #[test]
+    fn quote_binders_avoid_the_names_of_the_term() {
+        let term = LambdaTerm::parse(r"\q_abs1. q_var q_abs").unwrap();
+        let quoted = quote(&term).print();
+        assert!(
+            quoted.starts_with(r"\q_var1. \q_app. \q_abs2. "),
+            "{quoted}"
+        );
+        // the quote reaches the host as text, the free variables must stay free after parsing it
+        let mut free: Vec<_> = LambdaTerm::parse(&quoted)
+            .unwrap()
+            .free_variable()
+            .iter()
+            .map(|var| var.as_str().to_string())
+            .collect();
+        free.sort();
+        assert_eq!(free, ["q_abs", "q_var"]);
+    }
+
+    #[test]
+    fn self_interpreter_agrees_with_direct_reduction() {
+        let options = DiffOptions {
+            rinput: "0".to_string(),
+            ..DiffOptions::fuel(100, 10_000)
+        };
+        // normal forms are compared as text, so the results should not contain binders
+        assert_interpreter_agrees::<SelfInterpreter>(
+            r"\n. n f x",
+            &[r"\f x. x", r"\f x. f (f x)"],
+            &options,
+        );
+        assert_interpreter_agrees::<SelfInterpreter>(
+            r"\n m. m (\k. k f x) n",
+            &[r"\f x. f x, \a. a"],
+            &options,
+        );
+    }
+}
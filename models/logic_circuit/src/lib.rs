@@ -5,7 +5,7 @@ pub mod parse;
 
 pub use crate::machine::{Graph, LogicCircuit, LogicCircuitTrait, NamedPin, Signal};
 use utils::bool::Bool;
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 pub mod example {
     use crate::manipulation::{init_maps, List};
     use crate::parse::parse;
@@ -38,8 +38,83 @@ impl TextCodec for LogicCircuit {
         crate::parse::parse_main(text).map_err(|e| e.to_string())
     }
 
+    // a definition for every graph and iter, each after the ones it uses
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
-        write!(f, "logic_circuit")
+        write_definitions(self, &mut vec![], f)
+    }
+}
+
+// the name of `lc` in `graph` and `iter` bodies: a gate of the initial maps or its definition
+fn use_name(lc: &LogicCircuit) -> String {
+    match lc {
+        LogicCircuit::Gate(gate) => match (&gate.kind, gate.state) {
+            (machine::GateKind::End, _) => "END".to_string(),
+            (kind, Bool::T) => format!("{kind}-T"),
+            (kind, Bool::F) => format!("{kind}-F"),
+        },
+        _ => lc.kind().to_string(),
+    }
+}
+
+fn write_definitions(
+    lc: &LogicCircuit,
+    written: &mut Vec<utils::identifier::Identifier>,
+    f: &mut impl std::fmt::Write,
+) -> std::fmt::Result {
+    match lc {
+        LogicCircuit::Gate(_) => Ok(()),
+        _ if written.contains(&lc.kind()) => Ok(()),
+        LogicCircuit::MixLogicCircuit(mix) => {
+            for (_, vert) in &mix.verts {
+                write_definitions(vert, written, f)?;
+            }
+            written.push(mix.kind.clone());
+            let mut inputs = vec![];
+            for (external, _) in &mix.inpin_maps {
+                if !inputs.contains(&external.as_str()) {
+                    inputs.push(external.as_str());
+                }
+            }
+            writeln!(f, "graph: {} {{", mix.kind)?;
+            writeln!(f, "    in {{{}}}", inputs.join(", "))?;
+            let outputs = mix
+                .otpin_maps
+                .iter()
+                .map(|(external, (name, pin))| format!("{external}={name}.{pin}"))
+                .collect::<Vec<_>>();
+            writeln!(f, "    out {{{}}}", outputs.join(", "))?;
+            for (name, vert) in &mix.verts {
+                let from_verts = mix
+                    .edges
+                    .iter()
+                    .filter(|(_, to)| &to.0 == name)
+                    .map(|((from, otpin), (_, inpin))| format!("{inpin}={from}.{otpin}"));
+                let from_inputs = mix
+                    .inpin_maps
+                    .iter()
+                    .filter(|(_, internal)| &internal.0 == name)
+                    .map(|(external, (_, inpin))| format!("{inpin}={external}"));
+                let conns = from_verts.chain(from_inputs).collect::<Vec<_>>();
+                writeln!(f, "    {name}, {} {{{}}}", use_name(vert), conns.join(", "))?;
+            }
+            writeln!(f, "}}")
+        }
+        LogicCircuit::IterLogicCircuit(iter) => {
+            write_definitions(&iter.init, written, f)?;
+            written.push(iter.kind.clone());
+            let conns = |edges: &[(machine::Pin, machine::Pin)]| {
+                edges
+                    .iter()
+                    .map(|(from, to)| format!("{from}={to}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            writeln!(f, "iter: {} {{", iter.kind)?;
+            writeln!(f, "    {},", use_name(&iter.init))?;
+            writeln!(f, "    next {{{}}}", conns(&iter.next_edges))?;
+            writeln!(f, "    prev {{{}}}", conns(&iter.prev_edges))?;
+            writeln!(f, "}}")
+        }
     }
 }
 
@@ -124,6 +199,19 @@ impl Machine for LogicCircuit {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("logic_circuit")
+            .code("`graph: name { in {A, ..} out {O=G.PIN, ..} G, KIND {PIN=A, PIN=G.PIN, ..} .. }` definitions over gates like AND-F, OR-T, NOT-F, BR-F, DLY-F; `main` is run")
+            .ainput("empty")
+            .rinput("input pins set this tick, `main.A=#true, main.B=#false` (unset pins are #false)")
+            .default_rinput("")
+            .example(
+                "and gate",
+                "graph: main {\n    in {A, B}\n    out {O=G.OUT}\n    G, AND-F {IN0=A, IN1=B}\n}",
+                "",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let view = Snapshot::new(snapshot.kind(), snapshot.as_graph_group());
         let input_rows = view
@@ -169,6 +257,41 @@ impl Machine for LogicCircuit {
             })
             .collect::<Vec<_>>();
 
+        // circuit diagram: wires carrying #true are highlighted
+        let vert_nodes = view
+            .graph
+            .verts
+            .iter()
+            .map(|(name, lc)| {
+                let ports = lc
+                    .get_inpins()
+                    .into_iter()
+                    .chain(lc.get_otpins())
+                    .map(|pin| pin.1.to_string())
+                    .collect::<Vec<_>>();
+                let inner = utils::render_text!(format!("{name}: {}", lc.kind()));
+                if lc.state_as_gate() == Some(Bool::T) {
+                    utils::render_node!(name, inner: inner, ports: ports, class: "highlight")
+                } else {
+                    utils::render_node!(name, inner: inner, ports: ports)
+                }
+            })
+            .collect::<Vec<_>>();
+        let wire_edges = view
+            .graph
+            .edges
+            .iter()
+            .map(|(from, to)| {
+                let value = find_output_value(&view.graph, from);
+                let label = value.map(|b| b.print()).unwrap_or("?".to_string());
+                if value == Some(Bool::T) {
+                    utils::render_edge!(from.0 => to.0, from_port: from.1, to_port: to.1, label: label, class: "highlight")
+                } else {
+                    utils::render_edge!(from.0 => to.0, from_port: from.1, to_port: to.1, label: label)
+                }
+            })
+            .collect::<Vec<_>>();
+
         utils::render_state![
             utils::render_text!(view.kind.to_string(), title: "kind"),
             utils::render_table!(
@@ -193,7 +316,23 @@ impl Machine for LogicCircuit {
                 ],
                 rows: vert_rows,
                 title: "verts"
+            ),
+            utils::render_graph!(
+                nodes: vert_nodes,
+                edges: wire_edges,
+                orientation: utils::RenderOrientation::Horizontal,
+                title: "circuit"
             )
         ]
     }
 }
+
+impl Metrics for LogicCircuit {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("gates", self.as_graph_group().verts.len())]
+    }
+
+    fn counters(&self, rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        vec![("inputs", rinput.0.len())]
+    }
+}
@@ -151,3 +151,62 @@ fn test_mix() {
     ]));
     assert_eq!(mix.get_otputs(), vec![(pin("B", "OUT"), Bool::T)]);
 }
+
+#[test]
+fn render_draws_the_circuit() {
+    let mix = MixLogicCircuit {
+        kind: Identifier::new("MIX").unwrap(),
+        verts: vec![
+            (
+                Identifier::new("A").unwrap(),
+                LogicCircuit::Gate(Gate {
+                    kind: GateKind::And,
+                    state: Bool::T,
+                }),
+            ),
+            (
+                Identifier::new("B").unwrap(),
+                LogicCircuit::Gate(Gate {
+                    kind: GateKind::Or,
+                    state: Bool::F,
+                }),
+            ),
+        ],
+        edges: vec![(pin("A", "OUT"), pin("B", "IN0"))],
+        inpin_maps: vec![],
+        otpin_maps: vec![],
+    };
+    let state =
+        <LogicCircuit as utils::Machine>::render(LogicCircuit::MixLogicCircuit(Box::new(mix)));
+    let graph = state
+        .iter()
+        .find_map(|block| match block {
+            utils::RenderBlock::Graph(graph) => Some(graph),
+            _ => None,
+        })
+        .expect("expected a graph block");
+    assert_eq!(graph.nodes.len(), 2);
+    assert_eq!(graph.nodes[0].ports, vec!["IN0", "IN1", "OUT"]);
+    assert_eq!(graph.nodes[0].class_name.as_deref(), Some("highlight"));
+    assert_eq!(graph.nodes[1].class_name, None);
+    let edge = &graph.edges[0];
+    assert_eq!((edge.from.as_str(), edge.to.as_str()), ("A", "B"));
+    assert_eq!(edge.from_port.as_deref(), Some("OUT"));
+    assert_eq!(edge.to_port.as_deref(), Some("IN0"));
+    assert_eq!(edge.class_name.as_deref(), Some("highlight"));
+}
+
+#[test]
+fn code_roundtrip() {
+    let code = include_str!("./logic_circuits/examples.txt").to_string()
+        + "iter: chain {\n    tick-add1,\n    next {O=I}\n    prev {}\n}\n"
+        + "graph: main {\n    in {CK, D}\n    out {Q=F.Q, S=A.S}\n    F, DFF {CK=CK, D=D}\n    A, ADDR {I0=F.nQ, I1=D}\n    T, chain {}\n}\n";
+    let lc = <LogicCircuit as utils::TextCodec>::parse(&code).unwrap();
+    let printed = utils::TextCodec::print(&lc);
+    // only the definitions main uses are printed, each once
+    assert!(printed.starts_with("graph: one-shot {"));
+    assert_eq!(printed.matches("graph: tick-add1 {").count(), 1);
+    assert!(printed.contains("iter: chain {\n    tick-add1,\n    next {O=I}\n    prev {}\n}\n"));
+    assert!(printed.ends_with("    T, chain {}\n}\n"));
+    utils::pretty::check_roundtrip(&lc).unwrap();
+}
@@ -1,184 +1,102 @@
-use anyhow::{bail, Result};
-use utils::lexer::{lex_tree, DelimKind, Token, Tree};
+use anyhow::Result;
+use utils::diagnostic::summarize;
+use utils::lexer::{DelimKind, SpannedTree, Token};
+use utils::tree_parser::{parse_text, PResult, TreeParser};
 
 use crate::machine::Value;
 
 pub fn parse_value(text: &str) -> Result<Value> {
-    let trees = clean_trees(lex_tree(text).map_err(|err| anyhow::anyhow!(err.to_string()))?);
-    let mut parser = Parser::new(&trees);
-    let value = parse_value_inner(&mut parser)?;
-    parser.expect_eof()?;
-    Ok(value)
+    parse_text(text, parse_value_inner).map_err(|errors| summarize(errors).into())
 }
 
-fn parse_value_inner(parser: &mut Parser<'_>) -> Result<Value> {
-    if parser.is_prefixed_ident('#', "true") {
+fn parse_value_inner(parser: &mut TreeParser<'_>) -> PResult<Value> {
+    if is_prefixed_ident(parser, '#', "true") {
         parser.expect_symbol('#')?;
-        parser.expect_ident("true")?;
+        parser.expect_keyword("true")?;
         return Ok(Value::Bit(utils::bool::Bool::T));
     }
-    if parser.is_prefixed_ident('#', "false") {
+    if is_prefixed_ident(parser, '#', "false") {
         parser.expect_symbol('#')?;
-        parser.expect_ident("false")?;
+        parser.expect_keyword("false")?;
         return Ok(Value::Bit(utils::bool::Bool::F));
     }
 
     match parser.peek() {
-        Some(Tree::Delim {
+        Some(SpannedTree::Delim {
             delim: DelimKind::Bracket,
             ..
         }) => parse_array(parser),
-        Some(Tree::Delim {
+        Some(SpannedTree::Delim {
             delim: DelimKind::Brace,
             ..
         }) => parse_struct(parser),
-        Some(Tree::Token(Token::Symbol('<'))) => parse_enum(parser),
-        _ => bail!("expected value"),
+        Some(SpannedTree::Token(token)) if token.token == Token::Symbol('<') => parse_enum(parser),
+        _ => Err(parser.expected("value")),
     }
 }
 
-fn parse_array(parser: &mut Parser<'_>) -> Result<Value> {
+fn parse_array(parser: &mut TreeParser<'_>) -> PResult<Value> {
     let mut inner = parser.expect_delim(DelimKind::Bracket)?;
     let mut values = Vec::new();
-    inner.skip_commas();
-    while inner.peek().is_some() {
+    skip_commas(&mut inner);
+    while !inner.is_eof() {
         values.push(parse_value_inner(&mut inner)?);
-        inner.skip_commas();
+        skip_commas(&mut inner);
     }
     Ok(Value::Array(values))
 }
 
-fn parse_struct(parser: &mut Parser<'_>) -> Result<Value> {
+fn parse_struct(parser: &mut TreeParser<'_>) -> PResult<Value> {
     let mut inner = parser.expect_delim(DelimKind::Brace)?;
     let mut fields = Vec::new();
-    inner.skip_commas();
-    while inner.peek().is_some() {
-        let name = inner.parse_name()?;
+    skip_commas(&mut inner);
+    while !inner.is_eof() {
+        let name = parse_name(&mut inner)?;
         inner.expect_symbol(':')?;
         let value = parse_value_inner(&mut inner)?;
         fields.push((name, value));
-        inner.skip_commas();
+        skip_commas(&mut inner);
     }
-    Value::new_strct(fields).ok_or_else(|| anyhow::anyhow!("field 名がかぶってそう"))
+    Value::new_strct(fields).ok_or_else(|| parser.error_here("field 名がかぶってそう"))
 }
 
-fn parse_enum(parser: &mut Parser<'_>) -> Result<Value> {
+fn parse_enum(parser: &mut TreeParser<'_>) -> PResult<Value> {
     parser.expect_symbol('<')?;
-    let name = parser.parse_name()?;
+    let name = parse_name(parser)?;
     parser.expect_symbol('>')?;
     Ok(Value::new_enume(name))
 }
 
-fn clean_trees(items: Vec<Tree>) -> Vec<Tree> {
-    items
-        .into_iter()
-        .filter_map(|tree| match tree {
-            Tree::Token(Token::Whitespace(_) | Token::Comment(_)) => None,
-            Tree::Token(token) => Some(Tree::Token(token)),
-            Tree::Delim { delim, child } => Some(Tree::Delim {
-                delim,
-                child: clean_trees(child),
-            }),
-        })
-        .collect()
+fn skip_commas(parser: &mut TreeParser<'_>) {
+    while parser.eat_symbol(',') {}
 }
 
-struct Parser<'a> {
-    items: &'a [Tree],
-    pos: usize,
-}
-
-impl<'a> Parser<'a> {
-    fn new(items: &'a [Tree]) -> Self {
-        Self { items, pos: 0 }
-    }
-
-    fn peek(&self) -> Option<&'a Tree> {
-        self.items.get(self.pos)
-    }
-
-    fn next(&mut self) -> Option<&'a Tree> {
-        let item = self.items.get(self.pos);
-        if item.is_some() {
-            self.pos += 1;
-        }
-        item
-    }
-
-    fn expect_eof(&self) -> Result<()> {
-        if self.peek().is_none() {
-            Ok(())
-        } else {
-            bail!("unexpected trailing input")
-        }
-    }
-
-    fn expect_symbol(&mut self, ch: char) -> Result<()> {
-        match self.next() {
-            Some(Tree::Token(Token::Symbol(found))) if *found == ch => Ok(()),
-            _ => bail!("expected symbol '{ch}'"),
-        }
-    }
-
-    fn consume_symbol(&mut self, ch: char) -> bool {
-        match self.peek() {
-            Some(Tree::Token(Token::Symbol(found))) if *found == ch => {
-                self.pos += 1;
-                true
-            }
-            _ => false,
-        }
-    }
-
-    fn skip_commas(&mut self) {
-        while self.consume_symbol(',') {}
-    }
-
-    fn expect_ident(&mut self, expected: &str) -> Result<()> {
-        match self.next() {
-            Some(Tree::Token(Token::Ident(found))) if found == expected => Ok(()),
-            _ => bail!("expected identifier '{expected}'"),
-        }
-    }
-
-    fn is_prefixed_ident(&self, ch: char, ident: &str) -> bool {
-        matches!(
-            (self.items.get(self.pos), self.items.get(self.pos + 1)),
-            (
-                Some(Tree::Token(Token::Symbol(found))),
-                Some(Tree::Token(Token::Ident(found_ident)))
-            ) if *found == ch && found_ident == ident
+fn is_prefixed_ident(parser: &mut TreeParser<'_>, ch: char, ident: &str) -> bool {
+    parser.peek_symbol(ch)
+        && matches!(
+            parser.peek_second(),
+            Some(SpannedTree::Token(token)) if matches!(&token.token, Token::Ident(found) if found == ident)
         )
-    }
+}
 
-    fn expect_delim(&mut self, delim: DelimKind) -> Result<Parser<'a>> {
-        match self.next() {
-            Some(Tree::Delim { delim: found, child }) if *found == delim => Ok(Parser::new(child)),
-            _ => bail!("expected delimiter {:?}", delim),
+// names may contain '-' and '_' between the parts
+fn parse_name(parser: &mut TreeParser<'_>) -> PResult<String> {
+    let mut out = match parser.peek_token() {
+        Some(Token::Ident(text)) => text.clone(),
+        Some(Token::Symbol(ch @ ('-' | '_'))) => ch.to_string(),
+        _ => return Err(parser.expected("name")),
+    };
+    parser.advance();
+
+    while let Some(Token::Symbol(ch @ ('-' | '_'))) = parser.peek_token() {
+        out.push(*ch);
+        parser.advance();
+        match parser.peek_token() {
+            Some(Token::Ident(text) | Token::Number(text)) => out.push_str(text),
+            _ => return Err(parser.expected("name")),
         }
+        parser.advance();
     }
 
-    fn parse_name(&mut self) -> Result<String> {
-        let mut out = match self.next() {
-            Some(Tree::Token(Token::Ident(text))) => text.clone(),
-            Some(Tree::Token(Token::Symbol(ch @ ('-' | '_')))) => ch.to_string(),
-            _ => bail!("expected name"),
-        };
-
-        loop {
-            let Some(Tree::Token(Token::Symbol(ch @ ('-' | '_')))) = self.peek() else {
-                break;
-            };
-            out.push(*ch);
-            self.pos += 1;
-            match self.next() {
-                Some(Tree::Token(Token::Ident(text))) | Some(Tree::Token(Token::Number(text))) => {
-                    out.push_str(text);
-                }
-                _ => bail!("expected name"),
-            }
-        }
-
-        Ok(out)
-    }
+    Ok(out)
 }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, identifier::Identifier};
+use utils::{Machine, Metrics, StepResult, identifier::Identifier};
 
 use crate::expr_machine::{eval_binop, eval_unop};
 use crate::syntax::{BinOp, Expr, ExprCode, PrintEffect, UnOp};
@@ -121,6 +121,29 @@ impl Machine for CekMachine {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::cek_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("pcf-cek")
+            .code(crate::syntax::EXPR_FORMAT)
+            .ainput(crate::syntax::AINPUT_FORMAT)
+            .rinput("empty")
+            .default_rinput("")
+            .example("add", "fun x => fun y => x + y", "(3, 4)")
+            .example(
+                "recursion",
+                "rec f x => if #true then x else f(x) fi",
+                "(5)",
+            )
+    }
+}
+
+impl Metrics for CekMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        match &self.state {
+            CekState::Eval { env, kont, .. } => vec![("env", env.len()), ("kont", kont.len())],
+            CekState::Return { kont, .. } => vec![("kont", kont.len())],
+        }
+    }
 }
 
 fn step_state(state: CekState) -> Result<(CekState, PrintEffect), String> {
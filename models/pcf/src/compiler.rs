@@ -12,6 +12,10 @@ impl Compiler for ExprToCekCompiler {
     type Source = crate::expr_machine::ExprMachine;
     type Target = CekMachine;
 
+    fn describe() -> utils::CompilerDescription {
+        utils::CompilerDescription::of::<Self>().named("pcf-expr-cek")
+    }
+
     fn compile(
         source: <<Self as Compiler>::Source as Machine>::Code,
     ) -> Result<<<Self as Compiler>::Target as Machine>::Code, String> {
@@ -49,6 +53,10 @@ impl Compiler for ExprToSecdCompiler {
     type Source = crate::expr_machine::ExprMachine;
     type Target = SecdMachine;
 
+    fn describe() -> utils::CompilerDescription {
+        utils::CompilerDescription::of::<Self>().named("pcf-expr-secd")
+    }
+
     fn compile(
         source: <<Self as Compiler>::Source as Machine>::Code,
     ) -> Result<<<Self as Compiler>::Target as Machine>::Code, String> {
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, identifier::Identifier};
+use utils::{Machine, Metrics, StepResult, identifier::Identifier};
 
 use crate::syntax::{BinOp, Expr, ExprCode, PrintEffect, UnOp, substitute};
 
@@ -52,6 +52,26 @@ impl Machine for ExprMachine {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::expr_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("pcf-expr")
+            .code(crate::syntax::EXPR_FORMAT)
+            .ainput(crate::syntax::AINPUT_FORMAT)
+            .rinput("empty")
+            .default_rinput("")
+            .example("add", "fun x => fun y => x + y", "(3, 4)")
+            .example(
+                "print order",
+                "(fun x => (fun y => 7))(print 1)(print 2)",
+                "()",
+            )
+    }
+}
+
+impl Metrics for ExprMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("expr", self.expr.size())]
+    }
 }
 
 fn small_step(expr: Expr) -> Result<(Expr, PrintEffect), String> {
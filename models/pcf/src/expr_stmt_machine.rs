@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, identifier::Identifier};
+use utils::{Machine, Metrics, StepResult, identifier::Identifier};
 
 use crate::syntax::{BinOp, PrintEffect, UnOp};
 
@@ -199,6 +199,27 @@ impl Machine for ExprStmtMachine {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::expr_stmt_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("pcf-expr-stmt-cek")
+            .code("expression as in pcf-expr, with blocks `{ stmt; ..; e }` of skip, print n, let x := e, if e then stmt end, while e do stmt end")
+            .ainput(crate::syntax::AINPUT_FORMAT)
+            .rinput("empty")
+            .default_rinput("")
+            .example("block prints", "{ print 1; 2 } + { print 3; 4 }", "()")
+            .example("closure", "({ let x := 1; fun y => x + y })(2)", "()")
+    }
+}
+
+impl Metrics for ExprStmtMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        match &self.state {
+            State::EvalExpr { env, kont, .. }
+            | State::EvalStmt { env, kont, .. }
+            | State::Done { env, kont } => vec![("env", env.len()), ("kont", kont.len())],
+            State::Return { kont, .. } => vec![("kont", kont.len())],
+        }
+    }
 }
 
 fn step_state(state: State) -> Result<(State, PrintEffect), String> {
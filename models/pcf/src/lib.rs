@@ -7,14 +7,17 @@ pub mod syntax;
 
 mod cek_render;
 mod expr_render;
+mod expr_stmt_parser;
 mod expr_stmt_render;
 mod secd_render;
-mod expr_stmt_parser;
 mod syntax_parser;
 
 #[cfg(test)]
 mod tests {
-    use utils::{Compiler, Machine, StepResult, TextCodec};
+    use utils::diagnostic::Span;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
+    use utils::run::{RunOptions, run_code};
+    use utils::{Compiler, Machine, TextCodec};
 
     use crate::{
         cek_machine::CekMachine,
@@ -29,20 +32,13 @@ mod tests {
     where
         M: Machine<ROutput = PrintEffect, RInput = (), AInput = Vec<usize>>,
     {
-        let mut machine = M::make(code, ainput)?;
-        let mut outputs = Vec::new();
-        for _ in 0..200 {
-            match machine.step(())? {
-                StepResult::Continue { next, output } => {
-                    if let Some(value) = output.0 {
-                        outputs.push(value);
-                    }
-                    machine = next;
-                }
-                StepResult::Halt { output } => return Ok((outputs, output)),
-            }
-        }
-        Err("machine did not halt within the limit".to_string())
+        let report = run_code::<M, _>(code, ainput, std::iter::repeat(()), RunOptions::fuel(200))?;
+        let outputs = report
+            .routputs
+            .into_iter()
+            .filter_map(|output| output.0)
+            .collect();
+        Ok((outputs, report.outcome.into_foutput()?))
     }
 
     #[test]
@@ -92,4 +88,57 @@ mod tests {
         let result = run_machine::<ExprStmtMachine>(source, vec![]).unwrap();
         assert_eq!(result, (vec![], 3));
     }
+
+    #[test]
+    fn cek_and_secd_compilers_agree_with_expr() {
+        let options = DiffOptions::fuel(500, 500);
+        let print_order = "(fun x => (fun y => 7))(print 1)(print 2)";
+        assert_compiler_agrees::<ExprToCekCompiler>(print_order, &["()"], &options);
+        assert_compiler_agrees::<ExprToSecdCompiler>(print_order, &["()"], &options);
+
+        let recursion = "rec f x => if #true then x else f(x) fi";
+        assert_compiler_agrees::<ExprToCekCompiler>(recursion, &["(0)", "(5)"], &options);
+        assert_compiler_agrees::<ExprToSecdCompiler>(recursion, &["(0)", "(5)"], &options);
+    }
+
+    #[test]
+    fn parse_errors_point_into_the_code() {
+        let code = "fun x => if x then 1 else 2";
+        let error = ExprCode::parse_spanned(code).unwrap_err();
+        assert_eq!(error.message, "expected keyword 'fi', found end of input");
+        assert_eq!(error.span, Some(Span::at(code.len())));
+
+        let code = "{ print 1; (fun => 2) }";
+        let error = ExprStmtCode::parse_spanned(code).unwrap_err();
+        assert_eq!(error.message, "expected identifier, found '=>'");
+        let start = code.find("=>").unwrap();
+        assert_eq!(error.span, Some(Span::new(start, start + 2)));
+
+        // keywords are not variables, and split operators are not operators
+        let error = ExprCode::parse_spanned("fun if => 1").unwrap_err();
+        assert_eq!(error.message, "expected identifier, found keyword 'if'");
+        let error = ExprStmtCode::parse_spanned("{ let x : = 1; x }").unwrap_err();
+        assert_eq!(error.message, "expected ':=', found ':'");
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        use utils::pretty::check_roundtrip;
+
+        let source = ExprCode::parse("rec f x => if x then f(dec x) + 1 else print 0 fi").unwrap();
+        check_roundtrip(&source).unwrap();
+        check_roundtrip(&ExprToSecdCompiler::compile(source).unwrap()).unwrap();
+        let source = ExprStmtCode::parse("({ let x := 1; fun y => x + y })(2)").unwrap();
+        check_roundtrip(&source).unwrap();
+    }
+
+    #[test]
+    fn pretty_print_breaks_long_expressions() {
+        let source = ExprCode::parse("rec f x => if x then f(dec x) + 1 else print 0 fi").unwrap();
+        assert_eq!(
+            source.print_pretty(30),
+            "(rec f x =>\n  (if x\n    then (f((dec x)) + 1)\n    else print 0\n  fi))"
+        );
+        assert_eq!(source.print_pretty(usize::MAX), source.print());
+    }
 }
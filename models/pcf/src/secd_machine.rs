@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, TextCodec, identifier::Identifier};
+use utils::{Machine, Metrics, StepResult, TextCodec, identifier::Identifier};
 
 use crate::syntax::{BinOp, Expr, PrintEffect, UnOp};
 
@@ -128,6 +128,30 @@ impl Machine for SecdMachine {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::secd_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("pcf-secd")
+            .code("JSON list of SECD instructions, as compiled by pcf-expr-secd")
+            .ainput(crate::syntax::AINPUT_FORMAT)
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "increment",
+                r#"[{"MkCls":{"param":"x","code":[{"Acc":"x"},{"UnOp":"Inc"},"Ret"]}}]"#,
+                "(4)",
+            )
+    }
+}
+
+impl Metrics for SecdMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("stack", self.stack.len()),
+            ("env", self.env.len()),
+            ("control", self.control.len()),
+            ("dump", self.dump.len()),
+        ]
+    }
 }
 
 impl SecdMachine {
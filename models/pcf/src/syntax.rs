@@ -3,6 +3,11 @@ use std::collections::HashSet;
 use serde::{Deserialize, Serialize};
 use utils::{TextCodec, identifier::Identifier};
 
+// format hints of the models taking `ExprCode` (see `Machine::describe`)
+pub(crate) const EXPR_FORMAT: &str = "expression: n, #true, #false, #unit, print n, x, e + e, e - e, e && e, inc e, dec e, not e, fun x => e, rec f x => e, e(e), if e then e else e fi";
+pub(crate) const AINPUT_FORMAT: &str =
+    "naturals the code is applied to, comma-separated in parentheses: `(3, 4)`";
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ExprCode(pub Expr);
 
@@ -85,6 +90,22 @@ impl Expr {
         )
     }
 
+    // number of nodes, used as the term size in metrics
+    pub fn size(&self) -> usize {
+        match self {
+            Expr::Nat(_) | Expr::Bool(_) | Expr::Unit | Expr::Print(_) | Expr::Var(_) => 1,
+            Expr::UnOp(_, body) | Expr::Fun { body, .. } | Expr::Rec { body, .. } => {
+                1 + body.size()
+            }
+            Expr::BinOp(lhs, _, rhs) | Expr::App(lhs, rhs) => 1 + lhs.size() + rhs.size(),
+            Expr::If {
+                cond,
+                then_branch,
+                else_branch,
+            } => 1 + cond.size() + then_branch.size() + else_branch.size(),
+        }
+    }
+
     pub fn expect_nat(&self) -> Result<usize, String> {
         match self {
             Expr::Nat(value) => Ok(*value),
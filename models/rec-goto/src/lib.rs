@@ -291,19 +291,18 @@ impl Compiler for RecToGotoCompiler {
 #[cfg(test)]
 mod tests {
     use recursive_function::machine::{Program as RecProgram, RecursiveFunctions};
-    use utils::StepResult;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
+    use utils::run::{RunOptions, run, run_code};
 
     use super::*;
 
     fn run_rec(function: RecursiveFunctions, input: Vec<usize>) -> Number {
         let input: Vec<Number> = input.into_iter().map(Number::from).collect();
-        let mut machine = RecProgram::make(function.clone(), input).unwrap();
-        loop {
-            match machine.step(()).unwrap() {
-                StepResult::Continue { next, .. } => machine = next,
-                StepResult::Halt { output, .. } => return output,
-            }
-        }
+        let machine = RecProgram::make(function.clone(), input).unwrap();
+        run(machine, std::iter::repeat(()), RunOptions::fuel(usize::MAX))
+            .outcome
+            .into_foutput()
+            .unwrap()
     }
 
     fn run_goto(function: RecursiveFunctions, input: Vec<usize>, step_limit: usize) -> Number {
@@ -311,16 +310,14 @@ mod tests {
         let ainput =
             RecToGotoCompiler::encode_ainput(input.into_iter().map(Number::from).collect())
                 .unwrap();
-        let mut machine = Program::make(code, ainput).unwrap();
-        for _ in 0..step_limit {
-            match machine.step(()).unwrap() {
-                StepResult::Continue { next, .. } => machine = next,
-                StepResult::Halt { output, .. } => {
-                    return RecToGotoCompiler::decode_foutput(output).unwrap();
-                }
-            }
-        }
-        panic!("step limit exceeded");
+        let report = run_code::<Program, _>(
+            code,
+            ainput,
+            std::iter::repeat(()),
+            RunOptions::fuel(step_limit),
+        )
+        .unwrap();
+        RecToGotoCompiler::decode_foutput(report.outcome.into_foutput().unwrap()).unwrap()
     }
 
     #[test]
@@ -368,4 +365,16 @@ mod tests {
         let mu = RecursiveFunctions::muoperator(pred).unwrap();
         assert_eq!(run_rec(mu.clone(), vec![3]), run_goto(mu, vec![3], 5000));
     }
+
+    #[test]
+    fn compiler_agrees_with_recursive_function() {
+        let options = DiffOptions::fuel(10_000, 10_000);
+        assert_compiler_agrees::<RecToGotoCompiler>("SUCC", &["(0)", "(3)"], &options);
+        assert_compiler_agrees::<RecToGotoCompiler>(
+            "PRIM[z: PROJ[1, 0] s: COMP[SUCC: (PROJ[3, 0])]]",
+            &["(0, 0)", "(2, 4)", "(3, 1)"],
+            &options,
+        );
+        assert_compiler_agrees::<RecToGotoCompiler>("MUOP[PROJ[2, 0]]", &["(3)"], &options);
+    }
 }
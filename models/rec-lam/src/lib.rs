@@ -280,6 +280,10 @@ impl Compiler for Rec2LamCompiler {
     type Source = recursive_function::machine::Program;
     type Target = LambdaTerm;
 
+    fn describe() -> utils::CompilerDescription {
+        utils::CompilerDescription::of::<Self>().named("recursive_function_lambda_calculus")
+    }
+
     fn compile(
         source: <<Self as Compiler>::Source as utils::Machine>::Code,
     ) -> Result<<<Self as Compiler>::Target as utils::Machine>::Code, String> {
@@ -325,6 +329,7 @@ impl Compiler for Rec2LamCompiler {
 #[cfg(test)]
 mod tests {
     use lambda_calculus::machine::{alpha_eq, mark_redex, step, unmark_redex};
+    use utils::differential::{assert_compiler_agrees, DiffOptions};
     use utils::TextCodec;
 
     fn normalize(term: &LambdaTerm, limit: usize) -> LambdaTerm {
@@ -446,4 +451,15 @@ mod tests {
         eprintln!("expected: {}", expected.print());
         assert!(alpha_eq(&normalized, &expected))
     }
+
+    #[test]
+    fn compiler_agrees_with_recursive_function() {
+        let options = DiffOptions::fuel(1_000, 10_000);
+        assert_compiler_agrees::<Rec2LamCompiler>("SUCC", &["(0)", "(2)"], &options);
+        assert_compiler_agrees::<Rec2LamCompiler>(
+            "PRIM[z: PROJ[1, 0] s: COMP[SUCC: (PROJ[3, 0])]]",
+            &["(0, 1)", "(1, 1)", "(2, 1)"],
+            &options,
+        );
+    }
 }
@@ -0,0 +1 @@
+utils::compiler_entry!(recursive_function_turing_machine::RecToTmCompiler);
@@ -1,3 +1,10 @@
 pub mod rec_tm_ir;
 pub mod rec_tm_ir_jump;
 pub mod rec_to_ir;
+
+// recursive_function -> rec_tm_ir -> rec_tm_ir_jump -> turing_machine
+pub type RecToTmCompiler = utils::compose!(
+    rec_to_ir::RecToRecTmIrCompiler,
+    rec_tm_ir::RecTmIrToJumpCompiler,
+    rec_tm_ir_jump::RecTmIrJumpToTmCompiler,
+);
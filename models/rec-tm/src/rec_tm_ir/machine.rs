@@ -3,7 +3,7 @@ use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
 use turing_machine::machine::{Direction, Sign, Tape};
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 use super::parser::{parse_identifier, render_text};
 use super::validation::{
@@ -344,6 +344,19 @@ impl Machine for RecTmIrMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("rec_tm_ir")
+            .code("`alphabet: (a, ..)` then `fn name() { label l: { stmt .. } .. }` definitions, `main` runs first; statements LT, RT, x := @, @ := x, x := const a, jump l [if x == y], break, continue, return, call f")
+            .ainput("tape `left|head|right` over the alphabet, as turing_machine")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "scan and mark",
+                "alphabet: (m, a, b, x)\nfn main() {\n  label entry: {\n    mark := @\n    jump scan\n  }\n  label scan: {\n    RT\n    cur := @\n    jump done if cur == const x\n    jump scan\n  }\n  label done: {\n    @ := mark\n  }\n}",
+                "-|m|a,b,x,-",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let instruction = snapshot.peek_stmt().map(|stmt| render_text(&stmt));
         let stack = snapshot
@@ -413,6 +426,12 @@ impl Machine for RecTmIrMachine {
     }
 }
 
+impl Metrics for RecTmIrMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("tape", self.tape.size()), ("stack", self.stack.len())]
+    }
+}
+
 impl RecTmIrMachine {
     fn return_from_call(&mut self) -> Option<Tape> {
         if let Some(frame) = self.stack.pop() {
@@ -1,3 +1,6 @@
+use std::collections::HashSet;
+use std::rc::Rc;
+
 use turing_machine::machine::Sign;
 use utils::TextCodec;
 use utils::identifier::Identifier;
@@ -370,7 +373,7 @@ impl TextCodec for Program {
         write!(f, "alphabet: ")?;
         self.alphabet.write_fmt(f)?;
         writeln!(f)?;
-        for (idx, function) in self.functions.iter().enumerate() {
+        for (idx, function) in definition_order(&self.functions).iter().enumerate() {
             if idx > 0 {
                 writeln!(f)?;
             }
@@ -382,6 +385,27 @@ impl TextCodec for Program {
     }
 }
 
+// callees before their callers, as a call is resolved by the functions parsed before it
+fn definition_order(functions: &[Rc<Function>]) -> Vec<Rc<Function>> {
+    fn visit(func: &Rc<Function>, seen: &mut HashSet<String>, order: &mut Vec<Rc<Function>>) {
+        if !seen.insert(func.name.clone()) {
+            return;
+        }
+        for stmt in func.blocks.iter().flat_map(|block| &block.body) {
+            if let Stmt::Call { func: callee } = stmt {
+                visit(callee, seen, order);
+            }
+        }
+        order.push(func.clone());
+    }
+    let mut seen = HashSet::new();
+    let mut order = Vec::new();
+    for func in functions {
+        visit(func, &mut seen, &mut order);
+    }
+    order
+}
+
 pub(crate) fn render_text(stmt: &Stmt) -> String {
     let mut out = String::new();
     let _ = write_stmt_line(&mut out, stmt, 0);
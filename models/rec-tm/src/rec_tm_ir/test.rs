@@ -1,24 +1,17 @@
 use crate::{assign, lv, rv};
 use std::rc::Rc;
 use turing_machine::machine::{Sign, Tape};
+use utils::run::{RunOptions, run};
 use utils::{Machine, RenderBlock, StepResult, TextCodec, parse::ParseTextCodec};
 
 use crate::rec_tm_ir::{Block, Function, Program, Stmt};
 
 use super::RecTmIrMachine;
 
-fn run_until_halt(mut machine: RecTmIrMachine, limit: usize) -> Result<Tape, String> {
-    for _ in 0..limit {
-        match machine.step(())? {
-            StepResult::Continue { next, .. } => {
-                machine = next;
-            }
-            StepResult::Halt { output } => {
-                return Ok(output);
-            }
-        }
-    }
-    Err("step limit exceeded".to_string())
+fn run_until_halt(machine: RecTmIrMachine, limit: usize) -> Result<Tape, String> {
+    run(machine, std::iter::repeat(()), RunOptions::fuel(limit))
+        .outcome
+        .into_foutput()
 }
 
 fn tape_from_machine(machine: &RecTmIrMachine) -> Result<Tape, String> {
@@ -327,3 +320,45 @@ fn main() {
     let result = run_until_halt(machine, 8);
     assert!(result.is_err());
 }
+
+#[test]
+fn code_roundtrip() {
+    use crate::rec_tm_ir::RecTmIrToJumpCompiler;
+    use utils::Compiler;
+    use utils::pretty::check_roundtrip;
+
+    let code = r#"
+alphabet: (a, b)
+fn f() {
+  label entry: {
+    x := @
+  }
+}
+
+fn main() {
+  label entry: {
+    x := @
+    RT
+    call f
+    LT
+    @ := x
+  }
+}
+"#;
+    let program = <RecTmIrMachine as Machine>::parse_code(code).unwrap();
+    check_roundtrip(&program).unwrap();
+    check_roundtrip(&RecTmIrToJumpCompiler::compile(program).unwrap()).unwrap();
+}
+
+#[test]
+fn compiled_program_reparses() {
+    use crate::rec_to_ir::RecToRecTmIrCompiler;
+    use utils::Compiler;
+
+    // `main` comes first in the compiled program, but calls the functions after it
+    let code = <<RecToRecTmIrCompiler as Compiler>::Source as Machine>::parse_code("SUCC").unwrap();
+    let program = RecToRecTmIrCompiler::compile(code).unwrap();
+    let printed = program.print();
+    let reparsed = <RecTmIrMachine as Machine>::parse_code(&printed).unwrap();
+    assert_eq!(reparsed.print(), printed);
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use turing_machine::machine::{CodeEntry, Direction, Sign, State, TuringMachineDefinition};
-use utils::{Compiler, Machine, TextCodec};
+use utils::{Compiler, Machine, SourceMap, TextCodec};
 
 use super::machine::{LValue, Program, RValue, Stmt};
 
@@ -14,6 +14,12 @@ impl Compiler for RecTmIrJumpToTmCompiler {
     fn compile(
         source: <<Self as Compiler>::Source as Machine>::Code,
     ) -> Result<<<Self as Compiler>::Target as Machine>::Code, String> {
+        compile_program(&source).map(|(code, _)| code)
+    }
+
+    fn compile_mapped(
+        source: <<Self as Compiler>::Source as Machine>::Code,
+    ) -> Result<(<<Self as Compiler>::Target as Machine>::Code, SourceMap), String> {
         compile_program(&source)
     }
 
@@ -44,7 +50,8 @@ impl Compiler for RecTmIrJumpToTmCompiler {
     }
 }
 
-fn compile_program(program: &Program) -> Result<TuringMachineDefinition, String> {
+// the states `q_{pc}_{env}` map to the statement at pc
+fn compile_program(program: &Program) -> Result<(TuringMachineDefinition, SourceMap), String> {
     let alphabet = normalized_alphabet(&program.alphabet)?;
     validate_constants(program, &alphabet)?;
     let vars = collect_vars(&program.body);
@@ -52,9 +59,11 @@ fn compile_program(program: &Program) -> Result<TuringMachineDefinition, String>
     let state_map = build_state_map(program.body.len(), &envs)?;
 
     let mut code = Vec::new();
+    let mut map = SourceMap::default();
     for (pc, stmt) in program.body.iter().enumerate() {
         for (env_idx, env) in envs.iter().enumerate() {
             let state = state_for(pc, env_idx, &state_map)?;
+            map.insert(state.print(), pc);
             match stmt {
                 Stmt::Lt => add_for_all_tape(
                     &mut code,
@@ -182,7 +191,9 @@ fn compile_program(program: &Program) -> Result<TuringMachineDefinition, String>
     let accepted_states = (0..envs.len())
         .map(|env_idx| state_for(program.body.len(), env_idx, &state_map))
         .collect::<Result<Vec<_>, _>>()?;
-    TuringMachineDefinition::new(init_state, accepted_states, code).map_err(|e| e.to_string())
+    let definition = TuringMachineDefinition::new(init_state, accepted_states, code)
+        .map_err(|e| e.to_string())?;
+    Ok((definition, map))
 }
 
 fn validate_constants(program: &Program, alphabet: &[Sign]) -> Result<(), String> {
@@ -367,3 +378,37 @@ where
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use turing_machine::machine::TuringMachine;
+    use utils::{StepResult, source_map::lookup};
+
+    use super::*;
+
+    #[test]
+    fn compiled_states_map_to_their_statements() {
+        let code = "alphabet: (a)\nRT\nx := @\n@ := x\n";
+        let program = Program::parse(code).unwrap();
+        let (tm, map) = RecTmIrJumpToTmCompiler::compile_mapped(program).unwrap();
+        let mappings = map.resolve(&Program::node_spans(code).unwrap()).unwrap();
+
+        // the source lines of the states the compiled machine goes through
+        let mut lines: Vec<&str> = Vec::new();
+        let mut machine =
+            TuringMachine::make(tm, TuringMachine::parse_ainput("-|-|a").unwrap()).unwrap();
+        loop {
+            if let Some(mapping) = lookup(&mappings, &machine.location().unwrap()) {
+                let (_, _, line) = mapping.line(code);
+                if lines.last() != Some(&line) {
+                    lines.push(line);
+                }
+            }
+            match machine.step(()).unwrap() {
+                StepResult::Continue { next, .. } => machine = next,
+                StepResult::Halt { .. } => break,
+            }
+        }
+        assert_eq!(lines, ["RT", "x := @", "@ := x"]);
+    }
+}
@@ -2,7 +2,7 @@ use std::collections::{BTreeMap, BTreeSet, HashSet};
 
 use serde::{Deserialize, Serialize};
 use turing_machine::machine::{Direction, Sign, Tape};
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 use super::parser::parse_identifier;
 
@@ -233,6 +233,19 @@ impl Machine for RecTmIrJumpMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("rec_tm_ir_jump")
+            .code("`alphabet: (a, ..)` then one statement per line: LT, RT, x := @, @ := x, x := const a, jump [if x == y] n (0-indexed)")
+            .ainput("tape `left|head|right` over the alphabet, as turing_machine")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "scan and mark",
+                "alphabet: (m, a, b, x)\nmark := @\njump 2\nRT\ncur := @\njump if cur == const x 6\njump 2\n@ := mark",
+                "-|m|a,b,x,-",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         fn render_lvalue(value: &LValue) -> String {
             match value {
@@ -314,6 +327,12 @@ impl Machine for RecTmIrJumpMachine {
     }
 }
 
+impl Metrics for RecTmIrJumpMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("tape", self.tape.size())]
+    }
+}
+
 fn validate_program(program: &Program) -> Result<(), String> {
     let len = program.body.len();
     for (idx, stmt) in program.body.iter().enumerate() {
@@ -1,5 +1,6 @@
 use turing_machine::machine::Sign;
 use utils::TextCodec;
+use utils::diagnostic::Span;
 use utils::identifier::Identifier;
 
 use super::machine::{Condition, LValue, Program, RValue, Stmt};
@@ -30,22 +31,21 @@ fn is_keyword(name: &str) -> bool {
     KEYWORDS.contains(&name)
 }
 
-fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+// tokens with their byte ranges
+fn tokenize(text: &str) -> Result<Vec<(Token, Span)>, String> {
     let mut tokens = Vec::new();
-    let mut chars = text.chars().peekable();
-    while let Some(&ch) = chars.peek() {
+    let mut chars = text.char_indices().peekable();
+    while let Some(&(start, ch)) = chars.peek() {
         if ch.is_whitespace() {
             chars.next();
             continue;
         }
-        if ch == '-' {
+        let token = if ch == '-' {
             chars.next();
-            tokens.push(Token::Ident("-".to_string()));
-            continue;
-        }
-        if ch.is_ascii_digit() {
+            Token::Ident("-".to_string())
+        } else if ch.is_ascii_digit() {
             let mut digits = String::new();
-            while let Some(&next) = chars.peek() {
+            while let Some(&(_, next)) = chars.peek() {
                 if next.is_ascii_digit() {
                     digits.push(next);
                     chars.next();
@@ -53,14 +53,12 @@ fn tokenize(text: &str) -> Result<Vec<Token>, String> {
                     break;
                 }
             }
-            tokens.push(Token::Number(digits));
-            continue;
-        }
-        if ch.is_ascii_alphabetic() || ch == '_' {
+            Token::Number(digits)
+        } else if ch.is_ascii_alphabetic() || ch == '_' {
             let mut ident = String::new();
             ident.push(ch);
             chars.next();
-            while let Some(&next) = chars.peek() {
+            while let Some(&(_, next)) = chars.peek() {
                 if next.is_ascii_alphanumeric() || next == '_' || next == '-' {
                     ident.push(next);
                     chars.next();
@@ -68,52 +66,63 @@ fn tokenize(text: &str) -> Result<Vec<Token>, String> {
                     break;
                 }
             }
-            tokens.push(Token::Ident(ident));
-            continue;
-        }
-        match ch {
-            '(' | ')' | '{' | '}' | ',' => {
-                chars.next();
-                tokens.push(Token::Symbol(ch.to_string()));
-            }
-            '@' => {
-                chars.next();
-                tokens.push(Token::Symbol("@".to_string()));
-            }
-            ':' => {
-                chars.next();
-                if let Some('=') = chars.peek() {
+            Token::Ident(ident)
+        } else {
+            match ch {
+                '(' | ')' | '{' | '}' | ',' => {
                     chars.next();
-                    tokens.push(Token::Symbol(":=".to_string()));
-                } else {
-                    tokens.push(Token::Symbol(":".to_string()));
+                    Token::Symbol(ch.to_string())
                 }
-            }
-            '=' => {
-                chars.next();
-                if let Some('=') = chars.peek() {
+                '@' => {
                     chars.next();
-                    tokens.push(Token::Symbol("==".to_string()));
-                } else {
-                    return Err("Unexpected '='".to_string());
+                    Token::Symbol("@".to_string())
+                }
+                ':' => {
+                    chars.next();
+                    if let Some((_, '=')) = chars.peek() {
+                        chars.next();
+                        Token::Symbol(":=".to_string())
+                    } else {
+                        Token::Symbol(":".to_string())
+                    }
+                }
+                '=' => {
+                    chars.next();
+                    if let Some((_, '=')) = chars.peek() {
+                        chars.next();
+                        Token::Symbol("==".to_string())
+                    } else {
+                        return Err("Unexpected '='".to_string());
+                    }
+                }
+                _ => {
+                    return Err(format!("Unexpected character '{}'", ch));
                 }
             }
-            _ => {
-                return Err(format!("Unexpected character '{}'", ch));
-            }
-        }
+        };
+        let end = chars.peek().map_or(text.len(), |&(i, _)| i);
+        tokens.push((token, Span::new(start, end)));
     }
     Ok(tokens)
 }
 
 struct Parser {
     tokens: Vec<Token>,
+    spans: Vec<Span>,
     pos: usize,
+    // spans of the parsed statements, the i-th is the statement at pc i
+    stmt_spans: Vec<Span>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<Token>) -> Self {
-        Parser { tokens, pos: 0 }
+    fn new(tokens: Vec<(Token, Span)>) -> Self {
+        let (tokens, spans) = tokens.into_iter().unzip();
+        Parser {
+            tokens,
+            spans,
+            pos: 0,
+            stmt_spans: Vec::new(),
+        }
     }
 
     fn is_eof(&self) -> bool {
@@ -184,7 +193,10 @@ impl Parser {
         let alphabet = self.parse_alphabet()?;
         let mut body = Vec::new();
         while !self.is_eof() {
+            let start = self.spans[self.pos].start;
             body.push(self.parse_stmt()?);
+            let end = self.spans[self.pos - 1].end;
+            self.stmt_spans.push(Span::new(start, end));
         }
         Ok(Program { alphabet, body })
     }
@@ -310,19 +322,24 @@ impl Parser {
     }
 }
 
-fn parse_program(text: &str) -> Result<Program, String> {
+// program and the span of each statement, indexed by pc as in source maps of `compile_tm`
+pub fn parse_with_spans(text: &str) -> Result<(Program, Vec<Span>), String> {
     let tokens = tokenize(text)?;
     let mut parser = Parser::new(tokens);
     let program = parser.parse_program()?;
     if !parser.is_eof() {
         return Err("Unexpected trailing tokens".to_string());
     }
-    Ok(program)
+    Ok((program, parser.stmt_spans))
 }
 
 impl TextCodec for Program {
     fn parse(text: &str) -> Result<Self, String> {
-        parse_program(text)
+        parse_with_spans(text).map(|(program, _)| program)
+    }
+
+    fn node_spans(text: &str) -> Result<Vec<Span>, String> {
+        parse_with_spans(text).map(|(_, spans)| spans)
     }
 
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
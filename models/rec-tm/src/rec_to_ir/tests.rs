@@ -593,3 +593,37 @@ fn test_pred_tuple() {
     let rslt = snapshot_tape(snapshot);
     assert_eq!(rslt, expd);
 }
+
+/*
+=== whole pipeline ===
+*/
+
+#[test]
+fn composed_compiler_runs_on_turing_machine() {
+    use crate::RecToTmCompiler;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
+
+    // the generated machine grows quickly, so keep the function small
+    let options = DiffOptions::fuel(1_000, 10_000);
+    assert_compiler_agrees::<RecToTmCompiler>("SUCC", &["(0)", "(2)"], &options);
+}
+
+#[test]
+fn compiler_to_ir_agrees_with_recursive_function() {
+    use super::RecToRecTmIrCompiler;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
+
+    let options = DiffOptions::fuel(1_000, 100_000);
+    assert_compiler_agrees::<RecToRecTmIrCompiler>("ZERO", &["()"], &options);
+    assert_compiler_agrees::<RecToRecTmIrCompiler>("SUCC", &["(0)", "(3)"], &options);
+    assert_compiler_agrees::<RecToRecTmIrCompiler>(
+        "PROJ[3, 1]",
+        &["(2, 0, 3)", "(4, 5, 1)"],
+        &options,
+    );
+    assert_compiler_agrees::<RecToRecTmIrCompiler>(
+        "COMP[SUCC: (PROJ[2, 1])]",
+        &["(1, 2)"],
+        &options,
+    );
+}
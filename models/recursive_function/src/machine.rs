@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use std::fmt::Display;
-use utils::{number::*, Machine, StepResult, TextCodec};
+use utils::{number::*, Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum RecursiveFunctions {
@@ -111,38 +111,7 @@ impl RecursiveFunctions {
 
 impl Display for RecursiveFunctions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let str = match self {
-            RecursiveFunctions::ZeroConstant => "ZERO".to_string(),
-            RecursiveFunctions::Successor => "SUCC".to_string(),
-            RecursiveFunctions::Projection {
-                parameter_length,
-                projection_num,
-            } => {
-                format!("PROJ[{parameter_length},{projection_num}]")
-            }
-            RecursiveFunctions::Composition {
-                outer_func,
-                inner_funcs,
-                ..
-            } => {
-                let inner: String = inner_funcs
-                    .iter()
-                    .map(|func| format!("{func}"))
-                    .reduce(|str1, str2| str1 + &str2)
-                    .unwrap_or("no function".to_string());
-                format!("COMP[{outer_func}: {}]", inner)
-            }
-            RecursiveFunctions::PrimitiveRecursion {
-                zero_func,
-                succ_func,
-            } => {
-                format!("PRIM[z:{} s:{}]", zero_func, succ_func)
-            }
-            RecursiveFunctions::MuOperator { mu_func } => {
-                format!("MUOP[{mu_func}]")
-            }
-        };
-        write!(f, "{str}")
+        write!(f, "{}", self.print())
     }
 }
 
@@ -545,6 +514,44 @@ impl Machine for Program {
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::recursive_render::render_machine(snapshot)
     }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("recursive_function")
+            .code("`let name = f.` definitions, then a function built from ZERO, SUCC, PROJ[n,i], COMP[f: (g, ..)], PRIM[z: f s: g], MUOP[f]")
+            .ainput("the arguments, comma-separated numbers in parentheses: `(3, 4)`")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "add",
+                "let zf = PROJ[1,0].\nlet sf = COMP[SUCC: PROJ[3,0]].\nlet add = PRIM[z: zf s: sf].\nadd",
+                "(3, 4)",
+            )
+            .example("successor", "SUCC", "(2)")
+    }
+}
+
+// size and nesting depth of the process tree
+impl Metrics for Program {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        fn measure(process: &Process) -> (usize, usize) {
+            match process {
+                Process::Comp { args, .. } => {
+                    args.iter()
+                        .map(measure)
+                        .fold((1, 1), |(size, depth), (arg_size, arg_depth)| {
+                            (size + arg_size, depth.max(arg_depth + 1))
+                        })
+                }
+                Process::MuOpComp { process, .. } => {
+                    let (size, depth) = measure(process);
+                    (size + 1, depth + 1)
+                }
+                Process::Result(_) => (1, 1),
+            }
+        }
+        let (size, depth) = measure(&self.process);
+        vec![("process", size), ("depth", depth)]
+    }
 }
 
 #[cfg(test)]
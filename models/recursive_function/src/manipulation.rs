@@ -1,3 +1,3 @@
 pub fn parse(str: &str) -> Result<crate::machine::RecursiveFunctions, String> {
-    crate::parser::parse(str)
+    crate::parser::parse(str).map_err(String::from)
 }
@@ -1,13 +1,15 @@
 use std::collections::HashMap;
 
-use utils::lexer::{lex_tree, DelimKind, Token, Tree};
+use utils::diagnostic::{Diagnostic, Span};
+use utils::lexer::{lex_tree_spanned, DelimKind, SpannedTree, Token};
+use utils::pretty::Doc;
 use utils::TextCodec;
 
 use crate::machine::RecursiveFunctions;
 
-pub fn parse(text: &str) -> Result<RecursiveFunctions, String> {
-    let trees = clean_trees(lex_tree(text).map_err(|err| err.to_string())?);
-    let mut parser = Parser::new(&trees);
+pub fn parse(text: &str) -> Result<RecursiveFunctions, Diagnostic> {
+    let trees = clean_trees(lex_tree_spanned(text)?);
+    let mut parser = Parser::new(&trees, Span::at(text.len()));
     let mut map = HashMap::new();
 
     while parser.is_keyword("let") {
@@ -22,25 +24,70 @@ pub fn parse(text: &str) -> Result<RecursiveFunctions, String> {
 
 impl TextCodec for RecursiveFunctions {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
         parse(text)
     }
 
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
-        write!(f, "{}", self)
+        f.write_str(&self.print_pretty(usize::MAX))
+    }
+
+    fn to_doc(&self) -> Doc {
+        match self {
+            RecursiveFunctions::ZeroConstant => Doc::text("ZERO"),
+            RecursiveFunctions::Successor => Doc::text("SUCC"),
+            RecursiveFunctions::Projection {
+                parameter_length,
+                projection_num,
+            } => Doc::text(format!("PROJ[{parameter_length},{projection_num}]")),
+            RecursiveFunctions::Composition {
+                outer_func,
+                inner_funcs,
+                ..
+            } => {
+                let inner = Doc::join(
+                    inner_funcs.iter().map(TextCodec::to_doc),
+                    Doc::text(",") + Doc::line(),
+                );
+                let body = outer_func.to_doc() + Doc::text(":") + Doc::line() + inner.group();
+                Doc::bracket("COMP[", body, "]", 2)
+            }
+            RecursiveFunctions::PrimitiveRecursion {
+                zero_func,
+                succ_func,
+            } => {
+                let body = Doc::text("z: ")
+                    + zero_func.to_doc()
+                    + Doc::line()
+                    + Doc::text("s: ")
+                    + succ_func.to_doc();
+                Doc::bracket("PRIM[", body, "]", 2)
+            }
+            RecursiveFunctions::MuOperator { mu_func } => {
+                Doc::bracket("MUOP[", mu_func.to_doc(), "]", 2)
+            }
+        }
     }
 }
 
 fn parse_let_statement(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<(String, RecursiveFunctions), String> {
+) -> Result<(String, RecursiveFunctions), Diagnostic> {
     parser.expect_keyword("let")?;
+    let name_span = parser.span_here();
     let name = parser.parse_name()?;
     parser.expect_symbol('=')?;
     let func = parse_func(parser, map)?;
     parser.expect_symbol('.')?;
     if map.contains_key(&name) {
-        return Err(format!("Function {name} already exists"));
+        return Err(Diagnostic::error(
+            name_span,
+            format!("Function {name} already exists"),
+        ));
     }
     Ok((name, func))
 }
@@ -48,48 +95,51 @@ fn parse_let_statement(
 fn parse_func(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<RecursiveFunctions, String> {
+) -> Result<RecursiveFunctions, Diagnostic> {
+    let name_span = parser.span_here();
     let name = parser.parse_name()?;
+    // errors of the constructors point to the whole "NAME[...]"
+    let whole = |parser: &Parser<'_>| name_span.to(parser.span_prev());
     match name.as_str() {
         "ZERO" => Ok(RecursiveFunctions::zero()),
         "SUCC" => Ok(RecursiveFunctions::succ()),
-        "PROJ" => parse_proj(parser),
-        "COMP" => parse_comp(parser, map),
-        "PRIM" => parse_prim(parser, map),
-        "MUOP" => parse_muop(parser, map),
+        "PROJ" => parse_proj(parser).map_err(|e| e.or_span(whole(parser))),
+        "COMP" => parse_comp(parser, map).map_err(|e| e.or_span(whole(parser))),
+        "PRIM" => parse_prim(parser, map).map_err(|e| e.or_span(whole(parser))),
+        "MUOP" => parse_muop(parser, map).map_err(|e| e.or_span(whole(parser))),
         _ => map
             .get(&name)
             .cloned()
-            .ok_or_else(|| format!("Function {name} not found")),
+            .ok_or_else(|| Diagnostic::error(name_span, format!("Function {name} not found"))),
     }
 }
 
-fn parse_proj(parser: &mut Parser<'_>) -> Result<RecursiveFunctions, String> {
+fn parse_proj(parser: &mut Parser<'_>) -> Result<RecursiveFunctions, Diagnostic> {
     let mut inner = parser.expect_delim(DelimKind::Bracket)?;
     let length = inner.parse_number()?;
     inner.expect_symbol(',')?;
     let number = inner.parse_number()?;
     inner.expect_eof()?;
-    RecursiveFunctions::projection(length, number)
+    Ok(RecursiveFunctions::projection(length, number)?)
 }
 
 fn parse_comp(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<RecursiveFunctions, String> {
+) -> Result<RecursiveFunctions, Diagnostic> {
     let mut inner = parser.expect_delim(DelimKind::Bracket)?;
     let outer = parse_func(&mut inner, map)?;
     inner.expect_symbol(':')?;
     let inner_funcs = parse_func_list(&mut inner, map)?;
     inner.skip_commas();
     inner.expect_eof()?;
-    RecursiveFunctions::composition(outer, inner_funcs)
+    Ok(RecursiveFunctions::composition(outer, inner_funcs)?)
 }
 
 fn parse_prim(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<RecursiveFunctions, String> {
+) -> Result<RecursiveFunctions, Diagnostic> {
     let mut inner = parser.expect_delim(DelimKind::Bracket)?;
     inner.expect_keyword("z")?;
     inner.expect_symbol(':')?;
@@ -98,36 +148,37 @@ fn parse_prim(
     inner.expect_symbol(':')?;
     let succ = parse_func(&mut inner, map)?;
     inner.expect_eof()?;
-    RecursiveFunctions::primitive_recursion(zero, succ)
+    Ok(RecursiveFunctions::primitive_recursion(zero, succ)?)
 }
 
 fn parse_muop(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<RecursiveFunctions, String> {
+) -> Result<RecursiveFunctions, Diagnostic> {
     let mut inner = parser.expect_delim(DelimKind::Bracket)?;
     let func = parse_func(&mut inner, map)?;
     inner.expect_eof()?;
-    RecursiveFunctions::muoperator(func)
+    Ok(RecursiveFunctions::muoperator(func)?)
 }
 
 fn parse_func_list(
     parser: &mut Parser<'_>,
     map: &HashMap<String, RecursiveFunctions>,
-) -> Result<Vec<RecursiveFunctions>, String> {
+) -> Result<Vec<RecursiveFunctions>, Diagnostic> {
     let mut items = Vec::new();
     if parser.peek().is_none() {
         return Ok(items);
     }
 
     loop {
-        if let Some(Tree::Delim {
+        if let Some(SpannedTree::Delim {
             delim: DelimKind::Paren,
+            span,
             child,
         }) = parser.peek()
         {
             parser.pos += 1;
-            let mut inner = Parser::new(child);
+            let mut inner = Parser::new(child, closing(*span));
             items.extend(parse_func_list(&mut inner, map)?);
             inner.skip_commas();
             inner.expect_eof()?;
@@ -147,60 +198,87 @@ fn parse_func_list(
     Ok(items)
 }
 
-fn clean_trees(items: Vec<Tree>) -> Vec<Tree> {
+fn clean_trees(items: Vec<SpannedTree>) -> Vec<SpannedTree> {
     items
         .into_iter()
         .filter_map(|tree| match tree {
-            Tree::Token(Token::Whitespace(_) | Token::Comment(_)) => None,
-            Tree::Token(token) => Some(Tree::Token(token)),
-            Tree::Delim { delim, child } => Some(Tree::Delim {
+            SpannedTree::Token(token)
+                if matches!(token.token, Token::Whitespace(_) | Token::Comment(_)) =>
+            {
+                None
+            }
+            SpannedTree::Token(token) => Some(SpannedTree::Token(token)),
+            SpannedTree::Delim { delim, span, child } => Some(SpannedTree::Delim {
                 delim,
+                span,
                 child: clean_trees(child),
             }),
         })
         .collect()
 }
 
+// span of the closing delimiter of a group
+fn closing(span: Span) -> Span {
+    Span::new(span.end.saturating_sub(1), span.end)
+}
+
+fn token(tree: Option<&SpannedTree>) -> Option<&Token> {
+    match tree {
+        Some(SpannedTree::Token(token)) => Some(&token.token),
+        _ => None,
+    }
+}
+
 struct Parser<'a> {
-    items: &'a [Tree],
+    items: &'a [SpannedTree],
     pos: usize,
+    // where "unexpected end" errors point (the closing delimiter or the end of input)
+    end: Span,
 }
 
 impl<'a> Parser<'a> {
-    fn new(items: &'a [Tree]) -> Self {
-        Self { items, pos: 0 }
+    fn new(items: &'a [SpannedTree], end: Span) -> Self {
+        Self { items, pos: 0, end }
     }
 
-    fn peek(&self) -> Option<&'a Tree> {
+    fn peek(&self) -> Option<&'a SpannedTree> {
         self.items.get(self.pos)
     }
 
-    fn next(&mut self) -> Option<&'a Tree> {
-        let item = self.items.get(self.pos);
-        if item.is_some() {
-            self.pos += 1;
-        }
-        item
+    fn span_here(&self) -> Span {
+        self.peek().map_or(self.end, SpannedTree::span)
+    }
+
+    fn span_prev(&self) -> Span {
+        self.pos
+            .checked_sub(1)
+            .and_then(|pos| self.items.get(pos))
+            .map_or(self.end, SpannedTree::span)
     }
 
-    fn expect_eof(&self) -> Result<(), String> {
+    fn error_here(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(self.span_here(), message)
+    }
+
+    fn expect_eof(&self) -> Result<(), Diagnostic> {
         if self.peek().is_none() {
             Ok(())
         } else {
-            Err("unexpected trailing input".to_string())
+            Err(self.error_here("unexpected trailing input"))
         }
     }
 
-    fn expect_symbol(&mut self, ch: char) -> Result<(), String> {
-        match self.next() {
-            Some(Tree::Token(Token::Symbol(found))) if *found == ch => Ok(()),
-            _ => Err(format!("expected symbol '{ch}'")),
+    fn expect_symbol(&mut self, ch: char) -> Result<(), Diagnostic> {
+        if self.consume_symbol(ch) {
+            Ok(())
+        } else {
+            Err(self.error_here(format!("expected symbol '{ch}'")))
         }
     }
 
     fn consume_symbol(&mut self, ch: char) -> bool {
-        match self.peek() {
-            Some(Tree::Token(Token::Symbol(found))) if *found == ch => {
+        match token(self.peek()) {
+            Some(Token::Symbol(found)) if *found == ch => {
                 self.pos += 1;
                 true
             }
@@ -212,12 +290,16 @@ impl<'a> Parser<'a> {
         while self.consume_symbol(',') {}
     }
 
-    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), Diagnostic> {
+        let span = self.span_here();
         let name = self.parse_name()?;
         if name == keyword {
             Ok(())
         } else {
-            Err(format!("expected keyword '{keyword}'"))
+            Err(Diagnostic::error(
+                span.to(self.span_prev()),
+                format!("expected keyword '{keyword}'"),
+            ))
         }
     }
 
@@ -225,44 +307,55 @@ impl<'a> Parser<'a> {
         let mut clone = Self {
             items: self.items,
             pos: self.pos,
+            end: self.end,
         };
         clone.parse_name().is_ok_and(|name| name == keyword)
     }
 
-    fn expect_delim(&mut self, delim: DelimKind) -> Result<Parser<'a>, String> {
-        match self.next() {
-            Some(Tree::Delim { delim: found, child }) if *found == delim => Ok(Parser::new(child)),
-            _ => Err(format!("expected delimiter {:?}", delim)),
+    fn expect_delim(&mut self, delim: DelimKind) -> Result<Parser<'a>, Diagnostic> {
+        match self.peek() {
+            Some(SpannedTree::Delim {
+                delim: found,
+                span,
+                child,
+            }) if *found == delim => {
+                self.pos += 1;
+                Ok(Parser::new(child, closing(*span)))
+            }
+            _ => Err(self.error_here(format!("expected delimiter {:?}", delim))),
         }
     }
 
-    fn parse_number(&mut self) -> Result<usize, String> {
-        match self.next() {
-            Some(Tree::Token(Token::Number(num))) => num
-                .parse::<usize>()
-                .map_err(|_| format!("invalid number '{num}'")),
-            _ => Err("expected number".to_string()),
+    fn parse_number(&mut self) -> Result<usize, Diagnostic> {
+        match token(self.peek()) {
+            Some(Token::Number(num)) => {
+                let num = num
+                    .parse::<usize>()
+                    .map_err(|_| self.error_here(format!("invalid number '{num}'")))?;
+                self.pos += 1;
+                Ok(num)
+            }
+            _ => Err(self.error_here("expected number")),
         }
     }
 
-    fn parse_name(&mut self) -> Result<String, String> {
-        let mut out = match self.next() {
-            Some(Tree::Token(Token::Ident(text))) => text.clone(),
-            Some(Tree::Token(Token::Symbol(ch @ ('-' | '_')))) => ch.to_string(),
-            _ => return Err("expected name".to_string()),
+    fn parse_name(&mut self) -> Result<String, Diagnostic> {
+        let mut out = match token(self.peek()) {
+            Some(Token::Ident(text)) => text.clone(),
+            Some(Token::Symbol(ch @ ('-' | '_'))) => ch.to_string(),
+            _ => return Err(self.error_here("expected name")),
         };
+        self.pos += 1;
 
-        loop {
-            let Some(Tree::Token(Token::Symbol(ch @ ('-' | '_')))) = self.peek() else {
-                break;
-            };
+        while let Some(Token::Symbol(ch @ ('-' | '_'))) = token(self.peek()) {
             out.push(*ch);
             self.pos += 1;
-            match self.next() {
-                Some(Tree::Token(Token::Ident(text))) | Some(Tree::Token(Token::Number(text))) => {
+            match token(self.peek()) {
+                Some(Token::Ident(text)) | Some(Token::Number(text)) => {
                     out.push_str(text);
+                    self.pos += 1;
                 }
-                _ => return Err("expected name".to_string()),
+                _ => return Err(self.error_here("expected name")),
             }
         }
 
@@ -370,4 +463,72 @@ mod tests {
             .unwrap()
         );
     }
+
+    #[test]
+    fn parse_errors_point_into_the_code() {
+        let code = "let f = ZERO.\nCOMP[f: (SUCC, g)]";
+        let err = parse(code).unwrap_err();
+        assert_eq!(err.message, "Function g not found");
+        assert_eq!(err.line_col(code), Some((2, 16)));
+
+        let code = "PROJ[2 0]";
+        let err = parse(code).unwrap_err();
+        assert_eq!(err.span, Some(Span::new(7, 8)));
+
+        let code = "PRIM[z: ZERO s: SUCC]";
+        let err = parse(code).unwrap_err();
+        assert_eq!(err.span, Some(Span::new(0, code.len())));
+    }
+
+    #[test]
+    fn print_nests_compositions() {
+        let code = "COMP[PRIM[z: PROJ[1,0] s: PROJ[3,0]]: COMP[SUCC: PROJ[2,1]], PROJ[2,0]]";
+        let func = parse(code).unwrap();
+        assert_eq!(func.print(), code);
+        assert_eq!(
+            func.print_pretty(40),
+            "COMP[\n  PRIM[z: PROJ[1,0] s: PROJ[3,0]]:\n  COMP[SUCC: PROJ[2,1]], PROJ[2,0]\n]"
+        );
+    }
+
+    // pseudo random functions with `arity` parameters
+    fn random_func(seed: &mut u64, arity: usize, depth: usize) -> RecursiveFunctions {
+        let mut next = |n: u64| {
+            *seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((*seed >> 33) % n) as usize
+        };
+        match if depth == 0 { 0 } else { next(4) } {
+            0 if arity == 0 => RecursiveFunctions::zero(),
+            0 if arity == 1 && next(2) == 0 => RecursiveFunctions::succ(),
+            0 => RecursiveFunctions::projection(arity, next(arity as u64)).unwrap(),
+            1 => {
+                let outer_arity = 1 + next(3);
+                let outer = random_func(seed, outer_arity, depth - 1);
+                let inner = (0..outer_arity)
+                    .map(|_| random_func(seed, arity, depth - 1))
+                    .collect();
+                RecursiveFunctions::composition(outer, inner).unwrap()
+            }
+            2 if arity > 0 => RecursiveFunctions::primitive_recursion(
+                random_func(seed, arity - 1, depth - 1),
+                random_func(seed, arity + 1, depth - 1),
+            )
+            .unwrap(),
+            _ => RecursiveFunctions::muoperator(random_func(seed, arity + 1, depth - 1)).unwrap(),
+        }
+    }
+
+    #[test]
+    fn pretty_print_roundtrip_random_functions() {
+        let mut seed = 7;
+        for i in 0..200 {
+            let func = random_func(&mut seed, i % 3, 4);
+            utils::pretty::check_roundtrip(&func).unwrap();
+            for width in utils::pretty::ROUNDTRIP_WIDTHS {
+                assert_eq!(parse(&func.print_pretty(width)).unwrap(), func);
+            }
+        }
+    }
 }
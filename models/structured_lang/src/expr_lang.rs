@@ -6,7 +6,7 @@ pub mod expr_lang_parser;
 use serde::{Deserialize, Serialize};
 use utils::identifier::Identifier;
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 pub use expr_lang_compiler::ExprLangToFlowIrCompiler;
 
@@ -212,7 +212,23 @@ impl Machine for ExprLangMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("expr_lang")
+            .code("statements `x := a`, `s ; s`, `if b s`, `while b [ s ]`, Nop; a: numbers, variables, + -, if b then a else a; b: a < a, a = a, b || b, !b")
+            .ainput("initial variables, `x = 3` per line (others are 0)")
+            .rinput("empty")
+            .default_rinput("")
+            .example("count up", "x := 0 ; while x < 3 [ x := x + 1 ]", "")
+            .example("add", "while 0 < y [ x := x + 1 ; y := y - 1 ]", "x = 3\ny = 4")
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::expr_lang_render::render_machine(snapshot)
     }
 }
+
+impl Metrics for ExprLangMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("vars", self.env.vars.len())]
+    }
+}
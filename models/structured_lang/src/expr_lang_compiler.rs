@@ -6,7 +6,7 @@ use flow_ir::flow_ir::{
     Region, StaticDef, StaticEnv, Stmt as IrStmt, ValueExpr,
 };
 use utils::number::Number;
-use utils::{Compiler, TextCodec};
+use utils::{Compiler, SourceMap, TextCodec};
 
 #[derive(Debug, Clone, Default)]
 pub struct ExprLangToFlowIrCompiler;
@@ -17,6 +17,8 @@ struct CompileCtx {
     region_id: usize,
     block_id: usize,
     vreg_id: usize,
+    // regions of the source statements, statements are numbered as in `expr_lang_parser::parse_with_spans`
+    map: SourceMap,
 }
 
 impl CompileCtx {
@@ -66,10 +68,25 @@ fn move_entry_first(mut blocks: Vec<Block>, entry: &str) -> Result<Vec<Block>, S
     Ok(blocks)
 }
 
-fn compile_stmt(stmt: &Stmt, cont_region: &str, ctx: &mut CompileCtx) -> Result<String, String> {
+// number of statements in the tree, the last child of the statement `id` is `id - 1`
+fn stmt_size(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::Nop | Stmt::Assign { .. } => 1,
+        Stmt::Seq(a, b) => 1 + stmt_size(a) + stmt_size(b),
+        Stmt::If { body, .. } | Stmt::While { body, .. } => 1 + stmt_size(body),
+    }
+}
+
+fn compile_stmt(
+    stmt: &Stmt,
+    id: usize,
+    cont_region: &str,
+    ctx: &mut CompileCtx,
+) -> Result<String, String> {
     match stmt {
         Stmt::Nop => {
             let region_label = ctx.fresh_region("stmt_nop");
+            ctx.map.insert(&region_label, id);
             let entry = ctx.fresh_block("entry");
             ctx.regions.push(Region {
                 label: region_label.clone(),
@@ -83,6 +100,7 @@ fn compile_stmt(stmt: &Stmt, cont_region: &str, ctx: &mut CompileCtx) -> Result<
         }
         Stmt::Assign { var, expr } => {
             let region_label = ctx.fresh_region("stmt_assign");
+            ctx.map.insert(&region_label, id);
             let dst = ctx.fresh_vreg("assign");
             let end = ctx.fresh_block("assign_end");
             let mut blocks = vec![Block {
@@ -102,17 +120,21 @@ fn compile_stmt(stmt: &Stmt, cont_region: &str, ctx: &mut CompileCtx) -> Result<
             Ok(region_label)
         }
         Stmt::Seq(a, b) => {
-            let b_entry = compile_stmt(b, cont_region, ctx)?;
-            compile_stmt(a, &b_entry, ctx)
+            let b_entry = compile_stmt(b, id - 1, cont_region, ctx)?;
+            compile_stmt(a, id - 1 - stmt_size(b), &b_entry, ctx)
         }
         Stmt::If { cond, body } => {
-            let body_entry = compile_stmt(body, cont_region, ctx)?;
-            compile_bexp_region(cond, &body_entry, cont_region, ctx)
+            let body_entry = compile_stmt(body, id - 1, cont_region, ctx)?;
+            let cond_region = compile_bexp_region(cond, &body_entry, cont_region, ctx)?;
+            ctx.map.insert(&cond_region, id);
+            Ok(cond_region)
         }
         Stmt::While { cond, body } => {
             let head_region = ctx.fresh_region("while_head");
-            let body_entry = compile_stmt(body, &head_region, ctx)?;
+            ctx.map.insert(&head_region, id);
+            let body_entry = compile_stmt(body, id - 1, &head_region, ctx)?;
             let br_entry = compile_bexp_region(cond, &body_entry, cont_region, ctx)?;
+            ctx.map.insert(&br_entry, id);
 
             let head_block = ctx.fresh_block("head_entry");
             ctx.regions.push(Region {
@@ -273,7 +295,15 @@ impl Compiler for ExprLangToFlowIrCompiler {
     type Source = ExprLangMachine;
     type Target = FlowIrMachine;
 
+    fn describe() -> utils::CompilerDescription {
+        utils::CompilerDescription::of::<Self>().named("expr_to_flow_ir")
+    }
+
     fn compile(source: ExprCode) -> Result<FlowIrCode, String> {
+        Self::compile_mapped(source).map(|(code, _)| code)
+    }
+
+    fn compile_mapped(source: ExprCode) -> Result<(FlowIrCode, SourceMap), String> {
         let vars: Vec<String> = collect_vars(&source.0).into_iter().collect();
 
         let mut ctx = CompileCtx::default();
@@ -288,7 +318,8 @@ impl Compiler for ExprLangToFlowIrCompiler {
             }],
         });
 
-        let entry_region = compile_stmt(&source.0, &halt_region, &mut ctx)?;
+        let root = stmt_size(&source.0) - 1;
+        let entry_region = compile_stmt(&source.0, root, &halt_region, &mut ctx)?;
 
         let main_region = Region {
             label: "main".to_string(),
@@ -310,7 +341,7 @@ impl Compiler for ExprLangToFlowIrCompiler {
         let mut regions = vec![main_region];
         regions.extend(ctx.regions);
 
-        Ok(FlowIrCode(Program { statics, regions }))
+        Ok((FlowIrCode(Program { statics, regions }), ctx.map))
     }
 
     fn encode_ainput(
@@ -427,6 +458,7 @@ fn collect_bexp_vars(exp: &BExp, vars: &mut BTreeSet<String>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
 
     #[test]
     fn compile_emits_parseable_flow_ir() {
@@ -436,4 +468,51 @@ mod tests {
         let reparsed = FlowIrCode::parse(&printed).unwrap();
         assert_eq!(ir, reparsed);
     }
+
+    #[test]
+    fn compiled_flow_ir_agrees_with_expr_lang() {
+        assert_compiler_agrees::<ExprLangToFlowIrCompiler>(
+            "x := 0 ; while x < 3 [ x := x + 1 ]",
+            &["x = 0", "x = 5"],
+            &DiffOptions::fuel(1_000, 1_000),
+        );
+    }
+
+    #[test]
+    fn source_map_points_regions_to_their_statements() {
+        let text = "x := 0 ; if x < 1 y := 2 ; while x < 3 [ x := x + 1 ]";
+        let code = ExprCode::parse(text).unwrap();
+        let (ir, map) = ExprLangToFlowIrCompiler::compile_mapped(code).unwrap();
+        let spans = ExprCode::node_spans(text).unwrap();
+        let mappings = map.resolve(&spans).unwrap();
+        let regions: BTreeSet<&str> = ir.0.regions.iter().map(|r| r.label.as_str()).collect();
+        let mut mapped = BTreeSet::new();
+        for mapping in &mappings {
+            assert!(
+                regions.contains(mapping.target.as_str()),
+                "{}",
+                mapping.target
+            );
+            let source = &text[mapping.span.start..mapping.span.end];
+            let prefix = mapping.target.rsplit_once('_').unwrap().0;
+            let expected = match prefix {
+                "stmt_assign" => ":=",
+                "stmt_cond" => "<",
+                "while_head" => "while",
+                other => panic!("unexpected region {other}"),
+            };
+            assert!(source.contains(expected), "{} -> {source}", mapping.target);
+            mapped.insert(source);
+        }
+        assert_eq!(
+            mapped,
+            BTreeSet::from([
+                "x := 0",
+                "if x < 1 y := 2",
+                "y := 2",
+                "while x < 3 [ x := x + 1 ]",
+                "x := x + 1",
+            ])
+        );
+    }
 }
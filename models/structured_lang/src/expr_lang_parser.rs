@@ -1,6 +1,7 @@
+use utils::diagnostic::Span;
 use utils::identifier::Identifier;
 use utils::number::Number;
-use utils::{DelimKind, TextCodec, Token as LexToken, Tree, lex_tree};
+use utils::{DelimKind, SpannedTree, TextCodec, Token as LexToken, lex_tree_spanned};
 
 use crate::expr_lang::*;
 
@@ -36,11 +37,11 @@ impl TextCodec for Environment {
 
 impl TextCodec for ExprCode {
     fn parse(text: &str) -> Result<Self, String> {
-        let trees = lex_tree(text).map_err(|e| e.to_string())?;
-        let mut ps = Parser::new(normalize_trees(trees)?);
-        let stmt = ps.parse_stmt()?;
-        ps.expect_eof()?;
-        Ok(ExprCode(stmt))
+        parse_with_spans(text).map(|(code, _)| code)
+    }
+
+    fn node_spans(text: &str) -> Result<Vec<Span>, String> {
+        parse_with_spans(text).map(|(_, spans)| spans)
     }
 
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
@@ -48,43 +49,98 @@ impl TextCodec for ExprCode {
     }
 }
 
+// code and the spans of its statements in post-order (children before parents)
+// the i-th span belongs to the statement numbered i by compilers that emit source maps
+pub fn parse_with_spans(text: &str) -> Result<(ExprCode, Vec<Span>), String> {
+    let trees = lex_tree_spanned(text).map_err(|e| e.to_string())?;
+    let mut ps = Parser::new(normalize_trees(trees)?);
+    let stmt = ps.parse_stmt()?;
+    ps.expect_eof()?;
+    Ok((ExprCode(stmt), ps.stmt_spans))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Node {
     Token(LexToken),
-    Paren(Vec<Node>),
-    Bracket(Vec<Node>),
+    Paren(Group),
+    Bracket(Group),
 }
 
-fn normalize_trees(trees: Vec<Tree>) -> Result<Vec<Node>, String> {
-    let mut out = Vec::new();
+// nodes with their spans, printed as the list of nodes in error messages
+#[derive(Clone, PartialEq, Eq)]
+struct Group {
+    nodes: Vec<Node>,
+    spans: Vec<Span>,
+}
+
+impl std::fmt::Debug for Group {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(&self.nodes).finish()
+    }
+}
+
+fn normalize_trees(trees: Vec<SpannedTree>) -> Result<Group, String> {
+    let mut out = Group {
+        nodes: Vec::new(),
+        spans: Vec::new(),
+    };
     for tree in trees {
-        match tree {
-            Tree::Token(LexToken::Whitespace(_) | LexToken::Comment(_)) => {}
-            Tree::Token(token) => out.push(Node::Token(token)),
-            Tree::Delim {
+        let span = tree.span();
+        let node = match tree {
+            SpannedTree::Token(token) => match token.token {
+                LexToken::Whitespace(_) | LexToken::Comment(_) => continue,
+                token => Node::Token(token),
+            },
+            SpannedTree::Delim {
                 delim: DelimKind::Paren,
                 child,
-            } => out.push(Node::Paren(normalize_trees(child)?)),
-            Tree::Delim {
+                ..
+            } => Node::Paren(normalize_trees(child)?),
+            SpannedTree::Delim {
                 delim: DelimKind::Bracket,
                 child,
-            } => out.push(Node::Bracket(normalize_trees(child)?)),
-            Tree::Delim { delim, .. } => {
+                ..
+            } => Node::Bracket(normalize_trees(child)?),
+            SpannedTree::Delim { delim, .. } => {
                 return Err(format!("unexpected delimiter in expr_lang: {:?}", delim));
             }
-        }
+        };
+        out.nodes.push(node);
+        out.spans.push(span);
     }
     Ok(out)
 }
 
 struct Parser {
     nodes: Vec<Node>,
+    spans: Vec<Span>,
     pos: usize,
+    // spans of the parsed statements in post-order
+    stmt_spans: Vec<Span>,
 }
 
 impl Parser {
-    fn new(nodes: Vec<Node>) -> Self {
-        Self { nodes, pos: 0 }
+    fn new(group: Group) -> Self {
+        Self {
+            nodes: group.nodes,
+            spans: group.spans,
+            pos: 0,
+            stmt_spans: Vec::new(),
+        }
+    }
+
+    // start of the next node
+    fn start(&self) -> usize {
+        match self.spans.get(self.pos) {
+            Some(span) => span.start,
+            None => self.spans.last().map_or(0, |span| span.end),
+        }
+    }
+
+    // records a statement from `start` to the end of the last consumed node
+    fn record(&mut self, start: usize) {
+        let end = self.pos.checked_sub(1).map_or(start, |i| self.spans[i].end);
+        self.stmt_spans.push(Span::new(start, end));
     }
 
     fn peek(&self) -> Option<&Node> {
@@ -157,21 +213,26 @@ impl Parser {
     }
 
     fn parse_stmt(&mut self) -> Result<Stmt, String> {
+        let start = self.start();
         let mut left = self.parse_stmt_single()?;
         while self.eat_symbol(';') {
             let right = self.parse_stmt_single()?;
             left = Stmt::Seq(Box::new(left), Box::new(right));
+            self.record(start);
         }
         Ok(left)
     }
 
     fn parse_stmt_single(&mut self) -> Result<Stmt, String> {
+        let start = self.start();
         if self.eat_ident("Nop") {
+            self.record(start);
             return Ok(Stmt::Nop);
         }
         if self.eat_ident("if") {
             let cond = self.parse_bexp()?;
             let body = self.parse_stmt_single()?;
+            self.record(start);
             return Ok(Stmt::If {
                 cond,
                 body: Box::new(body),
@@ -180,6 +241,7 @@ impl Parser {
         if self.eat_ident("while") {
             let cond = self.parse_bexp()?;
             let body = self.parse_bracketed_stmt()?;
+            self.record(start);
             return Ok(Stmt::While {
                 cond,
                 body: Box::new(body),
@@ -194,6 +256,7 @@ impl Parser {
             self.expect_symbol(':')?;
             self.expect_symbol('=')?;
             let expr = self.parse_aexp()?;
+            self.record(start);
             return Ok(Stmt::Assign { var, expr });
         }
         Err(self.error_here("Invalid statement"))
@@ -302,6 +365,7 @@ impl Parser {
         let mut parser = Parser::new(inner);
         let stmt = parser.parse_stmt()?;
         parser.expect_eof()?;
+        self.stmt_spans.append(&mut parser.stmt_spans);
         Ok(stmt)
     }
 
@@ -312,17 +376,18 @@ impl Parser {
         let mut parser = Parser::new(inner);
         let stmt = parser.parse_stmt()?;
         parser.expect_eof()?;
+        self.stmt_spans.append(&mut parser.stmt_spans);
         Ok(stmt)
     }
 
-    fn parse_group_as_aexp(inner: Vec<Node>) -> Result<AExp, String> {
+    fn parse_group_as_aexp(inner: Group) -> Result<AExp, String> {
         let mut parser = Parser::new(inner);
         let exp = parser.parse_aexp()?;
         parser.expect_eof()?;
         Ok(exp)
     }
 
-    fn parse_group_as_bexp(inner: Vec<Node>) -> Result<BExp, String> {
+    fn parse_group_as_bexp(inner: Group) -> Result<BExp, String> {
         let mut parser = Parser::new(inner);
         let exp = parser.parse_bexp()?;
         parser.expect_eof()?;
@@ -398,6 +463,7 @@ while x < 3 [
         let printed = parsed.print();
         let reparsed = ExprCode::parse(&printed).unwrap();
         assert_eq!(parsed, reparsed);
+        utils::pretty::check_roundtrip(&parsed).unwrap();
     }
 
     #[test]
@@ -422,4 +488,22 @@ while x < 3 [
         }
         panic!("did not halt");
     }
+
+    #[test]
+    fn statement_spans_are_post_order() {
+        let text = "x := 0 ;\nwhile x < 3 [ (x := x + 1 ; Nop) ]";
+        let (_, spans) = parse_with_spans(text).unwrap();
+        let texts: Vec<&str> = spans.iter().map(|s| &text[s.start..s.end]).collect();
+        assert_eq!(
+            texts,
+            [
+                "x := 0",
+                "x := x + 1",
+                "Nop",
+                "x := x + 1 ; Nop",
+                "while x < 3 [ (x := x + 1 ; Nop) ]",
+                text,
+            ]
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FnPtrCode(pub Program);
@@ -77,9 +77,30 @@ pub enum Value {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FrameStore {
     pub frame_id: usize,
+    #[serde(with = "location_map")]
     pub values: BTreeMap<Location, Value>,
 }
 
+// JSON keys must be strings, so the store is written as a list of pairs
+mod location_map {
+    use super::{BTreeMap, Location, Value};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        map: &BTreeMap<Location, Value>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Location, Value>, D::Error> {
+        let pairs = Vec::<(Location, Value)>::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CallFrame {
     pub env: BTreeMap<String, Location>,
@@ -292,11 +313,31 @@ impl Machine for FnPtrMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("fn_ptr")
+            .code("`fn name(x, ..) { stmt; ..; return }` definitions, `main` runs first; `assign place := e`, `call f(e, ..)`, places and pointers via `x #addr`, `(ld e) #loc`")
+            .ainput("empty")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "increment through a pointer",
+                "fn inc(ptr) {\n    assign (ld ptr) #loc := (ld (ld ptr) #loc) + 1;\n    return\n}\n\nfn main(x) {\n    assign x := 4;\n    call inc(x #addr);\n    return\n}",
+                "",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::fn_ptr_render::render_machine(snapshot)
     }
 }
 
+impl Metrics for FnPtrMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let values = self.store.iter().map(|frame| frame.values.len()).sum();
+        vec![("store", values), ("stack", self.fn_k.len())]
+    }
+}
+
 impl FnPtrMachine {
     fn eval_place(&self, place: &PlaceExpr) -> Result<Location, String> {
         match place {
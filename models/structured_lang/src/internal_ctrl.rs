@@ -8,7 +8,7 @@ use std::collections::BTreeSet;
 use serde::{Deserialize, Serialize};
 use utils::identifier::Identifier;
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 pub use internal_ctrl_compiler::InternalCtrlToFlowIrCompiler;
 
@@ -327,6 +327,19 @@ impl Machine for InternalCtrlMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("internal_ctrl")
+            .code("`static x, ..;` then statements `x := a op b`, `s; s`, `if a rel b { s }`, `loop :l ( s ) -> x`, `break :l x`, `continue :l`")
+            .ainput("the value of every static, `x = 3` per line")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "nested control",
+                "static x, y; loop :outer ( if x < 3 { x := x + 1; continue :outer }; break :outer x ) -> y",
+                "x = 1\ny = 0",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let env = snapshot
             .env
@@ -353,3 +366,9 @@ impl Machine for InternalCtrlMachine {
         ]
     }
 }
+
+impl Metrics for InternalCtrlMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("vars", self.env.vars.len()), ("stack", self.stack.len())]
+    }
+}
@@ -148,19 +148,28 @@ fn compile_stmt(
                 RelOp::Eq => Cond::Eq(ValueExpr::VReg(lhs), ValueExpr::VReg(rhs)),
                 RelOp::Gt => Cond::Gt(ValueExpr::VReg(lhs), ValueExpr::VReg(rhs)),
             };
+            // conditional jumps are local, so the body region is entered from a separate block
+            let then_block = ctx.fresh_block("then");
             ctx.regions.push(Region {
                 label: label.clone(),
-                blocks: vec![Block {
-                    label: "entry".to_string(),
-                    stmts,
-                    cont: Cont::Enter {
-                        ifs: vec![JumpIf {
-                            cond,
-                            target: ValueExpr::CodeLabel(body_region),
-                        }],
-                        target: ValueExpr::CodeLabel(cont_region.to_string()),
+                blocks: vec![
+                    Block {
+                        label: "entry".to_string(),
+                        stmts,
+                        cont: Cont::Enter {
+                            ifs: vec![JumpIf {
+                                cond,
+                                target: ValueExpr::CodeLabel(then_block.clone()),
+                            }],
+                            target: ValueExpr::CodeLabel(cont_region.to_string()),
+                        },
                     },
-                }],
+                    Block {
+                        label: then_block,
+                        stmts: vec![IrStmt::Nop],
+                        cont: enter_region(&body_region),
+                    },
+                ],
             });
             Ok(label)
         }
@@ -257,6 +266,10 @@ impl Compiler for InternalCtrlToFlowIrCompiler {
     type Source = InternalCtrlMachine;
     type Target = FlowIrMachine;
 
+    fn describe() -> utils::CompilerDescription {
+        utils::CompilerDescription::of::<Self>().named("internal_ctrl_to_flow_ir")
+    }
+
     fn compile(source: InternalCtrlCode) -> Result<FlowIrCode, String> {
         let mut statics: BTreeSet<String> = source
             .statics
@@ -400,6 +413,7 @@ fn collect_bexp_vars(exp: &BExp, vars: &mut BTreeSet<String>) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
 
     #[test]
     fn compile_emits_parseable_flow_ir() {
@@ -412,4 +426,50 @@ mod tests {
         let reparsed = FlowIrCode::parse(&printed).unwrap();
         assert_eq!(ir, reparsed);
     }
+
+    #[test]
+    fn code_and_compiled_flow_ir_roundtrip() {
+        let code = InternalCtrlCode::parse(
+            "static x, y; loop :outer ( if x < 3 { x := x + 1; continue :outer }; break :outer x ) -> y",
+        )
+        .unwrap();
+        let ir = <InternalCtrlToFlowIrCompiler as Compiler>::compile(code.clone()).unwrap();
+        utils::pretty::check_roundtrip(&code).unwrap();
+        utils::pretty::check_roundtrip(&ir).unwrap();
+    }
+
+    #[test]
+    fn conditional_enters_the_body_region_through_a_local_block() {
+        // a conditional jump can not leave its region, so `if` must not jump to the body region itself
+        let code = InternalCtrlCode::parse(
+            "static x, y; loop :outer ( if x < 3 { x := x + 1; continue :outer }; break :outer x ) -> y",
+        )
+        .unwrap();
+        let ir = <InternalCtrlToFlowIrCompiler as Compiler>::compile(code).unwrap();
+        let ainput = FlowIrMachine::parse_ainput("x = 0\ny = 0").unwrap();
+        let rinputs = std::iter::repeat(String::new());
+        let report = utils::run::run_code::<FlowIrMachine, _>(
+            ir,
+            ainput,
+            rinputs,
+            utils::run::RunOptions::fuel(1_000),
+        )
+        .unwrap();
+        let foutput = report.outcome.into_foutput().unwrap();
+        assert_eq!(foutput.print(), "x = 3\ny = 3\n");
+    }
+
+    #[test]
+    fn compiled_flow_ir_agrees_with_internal_ctrl() {
+        // routputs of internal_ctrl are an execution trace, not part of the semantics
+        let options = DiffOptions {
+            routputs: false,
+            ..DiffOptions::fuel(1_000, 1_000)
+        };
+        assert_compiler_agrees::<InternalCtrlToFlowIrCompiler>(
+            "static x, y; loop :outer ( if x < 3 { x := x + 1; continue :outer }; break :outer x ) -> y",
+            &["x = 0\ny = 0", "x = 7\ny = 1"],
+            &options,
+        );
+    }
 }
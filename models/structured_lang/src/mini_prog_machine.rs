@@ -1,7 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MiniProgCode(pub Program);
@@ -290,11 +290,35 @@ impl Machine for MiniProgMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("mini_prog")
+            .code("`fn name(x: #num, ..) { stmt }` definitions, `main` takes the ainput; blocks `block (x: #ptr) { .. }`, `call fn f(e, ..) -> place, ..`, `halloc`, `assign`, `return e, ..` with postfix arithmetic")
+            .ainput("the arguments of main, comma-separated naturals in parentheses: `(3, 4)`")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "pair up",
+                "fn pair_up(x: #num, y: #num) {\n    return ld local x, ld local y\n}\n\nfn main(a: #num, b: #num) {\n    block (x: #num, y: #num) {\n        call fn pair_up(ld local a, ld local b) -> local x, local y;\n        return ld local x ld local y +\n    }\n}",
+                "(3, 4)",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::mini_prog_render::render_machine(snapshot)
     }
 }
 
+impl Metrics for MiniProgMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let locals = self.store.locals.iter().flatten().map(BTreeMap::len).sum();
+        vec![
+            ("heap", self.store.heap.len()),
+            ("locals", locals),
+            ("stack", self.fn_k.len()),
+        ]
+    }
+}
+
 impl MiniProgMachine {
     fn step_eval(&mut self, stmt: Stmt) -> Result<(), String> {
         match stmt {
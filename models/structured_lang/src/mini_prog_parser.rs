@@ -1,4 +1,6 @@
 use utils::TextCodec;
+use utils::lexer::{LexerConfig, Token as LexToken, lex_with};
+use utils::pretty::Doc;
 
 use crate::mini_prog_machine::{
     BinOp, Block, FnDecl, MiniProgCode, PlaceExpr, Program, StaticDecl, Stmt, Type, UnOp, ValueExpr,
@@ -14,7 +16,157 @@ impl TextCodec for MiniProgCode {
     }
 
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
-        write!(f, "{:?}", self.0)
+        f.write_str(&self.print_pretty(usize::MAX))
+    }
+
+    fn to_doc(&self) -> Doc {
+        program_doc(&self.0)
+    }
+}
+
+// statics one per line, then the functions separated by blank lines
+fn program_doc(program: &Program) -> Doc {
+    let statics = program
+        .statics
+        .iter()
+        .map(|decl| Doc::text(format!("static {}: {}", decl.name, type_text(&decl.ty))));
+    let functions = program
+        .functions
+        .iter()
+        .map(|decl| Doc::text(format!("fn {}", decl.name)) + block_doc(&decl.block));
+    let mut items = vec![];
+    if !program.statics.is_empty() {
+        items.push(Doc::join(statics, Doc::hardline()));
+    }
+    items.extend(functions);
+    Doc::join(items, Doc::hardline() + Doc::hardline())
+}
+
+// `(bindings) { stmt; .. }`, the statements are indented on their own lines when they do not fit
+fn block_doc(block: &Block) -> Doc {
+    let bindings = block
+        .bindings
+        .iter()
+        .map(|(name, ty)| format!("{name}: {}", type_text(ty)))
+        .collect::<Vec<_>>();
+    let head = Doc::text(format!("({}) {{", bindings.join(", ")));
+    if block.stmts.is_empty() {
+        return head + Doc::text("}");
+    }
+    let stmts = Doc::join(
+        block.stmts.iter().map(stmt_doc),
+        Doc::text(";") + Doc::line(),
+    );
+    (head + (Doc::line() + stmts).nest(2) + Doc::line() + Doc::text("}")).group()
+}
+
+fn stmt_doc(stmt: &Stmt) -> Doc {
+    // `head stmt` with the statement on the next line when it does not fit
+    let nested = |head: String, stmt: &Stmt| {
+        (Doc::text(head) + (Doc::line() + stmt_doc(stmt)).nest(2)).group()
+    };
+    let values =
+        |values: &[ValueExpr]| values.iter().map(value_text).collect::<Vec<_>>().join(", ");
+    match stmt {
+        Stmt::Assign { place, value } => Doc::text(format!(
+            "assign {} := {}",
+            place_text(place),
+            value_text(value)
+        )),
+        Stmt::If { cond, stmt } => nested(format!("if {}", value_text(cond)), stmt),
+        Stmt::Case { tag, value, stmt } => {
+            nested(format!("case {tag} of {}", value_text(value)), stmt)
+        }
+        Stmt::HAlloc { ty, place } => {
+            Doc::text(format!("halloc {} -> {}", type_text(ty), place_text(place)))
+        }
+        Stmt::HFree { value } => Doc::text(format!("hfree {}", value_text(value))),
+        Stmt::Loop { label, stmt } => nested(format!("loop {label}:"), stmt),
+        Stmt::Break(label) => Doc::text(format!("break {label}")),
+        Stmt::Continue(label) => Doc::text(format!("continue {label}")),
+        Stmt::Call { callee, args, rets } => {
+            let rets = rets.iter().map(place_text).collect::<Vec<_>>();
+            Doc::text(format!(
+                "call {}({}) -> {}",
+                value_text(callee),
+                values(args),
+                rets.join(", ")
+            ))
+        }
+        Stmt::Return(returned) if returned.is_empty() => Doc::text("return"),
+        Stmt::Return(returned) => Doc::text(format!("return {}", values(returned))),
+        Stmt::Block(block) => Doc::text("block ") + block_doc(block),
+    }
+}
+
+// value expressions are postfix: operands first, then the operator
+fn value_text(value: &ValueExpr) -> String {
+    match value {
+        ValueExpr::Number(n) => n.to_string(),
+        ValueExpr::Char(ch) => match ch {
+            '\n' => "'\\n'".to_string(),
+            '\r' => "'\\r'".to_string(),
+            '\t' => "'\\t'".to_string(),
+            '\\' | '\'' => format!("'\\{ch}'"),
+            ch => format!("'{ch}'"),
+        },
+        ValueExpr::Bool(true) => "#true".to_string(),
+        ValueExpr::Bool(false) => "#false".to_string(),
+        ValueExpr::Unit => "#unit".to_string(),
+        ValueExpr::NullPtr => "#null-ptr".to_string(),
+        ValueExpr::NullFn => "#null-fn".to_string(),
+        ValueExpr::BinOp(lhs, op, rhs) => {
+            let op = match op {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Eq => "==",
+                BinOp::Lt => "<",
+                BinOp::And => "&&",
+                BinOp::Or => "||",
+            };
+            format!("{} {} {op}", value_text(lhs), value_text(rhs))
+        }
+        ValueExpr::UnOp(UnOp::Not, value) => format!("{} !", value_text(value)),
+        ValueExpr::Pair(items) => {
+            let items = items.iter().map(value_text).collect::<Vec<_>>();
+            if items.is_empty() {
+                "pair(0)".to_string()
+            } else {
+                format!("{} pair({})", items.join(" "), items.len())
+            }
+        }
+        ValueExpr::Tag(tag, value) => format!("{} tag({tag})", value_text(value)),
+        ValueExpr::Load(place) => format!("ld {}", place_text(place)),
+        ValueExpr::Addr(place) => format!("{} #addr", place_text(place)),
+        ValueExpr::Fn(name) => format!("fn {name}"),
+    }
+}
+
+fn place_text(place: &PlaceExpr) -> String {
+    match place {
+        PlaceExpr::Static(name) => format!("static {name}"),
+        PlaceExpr::Local(name) => format!("local {name}"),
+        PlaceExpr::Field(place, index) => format!("{}.{index}", place_text(place)),
+        PlaceExpr::Tag(place, tag) => format!("{}?{tag}", place_text(place)),
+        PlaceExpr::Index(place, index) => {
+            format!("{}[{}]", place_text(place), value_text(index))
+        }
+        PlaceExpr::Deref(value) => format!("({}) #loc", value_text(value)),
+    }
+}
+
+// products and sums have atoms as items, as the parser reads them
+fn type_text(ty: &Type) -> String {
+    match ty {
+        Type::Num => "#num".to_string(),
+        Type::U8 => "#u8".to_string(),
+        Type::Bool => "#bool".to_string(),
+        Type::Unit => "#unit".to_string(),
+        Type::Ptr => "#ptr".to_string(),
+        Type::Fn => "#fn".to_string(),
+        Type::Product(items) => items.iter().map(type_text).collect::<Vec<_>>().join(" * "),
+        Type::Sum(items) => items.iter().map(type_text).collect::<Vec<_>>().join(" + "),
+        Type::Array(item, len) => format!("[{}; {len}]", type_text(item)),
     }
 }
 
@@ -76,153 +228,105 @@ enum Token {
     Star,
 }
 
+const KEYWORDS: [(&str, Token); 18] = [
+    ("static", Token::Static),
+    ("local", Token::Local),
+    ("fn", Token::Fn),
+    ("assign", Token::Assign),
+    ("if", Token::If),
+    ("case", Token::Case),
+    ("of", Token::Of),
+    ("halloc", Token::HAlloc),
+    ("hfree", Token::HFree),
+    ("loop", Token::Loop),
+    ("break", Token::Break),
+    ("continue", Token::Continue),
+    ("call", Token::Call),
+    ("return", Token::Return),
+    ("block", Token::Block),
+    ("ld", Token::Load),
+    ("pair", Token::Pair),
+    ("tag", Token::Tag),
+];
+
+const OPERATORS: [(&str, Token); 5] = [
+    ("->", Token::Arrow),
+    (":=", Token::ColonEq),
+    ("==", Token::EqEq),
+    ("&&", Token::AndAnd),
+    ("||", Token::OrOr),
+];
+
+const ATOMS: [(&str, Token); 12] = [
+    ("num", Token::TypeNum),
+    ("u8", Token::TypeU8),
+    ("bool", Token::TypeBool),
+    ("unit", Token::TypeUnit),
+    ("ptr", Token::TypePtr),
+    ("fn", Token::TypeFn),
+    ("true", Token::True),
+    ("false", Token::False),
+    ("null-ptr", Token::NullPtr),
+    ("null-fn", Token::NullFn),
+    ("addr", Token::HashAddr),
+    ("loc", Token::HashLoc),
+];
+
 fn lex(text: &str) -> Result<Vec<Token>, String> {
+    let config = LexerConfig::new()
+        .operators(&OPERATORS.map(|(op, _)| op))
+        .keywords(&KEYWORDS.map(|(word, _)| word))
+        .atoms()
+        .char_literals();
     let mut tokens = Vec::new();
-    let chars: Vec<char> = text.chars().collect();
-    let mut i = 0;
-    while i < chars.len() {
-        let ch = chars[i];
-        if ch.is_whitespace() {
-            i += 1;
-            continue;
-        }
-        if ch == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
-            i += 2;
-            while i < chars.len() && chars[i] != '\n' {
-                i += 1;
-            }
-            continue;
-        }
-        if ch == '\'' {
-            if i + 2 >= chars.len() || chars[i + 2] != '\'' {
-                return Err("invalid char literal".to_string());
-            }
-            tokens.push(Token::Char(chars[i + 1]));
-            i += 3;
-            continue;
-        }
-        if ch.is_ascii_digit() {
-            let start = i;
-            i += 1;
-            while i < chars.len() && chars[i].is_ascii_digit() {
-                i += 1;
-            }
-            tokens.push(Token::Number(
-                text[start..i].parse::<usize>().map_err(|e| e.to_string())?,
-            ));
-            continue;
-        }
-        if ch.is_ascii_alphabetic() || ch == '_' {
-            let start = i;
-            i += 1;
-            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
-                i += 1;
-            }
-            let word = &text[start..i];
-            tokens.push(match word {
-                "static" => Token::Static,
-                "local" => Token::Local,
-                "fn" => Token::Fn,
-                "assign" => Token::Assign,
-                "if" => Token::If,
-                "case" => Token::Case,
-                "of" => Token::Of,
-                "halloc" => Token::HAlloc,
-                "hfree" => Token::HFree,
-                "loop" => Token::Loop,
-                "break" => Token::Break,
-                "continue" => Token::Continue,
-                "call" => Token::Call,
-                "return" => Token::Return,
-                "block" => Token::Block,
-                "ld" => Token::Load,
-                "pair" => Token::Pair,
-                "tag" => Token::Tag,
-                _ => Token::Ident(word.to_string()),
-            });
-            continue;
-        }
-        if ch == '#' {
-            let rest = &text[i..];
-            let matched = [
-                ("#num", Token::TypeNum),
-                ("#u8", Token::TypeU8),
-                ("#bool", Token::TypeBool),
-                ("#unit", Token::TypeUnit),
-                ("#ptr", Token::TypePtr),
-                ("#fn", Token::TypeFn),
-                ("#true", Token::True),
-                ("#false", Token::False),
-                ("#null-ptr", Token::NullPtr),
-                ("#null-fn", Token::NullFn),
-                ("#addr", Token::HashAddr),
-                ("#loc", Token::HashLoc),
-            ]
-            .into_iter()
-            .find(|(pat, _)| rest.starts_with(pat));
-            if let Some((pat, token)) = matched {
-                tokens.push(token);
-                i += pat.len();
-                continue;
-            }
-            return Err(format!("unknown token starting with '#': {rest}"));
-        }
-        match ch {
-            '(' => tokens.push(Token::LParen),
-            ')' => tokens.push(Token::RParen),
-            '{' => tokens.push(Token::LBrace),
-            '}' => tokens.push(Token::RBrace),
-            '[' => tokens.push(Token::LBracket),
-            ']' => tokens.push(Token::RBracket),
-            ',' => tokens.push(Token::Comma),
-            ';' => tokens.push(Token::Semi),
-            '.' => tokens.push(Token::Dot),
-            '?' => tokens.push(Token::Question),
-            '*' => tokens.push(Token::Star),
-            '!' => tokens.push(Token::Bang),
-            '<' => tokens.push(Token::Lt),
-            '+' => tokens.push(Token::Plus),
-            '-' => {
-                if i + 1 < chars.len() && chars[i + 1] == '>' {
-                    tokens.push(Token::Arrow);
-                    i += 2;
-                    continue;
-                }
-                tokens.push(Token::Minus);
-            }
-            ':' => {
-                if i + 1 < chars.len() && chars[i + 1] == '=' {
-                    tokens.push(Token::ColonEq);
-                    i += 2;
-                    continue;
-                }
-                tokens.push(Token::Colon);
-            }
-            '=' => {
-                if i + 1 < chars.len() && chars[i + 1] == '=' {
-                    tokens.push(Token::EqEq);
-                    i += 2;
-                    continue;
-                }
-                return Err("unexpected '='".to_string());
-            }
-            '&' if i + 1 < chars.len() && chars[i + 1] == '&' => {
-                tokens.push(Token::AndAnd);
-                i += 2;
-                continue;
-            }
-            '|' if i + 1 < chars.len() && chars[i + 1] == '|' => {
-                tokens.push(Token::OrOr);
-                i += 2;
-                continue;
-            }
-            _ => return Err(format!("unexpected character: {ch}")),
-        }
-        i += 1;
+    for token in lex_with(text, &config).map_err(|e| e.to_string())? {
+        tokens.push(match token {
+            LexToken::Whitespace(_) | LexToken::Comment(_) => continue,
+            LexToken::Ident(name) => Token::Ident(name),
+            LexToken::Number(n) => Token::Number(n.parse::<usize>().map_err(|e| e.to_string())?),
+            LexToken::Char(ch) => Token::Char(ch),
+            LexToken::Keyword(word) => {
+                lookup(&KEYWORDS, &word).ok_or_else(|| format!("unknown keyword: {word}"))?
+            }
+            LexToken::Atom(name) => lookup(&ATOMS, &name)
+                .ok_or_else(|| format!("unknown token starting with '#': #{name}"))?,
+            LexToken::Op(op) => {
+                lookup(&OPERATORS, &op).ok_or_else(|| format!("unknown operator: {op}"))?
+            }
+            LexToken::Symbol(ch) => match ch {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                ',' => Token::Comma,
+                ';' => Token::Semi,
+                '.' => Token::Dot,
+                '?' => Token::Question,
+                '*' => Token::Star,
+                '!' => Token::Bang,
+                '<' => Token::Lt,
+                '+' => Token::Plus,
+                '-' => Token::Minus,
+                ':' => Token::Colon,
+                _ => return Err(format!("unexpected character: {ch}")),
+            },
+            LexToken::StringLiteral(literal) => {
+                return Err(format!("unexpected string literal: {literal}"));
+            }
+        });
     }
     Ok(tokens)
 }
 
+fn lookup(table: &[(&str, Token)], word: &str) -> Option<Token> {
+    table
+        .iter()
+        .find(|(found, _)| *found == word)
+        .map(|(_, token)| token.clone())
+}
+
 struct Parser {
     tokens: Vec<Token>,
     pos: usize,
@@ -1,4 +1,5 @@
-use utils::{Machine, StepResult, TextCodec};
+use utils::TextCodec;
+use utils::run::{RunOptions, run_code};
 
 use crate::{
     fn_ptr_machine::{FnPtrCode, FnPtrMachine},
@@ -7,26 +8,16 @@ use crate::{
 
 fn run(source: &str) -> Result<String, String> {
     let code = FnPtrCode::parse(source)?;
-    let mut machine = FnPtrMachine::make(code, ())?;
-    for _ in 0..100 {
-        match machine.step(())? {
-            StepResult::Continue { next, .. } => machine = next,
-            StepResult::Halt { output } => return Ok(output),
-        }
-    }
-    Err("machine did not halt within the limit".to_string())
+    run_code::<FnPtrMachine, _>(code, (), std::iter::repeat(()), RunOptions::fuel(100))?
+        .outcome
+        .into_foutput()
 }
 
 fn run_full(source: &str, input: Vec<usize>) -> Result<usize, String> {
     let code = MiniProgCode::parse(source)?;
-    let mut machine = MiniProgMachine::make(code, input)?;
-    for _ in 0..300 {
-        match machine.step(())? {
-            StepResult::Continue { next, .. } => machine = next,
-            StepResult::Halt { output } => return Ok(output),
-        }
-    }
-    Err("machine did not halt within the limit".to_string())
+    run_code::<MiniProgMachine, _>(code, input, std::iter::repeat(()), RunOptions::fuel(300))?
+        .outcome
+        .into_foutput()
 }
 
 #[test]
@@ -119,3 +110,94 @@ fn full_machine_supports_heap_and_block_shadowing() {
     .unwrap();
     assert_eq!(output, 9);
 }
+
+#[test]
+fn code_roundtrip() {
+    let fn_ptr = FnPtrCode::parse(
+        r#"
+        fn inc(ptr) {
+            assign (ld ptr) #loc := (ld (ld ptr) #loc) + 1;
+            return
+        }
+
+        fn main(x) {
+            assign x := 4;
+            call inc(x #addr);
+            return
+        }
+        "#,
+    )
+    .unwrap();
+    utils::pretty::check_roundtrip(&fn_ptr).unwrap();
+}
+
+#[test]
+fn mini_prog_code_roundtrip() {
+    let code = MiniProgCode::parse(
+        r#"
+        static count: #num
+        static pairs: [#num * #bool; 2]
+
+        fn add(x: #num, y: #num) {
+            return ld local x ld local y +
+        }
+
+        fn main(n: #num) {
+            block (p: #ptr, f: #fn, v: #num + #unit, c: #u8) {
+                halloc #num * #num -> local p;
+                assign (ld local p) #loc.1 := 'a';
+                assign static pairs[ld local n 1 -].0 := #true #false || !;
+                assign local f := fn add;
+                assign local v := #unit tag(1);
+                case 1 of ld local v?1 assign local c := '\n';
+                loop top: block () {
+                    if ld local n 0 == break top;
+                    call ld local f(ld local n, 1) -> local n;
+                    continue top
+                };
+                hfree ld local p;
+                return 1 2 pair(2) #null-ptr #null-fn pair(2) pair(2)
+            }
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        code.print_pretty(40).lines().take(5).collect::<Vec<_>>(),
+        vec![
+            "static count: #num",
+            "static pairs: [#num * #bool; 2]",
+            "",
+            "fn add(x: #num, y: #num) {",
+            "  return ld local x ld local y +",
+        ]
+    );
+    utils::pretty::check_roundtrip(&code).unwrap();
+}
+
+#[test]
+fn fn_ptr_snapshot_roundtrips_through_json() {
+    use utils::{Machine, StepResult};
+
+    let code = FnPtrCode::parse(
+        r#"
+        fn main(x) {
+            assign x := 4;
+            return
+        }
+        "#,
+    )
+    .unwrap();
+    let mut machine = FnPtrMachine::make(code, ()).unwrap();
+    for _ in 0..2 {
+        machine = match machine.step(()).unwrap() {
+            StepResult::Continue { next, .. } => next,
+            StepResult::Halt { .. } => panic!("halted too early"),
+        };
+    }
+    // the store is keyed by locations, written as a list of [location, value] pairs
+    let json = serde_json::to_value(machine.snapshot()).unwrap();
+    assert!(json["store"][0]["values"].is_array());
+    let restored = FnPtrMachine::restore(serde_json::from_value(json.clone()).unwrap());
+    assert_eq!(serde_json::to_value(restored.snapshot()).unwrap(), json);
+}
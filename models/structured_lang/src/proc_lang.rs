@@ -1,7 +1,7 @@
 use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, number::Number};
+use utils::{Machine, Metrics, StepResult, number::Number};
 
 #[path = "proc_lang_compiler.rs"]
 mod proc_lang_compiler;
@@ -237,6 +237,8 @@ impl ProcLangMachine {
             }),
             Stmt::Seq(a, b) => match *a {
                 Stmt::Nop => Ok(*b),
+                // (s1; s2); s3 = s1; (s2; s3) so that a call in s1 captures the whole continuation
+                Stmt::Seq(a1, a2) => self.step_stmt(Stmt::Seq(a1, Box::new(Stmt::Seq(a2, b)))),
                 Stmt::Return { vars } => Ok(Stmt::Return { vars }),
                 Stmt::Call { name, args, rets } => self.call_into_proc(&name, &args, &rets, *b),
                 left => {
@@ -394,6 +396,19 @@ impl Machine for ProcLangMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("proc_lang")
+            .code("`static x, ..;` then procedures `name(p, ..)[ local v  stmt ]`, `main()` runs first; statements `x := a op b`, `s ; s`, `if a rel b [ s ]`, `while a rel b [ s ]`, `call f(x, ..) -> y, ..`, `return x, ..`")
+            .ainput("initial statics, `x = 3` per line")
+            .rinput("anything, ignored")
+            .default_rinput("")
+            .example(
+                "double",
+                "static r;\ndouble(x)[ local y  y := x + x ; return y ]\nmain()[ local a  call double(r) -> a ; r := a + 1 ]",
+                "r = 4",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let globals = snapshot
             .global_env
@@ -418,3 +433,133 @@ impl Machine for ProcLangMachine {
         ]
     }
 }
+
+impl Metrics for ProcLangMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("globals", self.global_env.vars.len()),
+            ("locals", self.local_env.len()),
+            ("stack", self.call_stack.len()),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::TextCodec;
+    use utils::run::{RunOptions, run_code};
+
+    #[test]
+    fn call_in_a_left_nested_sequence_keeps_the_rest_of_it() {
+        // the body of main is `(call; assign); return`, the assign runs after the call returns
+        let code = ProcLangMachine::parse_code(
+            "static r;
+            double(x)[ local y  y := x + x ; return y ]
+            main()[ local a  call double(r) -> a ; r := a + 1 ]",
+        )
+        .unwrap();
+        let ainput = ProcLangMachine::parse_ainput("r = 4").unwrap();
+        let rinputs = std::iter::repeat(String::new());
+        let report =
+            run_code::<ProcLangMachine, _>(code, ainput, rinputs, RunOptions::fuel(100)).unwrap();
+        assert_eq!(report.outcome.into_foutput().unwrap().print(), "r = 9\n");
+    }
+
+    // pseudo random number below `n`
+    fn next(seed: &mut u64, n: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % n
+    }
+
+    fn random_vars(seed: &mut u64, max: u64) -> Vec<String> {
+        (0..next(seed, max + 1))
+            .map(|_| ["x", "y", "r"][next(seed, 3) as usize].to_string())
+            .collect()
+    }
+
+    fn random_atom(seed: &mut u64) -> Atom {
+        match next(seed, 2) {
+            0 => Atom::Var(["x", "y", "r"][next(seed, 3) as usize].to_string()),
+            _ => Atom::Imm(Number::from(next(seed, 300) as usize)),
+        }
+    }
+
+    fn random_bexp(seed: &mut u64) -> BExp {
+        let lhs = random_atom(seed);
+        let op = [RelOp::Lt, RelOp::Eq, RelOp::Gt][next(seed, 3) as usize].clone();
+        BExp {
+            lhs,
+            op,
+            rhs: random_atom(seed),
+        }
+    }
+
+    fn random_stmt(seed: &mut u64, depth: usize) -> Stmt {
+        // only the first four kinds have no sub-statement
+        let kinds = if depth == 0 { 4 } else { 7 };
+        match next(seed, kinds) {
+            0 => Stmt::Nop,
+            1 => Stmt::Assign {
+                var: ["x", "y", "r"][next(seed, 3) as usize].to_string(),
+                expr: match next(seed, 3) {
+                    0 => AExp::Atom(random_atom(seed)),
+                    n => AExp::Bin {
+                        lhs: random_atom(seed),
+                        op: if n == 1 { ABinOp::Add } else { ABinOp::Sub },
+                        rhs: random_atom(seed),
+                    },
+                },
+            },
+            2 => Stmt::Call {
+                name: ["f", "main"][next(seed, 2) as usize].to_string(),
+                args: random_vars(seed, 2),
+                rets: random_vars(seed, 2),
+            },
+            3 => Stmt::Return {
+                vars: random_vars(seed, 2),
+            },
+            4 => Stmt::If {
+                cond: random_bexp(seed),
+                body: Box::new(random_stmt(seed, depth - 1)),
+            },
+            5 => Stmt::While {
+                cond: random_bexp(seed),
+                body: Box::new(random_stmt(seed, depth - 1)),
+            },
+            _ => Stmt::Seq(
+                Box::new(random_stmt(seed, depth - 1)),
+                Box::new(random_stmt(seed, depth - 1)),
+            ),
+        }
+    }
+
+    fn random_program(seed: &mut u64) -> ProcCode {
+        let procs = ["f", "main"]
+            .iter()
+            .map(|name| ProcDef {
+                name: name.to_string(),
+                params: random_vars(seed, 2),
+                locals: random_vars(seed, 2),
+                body: random_stmt(seed, 4),
+            })
+            .collect();
+        ProcCode(Program {
+            statics: random_vars(seed, 2),
+            procs,
+        })
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        let example = &ProcLangMachine::describe().examples[0];
+        utils::pretty::check_roundtrip(&ProcCode::parse(&example.code).unwrap()).unwrap();
+        let mut seed = 1;
+        for _ in 0..300 {
+            let code = random_program(&mut seed);
+            utils::pretty::check_roundtrip(&code).unwrap();
+        }
+    }
+}
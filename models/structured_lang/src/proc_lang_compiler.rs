@@ -6,7 +6,7 @@ use flow_ir::flow_ir::{
     PlaceExpr, Program as IrProgram, Region, StaticDef, StaticEnv, Stmt as IrStmt, ValueExpr,
 };
 use utils::number::Number;
-use utils::{Compiler, Machine};
+use utils::{Compiler, Machine, SourceMap};
 
 #[derive(Debug, Clone, Default)]
 pub struct ProcToFlowIrCompiler;
@@ -31,6 +31,8 @@ struct CompileCtx {
     regions: Vec<Region>,
     region_id: usize,
     vreg_id: usize,
+    // regions of the source statements, statements are numbered as in `proc_lang_parser::parse_with_spans`
+    map: SourceMap,
 }
 
 impl CompileCtx {
@@ -47,10 +49,12 @@ impl CompileCtx {
     }
 }
 
-fn ensure_with_return(stmt: Stmt) -> Stmt {
+// number of statements in the tree, the last child of the statement `id` is `id - 1`
+fn stmt_size(stmt: &Stmt) -> usize {
     match stmt {
-        s if s.ends_with_return() => s,
-        s => Stmt::Seq(Box::new(s), Box::new(Stmt::Return { vars: vec![] })),
+        Stmt::Seq(a, b) => 1 + stmt_size(a) + stmt_size(b),
+        Stmt::If { body, .. } | Stmt::While { body, .. } => 1 + stmt_size(body),
+        _ => 1,
     }
 }
 
@@ -254,6 +258,7 @@ fn compile_return_region(
 
 fn compile_stmt_to_region(
     stmt: &Stmt,
+    id: usize,
     cont_region: &str,
     scope: &ProcScope<'_>,
     ctx: &mut CompileCtx,
@@ -261,6 +266,7 @@ fn compile_stmt_to_region(
     match stmt {
         Stmt::Nop => {
             let label = ctx.fresh_region("nop");
+            ctx.map.insert(&label, id);
             ctx.regions.push(Region {
                 label: label.clone(),
                 blocks: vec![Block {
@@ -273,6 +279,7 @@ fn compile_stmt_to_region(
         }
         Stmt::Assign { var, expr } => {
             let label = ctx.fresh_region("assign");
+            ctx.map.insert(&label, id);
             let dst = ctx.fresh_vreg("assign");
             let mut stmts = Vec::new();
             emit_aexp_to_vreg(expr, &dst, scope, ctx, &mut stmts)?;
@@ -288,12 +295,13 @@ fn compile_stmt_to_region(
             Ok(label)
         }
         Stmt::Seq(a, b) => {
-            let b_region = compile_stmt_to_region(b, cont_region, scope, ctx)?;
-            compile_stmt_to_region(a, &b_region, scope, ctx)
+            let b_region = compile_stmt_to_region(b, id - 1, cont_region, scope, ctx)?;
+            compile_stmt_to_region(a, id - 1 - stmt_size(b), &b_region, scope, ctx)
         }
         Stmt::If { cond, body } => {
-            let body_region = compile_stmt_to_region(body, cont_region, scope, ctx)?;
+            let body_region = compile_stmt_to_region(body, id - 1, cont_region, scope, ctx)?;
             let label = ctx.fresh_region("if");
+            ctx.map.insert(&label, id);
             let lhs = ctx.fresh_vreg("if_lhs");
             let rhs = ctx.fresh_vreg("if_rhs");
             let mut stmts = Vec::new();
@@ -322,7 +330,8 @@ fn compile_stmt_to_region(
         }
         Stmt::While { cond, body } => {
             let test_region = ctx.fresh_region("while_test");
-            let body_region = compile_stmt_to_region(body, &test_region, scope, ctx)?;
+            ctx.map.insert(&test_region, id);
+            let body_region = compile_stmt_to_region(body, id - 1, &test_region, scope, ctx)?;
             let lhs = ctx.fresh_vreg("while_lhs");
             let rhs = ctx.fresh_vreg("while_rhs");
             let mut stmts = Vec::new();
@@ -351,6 +360,7 @@ fn compile_stmt_to_region(
         }
         Stmt::Call { name, args, rets } => {
             let ret_region = ctx.fresh_region("call_ret");
+            ctx.map.insert(&ret_region, id);
             let ret_label = ValueExpr::CodeLabel(ret_region.clone());
             let mut call_stmts = Vec::new();
 
@@ -370,6 +380,7 @@ fn compile_stmt_to_region(
                 .ok_or_else(|| format!("Unknown procedure in compiler: {name}"))?;
 
             let call_region = ctx.fresh_region("call");
+            ctx.map.insert(&call_region, id);
             ctx.regions.push(Region {
                 label: call_region.clone(),
                 blocks: vec![Block {
@@ -395,7 +406,11 @@ fn compile_stmt_to_region(
             });
             Ok(call_region)
         }
-        Stmt::Return { vars } => compile_return_region(vars, scope, ctx),
+        Stmt::Return { vars } => {
+            let label = compile_return_region(vars, scope, ctx)?;
+            ctx.map.insert(&label, id);
+            Ok(label)
+        }
     }
 }
 
@@ -427,6 +442,10 @@ impl Compiler for ProcToFlowIrCompiler {
     type Target = FlowIrMachine;
 
     fn compile(source: ProcCode) -> Result<FlowIrCode, String> {
+        Self::compile_mapped(source).map(|(code, _)| code)
+    }
+
+    fn compile_mapped(source: ProcCode) -> Result<(FlowIrCode, SourceMap), String> {
         let mut ctx = CompileCtx::default();
         let halt_region = ctx.fresh_region("halt");
         ctx.regions.push(Region {
@@ -446,10 +465,19 @@ impl Compiler for ProcToFlowIrCompiler {
             );
         }
 
+        // statements are numbered across the procs in order
+        let mut offset = 0;
         for proc in &source.0.procs {
             let scope = build_scope(&source, proc, &entry_regions)?;
-            let body = ensure_with_return(proc.body.clone());
-            let body_region = compile_stmt_to_region(&body, &halt_region, &scope, &mut ctx)?;
+            // a body without a final return gets one, which is not a source statement
+            let cont_region = if proc.body.ends_with_return() {
+                halt_region.clone()
+            } else {
+                compile_return_region(&[], &scope, &mut ctx)?
+            };
+            offset += stmt_size(&proc.body);
+            let body_region =
+                compile_stmt_to_region(&proc.body, offset - 1, &cont_region, &scope, &mut ctx)?;
 
             let mut stmts = Vec::new();
             for _ in &proc.locals {
@@ -499,10 +527,13 @@ impl Compiler for ProcToFlowIrCompiler {
             })
             .collect();
 
-        Ok(FlowIrCode(IrProgram {
-            statics,
-            regions: ctx.regions,
-        }))
+        Ok((
+            FlowIrCode(IrProgram {
+                statics,
+                regions: ctx.regions,
+            }),
+            ctx.map,
+        ))
     }
 
     fn encode_ainput(
@@ -549,3 +580,49 @@ impl Compiler for ProcToFlowIrCompiler {
         Ok(GlobalEnv { vars })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::TextCodec;
+    use utils::differential::{DiffOptions, assert_compiler_agrees};
+
+    #[test]
+    fn compiled_flow_ir_agrees_with_proc_lang() {
+        let code = "static r;
+            double(x)[ local y  y := x + x ; return y ]
+            main()[ local a  call double(r) -> a ; r := a + 1 ]";
+        // routputs of proc_lang are an execution trace, not part of the semantics
+        let options = DiffOptions {
+            routputs: false,
+            ..DiffOptions::fuel(1_000, 1_000)
+        };
+        assert_compiler_agrees::<ProcToFlowIrCompiler>(code, &["r = 0", "r = 4"], &options);
+    }
+
+    #[test]
+    fn source_map_numbers_statements_across_procs() {
+        let text = "static r;
+            double(x)[ local y  y := x + x ; return y ]
+            main()[ local a  call double(r) -> a ; r := a + 1 ]";
+        let code = ProcCode::parse(text).unwrap();
+        let (ir, map) = ProcToFlowIrCompiler::compile_mapped(code).unwrap();
+        let spans = ProcCode::node_spans(text).unwrap();
+        let mappings = map.resolve(&spans).unwrap();
+        let source_of = |target: &str| {
+            let mapping = utils::source_map::lookup(&mappings, target)
+                .unwrap_or_else(|| panic!("{target} is not mapped"));
+            &text[mapping.span.start..mapping.span.end]
+        };
+        for region in &ir.0.regions {
+            let label = region.label.as_str();
+            match label.rsplit_once('_').map_or(label, |(prefix, _)| prefix) {
+                "assign" => assert!(source_of(label).contains(":=")),
+                "call" | "call_ret" => assert_eq!(source_of(label), "call double(r) -> a"),
+                // the return of main is added by the compiler
+                "ret" if map.node(label).is_some() => assert_eq!(source_of(label), "return y"),
+                _ => assert_eq!(map.node(label), None, "{label}"),
+            }
+        }
+    }
+}
@@ -1,7 +1,8 @@
 use std::collections::BTreeMap;
 
+use utils::diagnostic::Span;
 use utils::number::Number;
-use utils::{TextCodec, Token as LexToken, lex};
+use utils::{SpannedToken, TextCodec, Token as LexToken, lex_spanned};
 
 use super::{ABinOp, AExp, Atom, BExp, GlobalEnv, ProcCode, ProcDef, Program, RelOp, Stmt};
 
@@ -43,6 +44,10 @@ impl TextCodec for ProcCode {
         Ok(Self(parse_program(text)?))
     }
 
+    fn node_spans(text: &str) -> Result<Vec<Span>, String> {
+        parse_with_spans(text).map(|(_, spans)| spans)
+    }
+
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
         let program = &self.0;
         write!(f, "static")?;
@@ -54,11 +59,13 @@ impl TextCodec for ProcCode {
         for proc in &program.procs {
             writeln!(f, "{}({})[", proc.name, proc.params.join(", "))?;
             if proc.locals.is_empty() {
+                // bracketed, or a body that starts with a name would be read as a local
                 writeln!(f, "  local")?;
+                writeln!(f, "  [{}]", stmt_to_text(&proc.body))?;
             } else {
                 writeln!(f, "  local {}", proc.locals.join(", "))?;
+                writeln!(f, "  {}", stmt_to_text(&proc.body))?;
             }
-            writeln!(f, "  {}", stmt_to_text(&proc.body))?;
             writeln!(f, "]")?;
         }
         Ok(())
@@ -66,24 +73,53 @@ impl TextCodec for ProcCode {
 }
 
 pub fn parse_program(text: &str) -> Result<Program, String> {
-    let mut parser = Parser::new(lex(text).map_err(|e| e.to_string())?);
+    parse_with_spans(text).map(|(program, _)| program)
+}
+
+// program and the spans of the statements of its procs in post-order (children before parents)
+// the i-th span belongs to the statement numbered i by compilers that emit source maps
+pub fn parse_with_spans(text: &str) -> Result<(Program, Vec<Span>), String> {
+    let mut parser = Parser::new(lex_spanned(text).map_err(|e| e.to_string())?);
     let program = parser.parse_program()?;
     parser.expect_eof()?;
-    Ok(program)
+    Ok((program, parser.stmt_spans))
 }
 
 struct Parser {
     tokens: Vec<LexToken>,
+    spans: Vec<Span>,
     pos: usize,
+    // spans of the parsed statements in post-order
+    stmt_spans: Vec<Span>,
 }
 
 impl Parser {
-    fn new(tokens: Vec<LexToken>) -> Self {
-        let tokens = tokens
+    fn new(tokens: Vec<SpannedToken>) -> Self {
+        let (tokens, spans) = tokens
             .into_iter()
-            .filter(|token| !matches!(token, LexToken::Whitespace(_) | LexToken::Comment(_)))
-            .collect();
-        Self { tokens, pos: 0 }
+            .filter(|token| !matches!(token.token, LexToken::Whitespace(_) | LexToken::Comment(_)))
+            .map(|token| (token.token, token.span))
+            .unzip();
+        Self {
+            tokens,
+            spans,
+            pos: 0,
+            stmt_spans: Vec::new(),
+        }
+    }
+
+    // start of the next token
+    fn start(&self) -> usize {
+        match self.spans.get(self.pos) {
+            Some(span) => span.start,
+            None => self.spans.last().map_or(0, |span| span.end),
+        }
+    }
+
+    // records a statement from `start` to the end of the last consumed token
+    fn record(&mut self, start: usize) {
+        let end = self.pos.checked_sub(1).map_or(start, |i| self.spans[i].end);
+        self.stmt_spans.push(Span::new(start, end));
     }
 
     fn is_eof(&self) -> bool {
@@ -225,12 +261,14 @@ impl Parser {
     }
 
     fn parse_stmt(&mut self) -> Result<Stmt, String> {
+        let start = self.start();
         let left = self.parse_stmt_single()?;
         if self.eat_symbol(';') {
             if self.peek_symbol(']') {
                 return Ok(left);
             }
             let right = self.parse_stmt()?;
+            self.record(start);
             Ok(Stmt::Seq(Box::new(left), Box::new(right)))
         } else {
             Ok(left)
@@ -238,7 +276,9 @@ impl Parser {
     }
 
     fn parse_stmt_single(&mut self) -> Result<Stmt, String> {
+        let start = self.start();
         if self.eat_ident("Nop") {
+            self.record(start);
             return Ok(Stmt::Nop);
         }
         if self.eat_ident("if") {
@@ -246,6 +286,7 @@ impl Parser {
             self.expect_symbol('[')?;
             let body = self.parse_stmt()?;
             self.expect_symbol(']')?;
+            self.record(start);
             return Ok(Stmt::If {
                 cond,
                 body: Box::new(body),
@@ -256,6 +297,7 @@ impl Parser {
             self.expect_symbol('[')?;
             let body = self.parse_stmt()?;
             self.expect_symbol(']')?;
+            self.record(start);
             return Ok(Stmt::While {
                 cond,
                 body: Box::new(body),
@@ -269,10 +311,12 @@ impl Parser {
             self.expect_symbol('-')?;
             self.expect_symbol('>')?;
             let rets = self.parse_var_list_stmt_boundary()?;
+            self.record(start);
             return Ok(Stmt::Call { name, args, rets });
         }
         if self.eat_ident("return") {
             let vars = self.parse_var_list_stmt_boundary()?;
+            self.record(start);
             return Ok(Stmt::Return { vars });
         }
         if self.eat_symbol('[') {
@@ -285,6 +329,7 @@ impl Parser {
             self.expect_symbol(':')?;
             self.expect_symbol('=')?;
             let expr = self.parse_aexp()?;
+            self.record(start);
             return Ok(Stmt::Assign { var, expr });
         }
         Err(self.error_here("unexpected token in statement"))
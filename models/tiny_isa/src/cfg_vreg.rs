@@ -2,7 +2,7 @@ use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CfgVRegCode(pub Program);
@@ -280,11 +280,30 @@ impl Machine for CfgVRegMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("cfg_vreg")
+            .code("`@label value` statics, then blocks `@name {` .. `}` of `%vN := v [+|- v];`, `[addr] := v;`, `if v < v then addr;` and a final `goto addr;`, `@main` runs first")
+            .ainput("words appended to the memory after the statics, comma-separated in parentheses: `(1, 2)`")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "count up",
+                "@x 3\n@main {\n  %v0 := *[@x];\n  %v1 := %v0 + #1;\n  [@x] := %v1;\n  goto @main;\n}",
+                "()",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::cfg_vreg_render::render_machine(snapshot)
     }
 }
 
+impl Metrics for CfgVRegMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("vregs", self.vregs.len()), ("memory", self.memory.len())]
+    }
+}
+
 fn compile_program(program: &Program) -> Result<CompiledProgram, String> {
     let mut block_index = HashMap::new();
     for (i, block) in program.blocks.iter().enumerate() {
@@ -1,10 +1,15 @@
 use utils::TextCodec;
+use utils::diagnostic::{Diagnostic, Span, lines_with_span};
 use utils::number::Number;
 
 use crate::cfg_vreg::*;
 
 impl TextCodec for CfgVRegCode {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
         Ok(Self(parse_program(text)?))
     }
 
@@ -32,31 +37,32 @@ impl TextCodec for CfgVRegCode {
     }
 }
 
-fn parse_program(text: &str) -> Result<Program, String> {
-    let mut lines = text
-        .lines()
-        .map(|l| l.split("//").next().unwrap_or("").trim().to_string())
+// errors point to the whole line
+fn parse_program(text: &str) -> Result<Program, Diagnostic> {
+    let mut lines = lines_with_span(text)
+        .map(|(span, l)| (span, l.split("//").next().unwrap_or("").trim().to_string()))
         .collect::<Vec<_>>();
-    lines.retain(|l| !l.is_empty());
+    lines.retain(|(_, l)| !l.is_empty());
 
     let mut i = 0usize;
     let mut statics = Vec::new();
     let mut blocks = Vec::new();
 
     while i < lines.len() {
-        let line = &lines[i];
+        let (span, line) = &lines[i];
+        let error = |e: String| Diagnostic::error(*span, e);
         if is_block_start(line) {
             let (block, next_i) = parse_block(&lines, i)?;
             blocks.push(block);
             i = next_i;
             continue;
         }
-        if let Some((label, value)) = parse_static_line(line)? {
+        if let Some((label, value)) = parse_static_line(line).map_err(error)? {
             statics.push(StaticDef { label, value });
             i += 1;
             continue;
         }
-        return Err(format!("Unexpected top-level line: {}", line));
+        return Err(error(format!("Unexpected top-level line: {}", line)));
     }
 
     Ok(Program { statics, blocks })
@@ -66,14 +72,16 @@ fn is_block_start(line: &str) -> bool {
     line.starts_with('@') && line.ends_with('{')
 }
 
-fn parse_block(lines: &[String], start: usize) -> Result<(Block, usize), String> {
-    let header = &lines[start];
+fn parse_block(lines: &[(Span, String)], start: usize) -> Result<(Block, usize), Diagnostic> {
+    let (header_span, header) = &lines[start];
     let label = header
         .strip_prefix('@')
         .and_then(|s| s.strip_suffix('{'))
         .map(str::trim)
         .filter(|s| !s.is_empty())
-        .ok_or_else(|| format!("Invalid block header: {}", header))?
+        .ok_or_else(|| {
+            Diagnostic::error(*header_span, format!("Invalid block header: {}", header))
+        })?
         .to_string();
 
     let mut i = start + 1;
@@ -82,38 +90,45 @@ fn parse_block(lines: &[String], start: usize) -> Result<(Block, usize), String>
     let mut final_jump: Option<AddrExpr> = None;
 
     while i < lines.len() {
-        let line = lines[i].trim();
+        let (span, line) = &lines[i];
+        let error = |e: String| Diagnostic::error(*span, e);
+        let line = line.trim();
         if line == "}" {
             break;
         }
-        if let Some(j) = parse_jump_if(line)? {
+        if let Some(j) = parse_jump_if(line).map_err(error)? {
             ifs.push(j);
             i += 1;
             continue;
         }
-        if let Some(target) = parse_goto(line)? {
+        if let Some(target) = parse_goto(line).map_err(error)? {
             if final_jump.is_some() {
-                return Err(format!("Multiple final goto in block @{}", label));
+                return Err(error(format!("Multiple final goto in block @{}", label)));
             }
             final_jump = Some(target);
             i += 1;
             continue;
         }
         if final_jump.is_some() {
-            return Err(format!("Statement after goto in block @{}", label));
+            return Err(error(format!("Statement after goto in block @{}", label)));
         }
-        if let Some(stmt) = parse_stmt(line)? {
+        if let Some(stmt) = parse_stmt(line).map_err(error)? {
             stmts.push(stmt);
             i += 1;
             continue;
         }
-        return Err(format!("Invalid line in block @{}: {}", label, line));
+        return Err(error(format!("Invalid line in block @{}: {}", label, line)));
     }
 
-    if i >= lines.len() || lines[i].trim() != "}" {
-        return Err(format!("Unclosed block: @{}", label));
+    if i >= lines.len() || lines[i].1.trim() != "}" {
+        return Err(Diagnostic::error(
+            *header_span,
+            format!("Unclosed block: @{}", label),
+        ));
     }
-    let jump = final_jump.ok_or_else(|| format!("Block @{} missing final goto", label))?;
+    let jump = final_jump.ok_or_else(|| {
+        Diagnostic::error(lines[i].0, format!("Block @{} missing final goto", label))
+    })?;
     Ok((
         Block {
             label,
@@ -340,3 +355,101 @@ fn stmt_to_text(s: &Stmt) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::Machine;
+
+    // pseudo random number below `n`
+    fn next(seed: &mut u64, n: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % n
+    }
+
+    fn random_label(seed: &mut u64) -> String {
+        ["main", "loop", "x"][next(seed, 3) as usize].to_string()
+    }
+
+    fn random_addr(seed: &mut u64) -> AddrExpr {
+        match next(seed, 3) {
+            0 => AddrExpr::VReg(next(seed, 4) as usize),
+            1 => AddrExpr::Label(random_label(seed)),
+            _ => AddrExpr::Imm(Number::from(next(seed, 100) as usize)),
+        }
+    }
+
+    fn random_value(seed: &mut u64) -> ValueExpr {
+        match next(seed, 4) {
+            0 => ValueExpr::VReg(next(seed, 4) as usize),
+            1 => ValueExpr::Imm(Number::from(next(seed, 100) as usize)),
+            2 => ValueExpr::Label(random_label(seed)),
+            _ => ValueExpr::Deref(PlaceExpr(Box::new(random_addr(seed)))),
+        }
+    }
+
+    fn random_stmt(seed: &mut u64) -> Stmt {
+        match next(seed, 3) {
+            0 => Stmt::Assign {
+                dst: next(seed, 4) as usize,
+                src: random_value(seed),
+            },
+            1 => Stmt::BinOp {
+                dst: next(seed, 4) as usize,
+                lhs: random_value(seed),
+                op: [BinOp::Add, BinOp::Sub][next(seed, 2) as usize],
+                rhs: random_value(seed),
+            },
+            _ => Stmt::Store {
+                place: PlaceExpr(Box::new(random_addr(seed))),
+                src: random_value(seed),
+            },
+        }
+    }
+
+    fn random_cond(seed: &mut u64) -> Cond {
+        let (lhs, rhs) = (random_value(seed), random_value(seed));
+        match next(seed, 3) {
+            0 => Cond::Lt(lhs, rhs),
+            1 => Cond::Eq(lhs, rhs),
+            _ => Cond::Gt(lhs, rhs),
+        }
+    }
+
+    fn random_code(seed: &mut u64) -> CfgVRegCode {
+        let statics = (0..next(seed, 3))
+            .map(|_| StaticDef {
+                label: random_label(seed),
+                value: Number::from(next(seed, 1000) as usize),
+            })
+            .collect();
+        let blocks = (0..next(seed, 4))
+            .map(|_| Block {
+                label: random_label(seed),
+                stmts: (0..next(seed, 4)).map(|_| random_stmt(seed)).collect(),
+                cont: Cont {
+                    ifs: (0..next(seed, 3))
+                        .map(|_| JumpIf {
+                            cond: random_cond(seed),
+                            target: random_addr(seed),
+                        })
+                        .collect(),
+                    jump: random_addr(seed),
+                },
+            })
+            .collect();
+        CfgVRegCode(Program { statics, blocks })
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        let example = &CfgVRegMachine::describe().examples[0];
+        utils::pretty::check_roundtrip(&CfgVRegCode::parse(&example.code).unwrap()).unwrap();
+        let mut seed = 1;
+        for _ in 0..300 {
+            utils::pretty::check_roundtrip(&random_code(&mut seed)).unwrap();
+        }
+    }
+}
@@ -1,6 +1,53 @@
-use utils::RenderState;
+use utils::{RenderBlock, RenderState};
 
-use crate::cfg_vreg::CfgVRegMachine;
+use crate::cfg_vreg::{AddrExpr, CfgVRegMachine};
+
+// the block a jump target names, if it is not computed at run time
+fn target_block(snapshot: &CfgVRegMachine, target: &AddrExpr) -> Option<usize> {
+    match target {
+        AddrExpr::Label(label) => snapshot.compiled.block_index.get(label).copied(),
+        AddrExpr::Imm(n) => n
+            .as_usize()
+            .ok()
+            .filter(|&index| index < snapshot.compiled.blocks.len()),
+        AddrExpr::VReg(_) => None,
+    }
+}
+
+fn render_cfg(snapshot: &CfgVRegMachine) -> RenderBlock {
+    let blocks = &snapshot.compiled.blocks;
+    let nodes = blocks
+        .iter()
+        .enumerate()
+        .map(|(index, block)| {
+            if index == snapshot.current_block {
+                utils::render_node!(block.label, class: "highlight")
+            } else {
+                utils::render_node!(block.label)
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut edges = vec![];
+    for block in blocks {
+        let jumps = block
+            .cont
+            .ifs
+            .iter()
+            .map(|jump_if| (&jump_if.target, Some("if")))
+            .chain(std::iter::once((&block.cont.jump, None)));
+        for (target, label) in jumps {
+            let Some(target) = target_block(snapshot, target) else {
+                continue;
+            };
+            let to = &blocks[target].label;
+            edges.push(match label {
+                Some(label) => utils::render_edge!(block.label => to, label: label),
+                None => utils::render_edge!(block.label => to),
+            });
+        }
+    }
+    utils::render_graph!(nodes: nodes, edges: edges, title: "cfg")
+}
 
 pub fn render_machine(snapshot: CfgVRegMachine) -> RenderState {
     let current_block = snapshot.compiled.blocks.get(snapshot.current_block);
@@ -65,6 +112,7 @@ pub fn render_machine(snapshot: CfgVRegMachine) -> RenderState {
             ],
             title: "status"
         ),
+        render_cfg(&snapshot),
         utils::render_table!(
             columns: vec![utils::render_text!("index".to_string()), utils::render_text!("stmt".to_string())],
             rows: block_rows,
@@ -82,3 +130,44 @@ pub fn render_machine(snapshot: CfgVRegMachine) -> RenderState {
         )
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use utils::{Machine, TextCodec};
+
+    use super::*;
+    use crate::cfg_vreg::CfgVRegCode;
+
+    #[test]
+    fn render_draws_the_cfg() {
+        let code = CfgVRegCode::parse(
+            "@main {\n  if %v0 = #0 then @done;\n  goto @loop;\n}\n@loop {\n  goto %v1;\n}\n@done {\n  goto @done;\n}",
+        )
+        .unwrap();
+        let machine = CfgVRegMachine::make(code, vec![]).unwrap();
+        let state = render_machine(machine);
+        let graph = state
+            .iter()
+            .find_map(|block| match block {
+                RenderBlock::Graph(graph) => Some(graph),
+                _ => None,
+            })
+            .expect("expected a graph block");
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(graph.nodes[0].class_name.as_deref(), Some("highlight"));
+        // the computed jump of @loop has no edge
+        let edges = graph
+            .edges
+            .iter()
+            .map(|edge| (edge.from.as_str(), edge.to.as_str(), edge.label.as_deref()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            edges,
+            vec![
+                ("main", "done", Some("if")),
+                ("main", "loop", None),
+                ("done", "done", None),
+            ]
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use std::collections::HashMap;
 
 use serde::{Deserialize, Serialize};
+use utils::diagnostic::{Diagnostic, lines_with_span};
 use utils::number::Number;
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AsmCode(pub RawProgramAst);
@@ -66,35 +67,42 @@ pub enum RawValue {
 }
 
 impl RawProgramAst {
-    pub(crate) fn parse_source(text: &str) -> Result<Self, String> {
+    // errors point to the whole line
+    pub(crate) fn parse_source(text: &str) -> Result<Self, Diagnostic> {
         let mut items = Vec::new();
         let mut section = AsmSection::Text;
-        for raw_line in text.lines() {
+        for (span, raw_line) in lines_with_span(text) {
             let line = raw_line.split("//").next().unwrap_or("").trim();
             if line.is_empty() {
                 continue;
             }
-            let item = if let Some(label) = RawItem::parse_label_line(line)? {
-                RawItem::Label(label)
-            } else if line.starts_with('.') {
-                let directive = RawDirective::parse_line(line)?;
-                match directive {
-                    RawDirective::Text => section = AsmSection::Text,
-                    RawDirective::Data => section = AsmSection::Data,
-                    RawDirective::Equ { .. } => {}
-                }
-                RawItem::Directive(directive)
-            } else {
-                match section {
-                    AsmSection::Text => RawItem::Inst(RawInst::parse_line(line)?),
-                    AsmSection::Data => RawItem::DataValue(RawValue::parse_line(line)?),
-                }
-            };
+            let item =
+                Self::parse_item(line, &mut section).map_err(|e| Diagnostic::error(span, e))?;
             items.push(item);
         }
         Ok(Self { items })
     }
 
+    fn parse_item(line: &str, section: &mut AsmSection) -> Result<RawItem, String> {
+        let item = if let Some(label) = RawItem::parse_label_line(line)? {
+            RawItem::Label(label)
+        } else if line.starts_with('.') {
+            let directive = RawDirective::parse_line(line)?;
+            match directive {
+                RawDirective::Text => *section = AsmSection::Text,
+                RawDirective::Data => *section = AsmSection::Data,
+                RawDirective::Equ { .. } => {}
+            }
+            RawItem::Directive(directive)
+        } else {
+            match section {
+                AsmSection::Text => RawItem::Inst(RawInst::parse_line(line)?),
+                AsmSection::Data => RawItem::DataValue(RawValue::parse_line(line)?),
+            }
+        };
+        Ok(item)
+    }
+
     pub(crate) fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
         for (i, item) in self.items.iter().enumerate() {
             if i > 0 {
@@ -969,7 +977,41 @@ impl Machine for SymbolicAsmMachine {
         snapshot
     }
 
+    // `@pc label` or `@pc n`: the next instruction is at the text label (or index n)
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, String> {
+        match name {
+            "pc" => {
+                let target = match self.symbols.text_labels.get(arg) {
+                    Some(&target) => target,
+                    None => arg
+                        .parse::<usize>()
+                        .map_err(|_| format!("@pc expects a text label or index, got `{arg}`"))?,
+                };
+                Ok(!self.halted && self.pc == target)
+            }
+            _ => Err(format!("unknown hook: @{name} (expected @pc)")),
+        }
+    }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("symbolic_asm")
+            .code("`.equ NAME #n` lines, `.text` with `@label:` and instructions like `ldi %r1 #3;`, `add %r1 %r2;`, `mov.f %r0 @label;`, `halt;`, then `.data` words")
+            .ainput("8 comma-separated registers (r0 is the pc), then data words appended to .data, one per line")
+            .rinput("empty")
+            .default_rinput("")
+            .example("add r2 to r1", ".text\nadd %r1 %r2;\nhalt;", "0, 3, 4, 0, 0, 0, 0, 0")
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::symbolic_asm_render::render_machine(snapshot)
     }
 }
+
+impl Metrics for SymbolicAsmMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![
+            ("static", self.static_data.len()),
+            ("data", self.data_memory.len()),
+        ]
+    }
+}
@@ -1,10 +1,15 @@
+use utils::Diagnostic;
 use utils::TextCodec;
-use utils::number::Number;
 
 use crate::symbolic_asm::{AsmCode, AsmInput, AsmOutput, RawProgramAst};
+use crate::tiny_isa_parser::parse_registers_and_numbers;
 
 impl TextCodec for AsmCode {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
         Ok(Self(RawProgramAst::parse_source(text)?))
     }
 
@@ -15,33 +20,11 @@ impl TextCodec for AsmCode {
 
 impl TextCodec for AsmInput {
     fn parse(text: &str) -> Result<Self, String> {
-        let mut lines = text.lines();
-        let reg_line = lines
-            .find(|line| !line.trim().is_empty())
-            .ok_or_else(|| "Expected first line with 8 registers".to_string())?;
-
-        let reg_parts: Vec<_> = reg_line
-            .split(',')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .collect();
-        if reg_parts.len() != 8 {
-            return Err("Expected 8 registers on the first line (comma separated)".to_string());
-        }
-
-        let regs: [Number; 8] = std::array::from_fn(|i| {
-            Number::parse(reg_parts[i]).expect("validated in parse; unreachable")
-        });
-
-        let mut data_extension = Vec::new();
-        for line in lines {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            data_extension.push(Number::parse(line)?);
-        }
+        Self::parse_spanned(text).map_err(String::from)
+    }
 
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        let (regs, data_extension) = parse_registers_and_numbers(text)?;
         Ok(Self {
             regs,
             data_extension,
@@ -65,33 +48,11 @@ impl TextCodec for AsmInput {
 
 impl TextCodec for AsmOutput {
     fn parse(text: &str) -> Result<Self, String> {
-        let mut lines = text.lines();
-        let reg_line = lines
-            .find(|line| !line.trim().is_empty())
-            .ok_or_else(|| "Expected first line with 8 registers".to_string())?;
-
-        let reg_parts: Vec<_> = reg_line
-            .split(',')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .collect();
-        if reg_parts.len() != 8 {
-            return Err("Expected 8 registers on the first line (comma separated)".to_string());
-        }
-
-        let regs: [Number; 8] = std::array::from_fn(|i| {
-            Number::parse(reg_parts[i]).expect("validated in parse; unreachable")
-        });
-
-        let mut data_memory = Vec::new();
-        for line in lines {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            data_memory.push(Number::parse(line)?);
-        }
+        Self::parse_spanned(text).map_err(String::from)
+    }
 
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        let (regs, data_memory) = parse_registers_and_numbers(text)?;
         Ok(Self { regs, data_memory })
     }
 
@@ -109,3 +70,30 @@ impl TextCodec for AsmOutput {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_roundtrip() {
+        let code = AsmCode::parse(
+            "
+.equ N #3
+.text
+@loop:
+ldi %r1 N;
+subi.f %r1 #1;
+eq %r1 %r2;
+mov.f %r0 @loop;
+halt;
+.data
+@buf:
+0
+@loop
+",
+        )
+        .unwrap();
+        utils::pretty::check_roundtrip(&code).unwrap();
+    }
+}
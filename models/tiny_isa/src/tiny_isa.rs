@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use utils::number::Number;
-use utils::{Machine, StepResult};
+use utils::{Machine, Metrics, StepResult};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MachineCode(pub Vec<Number>);
@@ -323,7 +323,47 @@ impl Machine for TinyIsaMachine {
         snapshot
     }
 
+    // `@pc n`: the next instruction is at address n
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, String> {
+        match name {
+            "pc" => {
+                let target = arg
+                    .parse::<usize>()
+                    .map_err(|_| format!("@pc expects an address, got `{arg}`"))?;
+                Ok(!self.halted && self.pc()? == target)
+            }
+            _ => Err(format!("unknown hook: @{name} (expected @pc)")),
+        }
+    }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("tiny_isa")
+            .code("one encoded instruction word per line (decimal or 0x hex), loaded from address 0")
+            .ainput("8 comma-separated registers (r0 is the pc), then memory words after the code, one per line")
+            .rinput("empty")
+            .default_rinput("")
+            .example("add r2 to r1", "1034\n64", "0, 3, 4, 0, 0, 0, 0, 0")
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::tiny_isa_render::render_machine(snapshot)
     }
 }
+
+impl Metrics for TinyIsaMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("memory", self.memory.len())]
+    }
+
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        if self.halted {
+            return vec![];
+        }
+        let inst = self.pc().and_then(|pc| Self::decode(&self.read_mem(pc)));
+        match inst {
+            Ok(DecodedInst::Ld { .. }) => vec![("load", 1)],
+            Ok(DecodedInst::St { .. }) => vec![("store", 1)],
+            _ => vec![],
+        }
+    }
+}
@@ -1,18 +1,18 @@
 use utils::TextCodec;
+use utils::diagnostic::{Diagnostic, lines_with_span};
 
 use crate::tiny_isa::{MachineCode, RegisterMemoryImage};
 use utils::number::Number;
 
 impl TextCodec for MachineCode {
     fn parse(text: &str) -> Result<Self, String> {
-        let mut code = Vec::new();
-        for line in text.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            code.push(Number::parse(line)?);
-        }
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        let code = lines_with_span(text)
+            .map(|(span, line)| Number::parse(line).map_err(|e| Diagnostic::error(span, e)))
+            .collect::<Result<_, _>>()?;
         Ok(Self(code))
     }
 
@@ -29,33 +29,11 @@ impl TextCodec for MachineCode {
 
 impl TextCodec for RegisterMemoryImage {
     fn parse(text: &str) -> Result<Self, String> {
-        let mut lines = text.lines();
-        let reg_line = lines
-            .find(|line| !line.trim().is_empty())
-            .ok_or_else(|| "Expected first line with 8 registers".to_string())?;
-
-        let reg_parts: Vec<_> = reg_line
-            .split(',')
-            .map(str::trim)
-            .filter(|s| !s.is_empty())
-            .collect();
-        if reg_parts.len() != 8 {
-            return Err("Expected 8 registers on the first line (comma separated)".to_string());
-        }
-
-        let register: [Number; 8] = std::array::from_fn(|i| {
-            Number::parse(reg_parts[i]).expect("validated in parse; unreachable")
-        });
-
-        let mut memory = Vec::new();
-        for line in lines {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            }
-            memory.push(Number::parse(line)?);
-        }
+        Self::parse_spanned(text).map_err(String::from)
+    }
 
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        let (register, memory) = parse_registers_and_numbers(text)?;
         Ok(Self { register, memory })
     }
 
@@ -73,3 +51,78 @@ impl TextCodec for RegisterMemoryImage {
         Ok(())
     }
 }
+
+// 8 comma separated registers on the first line, then one number per line
+// (shared with the input/output of symbolic_asm)
+pub(crate) fn parse_registers_and_numbers(
+    text: &str,
+) -> Result<([Number; 8], Vec<Number>), Diagnostic> {
+    let mut lines = lines_with_span(text);
+    let (reg_span, reg_line) = lines
+        .next()
+        .ok_or_else(|| Diagnostic::unspanned("Expected first line with 8 registers"))?;
+
+    let reg_parts: Vec<_> = reg_line
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect();
+    if reg_parts.len() != 8 {
+        return Err(Diagnostic::error(
+            reg_span,
+            "Expected 8 registers on the first line (comma separated)",
+        ));
+    }
+    let mut register: [Number; 8] = Default::default();
+    for (reg, part) in register.iter_mut().zip(reg_parts) {
+        *reg = Number::parse(part).map_err(|e| Diagnostic::error(reg_span, e))?;
+    }
+
+    let numbers = lines
+        .map(|(span, line)| Number::parse(line).map_err(|e| Diagnostic::error(span, e)))
+        .collect::<Result<_, _>>()?;
+    Ok((register, numbers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::Machine;
+
+    use crate::tiny_isa::TinyIsaMachine;
+
+    // pseudo random number below `n`
+    fn next(seed: &mut u64, n: u64) -> u64 {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) % n
+    }
+
+    // instruction-sized words, with some wider than 64 bits
+    fn random_code(seed: &mut u64) -> MachineCode {
+        let words = (0..next(seed, 8))
+            .map(|_| match next(seed, 3) {
+                0 => Number::from(next(seed, 2048) as usize),
+                1 => Number::from(next(seed, u32::MAX as u64) as usize),
+                _ => {
+                    let bytes = (0..next(seed, 12))
+                        .map(|_| next(seed, 256) as u8)
+                        .collect::<Vec<_>>();
+                    Number::from_u8array(&bytes)
+                }
+            })
+            .collect();
+        MachineCode(words)
+    }
+
+    #[test]
+    fn code_roundtrip() {
+        let example = &TinyIsaMachine::describe().examples[0];
+        utils::pretty::check_roundtrip(&MachineCode::parse(&example.code).unwrap()).unwrap();
+        let mut seed = 1;
+        for _ in 0..300 {
+            utils::pretty::check_roundtrip(&random_code(&mut seed)).unwrap();
+        }
+    }
+}
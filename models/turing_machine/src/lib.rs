@@ -1,6 +1,8 @@
-use crate::machine::{CodeEntry as CoreCodeEntry, Tape, TuringMachine, TuringMachineDefinition};
+use crate::machine::{
+    CodeEntry as CoreCodeEntry, Direction, Tape, TuringMachine, TuringMachineDefinition,
+};
 use serde::{Deserialize, Serialize};
-use utils::{Machine, StepResult, TextCodec};
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 pub mod machine;
 pub mod manipulation;
@@ -71,6 +73,32 @@ impl Machine for TuringMachine {
         snapshot
     }
 
+    // `@state q`: the machine is in state q
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, String> {
+        match name {
+            "state" => Ok(self.now_state().print() == arg),
+            _ => Err(format!("unknown hook: @{name} (expected @state)")),
+        }
+    }
+
+    // the current state, the unit of source maps into turing machines
+    fn location(&self) -> Option<String> {
+        Some(self.now_state().print())
+    }
+
+    fn describe() -> utils::Description {
+        utils::Description::new("turing_machine")
+            .code("initial state, accepted state, then one `sign,state,sign,state,L|R|C` rule per line")
+            .ainput("tape `left|head|right`, signs separated by commas (`-` is blank)")
+            .rinput("empty")
+            .default_rinput("")
+            .example(
+                "walk right",
+                "start\ngoal\na,start,b,start,R\nb,start,b,start,R\nc,start,c,goal,C",
+                "-|a|b,a,b,b,c",
+            )
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         let now = snapshot
             .next_code()
@@ -108,6 +136,58 @@ impl Machine for TuringMachine {
             })
             .collect::<Vec<_>>();
 
+        // state diagram: the current state and transition are highlighted
+        let mut states: Vec<String> = vec![];
+        let state_names = std::iter::once(snapshot.init_state())
+            .chain(snapshot.accepted_state())
+            .chain(
+                snapshot
+                    .code()
+                    .iter()
+                    .flat_map(|((_, s1), (_, s2, _))| [s1, s2]),
+            )
+            .map(|s| s.print());
+        for name in state_names {
+            if !states.contains(&name) {
+                states.push(name);
+            }
+        }
+        let accepted = snapshot
+            .accepted_state()
+            .iter()
+            .map(|s| s.print())
+            .collect::<Vec<_>>();
+        let state_nodes = states
+            .into_iter()
+            .map(|name| {
+                if name == state {
+                    utils::render_node!(name, class: "highlight")
+                } else if accepted.contains(&name) {
+                    utils::render_node!(name, class: "accepted")
+                } else {
+                    utils::render_node!(name)
+                }
+            })
+            .collect::<Vec<_>>();
+        let state_edges = snapshot
+            .code()
+            .iter()
+            .cloned()
+            .map(CodeEntry::from)
+            .enumerate()
+            .map(|(idx, entry)| {
+                let label = format!(
+                    "{}/{},{}",
+                    entry.key_sign, entry.next_sign, entry.direction
+                );
+                if idx == now {
+                    utils::render_edge!(entry.key_state => entry.next_state, label: label, class: "highlight")
+                } else {
+                    utils::render_edge!(entry.key_state => entry.next_state, label: label)
+                }
+            })
+            .collect::<Vec<_>>();
+
         let (tapes, head_pos) = tape.into_vec();
         let tape_children = tapes
             .into_iter()
@@ -134,6 +214,12 @@ impl Machine for TuringMachine {
                 title: "code"
             ),
             utils::render_text!(state, title: "state"),
+            utils::render_graph!(
+                nodes: state_nodes,
+                edges: state_edges,
+                orientation: utils::RenderOrientation::Horizontal,
+                title: "state diagram"
+            ),
             utils::render_container!(
                 children: tape_children,
                 orientation: utils::RenderOrientation::Horizontal,
@@ -143,3 +229,16 @@ impl Machine for TuringMachine {
         ]
     }
 }
+
+impl Metrics for TuringMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("tape", self.now_tape().size())]
+    }
+
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        match self.next_code() {
+            Some((_, (_, _, Direction::Constant))) | None => vec![],
+            Some(_) => vec![("head-move", 1)],
+        }
+    }
+}
@@ -101,6 +101,10 @@ impl Tape {
             Direction::Constant => {}
         }
     }
+    // number of cells written or visited so far
+    pub fn size(&self) -> usize {
+        self.left.len() + 1 + self.right.len()
+    }
     pub fn into_vec(&self) -> (Vec<Sign>, usize) {
         let mut v = self.left.clone();
         let pos = v.len();
@@ -49,3 +49,48 @@ fn tape_parse() {
     let _ = Tape::parse("a,b|-|c,d").unwrap();
     let _ = Tape::parse("|-|").unwrap();
 }
+
+#[test]
+fn breakpoint_on_state_hook() {
+    use crate::machine::TuringMachine;
+    use utils::breakpoint::{Breakpoints, Condition, JsonPath, Stop, run_until};
+    use utils::{Machine, parse::ParseTextCodec};
+
+    // alternates between `start` and `mid` over the a's
+    let code = "start\nend\na,start,a,mid,R\na,mid,a,start,R\n-,start,-,end,C\n-,mid,-,end,C";
+    let machine =
+        TuringMachine::make(code.parse_tc().unwrap(), "|a|a,a,a".parse_tc().unwrap()).unwrap();
+    assert!(machine.hook("state", "start").unwrap());
+    assert!(machine.hook("region", "x").is_err());
+
+    let mut breakpoints = Breakpoints::new();
+    breakpoints.add(Condition::parse("@state mid").unwrap());
+    breakpoints.watch(JsonPath::parse("machine_state.state").unwrap());
+
+    let report = run_until(machine, std::iter::repeat(()), 100, &breakpoints).unwrap();
+    assert_eq!(report.steps, 1);
+    assert_eq!(report.watched[0].1, Some("mid".into()));
+    let Stop::Breakpoint { machine, fired } = report.stop else {
+        panic!("expected a breakpoint stop");
+    };
+    assert_eq!(fired, vec![0]);
+
+    // the stopped machine continues to the next hit, then runs to the end
+    let report = run_until(machine, std::iter::repeat(()), 100, &breakpoints).unwrap();
+    assert_eq!(report.steps, 2);
+    let Stop::Breakpoint { machine, .. } = report.stop else {
+        panic!("expected a breakpoint stop");
+    };
+    let report = run_until(machine, std::iter::repeat(()), 100, &breakpoints).unwrap();
+    assert!(matches!(report.stop, Stop::Ended(outcome) if outcome.is_halted()));
+}
+
+#[test]
+fn code_roundtrip() {
+    use crate::machine::TuringMachineDefinition;
+    use utils::pretty::check_roundtrip;
+
+    let code = "start\nend\na,start,a,mid,R\na,mid,a,start,R\n-,start,-,end,C\n-,mid,-,end,C";
+    check_roundtrip(&TuringMachineDefinition::parse(code).unwrap()).unwrap();
+    check_roundtrip(&Tape::parse("a,b|-|c,d").unwrap()).unwrap();
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use utils::identifier::Identifier;
-use utils::{Machine, StepResult, TextCodec};
+use utils::lexer::{LexerConfig, Token as LexToken, lex_with};
+use utils::pretty::Doc;
+use utils::{Machine, Metrics, StepResult, TextCodec};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StrArrCode(pub Stmt);
@@ -163,11 +165,28 @@ impl Machine for StrArrMachine {
         snapshot
     }
 
+    fn describe() -> utils::Description {
+        utils::Description::new("strarr")
+            .code("statements `x := a`, `s ; s`, `if b then s end`, `while b { s }`, Nop; strings a: x, ['c', ..], a ++ a, head a, tail a; b: is-empty a, a == a")
+            .ainput("initial variables, `x = ['a', 'b']` per line (others are empty)")
+            .rinput("empty")
+            .default_rinput("")
+            .example("drop", "x := ['a', 'b'] ; while x == ['a', 'b'] { x := tail x }", "")
+            .example("concat", "z := x ++ head y", "x = ['a']\ny = ['b', 'c']")
+    }
+
     fn render(snapshot: Self::SnapShot) -> utils::RenderState {
         crate::strarr_render::render_machine(snapshot)
     }
 }
 
+impl Metrics for StrArrMachine {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        let chars = self.env.vars.iter().map(|(_, value)| value.len()).sum();
+        vec![("vars", self.env.vars.len()), ("chars", chars)]
+    }
+}
+
 impl TextCodec for Environment {
     fn parse(text: &str) -> Result<Self, String> {
         let mut env = Environment::default();
@@ -206,6 +225,10 @@ impl TextCodec for StrArrCode {
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
         write!(f, "{}", stmt_to_text(&self.0))
     }
+
+    fn to_doc(&self) -> Doc {
+        stmt_doc(&self.0)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -401,129 +424,67 @@ impl Parser {
     }
 }
 
+// the lexer config and the token of each word come from the same table
+const KEYWORDS: [(&str, Token); 8] = [
+    ("Nop", Token::Nop),
+    ("if", Token::If),
+    ("then", Token::Then),
+    ("end", Token::End),
+    ("while", Token::While),
+    ("is-empty", Token::IsEmpty),
+    ("head", Token::Head),
+    ("tail", Token::Tail),
+];
+
+const OPERATORS: [(&str, Token); 3] = [
+    (":=", Token::Assign),
+    ("++", Token::Concat),
+    ("==", Token::EqEq),
+];
+
+fn lookup(table: &[(&str, Token)], word: &str) -> Option<Token> {
+    table
+        .iter()
+        .find(|(found, _)| *found == word)
+        .map(|(_, token)| token.clone())
+}
+
 fn lex(text: &str) -> Result<Vec<Token>, String> {
+    let config = LexerConfig::new()
+        .operators(&OPERATORS.map(|(op, _)| op))
+        .keywords(&KEYWORDS.map(|(word, _)| word))
+        .ident_chars(&['-'])
+        .char_literals();
     let mut tokens = Vec::new();
-    let mut chars = text.chars().peekable();
-    while let Some(&ch) = chars.peek() {
-        if ch.is_whitespace() {
-            chars.next();
-            continue;
-        }
-        if ch.is_ascii_alphabetic() || ch == '_' {
-            let mut word = String::new();
-            while let Some(&c) = chars.peek() {
-                if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
-                    word.push(c);
-                    chars.next();
-                } else {
-                    break;
-                }
+    for token in lex_with(text, &config).map_err(|e| e.to_string())? {
+        tokens.push(match token {
+            LexToken::Whitespace(_) | LexToken::Comment(_) => continue,
+            LexToken::Ident(name) => Token::Ident(name),
+            LexToken::Char(ch) => Token::Char(ch),
+            LexToken::Keyword(word) => {
+                lookup(&KEYWORDS, &word).ok_or_else(|| format!("Unknown keyword: {word}"))?
             }
-            tokens.push(match word.as_str() {
-                "Nop" => Token::Nop,
-                "if" => Token::If,
-                "then" => Token::Then,
-                "end" => Token::End,
-                "while" => Token::While,
-                "is-empty" => Token::IsEmpty,
-                "head" => Token::Head,
-                "tail" => Token::Tail,
-                _ => Token::Ident(word),
-            });
-            continue;
-        }
-        match ch {
-            '(' => {
-                chars.next();
-                tokens.push(Token::LParen);
+            LexToken::Op(op) => {
+                lookup(&OPERATORS, &op).ok_or_else(|| format!("Unknown operator: {op}"))?
             }
-            ')' => {
-                chars.next();
-                tokens.push(Token::RParen);
-            }
-            '[' => {
-                chars.next();
-                tokens.push(Token::LBracket);
-            }
-            ']' => {
-                chars.next();
-                tokens.push(Token::RBracket);
-            }
-            '{' => {
-                chars.next();
-                tokens.push(Token::LBrace);
-            }
-            '}' => {
-                chars.next();
-                tokens.push(Token::RBrace);
-            }
-            ',' => {
-                chars.next();
-                tokens.push(Token::Comma);
-            }
-            ';' => {
-                chars.next();
-                tokens.push(Token::Semi);
-            }
-            ':' => {
-                chars.next();
-                if chars.next() == Some('=') {
-                    tokens.push(Token::Assign);
-                } else {
-                    return Err("Expected '=' after ':'".to_string());
-                }
-            }
-            '+' => {
-                chars.next();
-                if chars.next() == Some('+') {
-                    tokens.push(Token::Concat);
-                } else {
-                    return Err("Expected second '+'".to_string());
-                }
-            }
-            '=' => {
-                chars.next();
-                if chars.next() == Some('=') {
-                    tokens.push(Token::EqEq);
-                } else {
-                    return Err("Expected second '='".to_string());
-                }
-            }
-            '\'' => {
-                tokens.push(Token::Char(parse_char_literal(&mut chars)?));
-            }
-            _ => return Err(format!("Unexpected character: {ch}")),
-        }
+            LexToken::Symbol(ch) => match ch {
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                ',' => Token::Comma,
+                ';' => Token::Semi,
+                ':' => return Err("Expected '=' after ':'".to_string()),
+                _ => return Err(format!("Unexpected character: {ch}")),
+            },
+            other => return Err(format!("Unexpected token: {other:?}")),
+        });
     }
     Ok(tokens)
 }
 
-fn parse_char_literal<I>(chars: &mut std::iter::Peekable<I>) -> Result<char, String>
-where
-    I: Iterator<Item = char>,
-{
-    if chars.next() != Some('\'') {
-        return Err("Expected opening quote".to_string());
-    }
-    let ch = match chars.next() {
-        Some('\\') => match chars.next() {
-            Some('n') => '\n',
-            Some('r') => '\r',
-            Some('t') => '\t',
-            Some('\\') => '\\',
-            Some('\'') => '\'',
-            Some(other) => other,
-            None => return Err("Unterminated escape".to_string()),
-        },
-        Some(c) => c,
-        None => return Err("Unterminated character literal".to_string()),
-    };
-    if chars.next() != Some('\'') {
-        return Err("Expected closing quote".to_string());
-    }
-    Ok(ch)
-}
-
 fn char_to_text(ch: char) -> String {
     match ch {
         '\n' => "'\\n'".to_string(),
@@ -569,16 +530,33 @@ fn bexp_to_text(exp: &BExp) -> String {
 }
 
 pub(crate) fn stmt_to_text(stmt: &Stmt) -> String {
+    stmt_doc(stmt).render(usize::MAX)
+}
+
+// a sequence is broken into one statement per line and a body is indented between its keywords
+fn stmt_doc(stmt: &Stmt) -> Doc {
+    let block = |head: String, body: &Stmt, close: &str| {
+        (Doc::text(head) + (Doc::line() + stmt_doc(body)).nest(2) + Doc::line() + Doc::text(close))
+            .group()
+    };
     match stmt {
-        Stmt::Nop => "Nop".to_string(),
-        Stmt::Assign { var, expr } => format!("{} := {}", var.as_str(), aexp_to_text(expr)),
-        Stmt::Seq(lhs, rhs) => format!("{} ; {}", stmt_to_text(lhs), stmt_to_text(rhs)),
-        Stmt::If { cond, body } => {
-            format!("if {} then {} end", bexp_to_text(cond), stmt_to_text(body))
+        Stmt::Nop => Doc::text("Nop"),
+        Stmt::Assign { var, expr } => {
+            Doc::text(format!("{} := {}", var.as_str(), aexp_to_text(expr)))
         }
-        Stmt::While { cond, body } => {
-            format!("while {} {{ {} }}", bexp_to_text(cond), stmt_to_text(body))
+        Stmt::Seq(..) => {
+            let mut stmts = vec![];
+            let mut rest = vec![stmt];
+            while let Some(stmt) = rest.pop() {
+                match stmt {
+                    Stmt::Seq(lhs, rhs) => rest.extend([rhs.as_ref(), lhs.as_ref()]),
+                    stmt => stmts.push(stmt_doc(stmt)),
+                }
+            }
+            Doc::join(stmts, Doc::text(" ;") + Doc::line()).group()
         }
+        Stmt::If { cond, body } => block(format!("if {} then", bexp_to_text(cond)), body, "end"),
+        Stmt::While { cond, body } => block(format!("while {} {{", bexp_to_text(cond)), body, "}"),
     }
 }
 
@@ -593,6 +571,26 @@ mod tests {
         let printed = parsed.print();
         let reparsed = StrArrCode::parse(&printed).unwrap();
         assert_eq!(parsed, reparsed);
+        utils::pretty::check_roundtrip(&parsed).unwrap();
+    }
+
+    #[test]
+    fn every_table_word_lexes_to_its_token() {
+        for (word, token) in KEYWORDS.iter().chain(OPERATORS.iter()) {
+            assert_eq!(lex(word).unwrap(), vec![token.clone()]);
+        }
+    }
+
+    #[test]
+    fn pretty_print_indents_bodies() {
+        let code =
+            StrArrCode::parse("x := ['a', 'b'] ; while x == ['a', 'b'] { x := tail x ; y := x }")
+                .unwrap();
+        assert_eq!(
+            code.print_pretty(30),
+            "x := ['a', 'b'] ;\nwhile x == ['a', 'b'] {\n  x := tail x ; y := x\n}"
+        );
+        utils::pretty::check_roundtrip(&code).unwrap();
     }
 
     #[test]
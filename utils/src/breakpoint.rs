@@ -0,0 +1,519 @@
+use std::fmt::{self, Display};
+
+use serde_json::Value;
+
+use crate::number::Number;
+use crate::run::RunOutcome;
+use crate::{Machine, StepResult};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+// path into the JSON form of a snapshot: `$.frame.env[0]`, `tape.head` or `regs.0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(pub Vec<PathSegment>);
+
+impl JsonPath {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        let rest = text.strip_prefix('$').unwrap_or(text);
+        let mut segments = vec![];
+        let mut chars = rest.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {}
+                '[' => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(format!("unclosed `[` in path `{text}`")),
+                        }
+                    }
+                    let inner = inner.trim();
+                    let segment = match inner.parse() {
+                        Ok(index) => PathSegment::Index(index),
+                        Err(_) => PathSegment::Key(inner.trim_matches('"').to_string()),
+                    };
+                    segments.push(segment);
+                    continue;
+                }
+                _ => {
+                    let mut key = c.to_string();
+                    while let Some(&c) = chars.peek() {
+                        if c == '.' || c == '[' {
+                            break;
+                        }
+                        key.push(c);
+                        chars.next();
+                    }
+                    // a bare number is an index into arrays and a key into objects
+                    let segment = match key.parse() {
+                        Ok(index) => PathSegment::Index(index),
+                        Err(_) => PathSegment::Key(key),
+                    };
+                    segments.push(segment);
+                    continue;
+                }
+            }
+            if chars.peek().is_none_or(|&c| c == '.' || c == '[') {
+                return Err(format!("empty segment in path `{text}`"));
+            }
+        }
+        Ok(JsonPath(segments))
+    }
+
+    pub fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.0
+            .iter()
+            .try_fold(value, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(index) => match value {
+                    Value::Array(items) => items.get(*index),
+                    _ => value.get(index.to_string()),
+                },
+            })
+    }
+}
+
+impl Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.0 {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".{key}")?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    // longer operators first so that `<=` is not read as `<`
+    const ALL: [(&'static str, CompareOp); 6] = [
+        ("==", CompareOp::Eq),
+        ("!=", CompareOp::Ne),
+        ("<=", CompareOp::Le),
+        (">=", CompareOp::Ge),
+        ("<", CompareOp::Lt),
+        (">", CompareOp::Gt),
+    ];
+
+    fn as_str(self) -> &'static str {
+        Self::ALL.iter().find(|(_, op)| *op == self).unwrap().0
+    }
+}
+
+// a condition over a machine state
+// - `path` : the value at path exists and is not null, false, 0, "" or empty
+// - `path op literal` : comparison, the literal is JSON or else a bare string (`state == q1`)
+// - `@name arg` : model specific hook answered by `Machine::hook` (`@state q1`, `@region loop`)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Truthy(JsonPath),
+    Compare {
+        path: JsonPath,
+        op: CompareOp,
+        value: Value,
+    },
+    Hook {
+        name: String,
+        arg: String,
+    },
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if let Some(hook) = text.strip_prefix('@') {
+            let (name, arg) = hook.split_once(char::is_whitespace).unwrap_or((hook, ""));
+            if name.is_empty() {
+                return Err("missing hook name after `@`".to_string());
+            }
+            return Ok(Condition::Hook {
+                name: name.to_string(),
+                arg: arg.trim().to_string(),
+            });
+        }
+        let found = CompareOp::ALL
+            .iter()
+            .filter_map(|(symbol, op)| text.find(symbol).map(|at| (at, *symbol, *op)))
+            .min_by_key(|(at, symbol, _)| (*at, usize::MAX - symbol.len()));
+        let Some((at, symbol, op)) = found else {
+            // `x = 3` is a mistyped comparison, not a path
+            if text.contains('=') {
+                return Err(format!("unknown operator `=` in `{text}` (use `==`)"));
+            }
+            return Ok(Condition::Truthy(JsonPath::parse(text)?));
+        };
+        let literal = text[at + symbol.len()..].trim();
+        if literal.is_empty() {
+            return Err(format!("missing value after `{symbol}`"));
+        }
+        // naturals beyond u64 are kept as text, as `Number` serializes them
+        let value = if literal.bytes().all(|b| b.is_ascii_digit()) {
+            literal
+                .parse::<u64>()
+                .map_or_else(|_| Value::from(literal), Value::from)
+        } else {
+            serde_json::from_str(literal).unwrap_or_else(|_| Value::from(literal))
+        };
+        Ok(Condition::Compare {
+            path: JsonPath::parse(&text[..at])?,
+            op,
+            value,
+        })
+    }
+
+    // `snapshot` is the JSON form of the state, `hook` answers `@name arg`
+    pub fn eval(
+        &self,
+        snapshot: &Value,
+        hook: &mut impl FnMut(&str, &str) -> Result<bool, String>,
+    ) -> Result<bool, String> {
+        match self {
+            Condition::Truthy(path) => Ok(path.get(snapshot).is_some_and(truthy)),
+            Condition::Compare { path, op, value } => Ok(path
+                .get(snapshot)
+                .is_some_and(|found| compare(found, *op, value))),
+            Condition::Hook { name, arg } => hook(name, arg),
+        }
+    }
+
+    // evaluate on a machine directly
+    pub fn eval_machine<M: Machine>(&self, machine: &M) -> Result<bool, String> {
+        let snapshot = match self {
+            // hooks do not look at the snapshot
+            Condition::Hook { .. } => Value::Null,
+            _ => serde_json::to_value(machine.snapshot()).map_err(|e| e.to_string())?,
+        };
+        self.eval(&snapshot, &mut |name, arg| machine.hook(name, arg))
+    }
+}
+
+impl Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Truthy(path) => write!(f, "{path}"),
+            Condition::Compare { path, op, value } => write!(f, "{path} {} {value}", op.as_str()),
+            Condition::Hook { name, arg } if arg.is_empty() => write!(f, "@{name}"),
+            Condition::Hook { name, arg } => write!(f, "@{name} {arg}"),
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(fields) => !fields.is_empty(),
+    }
+}
+
+// numbers too large for JSON are serialized as decimal strings, so strings and numbers are compared by text
+fn compare(found: &Value, op: CompareOp, expected: &Value) -> bool {
+    let text = |value: &Value| match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let equal = found == expected || text(found) == text(expected);
+    // naturals of any size are compared exactly, other numbers as floats
+    let ordering = || {
+        let natural = |value: &Value| Number::from_decimal_str(&text(value)).ok();
+        if let (Some(found), Some(expected)) = (natural(found), natural(expected)) {
+            return Some(found.cmp(&expected));
+        }
+        let number = |value: &Value| text(value).parse::<f64>().ok();
+        number(found)?.partial_cmp(&number(expected)?)
+    };
+    match op {
+        CompareOp::Eq => equal,
+        CompareOp::Ne => !equal,
+        CompareOp::Lt => ordering().is_some_and(|o| o.is_lt()),
+        CompareOp::Le => ordering().is_some_and(|o| o.is_le()),
+        CompareOp::Gt => ordering().is_some_and(|o| o.is_gt()),
+        CompareOp::Ge => ordering().is_some_and(|o| o.is_ge()),
+    }
+}
+
+// breakpoints and watched paths of a debugging session
+#[derive(Debug, Clone, Default)]
+pub struct Breakpoints {
+    // `None` for a deleted breakpoint, so that the numbers stay stable
+    breakpoints: Vec<Option<Condition>>,
+    watches: Vec<JsonPath>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the number of the new breakpoint
+    pub fn add(&mut self, condition: Condition) -> usize {
+        self.breakpoints.push(Some(condition));
+        self.breakpoints.len() - 1
+    }
+
+    pub fn delete(&mut self, number: usize) -> Result<(), String> {
+        self.breakpoints
+            .get_mut(number)
+            .and_then(Option::take)
+            .map(|_| ())
+            .ok_or_else(|| format!("no breakpoint {number}"))
+    }
+
+    pub fn watch(&mut self, path: JsonPath) {
+        self.watches.push(path);
+    }
+
+    pub fn unwatch(&mut self, path: &JsonPath) {
+        self.watches.retain(|watched| watched != path);
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = (usize, &Condition)> {
+        self.breakpoints
+            .iter()
+            .enumerate()
+            .filter_map(|(number, condition)| Some((number, condition.as_ref()?)))
+    }
+
+    pub fn watches(&self) -> &[JsonPath] {
+        &self.watches
+    }
+
+    // numbers of the breakpoints that hold
+    pub fn fired(
+        &self,
+        snapshot: &Value,
+        mut hook: impl FnMut(&str, &str) -> Result<bool, String>,
+    ) -> Result<Vec<usize>, String> {
+        let mut fired = vec![];
+        for (number, condition) in self.breakpoints() {
+            if condition.eval(snapshot, &mut hook)? {
+                fired.push(number);
+            }
+        }
+        Ok(fired)
+    }
+
+    pub fn fired_machine<M: Machine>(&self, machine: &M) -> Result<Vec<usize>, String> {
+        let snapshot = serde_json::to_value(machine.snapshot()).map_err(|e| e.to_string())?;
+        self.fired(&snapshot, |name, arg| machine.hook(name, arg))
+    }
+
+    // current value of each watched path (`None` if the path does not exist)
+    pub fn watched(&self, snapshot: &Value) -> Vec<(JsonPath, Option<Value>)> {
+        self.watches
+            .iter()
+            .map(|path| (path.clone(), path.get(snapshot).cloned()))
+            .collect()
+    }
+}
+
+// where a `run_until` stopped
+pub enum Stop<M: Machine> {
+    // the listed breakpoints hold in `machine`
+    Breakpoint { machine: M, fired: Vec<usize> },
+    // the run ended without hitting a breakpoint
+    Ended(RunOutcome<M>),
+}
+
+pub struct StopReport<M: Machine> {
+    pub stop: Stop<M>,
+    // number of successful `step` calls
+    pub steps: usize,
+    // watched values at the stop, empty if the machine is gone
+    pub watched: Vec<(JsonPath, Option<Value>)>,
+}
+
+// step until a breakpoint holds after a step (the initial state is not checked, so a stopped
+// machine can be passed again to continue), the machine halts or the fuel runs out
+pub fn run_until<M, I>(
+    machine: M,
+    rinputs: I,
+    fuel: usize,
+    breakpoints: &Breakpoints,
+) -> Result<StopReport<M>, String>
+where
+    M: Machine,
+    I: IntoIterator<Item = M::RInput>,
+{
+    let mut rinputs = rinputs.into_iter();
+    let mut machine = machine;
+    let mut steps = 0;
+    loop {
+        if steps >= fuel {
+            return report(
+                Stop::Ended(RunOutcome::OutOfFuel {
+                    snapshot: machine.snapshot(),
+                }),
+                steps,
+                breakpoints,
+            );
+        }
+        let Some(rinput) = rinputs.next() else {
+            return report(
+                Stop::Ended(RunOutcome::InputExhausted {
+                    snapshot: machine.snapshot(),
+                }),
+                steps,
+                breakpoints,
+            );
+        };
+        match machine.step(rinput) {
+            Ok(StepResult::Continue { next, .. }) => {
+                steps += 1;
+                let fired = breakpoints.fired_machine(&next)?;
+                if !fired.is_empty() {
+                    let stop = Stop::Breakpoint {
+                        machine: next,
+                        fired,
+                    };
+                    return report(stop, steps, breakpoints);
+                }
+                machine = next;
+            }
+            Ok(StepResult::Halt { output }) => {
+                steps += 1;
+                let stop = Stop::Ended(RunOutcome::Halted { foutput: output });
+                return report(stop, steps, breakpoints);
+            }
+            Err(message) => {
+                let stop = Stop::Ended(RunOutcome::Error {
+                    step: steps,
+                    message,
+                });
+                return report(stop, steps, breakpoints);
+            }
+        }
+    }
+}
+
+fn report<M: Machine>(
+    stop: Stop<M>,
+    steps: usize,
+    breakpoints: &Breakpoints,
+) -> Result<StopReport<M>, String> {
+    let snapshot = match &stop {
+        Stop::Breakpoint { machine, .. } => Some(serde_json::to_value(machine.snapshot())),
+        Stop::Ended(RunOutcome::OutOfFuel { snapshot })
+        | Stop::Ended(RunOutcome::InputExhausted { snapshot }) => {
+            Some(serde_json::to_value(snapshot))
+        }
+        Stop::Ended(_) => None,
+    };
+    let watched = match snapshot {
+        Some(snapshot) => breakpoints.watched(&snapshot.map_err(|e| e.to_string())?),
+        None => vec![],
+    };
+    Ok(StopReport {
+        stop,
+        steps,
+        watched,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn no_hook(name: &str, _arg: &str) -> Result<bool, String> {
+        Err(format!("unknown hook: @{name}"))
+    }
+
+    #[test]
+    fn path_parse_and_get() {
+        let value = json!({"frame": {"env": [["x", 3], ["y", 4]]}, "regs": [7, 8]});
+        let path = JsonPath::parse("$.frame.env[1][1]").unwrap();
+        assert_eq!(path.to_string(), "$.frame.env[1][1]");
+        assert_eq!(path.get(&value), Some(&json!(4)));
+        assert_eq!(
+            JsonPath::parse("regs.1").unwrap().get(&value),
+            Some(&json!(8))
+        );
+        assert_eq!(JsonPath::parse("$").unwrap().get(&value), Some(&value));
+        assert_eq!(JsonPath::parse("regs[5]").unwrap().get(&value), None);
+        assert!(JsonPath::parse("regs[0").is_err());
+        assert!(JsonPath::parse("frame..env").is_err());
+    }
+
+    #[test]
+    fn condition_parse_and_eval() {
+        let value = json!({"state": "q1", "count": 3, "big": "18446744073709551616", "stack": []});
+        let holds = |text: &str| {
+            Condition::parse(text)
+                .unwrap()
+                .eval(&value, &mut no_hook)
+                .unwrap()
+        };
+        assert!(holds("state == q1"));
+        assert!(holds("state != \"q2\""));
+        assert!(holds("count >= 3"));
+        assert!(!holds("count < 3"));
+        assert!(holds("count"));
+        assert!(!holds("stack"));
+        assert!(!holds("missing"));
+        assert!(holds("big > 18446744073709551615"));
+        assert!(holds("big == 18446744073709551616"));
+
+        let condition = Condition::parse("count<=4").unwrap();
+        assert_eq!(condition.to_string(), "$.count <= 4");
+        assert!(Condition::parse("count ==").is_err());
+        assert_eq!(
+            Condition::parse("count = 3").unwrap_err(),
+            "unknown operator `=` in `count = 3` (use `==`)"
+        );
+
+        let hook = Condition::parse("@region :loop").unwrap();
+        assert_eq!(hook.to_string(), "@region :loop");
+        let mut seen = vec![];
+        let fired = hook
+            .eval(&value, &mut |name: &str, arg: &str| {
+                seen.push(format!("{name} {arg}"));
+                Ok(true)
+            })
+            .unwrap();
+        assert!(fired);
+        assert_eq!(seen, vec!["region :loop"]);
+        assert!(hook.eval(&value, &mut no_hook).is_err());
+    }
+
+    #[test]
+    fn breakpoints_keep_their_numbers() {
+        let value = json!({"count": 3});
+        let mut breakpoints = Breakpoints::new();
+        breakpoints.add(Condition::parse("count == 3").unwrap());
+        breakpoints.add(Condition::parse("count > 1").unwrap());
+        assert_eq!(breakpoints.fired(&value, no_hook).unwrap(), vec![0, 1]);
+        breakpoints.delete(0).unwrap();
+        assert!(breakpoints.delete(0).is_err());
+        assert_eq!(breakpoints.fired(&value, no_hook).unwrap(), vec![1]);
+        assert_eq!(breakpoints.add(Condition::parse("count").unwrap()), 2);
+
+        breakpoints.watch(JsonPath::parse("count").unwrap());
+        breakpoints.watch(JsonPath::parse("other").unwrap());
+        let watched = breakpoints.watched(&value);
+        assert_eq!(watched[0].1, Some(json!(3)));
+        assert_eq!(watched[1].1, None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::marker::PhantomData;
+
+use crate::{Compiler, Machine};
+
+// A: S -> M and B: M -> T gives Compose<A, B>: S -> T
+// - code and inputs go forward (A then B)
+// - outputs come back (B then A)
+// - no source map: the map of B points to statements of the intermediate code text,
+//   while the map of A is keyed by locations of the intermediate model, so they do not compose
+//   and `compile_mapped` gives an empty map
+pub struct Compose<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> Compiler for Compose<A, B>
+where
+    A: Compiler,
+    B: Compiler<Source = A::Target>,
+{
+    type Source = A::Source;
+    type Target = B::Target;
+
+    fn compile(
+        source: <<Self as Compiler>::Source as Machine>::Code,
+    ) -> Result<<<Self as Compiler>::Target as Machine>::Code, String> {
+        B::compile(A::compile(source)?)
+    }
+
+    fn encode_ainput(
+        ainput: <<Self as Compiler>::Source as Machine>::AInput,
+    ) -> Result<<<Self as Compiler>::Target as Machine>::AInput, String> {
+        B::encode_ainput(A::encode_ainput(ainput)?)
+    }
+
+    fn encode_rinput(
+        rinput: <<Self as Compiler>::Source as Machine>::RInput,
+    ) -> Result<<<Self as Compiler>::Target as Machine>::RInput, String> {
+        B::encode_rinput(A::encode_rinput(rinput)?)
+    }
+
+    fn decode_routput(
+        output: <<Self as Compiler>::Target as Machine>::ROutput,
+    ) -> Result<<<Self as Compiler>::Source as Machine>::ROutput, String> {
+        A::decode_routput(B::decode_routput(output)?)
+    }
+
+    fn decode_foutput(
+        output: <<Self as Compiler>::Target as Machine>::FOutput,
+    ) -> Result<<<Self as Compiler>::Source as Machine>::FOutput, String> {
+        A::decode_foutput(B::decode_foutput(output)?)
+    }
+}
+
+// compose!(A, B, C) = Compose<Compose<A, B>, C>
+#[macro_export]
+macro_rules! compose {
+    ($first:ty $(,)?) => {
+        $first
+    };
+    ($first:ty, $second:ty $(, $rest:ty)* $(,)?) => {
+        $crate::compose!($crate::compose::Compose<$first, $second> $(, $rest)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_machine::{Amount, Counter};
+    use crate::StepResult;
+
+    // doubles limit and start, halves foutput
+    struct Double;
+    // increments the limit, decrements foutput
+    struct Shift;
+
+    impl Compiler for Double {
+        type Source = Counter;
+        type Target = Counter;
+        fn compile(source: usize) -> Result<usize, String> {
+            Ok(source * 2)
+        }
+        fn encode_ainput(ainput: usize) -> Result<usize, String> {
+            Ok(ainput * 2)
+        }
+        fn encode_rinput(rinput: Amount) -> Result<Amount, String> {
+            Ok(rinput)
+        }
+        fn decode_routput(output: String) -> Result<String, String> {
+            Ok(output)
+        }
+        fn decode_foutput(output: usize) -> Result<usize, String> {
+            if output.is_multiple_of(2) {
+                Ok(output / 2)
+            } else {
+                Err(format!("odd output {output}"))
+            }
+        }
+    }
+
+    impl Compiler for Shift {
+        type Source = Counter;
+        type Target = Counter;
+        fn compile(source: usize) -> Result<usize, String> {
+            Ok(source + 1)
+        }
+        fn encode_ainput(ainput: usize) -> Result<usize, String> {
+            Ok(ainput)
+        }
+        fn encode_rinput(rinput: Amount) -> Result<Amount, String> {
+            Ok(rinput)
+        }
+        fn decode_routput(output: String) -> Result<String, String> {
+            Ok(output)
+        }
+        fn decode_foutput(output: usize) -> Result<usize, String> {
+            output.checked_sub(1).ok_or_else(|| "underflow".to_string())
+        }
+    }
+
+    // count up by one from `ainput` on the compiled machine and decode its foutput
+    fn run_through<C: Compiler<Source = Counter, Target = Counter>>(
+        code: usize,
+        ainput: usize,
+    ) -> Result<usize, String> {
+        let mut machine = Counter::make(C::compile(code)?, C::encode_ainput(ainput)?)?;
+        loop {
+            match machine.step(C::encode_rinput(Amount(1))?)? {
+                StepResult::Continue { next, .. } => machine = next,
+                StepResult::Halt { output } => return C::decode_foutput(output),
+            }
+        }
+    }
+
+    #[test]
+    fn compose_forwards_inputs_and_decodes_backwards() {
+        // Double then Shift: limit 3 -> 6 -> 7, halts at 7 -> 6 -> 3
+        assert_eq!(run_through::<Compose<Double, Shift>>(3, 0), Ok(3));
+        // Shift then Double: limit 3 -> 4 -> 8, halts at 8 -> 4 -> 3
+        assert_eq!(run_through::<compose!(Shift, Double)>(3, 0), Ok(3));
+        assert_eq!(
+            <compose!(Double, Double, Shift) as Compiler>::compile(1),
+            Ok(5)
+        );
+        let (code, map) = <Compose<Double, Shift> as Compiler>::compile_mapped(3).unwrap();
+        assert_eq!((code, map.entries().len()), (7, 0));
+    }
+
+    #[test]
+    fn compose_propagates_errors() {
+        assert_eq!(
+            <Compose<Double, Shift> as Compiler>::decode_foutput(2),
+            Err("odd output 1".to_string())
+        );
+        assert_eq!(
+            <Compose<Shift, Double> as Compiler>::decode_foutput(0),
+            Err("underflow".to_string())
+        );
+    }
+}
@@ -1,11 +1,17 @@
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::ops::{
+    Add, AddAssign, BitAnd, BitOr, BitXor, Div, Mul, MulAssign, Rem, Shl, Shr, Sub, SubAssign,
+};
 
 use serde::{Deserialize, Deserializer, Serialize};
 
 // Natural number represented in little-endian byte array
 // i.e., least significant byte first
 // n = \sum_{i=0}^{len-1} bytes[i] * 256^i
-#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+// trailing zero bytes are allowed, so comparison and hashing look at `trimmed_bytes`
+// multiplication and division work on 32-bit limbs
+#[derive(Debug, Default, Clone)]
 pub struct Number(Vec<u8>);
 
 impl Number {
@@ -44,24 +50,116 @@ impl Number {
         Some(u64::from_le_bytes(buf))
     }
     pub fn to_decimal_string(&self) -> String {
-        let mut bytes = self.trimmed_bytes();
-        if bytes.is_empty() {
+        // 9 digits at a time
+        const CHUNK: u32 = 1_000_000_000;
+        let mut limbs = self.limbs();
+        if limbs.is_empty() {
             return "0".to_string();
         }
-        let mut digits = Vec::new();
-        while !bytes.is_empty() {
-            let mut carry = 0u16;
-            for i in (0..bytes.len()).rev() {
-                let cur = (carry << 8) + bytes[i] as u16;
-                bytes[i] = (cur / 10) as u8;
-                carry = cur % 10;
+        let mut chunks = Vec::new();
+        while !limbs.is_empty() {
+            chunks.push(div_small(&mut limbs, CHUNK));
+        }
+        let mut out = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            out.push_str(&format!("{chunk:09}"));
+        }
+        out
+    }
+    pub fn to_hex_string(&self) -> String {
+        let bytes = self.trimmed_bytes();
+        let Some((last, rest)) = bytes.split_last() else {
+            return "0".to_string();
+        };
+        let mut out = format!("{last:x}");
+        for byte in rest.iter().rev() {
+            out.push_str(&format!("{byte:02x}"));
+        }
+        out
+    }
+    // digits only, of any length
+    pub fn from_decimal_str(text: &str) -> Result<Self, String> {
+        if text.is_empty() {
+            return Err("cannot parse integer from empty string".to_string());
+        }
+        if !text.chars().all(|ch| ch.is_ascii_digit()) {
+            return Err("invalid digit found in string".to_string());
+        }
+        let mut limbs = Vec::new();
+        // the first chunk takes the remainder so that the rest have 9 digits
+        let first = text.len() % 9;
+        let chunks = std::iter::once(&text[..first])
+            .filter(|chunk| !chunk.is_empty())
+            .chain(text.as_bytes()[first..].chunks(9).map(|chunk| {
+                // ASCII digits checked above
+                std::str::from_utf8(chunk).unwrap()
+            }));
+        for chunk in chunks {
+            let scale = 10u32.pow(chunk.len() as u32);
+            mul_add_small(&mut limbs, scale, chunk.parse().unwrap());
+        }
+        Ok(Self::from_limbs(&limbs))
+    }
+    // hex digits without prefix, of any length
+    pub fn from_hex_str(text: &str) -> Result<Self, String> {
+        if text.is_empty() {
+            return Err("cannot parse integer from empty string".to_string());
+        }
+        let mut bytes = Vec::with_capacity(text.len() / 2 + 1);
+        let digits = text
+            .chars()
+            .rev()
+            .map(|ch| {
+                ch.to_digit(16)
+                    .map(|d| d as u8)
+                    .ok_or_else(|| "invalid digit found in string".to_string())
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+        for pair in digits.chunks(2) {
+            bytes.push(pair[0] | pair.get(1).map_or(0, |hi| hi << 4));
+        }
+        Ok(Number(bytes))
+    }
+    pub fn is_one(&self) -> bool {
+        self.trimmed_bytes() == [1]
+    }
+    // number of bits without leading zeros (0 for zero)
+    pub fn bits(&self) -> usize {
+        let bytes = self.trimmed_bytes();
+        match bytes.last() {
+            Some(last) => bytes.len() * 8 - last.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+    // None for division by zero
+    pub fn checked_div_rem(&self, rhs: &Number) -> Option<(Number, Number)> {
+        let divisor = rhs.limbs();
+        if divisor.is_empty() {
+            return None;
+        }
+        let (quotient, remainder) = div_rem_limbs(&self.limbs(), &divisor);
+        Some((Self::from_limbs(&quotient), Self::from_limbs(&remainder)))
+    }
+    pub fn checked_div(&self, rhs: &Number) -> Option<Number> {
+        self.checked_div_rem(rhs).map(|(quotient, _)| quotient)
+    }
+    pub fn checked_rem(&self, rhs: &Number) -> Option<Number> {
+        self.checked_div_rem(rhs).map(|(_, remainder)| remainder)
+    }
+    // square and multiply
+    pub fn pow(&self, mut exp: u32) -> Number {
+        let mut base = self.clone();
+        let mut acc = Number::from(1);
+        while exp > 0 {
+            if exp & 1 == 1 {
+                acc = &acc * &base;
             }
-            digits.push((carry as u8 + b'0') as char);
-            while bytes.last() == Some(&0) {
-                bytes.pop();
+            exp >>= 1;
+            if exp > 0 {
+                base = &base * &base;
             }
         }
-        digits.iter().rev().collect()
+        acc
     }
     pub fn as_u8array(&self) -> &[u8] {
         &self.0
@@ -71,11 +169,204 @@ impl Number {
     }
 
     pub fn trimmed_bytes(&self) -> Vec<u8> {
-        let mut bytes = self.0.clone();
-        while bytes.last() == Some(&0) {
-            bytes.pop();
+        self.trimmed().to_vec()
+    }
+
+    fn trimmed(&self) -> &[u8] {
+        let len = self.0.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+        &self.0[..len]
+    }
+
+    // little-endian 32-bit limbs without leading zeros
+    fn limbs(&self) -> Vec<u32> {
+        self.trimmed()
+            .chunks(4)
+            .map(|chunk| {
+                let mut buf = [0u8; 4];
+                buf[..chunk.len()].copy_from_slice(chunk);
+                u32::from_le_bytes(buf)
+            })
+            .collect()
+    }
+
+    fn from_limbs(limbs: &[u32]) -> Self {
+        let mut number = Number(limbs.iter().flat_map(|limb| limb.to_le_bytes()).collect());
+        let len = number.trimmed().len();
+        number.0.truncate(len);
+        number
+    }
+}
+
+fn trim_limbs(limbs: &mut Vec<u32>) {
+    while limbs.last() == Some(&0) {
+        limbs.pop();
+    }
+}
+
+// limbs = limbs * scale + add
+fn mul_add_small(limbs: &mut Vec<u32>, scale: u32, add: u32) {
+    let mut carry = add as u64;
+    for limb in limbs.iter_mut() {
+        let t = *limb as u64 * scale as u64 + carry;
+        *limb = t as u32;
+        carry = t >> 32;
+    }
+    if carry > 0 {
+        limbs.push(carry as u32);
+    }
+}
+
+// limbs = limbs / divisor, returns the remainder
+fn div_small(limbs: &mut Vec<u32>, divisor: u32) -> u32 {
+    let mut rem = 0u64;
+    for limb in limbs.iter_mut().rev() {
+        let cur = (rem << 32) | *limb as u64;
+        *limb = (cur / divisor as u64) as u32;
+        rem = cur % divisor as u64;
+    }
+    trim_limbs(limbs);
+    rem as u32
+}
+
+// schoolbook multiplication
+fn mul_limbs(a: &[u32], b: &[u32]) -> Vec<u32> {
+    if a.is_empty() || b.is_empty() {
+        return vec![];
+    }
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let t = out[i + j] as u64 + x as u64 * y as u64 + carry;
+            out[i + j] = t as u32;
+            carry = t >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    trim_limbs(&mut out);
+    out
+}
+
+fn cmp_limbs(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+// shift left by less than 32 bits, keeping the length plus one limb for the carry
+fn shl_limbs(limbs: &[u32], shift: u32) -> Vec<u32> {
+    let mut out = Vec::with_capacity(limbs.len() + 1);
+    let mut carry = 0u32;
+    for &limb in limbs {
+        out.push((limb << shift) | carry);
+        carry = if shift == 0 { 0 } else { limb >> (32 - shift) };
+    }
+    out.push(carry);
+    out
+}
+
+// long division (Knuth, TAOCP vol.2 4.3.1 algorithm D)
+// `divisor` is non-empty and both are trimmed
+fn div_rem_limbs(dividend: &[u32], divisor: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_limbs(dividend, divisor) == Ordering::Less {
+        return (vec![], dividend.to_vec());
+    }
+    if divisor.len() == 1 {
+        let mut quotient = dividend.to_vec();
+        let rem = div_small(&mut quotient, divisor[0]);
+        let mut remainder = vec![rem];
+        trim_limbs(&mut remainder);
+        return (quotient, remainder);
+    }
+
+    // normalize so that the top bit of the divisor is set
+    let shift = divisor.last().unwrap().leading_zeros();
+    let mut v = shl_limbs(divisor, shift);
+    v.pop();
+    let mut u = shl_limbs(dividend, shift);
+    let n = v.len();
+    let m = dividend.len() - n;
+    let mut quotient = vec![0u32; m + 1];
+    const BASE: u64 = 1 << 32;
+
+    for j in (0..=m).rev() {
+        let top = ((u[j + n] as u64) << 32) | u[j + n - 1] as u64;
+        let mut qhat = top / v[n - 1] as u64;
+        let mut rhat = top % v[n - 1] as u64;
+        while qhat >= BASE || qhat * v[n - 2] as u64 > ((rhat << 32) | u[j + n - 2] as u64) {
+            qhat -= 1;
+            rhat += v[n - 1] as u64;
+            if rhat >= BASE {
+                break;
+            }
         }
-        bytes
+
+        // u[j..=j+n] -= qhat * v
+        let mut borrow = 0i64;
+        for i in 0..n {
+            let p = qhat * v[i] as u64;
+            let t = u[i + j] as i64 - borrow - (p & 0xFFFF_FFFF) as i64;
+            u[i + j] = t as u32;
+            borrow = (p >> 32) as i64 - (t >> 32);
+        }
+        let t = u[j + n] as i64 - borrow;
+        u[j + n] = t as u32;
+
+        quotient[j] = qhat as u32;
+        if t < 0 {
+            // qhat was one too large, add v back
+            quotient[j] -= 1;
+            let mut carry = 0u64;
+            for i in 0..n {
+                let t = u[i + j] as u64 + v[i] as u64 + carry;
+                u[i + j] = t as u32;
+                carry = t >> 32;
+            }
+            u[j + n] = u[j + n].wrapping_add(carry as u32);
+        }
+    }
+
+    // unnormalize the remainder
+    let mut remainder: Vec<u32> = (0..n)
+        .map(|i| {
+            if shift == 0 {
+                u[i]
+            } else {
+                (u[i] >> shift) | (u[i + 1] << (32 - shift))
+            }
+        })
+        .collect();
+    trim_limbs(&mut quotient);
+    trim_limbs(&mut remainder);
+    (quotient, remainder)
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Self) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+
+impl Eq for Number {}
+
+impl Hash for Number {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trimmed().hash(state);
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Number {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.trimmed(), other.trimmed());
+        a.len()
+            .cmp(&b.len())
+            .then_with(|| a.iter().rev().cmp(b.iter().rev()))
     }
 }
 
@@ -144,6 +435,9 @@ impl Sub for Number {
                 borrow = 0;
             }
         }
+        if borrow == 1 {
+            return Number::default();
+        }
         Number(v)
     }
 }
@@ -167,6 +461,110 @@ impl SubAssign<usize> for Number {
     }
 }
 
+impl Mul for &Number {
+    type Output = Number;
+    fn mul(self, rhs: Self) -> Self::Output {
+        Number::from_limbs(&mul_limbs(&self.limbs(), &rhs.limbs()))
+    }
+}
+
+impl Mul for Number {
+    type Output = Number;
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
+}
+
+impl Mul<usize> for Number {
+    type Output = Number;
+    fn mul(self, rhs: usize) -> Self::Output {
+        self * Number::from(rhs)
+    }
+}
+
+impl MulAssign for Number {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = &*self * &rhs
+    }
+}
+
+// panics on division by zero as the primitive integers do
+impl Div for Number {
+    type Output = Number;
+    fn div(self, rhs: Self) -> Self::Output {
+        self.checked_div(&rhs).expect("division by zero")
+    }
+}
+
+impl Rem for Number {
+    type Output = Number;
+    fn rem(self, rhs: Self) -> Self::Output {
+        self.checked_rem(&rhs).expect("division by zero")
+    }
+}
+
+impl Shl<usize> for Number {
+    type Output = Number;
+    fn shl(self, rhs: usize) -> Self::Output {
+        let (bytes, bits) = (rhs / 8, (rhs % 8) as u32);
+        let mut out = vec![0u8; bytes];
+        let mut carry = 0u8;
+        for &b in self.trimmed() {
+            out.push((b << bits) | carry);
+            carry = if bits == 0 { 0 } else { b >> (8 - bits) };
+        }
+        out.push(carry);
+        Number(out)
+    }
+}
+
+impl Shr<usize> for Number {
+    type Output = Number;
+    fn shr(self, rhs: usize) -> Self::Output {
+        let (bytes, bits) = (rhs / 8, (rhs % 8) as u32);
+        let src = self.trimmed().get(bytes..).unwrap_or(&[]);
+        let out = (0..src.len())
+            .map(|i| {
+                let hi = if bits == 0 {
+                    0
+                } else {
+                    src.get(i + 1).map_or(0, |&b| b << (8 - bits))
+                };
+                (src[i] >> bits) | hi
+            })
+            .collect();
+        Number(out)
+    }
+}
+
+fn zip_bytes(a: &Number, b: &Number, f: impl Fn(u8, u8) -> u8) -> Number {
+    let (a, b) = (a.trimmed(), b.trimmed());
+    let len = a.len().max(b.len());
+    let byte = |bytes: &[u8], i: usize| bytes.get(i).copied().unwrap_or(0);
+    Number((0..len).map(|i| f(byte(a, i), byte(b, i))).collect())
+}
+
+impl BitAnd for Number {
+    type Output = Number;
+    fn bitand(self, rhs: Self) -> Self::Output {
+        zip_bytes(&self, &rhs, |a, b| a & b)
+    }
+}
+
+impl BitOr for Number {
+    type Output = Number;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        zip_bytes(&self, &rhs, |a, b| a | b)
+    }
+}
+
+impl BitXor for Number {
+    type Output = Number;
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        zip_bytes(&self, &rhs, |a, b| a ^ b)
+    }
+}
+
 impl Serialize for Number {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -194,42 +592,83 @@ impl<'de> Deserialize<'de> for Number {
 
         match Repr::deserialize(deserializer)? {
             Repr::U64(v) => Ok(Number::from(v as usize)),
-            Repr::String(s) => {
-                if s.is_empty() {
-                    return Err(serde::de::Error::custom("empty decimal string"));
-                }
-
-                let mut digits: Vec<u8> = s
-                    .chars()
-                    .map(|ch| {
-                        ch.to_digit(10)
-                            .map(|d| d as u8)
-                            .ok_or_else(|| serde::de::Error::custom("invalid decimal string"))
-                    })
-                    .collect::<Result<_, _>>()?;
-
-                if digits.iter().all(|d| *d == 0) {
-                    return Ok(Number::default());
-                }
-
-                let mut bytes = Vec::new();
-                while !digits.is_empty() {
-                    let mut carry = 0u16;
-                    let mut next = Vec::new();
-                    for d in digits {
-                        let cur = carry * 10 + d as u16;
-                        let q = (cur / 256) as u8;
-                        carry = cur % 256;
-                        if !next.is_empty() || q != 0 {
-                            next.push(q);
-                        }
-                    }
-                    bytes.push(carry as u8);
-                    digits = next;
-                }
-
-                Ok(Number(bytes))
+            Repr::String(s) => Number::from_decimal_str(&s).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(value: u128) -> Number {
+        Number::from_decimal_str(&value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn comparison_ignores_trailing_zero_bytes() {
+        let padded = Number::from(vec![5, 0, 0]);
+        assert_eq!(padded, Number::from(5));
+        assert!(Number::from(vec![0, 1]) > Number::from(vec![255]));
+        assert!(Number::from(3) - Number::from(5) == Number::default());
+    }
+
+    #[test]
+    fn arithmetic_agrees_with_u128() {
+        let values = [
+            0,
+            1,
+            7,
+            255,
+            256,
+            65_535,
+            1 << 40,
+            u64::MAX as u128,
+            123_456_789_012_345,
+        ];
+        for &a in &values {
+            for &b in &values {
+                assert_eq!(n(a) * n(b) / n(b.max(1)), n(a * b / b.max(1)));
+                let expected = a.checked_div(b).zip(a.checked_rem(b));
+                assert_eq!(
+                    n(a).checked_div_rem(&n(b)),
+                    expected.map(|(q, r)| (n(q), n(r)))
+                );
+                assert_eq!(n(a) & n(b), n(a & b));
+                assert_eq!(n(a) | n(b), n(a | b));
+                assert_eq!(n(a) ^ n(b), n(a ^ b));
+                assert_eq!(n(a).cmp(&n(b)), a.cmp(&b));
             }
+            assert_eq!(n(a) << 13, n(a << 13));
+            assert_eq!(n(a) >> 9, n(a >> 9));
         }
     }
+
+    #[test]
+    fn long_division_of_large_numbers() {
+        // 3^200 = (3^100)^2, and (3^100 + 1) * 3^100 + 5 divided by 3^100 + 1
+        let a = Number::from(3).pow(100);
+        assert_eq!(&a * &a, Number::from(3).pow(200));
+        let b = a.clone() + 1;
+        let c = &b * &a + 5;
+        assert_eq!(c.checked_div_rem(&b), Some((a.clone(), Number::from(5))));
+        assert_eq!(a.bits(), 159);
+    }
+
+    #[test]
+    fn decimal_and_hex_text() {
+        let text = "340282366920938463463374607431768211456"; // 2^128
+        let value = Number::from_decimal_str(text).unwrap();
+        assert_eq!(value, Number::from(1) << 128);
+        assert_eq!(value.to_decimal_string(), text);
+        assert_eq!(value.to_hex_string(), format!("1{}", "0".repeat(32)));
+        assert_eq!(Number::from_hex_str("fF01").unwrap(), Number::from(0xff01));
+        assert_eq!(Number::default().to_decimal_string(), "0");
+        assert_eq!(
+            Number::from(1_000_000_000).to_decimal_string(),
+            "1000000000"
+        );
+        assert!(Number::from_decimal_str("12a").is_err());
+        assert!(Number::from_hex_str("").is_err());
+    }
 }
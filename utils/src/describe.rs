@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Compiler, Machine};
+
+// what a model is and which text it takes, returned by `Machine::describe`
+// the formats are short human-readable hints, not grammars a host can parse with
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Description {
+    // the component name ("turing_machine", "pcf-cek", ...)
+    pub name: String,
+    pub code_format: String,
+    pub ainput_format: String,
+    pub rinput_format: String,
+    // the rinput of `run` and of stepping without one, `None` if every step needs a chosen rinput
+    pub default_rinput: Option<String>,
+    pub examples: Vec<Example>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Example {
+    pub name: String,
+    pub code: String,
+    pub ainput: String,
+}
+
+// returned by `Compiler::describe`, source and target are model names
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompilerDescription {
+    pub name: String,
+    pub source: String,
+    pub target: String,
+}
+
+impl Description {
+    pub fn new(name: &str) -> Self {
+        Description {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn code(self, format: &str) -> Self {
+        Description {
+            code_format: format.to_string(),
+            ..self
+        }
+    }
+
+    pub fn ainput(self, format: &str) -> Self {
+        Description {
+            ainput_format: format.to_string(),
+            ..self
+        }
+    }
+
+    pub fn rinput(self, format: &str) -> Self {
+        Description {
+            rinput_format: format.to_string(),
+            ..self
+        }
+    }
+
+    pub fn default_rinput(self, rinput: &str) -> Self {
+        Description {
+            default_rinput: Some(rinput.to_string()),
+            ..self
+        }
+    }
+
+    pub fn example(mut self, name: &str, code: &str, ainput: &str) -> Self {
+        self.examples.push(Example {
+            name: name.to_string(),
+            code: code.to_string(),
+            ainput: ainput.to_string(),
+        });
+        self
+    }
+}
+
+impl CompilerDescription {
+    // `source-target`, the naming of the compiler components
+    pub fn of<C: Compiler>() -> Self {
+        let source = C::Source::describe().name;
+        let target = C::Target::describe().name;
+        CompilerDescription {
+            name: format!("{source}-{target}"),
+            source,
+            target,
+        }
+    }
+
+    // for the compilers whose component is not named `source-target`
+    pub fn named(self, name: &str) -> Self {
+        CompilerDescription {
+            name: name.to_string(),
+            ..self
+        }
+    }
+}
+
+// the last path segment of the type, the name of a model without a description
+pub fn type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use crate::lexer::LexError;
+
+// byte range in the source text
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+    // empty span at the byte (e.g. "expected ... at end of input")
+    pub fn at(byte: usize) -> Self {
+        Span {
+            start: byte,
+            end: byte,
+        }
+    }
+    // smallest span containing both
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+    pub fn offset(self, by: usize) -> Span {
+        Span {
+            start: self.start + by,
+            end: self.end + by,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+// error (or warning) reported by parsers, possibly pointing into the source text
+// errors without a position (e.g. runtime errors) have no span
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    pub fn warning(span: Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+
+    pub fn unspanned(message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    // give a position to a diagnostic without one
+    pub fn or_span(self, span: Span) -> Self {
+        Diagnostic {
+            span: self.span.or(Some(span)),
+            ..self
+        }
+    }
+
+    // for a text parsed as a part of a larger text starting at `by`
+    pub fn offset(self, by: usize) -> Self {
+        Diagnostic {
+            span: self.span.map(|span| span.offset(by)),
+            ..self
+        }
+    }
+
+    // 1-indexed line and column of the start of the span
+    pub fn line_col(&self, source: &str) -> Option<(usize, usize)> {
+        self.span.map(|span| line_col(source, span.start))
+    }
+
+    // message with the source line and a marker under the span
+    // error: expected symbol ';'
+    //  --> 2:5
+    //   |
+    // 2 | inc x
+    //   |     ^
+    pub fn render(&self, source: &str) -> String {
+        self.render_named("", source)
+    }
+
+    // same as `render` with the name of the source before the position (" --> code:2:5")
+    pub fn render_named(&self, name: &str, source: &str) -> String {
+        let mut out = format!("{}: {}", self.severity, self.message);
+        let Some(span) = self.span else {
+            return out;
+        };
+        let (line, column) = line_col(source, span.start);
+        let line_text = source.lines().nth(line - 1).unwrap_or("");
+        let line_start = source[..span.start.min(source.len())]
+            .rfind('\n')
+            .map_or(0, |i| i + 1);
+        // the marker stops at the end of the first line
+        let marker_end = span.end.min(line_start + line_text.len());
+        let width = source
+            .get(span.start.min(marker_end)..marker_end)
+            .map_or(0, |s| s.chars().count())
+            .max(1);
+        let gutter = " ".repeat(line.to_string().len());
+        let name = if name.is_empty() {
+            String::new()
+        } else {
+            format!("{name}:")
+        };
+        out.push_str(&format!("\n{gutter}--> {name}{line}:{column}"));
+        out.push_str(&format!("\n{gutter} |"));
+        out.push_str(&format!("\n{line} | {line_text}"));
+        out.push_str(&format!(
+            "\n{gutter} | {}{}",
+            " ".repeat(column - 1),
+            "^".repeat(width)
+        ));
+        out
+    }
+}
+
+// 1-indexed line and column (counted in chars) of the byte
+// a byte past the end is placed just after the last char
+pub fn line_col(source: &str, byte: usize) -> (usize, usize) {
+    let mut byte = byte.min(source.len());
+    while !source.is_char_boundary(byte) {
+        byte -= 1;
+    }
+    let before = &source[..byte];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+// non-empty lines with the span of their trimmed text, for line-oriented formats
+pub fn lines_with_span(text: &str) -> impl Iterator<Item = (Span, &str)> {
+    let mut offset = 0;
+    text.split_inclusive('\n').filter_map(move |raw_line| {
+        let start = offset + (raw_line.len() - raw_line.trim_start().len());
+        offset += raw_line.len();
+        let line = raw_line.trim();
+        (!line.is_empty()).then(|| (Span::new(start, start + line.len()), line))
+    })
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.span {
+            Some(span) => write!(f, "{} at byte {}", self.message, span.start),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostic {}
+
+// one diagnostic for the boundaries that take a single error
+// the first one is kept and the rest is counted in the message
+pub fn summarize(mut diagnostics: Vec<Diagnostic>) -> Diagnostic {
+    if diagnostics.is_empty() {
+        return Diagnostic::unspanned("unknown error");
+    }
+    let rest = diagnostics.len() - 1;
+    let mut first = diagnostics.remove(0);
+    if rest > 0 {
+        let plural = if rest == 1 { "" } else { "s" };
+        first.message = format!("{} (and {rest} more error{plural})", first.message);
+    }
+    first
+}
+
+impl From<String> for Diagnostic {
+    fn from(message: String) -> Self {
+        Diagnostic::unspanned(message)
+    }
+}
+
+impl From<&str> for Diagnostic {
+    fn from(message: &str) -> Self {
+        Diagnostic::unspanned(message)
+    }
+}
+
+impl From<Diagnostic> for String {
+    fn from(diagnostic: Diagnostic) -> Self {
+        diagnostic.to_string()
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        Diagnostic::error(Span::at(error.byte), error.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_col_counts_from_one() {
+        let source = "ab\ncd\n日本x";
+        assert_eq!(line_col(source, 0), (1, 1));
+        assert_eq!(line_col(source, 1), (1, 2));
+        assert_eq!(line_col(source, 3), (2, 1));
+        // columns are counted in chars
+        assert_eq!(line_col(source, source.len() - 1), (3, 3));
+        assert_eq!(line_col(source, 100), (3, 4));
+    }
+
+    #[test]
+    fn render_marks_the_span() {
+        let source = "inc x;\ninc yy\n";
+        let diagnostic = Diagnostic::error(Span::new(11, 13), "expected symbol ';'");
+        assert_eq!(diagnostic.line_col(source), Some((2, 5)));
+        assert_eq!(
+            diagnostic.render(source),
+            "error: expected symbol ';'\n --> 2:5\n  |\n2 | inc yy\n  |     ^^"
+        );
+        // an empty span still gets a marker
+        let diagnostic = Diagnostic::error(Span::at(source.len()), "unexpected end of input");
+        assert!(diagnostic.render(source).ends_with("3 | \n  | ^"));
+        assert!(diagnostic
+            .render_named("code", source)
+            .contains("\n --> code:3:1\n"));
+    }
+
+    #[test]
+    fn lines_with_span_skips_blank_lines() {
+        let lines: Vec<_> = lines_with_span("a\n\n  bc \r\n").collect();
+        assert_eq!(lines, vec![(Span::new(0, 1), "a"), (Span::new(5, 7), "bc")]);
+    }
+
+    #[test]
+    fn unspanned_renders_message_only() {
+        let diagnostic = Diagnostic::from("division by zero".to_string());
+        assert_eq!(diagnostic.render("x"), "error: division by zero");
+        assert_eq!(String::from(diagnostic), "division by zero");
+        let diagnostic = Diagnostic::error(Span::at(4), "oops").offset(2);
+        assert_eq!(diagnostic.to_string(), "oops at byte 6");
+    }
+
+    #[test]
+    fn summarize_keeps_the_first() {
+        let diagnostics = vec![
+            Diagnostic::error(Span::at(1), "first"),
+            Diagnostic::error(Span::at(5), "second"),
+            Diagnostic::error(Span::at(9), "third"),
+        ];
+        let summary = summarize(diagnostics);
+        assert_eq!(summary.message, "first (and 2 more errors)");
+        assert_eq!(summary.span, Some(Span::at(1)));
+    }
+}
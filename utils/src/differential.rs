@@ -0,0 +1,381 @@
+use std::fmt::{self, Display};
+
+use crate::run::{run, RunOptions, RunOutcome};
+use crate::{Compiler, Interpreter, Machine, TextCodec};
+
+// differential testing of a compiler (or an interpreter) against its source semantics
+// the source machine and the compiled target machine are run on the same input,
+// and the decoded target outputs are compared with the source outputs (as printed text)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffOptions {
+    pub source_fuel: usize,
+    pub target_fuel: usize,
+    // source rinput (as text) fed at every step, encoded for the target
+    pub rinput: String,
+    // also compare routputs; those printing as empty text are not observable and skipped
+    pub routputs: bool,
+}
+
+impl DiffOptions {
+    pub fn fuel(source_fuel: usize, target_fuel: usize) -> Self {
+        DiffOptions {
+            source_fuel,
+            target_fuel,
+            rinput: String::new(),
+            routputs: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mismatch {
+    SourceDidNotHalt(String),
+    TargetDidNotHalt(String),
+    Decode(String),
+    FOutput {
+        source: String,
+        target: String,
+    },
+    ROutputs {
+        source: Vec<String>,
+        target: Vec<String>,
+    },
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::SourceDidNotHalt(reason) => write!(f, "source did not halt: {reason}"),
+            Mismatch::TargetDidNotHalt(reason) => write!(f, "target did not halt: {reason}"),
+            Mismatch::Decode(reason) => write!(f, "decode failed: {reason}"),
+            Mismatch::FOutput { source, target } => {
+                write!(f, "foutput differs: source `{source}`, target `{target}`")
+            }
+            Mismatch::ROutputs { source, target } => write!(
+                f,
+                "routputs differ: source [{}], target [{}]",
+                source.join(", "),
+                target.join(", ")
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffCase {
+    pub ainput: String,
+    pub source_steps: usize,
+    pub target_steps: usize,
+    pub mismatch: Option<Mismatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffReport {
+    pub cases: Vec<DiffCase>,
+}
+
+impl DiffReport {
+    pub fn is_ok(&self) -> bool {
+        self.cases.iter().all(|case| case.mismatch.is_none())
+    }
+}
+
+impl Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            let result = match &case.mismatch {
+                None => "ok".to_string(),
+                Some(mismatch) => mismatch.to_string(),
+            };
+            writeln!(
+                f,
+                "ainput `{}`: {} (source {} steps, target {} steps)",
+                case.ainput, result, case.source_steps, case.target_steps
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn observable<T: TextCodec>(routputs: Vec<T>) -> Vec<String> {
+    routputs
+        .into_iter()
+        .map(|output| output.print())
+        .filter(|text| !text.trim().is_empty())
+        .collect()
+}
+
+// encoding/decoding between the two machines of a check
+struct Bridge<S: Machine, T: Machine> {
+    encode_rinput: fn(S::RInput) -> Result<T::RInput, String>,
+    decode_routput: fn(T::ROutput) -> Result<S::ROutput, String>,
+    decode_foutput: fn(T::FOutput) -> Result<S::FOutput, String>,
+}
+
+fn check_case<C: Compiler>(
+    code: &str,
+    ainput: &str,
+    options: &DiffOptions,
+) -> Result<DiffCase, String> {
+    // code and inputs are parsed twice since the machine types need not be `Clone`
+    let source = <C::Source as Machine>::make(
+        <C::Source as Machine>::parse_code(code)?,
+        <C::Source as Machine>::parse_ainput(ainput)?,
+    )?;
+    let target = <C::Target as Machine>::make(
+        C::compile(<C::Source as Machine>::parse_code(code)?)?,
+        C::encode_ainput(<C::Source as Machine>::parse_ainput(ainput)?)?,
+    )?;
+    let bridge = Bridge::<C::Source, C::Target> {
+        encode_rinput: C::encode_rinput,
+        decode_routput: C::decode_routput,
+        decode_foutput: C::decode_foutput,
+    };
+    run_case(ainput, source, target, bridge, options)
+}
+
+fn check_interpreter_case<I: Interpreter>(
+    code: &str,
+    ainput: &str,
+    options: &DiffOptions,
+) -> Result<DiffCase, String> {
+    let guest = <I::Guest as Machine>::make(
+        <I::Guest as Machine>::parse_code(code)?,
+        <I::Guest as Machine>::parse_ainput(ainput)?,
+    )?;
+    let host = <I::Host as Machine>::make(
+        I::interpreter()?,
+        I::encode_ainput(
+            <I::Guest as Machine>::parse_code(code)?,
+            <I::Guest as Machine>::parse_ainput(ainput)?,
+        )?,
+    )?;
+    let bridge = Bridge::<I::Guest, I::Host> {
+        encode_rinput: I::encode_rinput,
+        decode_routput: I::decode_routput,
+        decode_foutput: I::decode_foutput,
+    };
+    run_case(ainput, guest, host, bridge, options)
+}
+
+fn run_case<S: Machine, T: Machine>(
+    ainput: &str,
+    source: S,
+    target: T,
+    bridge: Bridge<S, T>,
+    options: &DiffOptions,
+) -> Result<DiffCase, String> {
+    (bridge.encode_rinput)(S::parse_rinput(&options.rinput)?)?;
+    let source_rinputs = std::iter::from_fn(|| S::parse_rinput(&options.rinput).ok());
+    let target_rinputs = std::iter::from_fn(|| {
+        S::parse_rinput(&options.rinput)
+            .ok()
+            .and_then(|rinput| (bridge.encode_rinput)(rinput).ok())
+    });
+
+    let source = run(
+        source,
+        source_rinputs,
+        RunOptions::fuel(options.source_fuel),
+    );
+    let target = run(
+        target,
+        target_rinputs,
+        RunOptions::fuel(options.target_fuel),
+    );
+
+    let (source_steps, target_steps) = (source.steps, target.steps);
+    let mismatch = compare(
+        source.outcome,
+        source.routputs,
+        target.outcome,
+        target.routputs,
+        &bridge,
+        options,
+    )
+    .err();
+    Ok(DiffCase {
+        ainput: ainput.to_string(),
+        source_steps,
+        target_steps,
+        mismatch,
+    })
+}
+
+fn compare<S: Machine, T: Machine>(
+    source_outcome: RunOutcome<S>,
+    source_routputs: Vec<S::ROutput>,
+    target_outcome: RunOutcome<T>,
+    target_routputs: Vec<T::ROutput>,
+    bridge: &Bridge<S, T>,
+    options: &DiffOptions,
+) -> Result<(), Mismatch> {
+    let source = source_outcome
+        .into_foutput()
+        .map_err(Mismatch::SourceDidNotHalt)?;
+    let target = target_outcome
+        .into_foutput()
+        .map_err(Mismatch::TargetDidNotHalt)?;
+    let target = (bridge.decode_foutput)(target).map_err(Mismatch::Decode)?;
+
+    if options.routputs {
+        let decoded = target_routputs
+            .into_iter()
+            .map(bridge.decode_routput)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Mismatch::Decode)?;
+        let source = observable(source_routputs);
+        let target = observable(decoded);
+        if source != target {
+            return Err(Mismatch::ROutputs { source, target });
+        }
+    }
+
+    let (source, target) = (source.print(), target.print());
+    if source != target {
+        return Err(Mismatch::FOutput { source, target });
+    }
+    Ok(())
+}
+
+// run the source and the compiled code on every ainput (as text) and compare
+// parse/compile/encode failures are returned as `Err` since no comparison is possible
+pub fn check_compiler<C: Compiler>(
+    code: &str,
+    ainputs: &[&str],
+    options: &DiffOptions,
+) -> Result<DiffReport, String> {
+    let cases = ainputs
+        .iter()
+        .map(|ainput| check_case::<C>(code, ainput, options))
+        .collect::<Result<_, _>>()?;
+    Ok(DiffReport { cases })
+}
+
+// `check_compiler` for tests: panics with the whole report on any mismatch
+pub fn assert_compiler_agrees<C: Compiler>(code: &str, ainputs: &[&str], options: &DiffOptions) {
+    match check_compiler::<C>(code, ainputs, options) {
+        Ok(report) if report.is_ok() => {}
+        Ok(report) => panic!("compiler disagrees with source semantics:\n{report}"),
+        Err(err) => panic!("differential check could not run: {err}"),
+    }
+}
+
+// the same check for an interpreter: the guest machine is the "source" and
+// the host machine running `I::interpreter()` on the encoded ainput is the "target"
+pub fn check_interpreter<I: Interpreter>(
+    code: &str,
+    ainputs: &[&str],
+    options: &DiffOptions,
+) -> Result<DiffReport, String> {
+    let cases = ainputs
+        .iter()
+        .map(|ainput| check_interpreter_case::<I>(code, ainput, options))
+        .collect::<Result<_, _>>()?;
+    Ok(DiffReport { cases })
+}
+
+pub fn assert_interpreter_agrees<I: Interpreter>(
+    code: &str,
+    ainputs: &[&str],
+    options: &DiffOptions,
+) {
+    match check_interpreter::<I>(code, ainputs, options) {
+        Ok(report) if report.is_ok() => {}
+        Ok(report) => panic!("interpreter disagrees with guest semantics:\n{report}"),
+        Err(err) => panic!("differential check could not run: {err}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_machine::{Amount, Counter};
+
+    // counts twice as slowly; routputs of the extra (odd) counts are empty
+    struct Slow;
+    // forgets the ainput
+    struct Forgetful;
+
+    impl Compiler for Slow {
+        type Source = Counter;
+        type Target = Counter;
+        fn compile(source: usize) -> Result<usize, String> {
+            Ok(source * 2)
+        }
+        fn encode_ainput(ainput: usize) -> Result<usize, String> {
+            Ok(ainput * 2)
+        }
+        fn encode_rinput(rinput: Amount) -> Result<Amount, String> {
+            Ok(rinput)
+        }
+        fn decode_routput(output: String) -> Result<String, String> {
+            let n: usize = output.parse().map_err(|_| "not a number".to_string())?;
+            if n.is_multiple_of(2) {
+                Ok((n / 2).to_string())
+            } else {
+                Ok(String::new())
+            }
+        }
+        fn decode_foutput(output: usize) -> Result<usize, String> {
+            Ok(output / 2)
+        }
+    }
+
+    impl Compiler for Forgetful {
+        type Source = Counter;
+        type Target = Counter;
+        fn compile(source: usize) -> Result<usize, String> {
+            Ok(source)
+        }
+        fn encode_ainput(_ainput: usize) -> Result<usize, String> {
+            Ok(0)
+        }
+        fn encode_rinput(rinput: Amount) -> Result<Amount, String> {
+            Ok(rinput)
+        }
+        fn decode_routput(output: String) -> Result<String, String> {
+            Ok(output)
+        }
+        fn decode_foutput(output: usize) -> Result<usize, String> {
+            Ok(output)
+        }
+    }
+
+    #[test]
+    fn agreeing_compiler_reports_step_counts() {
+        let report = check_compiler::<Slow>("3", &["0", "1"], &DiffOptions::fuel(10, 10)).unwrap();
+        assert!(report.is_ok(), "{report}");
+        assert_eq!(report.cases[0].source_steps, 3);
+        assert_eq!(report.cases[0].target_steps, 6);
+        assert_eq!(report.cases[1].source_steps, 2);
+        assert_eq!(report.cases[1].target_steps, 4);
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let report =
+            check_compiler::<Forgetful>("1", &["0", "2"], &DiffOptions::fuel(10, 10)).unwrap();
+        assert_eq!(report.cases[0].mismatch, None);
+        assert_eq!(
+            report.cases[1].mismatch,
+            Some(Mismatch::FOutput {
+                source: "3".to_string(),
+                target: "1".to_string()
+            })
+        );
+
+        let report = check_compiler::<Slow>("3", &["0"], &DiffOptions::fuel(10, 5)).unwrap();
+        assert!(matches!(
+            report.cases[0].mismatch,
+            Some(Mismatch::TargetDidNotHalt(_))
+        ));
+    }
+
+    #[test]
+    fn setup_errors_are_not_mismatches() {
+        assert!(check_compiler::<Slow>("three", &["1"], &DiffOptions::fuel(10, 10)).is_err());
+        assert!(check_compiler::<Slow>("0", &["1"], &DiffOptions::fuel(10, 10)).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::marker::PhantomData;
+
+use crate::history::History;
+use crate::source_map::SourceMapping;
+use crate::wasm_util::{wasm_compiler, wasm_interpreter, wasm_model, Batch, BoundaryError};
+use crate::{Compiler, CompilerDescription, Description, Interpreter, Machine};
+
+// object safe view of a `Machine`, every value crosses it in its text (or JSON) form
+// the methods are the same as the wit `model` world, and behave the same as the wasm component
+pub trait DynMachine {
+    fn describe(&self) -> Description;
+    fn make(&mut self, code: &str, ainput: &str) -> Result<(), BoundaryError>;
+    // JSON `{"kind": "continue", "routput": ..}` or `{"kind": "halt", "foutput": ..}`
+    fn step(&mut self, rinput: &str) -> Result<String, BoundaryError>;
+    fn step_n(&mut self, count: u32, rinput: &str) -> Result<Batch, BoundaryError>;
+    fn run(&mut self, fuel: u32) -> Result<Batch, BoundaryError>;
+    fn snapshot(&self) -> Result<String, BoundaryError>;
+    fn restore(&mut self, snapshot: &str) -> Result<(), BoundaryError>;
+    fn render(&self, snapshot: &str) -> Result<String, BoundaryError>;
+    fn rewind(&mut self, step: u32) -> Result<(), BoundaryError>;
+    fn history_len(&self) -> u32;
+    fn history_position(&self) -> u32;
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, BoundaryError>;
+    fn location(&self) -> Option<String>;
+}
+
+// object safe view of a `Compiler`, as the wit `compiler` world
+pub trait DynCompiler {
+    fn describe(&self) -> CompilerDescription;
+    fn compile_code(&self, code: &str) -> Result<String, BoundaryError>;
+    fn source_map(&self, code: &str) -> Result<Vec<SourceMapping>, BoundaryError>;
+    fn encode_ainput(&self, ainput: &str) -> Result<String, BoundaryError>;
+    fn encode_rinput(&self, rinput: &str) -> Result<String, BoundaryError>;
+    fn decode_routput(&self, output: &str) -> Result<String, BoundaryError>;
+    fn decode_foutput(&self, output: &str) -> Result<String, BoundaryError>;
+}
+
+// object safe view of an `Interpreter`, as the wit `interpreter` world
+pub trait DynInterpreter {
+    fn interpreter_code(&self) -> Result<String, BoundaryError>;
+    fn encode_ainput(&self, code: &str, ainput: &str) -> Result<String, BoundaryError>;
+    fn encode_rinput(&self, rinput: &str) -> Result<String, BoundaryError>;
+    fn decode_routput(&self, output: &str) -> Result<String, BoundaryError>;
+    fn decode_foutput(&self, output: &str) -> Result<String, BoundaryError>;
+}
+
+// the machine of the component, `None` before `make`
+pub struct TypedMachine<T: Machine>(Option<History<T>>);
+
+impl<T: Machine> Default for TypedMachine<T> {
+    fn default() -> Self {
+        TypedMachine(None)
+    }
+}
+
+impl<T: Machine> DynMachine for TypedMachine<T> {
+    fn describe(&self) -> Description {
+        T::describe()
+    }
+
+    fn make(&mut self, code: &str, ainput: &str) -> Result<(), BoundaryError> {
+        self.0 = Some(wasm_model::make_machine_impl::<T>(code, ainput)?);
+        Ok(())
+    }
+
+    fn step(&mut self, rinput: &str) -> Result<String, BoundaryError> {
+        wasm_model::step_machine_impl(&mut self.0, rinput)
+    }
+
+    fn step_n(&mut self, count: u32, rinput: &str) -> Result<Batch, BoundaryError> {
+        wasm_model::step_n_machine_impl(&mut self.0, count, rinput)
+    }
+
+    fn run(&mut self, fuel: u32) -> Result<Batch, BoundaryError> {
+        wasm_model::run_machine_impl(&mut self.0, fuel)
+    }
+
+    fn snapshot(&self) -> Result<String, BoundaryError> {
+        wasm_model::snapshot_machine_impl(&self.0)
+    }
+
+    fn restore(&mut self, snapshot: &str) -> Result<(), BoundaryError> {
+        self.0 = Some(wasm_model::restore_machine_impl::<T>(snapshot)?);
+        Ok(())
+    }
+
+    fn render(&self, snapshot: &str) -> Result<String, BoundaryError> {
+        wasm_model::render_machine_impl::<T>(snapshot)
+    }
+
+    fn rewind(&mut self, step: u32) -> Result<(), BoundaryError> {
+        wasm_model::rewind_machine_impl(&mut self.0, step)
+    }
+
+    fn history_len(&self) -> u32 {
+        wasm_model::history_len_impl(&self.0)
+    }
+
+    fn history_position(&self) -> u32 {
+        wasm_model::history_position_impl(&self.0)
+    }
+
+    fn hook(&self, name: &str, arg: &str) -> Result<bool, BoundaryError> {
+        wasm_model::hook_machine_impl(&self.0, name, arg)
+    }
+
+    fn location(&self) -> Option<String> {
+        wasm_model::location_machine_impl(&self.0)
+    }
+}
+
+pub struct TypedCompiler<T: Compiler>(PhantomData<T>);
+
+impl<T: Compiler> Default for TypedCompiler<T> {
+    fn default() -> Self {
+        TypedCompiler(PhantomData)
+    }
+}
+
+impl<T: Compiler> DynCompiler for TypedCompiler<T> {
+    fn describe(&self) -> CompilerDescription {
+        T::describe()
+    }
+
+    fn compile_code(&self, code: &str) -> Result<String, BoundaryError> {
+        wasm_compiler::compile_code_impl::<T>(code)
+    }
+
+    fn source_map(&self, code: &str) -> Result<Vec<SourceMapping>, BoundaryError> {
+        wasm_compiler::source_map_impl::<T>(code)
+    }
+
+    fn encode_ainput(&self, ainput: &str) -> Result<String, BoundaryError> {
+        wasm_compiler::encode_ainput_impl::<T>(ainput)
+    }
+
+    fn encode_rinput(&self, rinput: &str) -> Result<String, BoundaryError> {
+        wasm_compiler::encode_rinput_impl::<T>(rinput)
+    }
+
+    fn decode_routput(&self, output: &str) -> Result<String, BoundaryError> {
+        wasm_compiler::decode_routput_impl::<T>(output)
+    }
+
+    fn decode_foutput(&self, output: &str) -> Result<String, BoundaryError> {
+        wasm_compiler::decode_foutput_impl::<T>(output)
+    }
+}
+
+pub struct TypedInterpreter<T: Interpreter>(PhantomData<T>);
+
+impl<T: Interpreter> Default for TypedInterpreter<T> {
+    fn default() -> Self {
+        TypedInterpreter(PhantomData)
+    }
+}
+
+impl<T: Interpreter> DynInterpreter for TypedInterpreter<T> {
+    fn interpreter_code(&self) -> Result<String, BoundaryError> {
+        wasm_interpreter::interpreter_code_impl::<T>()
+    }
+
+    fn encode_ainput(&self, code: &str, ainput: &str) -> Result<String, BoundaryError> {
+        wasm_interpreter::encode_ainput_impl::<T>(code, ainput)
+    }
+
+    fn encode_rinput(&self, rinput: &str) -> Result<String, BoundaryError> {
+        wasm_interpreter::encode_rinput_impl::<T>(rinput)
+    }
+
+    fn decode_routput(&self, output: &str) -> Result<String, BoundaryError> {
+        wasm_interpreter::decode_routput_impl::<T>(output)
+    }
+
+    fn decode_foutput(&self, output: &str) -> Result<String, BoundaryError> {
+        wasm_interpreter::decode_foutput_impl::<T>(output)
+    }
+}
+
+// constructors with the signature of a registry entry
+pub fn machine<T: Machine + 'static>() -> Box<dyn DynMachine> {
+    Box::new(TypedMachine::<T>::default())
+}
+
+pub fn compiler<T: Compiler + 'static>() -> Box<dyn DynCompiler> {
+    Box::new(TypedCompiler::<T>::default())
+}
+
+pub fn interpreter<T: Interpreter + 'static>() -> Box<dyn DynInterpreter> {
+    Box::new(TypedInterpreter::<T>::default())
+}
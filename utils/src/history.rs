@@ -0,0 +1,534 @@
+use std::collections::VecDeque;
+
+use crate::{Machine, StepResult, TextCodec};
+
+// what a recorded step produced
+pub enum StepOutput<M: Machine> {
+    Continue(M::ROutput),
+    Halt(M::FOutput),
+}
+
+// how `History::advance` ended, `F` is the foutput
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchEnd<F> {
+    // the step count was used up
+    Running,
+    Halted(F),
+    // the step after the counted ones failed, the machine stays before it
+    Failed(String),
+}
+
+// a recorded entry: one step, or the steps of one `advance` with the same rinput
+pub struct HistoryStep<M: Machine> {
+    pub rinput: M::RInput,
+    // number of steps, 1 for `step`
+    pub count: usize,
+    // the output of the last of the steps
+    pub output: StepOutput<M>,
+}
+
+// recorder of a machine run that can be stepped backwards
+// - the state after every entry is kept as a serialized snapshot, so `M::SnapShot` needs no `Clone`
+// - steps are numbered from the start of the run; only the last `capacity` states are kept
+// - a position inside an `advance` entry is reached by replaying the entry from its start
+// - `step` at a past position forks: the steps after it are replaced by the new branch
+pub struct History<M: Machine> {
+    // `None` after a halting step
+    current: Option<M>,
+    // states[i] is the state after `positions[i]` steps
+    states: VecDeque<serde_json::Value>,
+    positions: VecDeque<usize>,
+    // steps[i] leads from states[i] (the last one may halt and have no state after it)
+    steps: VecDeque<HistoryStep<M>>,
+    // position in `steps`
+    cursor: usize,
+    capacity: usize,
+}
+
+impl<M: Machine> History<M> {
+    pub const DEFAULT_CAPACITY: usize = 1000;
+
+    pub fn new(machine: M) -> Result<Self, String> {
+        Self::with_capacity(machine, Self::DEFAULT_CAPACITY)
+    }
+
+    // keep at most `capacity` (at least 1) states
+    pub fn with_capacity(machine: M, capacity: usize) -> Result<Self, String> {
+        let state = encode(&machine)?;
+        Ok(History {
+            current: Some(machine),
+            states: VecDeque::from([state]),
+            positions: VecDeque::from([0]),
+            steps: VecDeque::new(),
+            cursor: 0,
+            capacity: capacity.max(1),
+        })
+    }
+
+    // machine at the current position, `None` if it has halted there
+    pub fn machine(&self) -> Option<&M> {
+        self.current.as_ref()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.current.is_none()
+    }
+
+    // number of steps up to the current position
+    pub fn position(&self) -> usize {
+        self.positions
+            .get(self.cursor)
+            .copied()
+            .unwrap_or_else(|| self.len())
+    }
+
+    // number of steps recorded in the current branch (including those after the position)
+    pub fn len(&self) -> usize {
+        let last = self.positions.len() - 1;
+        // after a halting entry, which has no state
+        let halted = self.steps.get(last).map_or(0, |step| step.count);
+        self.positions[last] + halted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // the first step that can still be jumped to
+    pub fn oldest(&self) -> usize {
+        self.positions[0]
+    }
+
+    // recorded entry starting at step `n`, if kept
+    pub fn get(&self, n: usize) -> Option<&HistoryStep<M>> {
+        let index = self.positions.binary_search(&n).ok()?;
+        self.steps.get(index)
+    }
+
+    // kept entries with the number of their first step
+    pub fn steps(&self) -> impl Iterator<Item = (usize, &HistoryStep<M>)> {
+        self.positions.iter().copied().zip(self.steps.iter())
+    }
+
+    pub fn step(&mut self, rinput: M::RInput) -> Result<&StepOutput<M>, String> {
+        // `RInput` is not `Clone`: the machine gets a copy through its text form,
+        // made before the machine is taken so that a failing parse leaves it in place
+        let rinput_copy = M::RInput::parse(&rinput.print())?;
+        let machine = self
+            .current
+            .take()
+            .ok_or_else(|| "machine has halted".to_string())?;
+        // a failed step consumes the machine, so it is rebuilt from the recorded state
+        let result = match machine.step(rinput_copy) {
+            Ok(result) => result,
+            Err(err) => {
+                self.current = Some(self.restore_at(self.cursor)?);
+                return Err(err);
+            }
+        };
+
+        let output = match result {
+            StepResult::Continue { next, output } => {
+                self.current = Some(next);
+                StepOutput::Continue(output)
+            }
+            StepResult::Halt { output } => StepOutput::Halt(output),
+        };
+        if let Err(err) = self.record(rinput, 1, output) {
+            self.current = Some(self.restore_at(self.cursor)?);
+            return Err(err);
+        }
+        Ok(&self.steps[self.cursor - 1].output)
+    }
+
+    // take up to `count` steps with the same rinput and record them as one entry,
+    // for long runs where a snapshot per step is too slow
+    // `routput` gets the (1-indexed) step of the batch and its routput
+    // a failing step consumes the machine, so the batch is then replayed from its start
+    // to rebuild the machine before it: a failure costs the steps of the batch twice
+    pub fn advance(
+        &mut self,
+        count: usize,
+        rinput: &M::RInput,
+        mut routput: impl FnMut(usize, M::ROutput),
+    ) -> Result<(usize, BatchEnd<M::FOutput>), String> {
+        // parsed before the machine is taken, as in `step`
+        let rinput = rinput.print();
+        let recorded = M::RInput::parse(&rinput)?;
+        let mut machine = self
+            .current
+            .take()
+            .ok_or_else(|| "machine has halted".to_string())?;
+        let mut steps = 0;
+        // the text of the last routput, the output of the recorded entry
+        let mut last = None;
+        let end = loop {
+            if steps == count {
+                self.current = Some(machine);
+                break BatchEnd::Running;
+            }
+            let copy = match M::RInput::parse(&rinput) {
+                Ok(copy) => copy,
+                Err(err) => {
+                    self.current = Some(machine);
+                    break BatchEnd::Failed(err);
+                }
+            };
+            match machine.step(copy) {
+                Ok(StepResult::Continue { next, output }) => {
+                    steps += 1;
+                    last = Some(output.print());
+                    routput(steps, output);
+                    machine = next;
+                }
+                Ok(StepResult::Halt { output }) => {
+                    steps += 1;
+                    break BatchEnd::Halted(output);
+                }
+                Err(err) => match self.replay(self.cursor, &rinput, steps) {
+                    Ok((machine, _)) => {
+                        self.current = Some(machine);
+                        break BatchEnd::Failed(err);
+                    }
+                    // the batch is dropped and the machine goes back to its start
+                    Err(replay_err) => {
+                        self.current = Some(self.restore_at(self.cursor)?);
+                        return Err(replay_err);
+                    }
+                },
+            }
+        };
+        if steps > 0 {
+            let result = batch_output::<M>(&end, last)
+                .and_then(|output| self.record(recorded, steps, output));
+            // as above, the batch is dropped
+            if let Err(err) = result {
+                self.current = Some(self.restore_at(self.cursor)?);
+                return Err(err);
+            }
+        }
+        Ok((steps, end))
+    }
+
+    // go back one step, false at the oldest kept state
+    pub fn undo(&mut self) -> Result<bool, String> {
+        if self.position() == self.oldest() {
+            return Ok(false);
+        }
+        self.jump(self.position() - 1)?;
+        Ok(true)
+    }
+
+    // go forward one recorded step, false at the end of the branch
+    pub fn redo(&mut self) -> Result<bool, String> {
+        if self.position() == self.len() {
+            return Ok(false);
+        }
+        self.jump(self.position() + 1)?;
+        Ok(true)
+    }
+
+    // move to the position after `n` steps of the current branch
+    pub fn jump(&mut self, n: usize) -> Result<(), String> {
+        if n < self.oldest() {
+            return Err(format!(
+                "step {n} is no longer recorded (oldest is {})",
+                self.oldest()
+            ));
+        }
+        if n > self.len() {
+            return Err(format!("step {n} is not recorded (last is {})", self.len()));
+        }
+        let cursor = match self.positions.binary_search(&n) {
+            Ok(cursor) => cursor,
+            // after the halting entry
+            Err(cursor) if n == self.len() => {
+                self.current = None;
+                self.cursor = cursor;
+                return Ok(());
+            }
+            Err(cursor) => self.split(cursor - 1, n)?,
+        };
+        self.current = Some(self.restore_at(cursor)?);
+        self.cursor = cursor;
+        Ok(())
+    }
+
+    // go back to the position after `n` steps and take a different step from there
+    pub fn fork(&mut self, n: usize, rinput: M::RInput) -> Result<&StepOutput<M>, String> {
+        self.jump(n)?;
+        self.step(rinput)
+    }
+
+    // snapshot at the current position
+    pub fn snapshot(&self) -> Result<M::SnapShot, String> {
+        let state = self
+            .states
+            .get(self.cursor)
+            .ok_or_else(|| "machine has halted".to_string())?;
+        serde_json::from_value(state.clone()).map_err(|e| e.to_string())
+    }
+
+    // append an entry at the cursor (replacing the later ones), `current` is the state after it
+    // on error nothing is changed
+    fn record(
+        &mut self,
+        rinput: M::RInput,
+        count: usize,
+        output: StepOutput<M>,
+    ) -> Result<(), String> {
+        let state = self.current.as_ref().map(encode).transpose()?;
+        let position = self.position() + count;
+        self.steps.truncate(self.cursor);
+        self.states.truncate(self.cursor + 1);
+        self.positions.truncate(self.cursor + 1);
+        if let Some(state) = state {
+            self.states.push_back(state);
+            self.positions.push_back(position);
+        }
+        self.steps.push_back(HistoryStep {
+            rinput,
+            count,
+            output,
+        });
+        self.cursor += 1;
+        self.drop_oldest();
+        Ok(())
+    }
+
+    // split the entry at `index` so that the position `n` inside it gets a state,
+    // returns the index of that state
+    fn split(&mut self, index: usize, n: usize) -> Result<usize, String> {
+        let start = self.positions[index];
+        let rinput = self.steps[index].rinput.print();
+        let (machine, last) = self.replay(index, &rinput, n - start)?;
+        let first = HistoryStep {
+            rinput: M::RInput::parse(&rinput)?,
+            count: n - start,
+            output: StepOutput::Continue(last.ok_or("empty replay")?),
+        };
+        self.steps[index].count -= n - start;
+        self.steps.insert(index, first);
+        self.states.insert(index + 1, encode(&machine)?);
+        self.positions.insert(index + 1, n);
+        if self.cursor > index {
+            self.cursor += 1;
+        }
+        let dropped = self.drop_oldest();
+        Ok(index + 1 - dropped)
+    }
+
+    // drop the oldest states beyond the capacity, returns how many were dropped
+    fn drop_oldest(&mut self) -> usize {
+        let mut dropped = 0;
+        while self.states.len() > self.capacity {
+            self.states.pop_front();
+            self.positions.pop_front();
+            self.steps.pop_front();
+            self.cursor = self.cursor.saturating_sub(1);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    // the machine after `count` steps with `rinput` from states[cursor], and the last routput
+    fn replay(
+        &self,
+        cursor: usize,
+        rinput: &str,
+        count: usize,
+    ) -> Result<(M, Option<M::ROutput>), String> {
+        let mut machine = self.restore_at(cursor)?;
+        let mut last = None;
+        for _ in 0..count {
+            match machine.step(M::RInput::parse(rinput)?)? {
+                StepResult::Continue { next, output } => {
+                    machine = next;
+                    last = Some(output);
+                }
+                StepResult::Halt { .. } => return Err("replay halted".to_string()),
+            }
+        }
+        Ok((machine, last))
+    }
+
+    fn restore_at(&self, cursor: usize) -> Result<M, String> {
+        let snapshot =
+            serde_json::from_value(self.states[cursor].clone()).map_err(|e| e.to_string())?;
+        Ok(M::restore(snapshot))
+    }
+}
+
+// output of an `advance` entry: its foutput, or the routput of its last step
+fn batch_output<M: Machine>(
+    end: &BatchEnd<M::FOutput>,
+    last: Option<String>,
+) -> Result<StepOutput<M>, String> {
+    match (end, last) {
+        (BatchEnd::Halted(foutput), _) => {
+            Ok(StepOutput::Halt(M::FOutput::parse(&foutput.print())?))
+        }
+        (_, Some(last)) => Ok(StepOutput::Continue(M::ROutput::parse(&last)?)),
+        (_, None) => unreachable!("a continuing batch has a routput"),
+    }
+}
+
+fn encode<M: Machine>(machine: &M) -> Result<serde_json::Value, String> {
+    serde_json::to_value(machine.snapshot()).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_machine::{Amount, Counter};
+
+    fn count(history: &History<Counter>) -> Option<usize> {
+        history.machine().map(|m| m.count)
+    }
+
+    #[test]
+    fn undo_redo_and_jump() {
+        let mut history = History::new(Counter::make(10, 0).unwrap()).unwrap();
+        for n in [1, 2, 3] {
+            history.step(Amount(n)).unwrap();
+        }
+        assert_eq!((history.position(), history.len()), (3, 3));
+        assert_eq!(count(&history), Some(6));
+
+        assert!(history.undo().unwrap());
+        assert_eq!(count(&history), Some(3));
+        history.jump(0).unwrap();
+        assert_eq!(count(&history), Some(0));
+        assert!(!history.undo().unwrap());
+        assert!(history.redo().unwrap());
+        assert_eq!(count(&history), Some(1));
+        assert_eq!(history.len(), 3);
+
+        assert!(matches!(
+            history.step(Amount(100)),
+            Ok(StepOutput::Halt(101))
+        ));
+        assert!(history.is_halted());
+        assert_eq!(history.len(), 2);
+        assert!(history.undo().unwrap());
+        assert_eq!(count(&history), Some(1));
+        assert!(history.redo().unwrap());
+        assert!(history.is_halted());
+    }
+
+    #[test]
+    fn fork_replaces_the_later_steps() {
+        let mut history = History::new(Counter::make(100, 0).unwrap()).unwrap();
+        for n in [1, 2, 3] {
+            history.step(Amount(n)).unwrap();
+        }
+        history.fork(1, Amount(10)).unwrap();
+        assert_eq!(count(&history), Some(11));
+        let rinputs = history.steps().map(|(_, s)| s.rinput).collect::<Vec<_>>();
+        assert_eq!(rinputs, vec![Amount(1), Amount(10)]);
+    }
+
+    #[test]
+    fn failed_step_keeps_the_state() {
+        let mut history = History::new(Counter::make(100, 0).unwrap()).unwrap();
+        history.step(Amount(5)).unwrap();
+        assert!(history.step(Amount(0)).is_err());
+        assert_eq!(count(&history), Some(5));
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn advance_records_one_entry() {
+        let mut history = History::new(Counter::make(10, 0).unwrap()).unwrap();
+        history.step(Amount(1)).unwrap();
+        let mut routputs = Vec::new();
+        let batch = history.advance(3, &Amount(2), |step, total| routputs.push((step, total)));
+        assert_eq!(batch, Ok((3, BatchEnd::Running)));
+        assert_eq!(
+            routputs,
+            vec![
+                (1, "3".to_string()),
+                (2, "5".to_string()),
+                (3, "7".to_string())
+            ]
+        );
+        assert_eq!(
+            (history.oldest(), history.position(), history.len()),
+            (0, 4, 4)
+        );
+        assert_eq!(count(&history), Some(7));
+        assert_eq!(
+            history.get(1).map(|s| (s.rinput, s.count)),
+            Some((Amount(2), 3))
+        );
+
+        // a position inside the entry is replayed and splits it
+        history.jump(3).unwrap();
+        assert_eq!(count(&history), Some(5));
+        let counts = history
+            .steps()
+            .map(|(n, s)| (n, s.count))
+            .collect::<Vec<_>>();
+        assert_eq!(counts, vec![(0, 1), (1, 2), (3, 1)]);
+        assert!(history.undo().unwrap());
+        assert_eq!(count(&history), Some(3));
+        history.jump(4).unwrap();
+
+        let batch = history.advance(5, &Amount(2), |_, _| {});
+        assert_eq!(batch, Ok((2, BatchEnd::Halted(11))));
+        assert!(history.is_halted());
+        assert_eq!((history.position(), history.len()), (6, 6));
+        assert!(history.snapshot().is_err());
+        assert!(history.undo().unwrap());
+        assert_eq!(count(&history), Some(9));
+        assert!(history.redo().unwrap());
+        assert!(history.is_halted());
+    }
+
+    #[test]
+    fn failed_advance_stops_before_the_step() {
+        let mut history = History::new(Counter::make(100, 0).unwrap()).unwrap();
+        history.step(Amount(5)).unwrap();
+        assert_eq!(
+            history.advance(3, &Amount(0), |_, _| {}),
+            Ok((0, BatchEnd::Failed("zero amount".to_string())))
+        );
+        assert_eq!(count(&history), Some(5));
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn unparsable_rinput_keeps_the_machine() {
+        let mut history = History::new(Counter::make(100, 0).unwrap()).unwrap();
+        history.step(Amount(5)).unwrap();
+        // over `Amount::MAX`, so its text form does not parse back
+        let rinput = Amount(Amount::MAX + 1);
+        assert!(history.step(rinput).is_err());
+        assert!(history.advance(3, &rinput, |_, _| {}).is_err());
+        assert_eq!(count(&history), Some(5));
+        assert_eq!((history.position(), history.len()), (1, 1));
+
+        history.step(Amount(1)).unwrap();
+        assert!(history.undo().unwrap());
+        assert_eq!(count(&history), Some(5));
+        assert_eq!(
+            history.advance(2, &Amount(1), |_, _| {}),
+            Ok((2, BatchEnd::Running))
+        );
+        assert_eq!(count(&history), Some(7));
+    }
+
+    #[test]
+    fn capacity_drops_the_oldest_states() {
+        let mut history = History::with_capacity(Counter::make(100, 0).unwrap(), 3).unwrap();
+        for _ in 0..5 {
+            history.step(Amount(1)).unwrap();
+        }
+        assert_eq!((history.oldest(), history.len()), (3, 5));
+        assert!(history.jump(2).is_err());
+        history.jump(3).unwrap();
+        assert_eq!(count(&history), Some(3));
+        assert!(history.get(2).is_none());
+        assert_eq!(history.get(3).map(|s| s.rinput), Some(Amount(1)));
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use logos::{Lexer, Logos};
 
+use crate::diagnostic::Span;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
     Ident(String),
@@ -10,6 +12,58 @@ pub enum Token {
     Whitespace(String),
     Comment(String),
     Symbol(char),
+    // the following are produced only by `LexerConfig`
+    // multi-character operator such as ":="
+    Op(String),
+    Keyword(String),
+    // `#name` without '#'
+    Atom(String),
+    Char(char),
+}
+
+// per-language token classes on top of the common tokens
+// - operators: multi-character symbols, the longest one wins (so "a : = b" is not "a := b")
+// - keywords: identifiers that are never names
+// - ident_chars: symbols allowed inside identifiers (e.g. '-' for `is-atom`)
+// - atoms: `#name` as one token (the name may contain '-')
+// - char literals: 'a', '\n', '\''
+#[derive(Debug, Clone, Default)]
+pub struct LexerConfig {
+    operators: Vec<String>,
+    keywords: Vec<String>,
+    ident_chars: Vec<char>,
+    atoms: bool,
+    char_literals: bool,
+}
+
+impl LexerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn operators(mut self, operators: &[&str]) -> Self {
+        self.operators
+            .extend(operators.iter().map(|op| op.to_string()));
+        // longest first
+        self.operators.sort_by_key(|op| std::cmp::Reverse(op.len()));
+        self
+    }
+    pub fn keywords(mut self, keywords: &[&str]) -> Self {
+        self.keywords
+            .extend(keywords.iter().map(|word| word.to_string()));
+        self
+    }
+    pub fn ident_chars(mut self, chars: &[char]) -> Self {
+        self.ident_chars.extend(chars);
+        self
+    }
+    pub fn atoms(mut self) -> Self {
+        self.atoms = true;
+        self
+    }
+    pub fn char_literals(mut self) -> Self {
+        self.char_literals = true;
+        self
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -25,10 +79,42 @@ pub enum Tree {
     Delim { delim: DelimKind, child: Vec<Tree> },
 }
 
+// token with its byte range in the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
+// `Tree` with byte ranges
+// a delimited group spans from its opening to its closing delimiter
 #[derive(Debug, Clone, PartialEq, Eq)]
-struct SpannedToken {
-    token: Token,
-    byte: usize,
+pub enum SpannedTree {
+    Token(SpannedToken),
+    Delim {
+        delim: DelimKind,
+        span: Span,
+        child: Vec<SpannedTree>,
+    },
+}
+
+impl SpannedTree {
+    pub fn span(&self) -> Span {
+        match self {
+            SpannedTree::Token(token) => token.span,
+            SpannedTree::Delim { span, .. } => *span,
+        }
+    }
+
+    pub fn into_tree(self) -> Tree {
+        match self {
+            SpannedTree::Token(token) => Tree::Token(token.token),
+            SpannedTree::Delim { delim, child, .. } => Tree::Delim {
+                delim,
+                child: child.into_iter().map(SpannedTree::into_tree).collect(),
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -69,22 +155,82 @@ pub fn lex(text: &str) -> Result<Vec<Token>, LexError> {
 }
 
 pub fn lex_tree(text: &str) -> Result<Vec<Tree>, LexError> {
+    Ok(lex_tree_spanned(text)?
+        .into_iter()
+        .map(SpannedTree::into_tree)
+        .collect())
+}
+
+pub fn lex_tree_spanned(text: &str) -> Result<Vec<SpannedTree>, LexError> {
     nest_spanned(lex_spanned(text)?)
 }
 
 pub fn nest(tokens: Vec<Token>) -> Result<Vec<Tree>, LexError> {
     let spanned = tokens
         .into_iter()
-        .map(|token| SpannedToken { token, byte: 0 })
+        .map(|token| SpannedToken {
+            token,
+            span: Span::at(0),
+        })
         .collect();
-    nest_spanned(spanned)
+    Ok(nest_spanned(spanned)?
+        .into_iter()
+        .map(SpannedTree::into_tree)
+        .collect())
 }
 
-fn lex_spanned(text: &str) -> Result<Vec<SpannedToken>, LexError> {
+pub fn lex_with(text: &str, config: &LexerConfig) -> Result<Vec<Token>, LexError> {
+    Ok(lex_spanned_with(text, config)?
+        .into_iter()
+        .map(|token| token.token)
+        .collect())
+}
+
+pub fn lex_tree_with(text: &str, config: &LexerConfig) -> Result<Vec<Tree>, LexError> {
+    Ok(lex_tree_spanned_with(text, config)?
+        .into_iter()
+        .map(SpannedTree::into_tree)
+        .collect())
+}
+
+pub fn lex_tree_spanned_with(
+    text: &str,
+    config: &LexerConfig,
+) -> Result<Vec<SpannedTree>, LexError> {
+    nest_spanned(lex_spanned_with(text, config)?)
+}
+
+pub fn lex_spanned_with(text: &str, config: &LexerConfig) -> Result<Vec<SpannedToken>, LexError> {
+    let tokens = lex_common(text, config.char_literals)?;
+    Ok(classify(text, tokens, config))
+}
+
+pub fn lex_spanned(text: &str) -> Result<Vec<SpannedToken>, LexError> {
+    lex_common(text, false)
+}
+
+fn lex_common(text: &str, char_literals: bool) -> Result<Vec<SpannedToken>, LexError> {
     let mut lexer = RawToken::lexer(text);
     let mut tokens = Vec::new();
 
-    while let Some(result) = lexer.next() {
+    loop {
+        // char literals are cut out first, since '"' would start a string literal
+        if char_literals && lexer.remainder().starts_with('\'') {
+            let start = text.len() - lexer.remainder().len();
+            let (ch, len) = char_literal(lexer.remainder()).ok_or_else(|| LexError {
+                message: "invalid char literal".to_string(),
+                byte: start,
+            })?;
+            tokens.push(SpannedToken {
+                token: Token::Char(ch),
+                span: Span::new(start, start + len),
+            });
+            lexer.bump(len);
+            continue;
+        }
+        let Some(result) = lexer.next() else {
+            break;
+        };
         let span = lexer.span();
         let raw = result.map_err(|_| LexError {
             message: invalid_character_message(text, span.start),
@@ -106,30 +252,119 @@ fn lex_spanned(text: &str) -> Result<Vec<SpannedToken>, LexError> {
 
         tokens.push(SpannedToken {
             token,
-            byte: span.start,
+            span: Span::new(span.start, span.end),
         });
     }
 
     Ok(tokens)
 }
 
-fn nest_spanned(tokens: Vec<SpannedToken>) -> Result<Vec<Tree>, LexError> {
+// 'a', '\n', '\'' and so on at the start of `text`, with its byte length
+fn char_literal(text: &str) -> Option<(char, usize)> {
+    let mut chars = text.char_indices().skip(1);
+    let ch = match chars.next()? {
+        (_, '\\') => match chars.next()?.1 {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            other => other,
+        },
+        (_, '\'') => return None,
+        (_, ch) => ch,
+    };
+    match chars.next()? {
+        (i, '\'') => Some((ch, i + 1)),
+        _ => None,
+    }
+}
+
+// apply the token classes of `config` to the common tokens
+fn classify(text: &str, tokens: Vec<SpannedToken>, config: &LexerConfig) -> Vec<SpannedToken> {
+    // tokens[from..] continuing a name (adjacent identifiers, numbers and `extra` symbols)
+    let name_end = |from: usize, extra: &dyn Fn(char) -> bool| {
+        let mut end = from;
+        while end < tokens.len()
+            && (end == 0 || tokens[end].span.start == tokens[end - 1].span.end)
+            && match &tokens[end].token {
+                Token::Ident(_) | Token::Number(_) => true,
+                Token::Symbol(ch) => extra(*ch),
+                _ => false,
+            }
+        {
+            end += 1;
+        }
+        end
+    };
+    let in_ident = |ch: char| config.ident_chars.contains(&ch);
+
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        let span = tokens[i].span;
+        let (token, next) = match &tokens[i].token {
+            Token::Ident(_) => {
+                let next = name_end(i + 1, &in_ident);
+                let word = text[span.start..tokens[next - 1].span.end].to_string();
+                if config.keywords.contains(&word) {
+                    (Token::Keyword(word), next)
+                } else {
+                    (Token::Ident(word), next)
+                }
+            }
+            Token::Symbol('#')
+                if config.atoms
+                    && matches!(tokens.get(i + 1), Some(SpannedToken { token: Token::Ident(_), span: next }) if next.start == span.end) =>
+            {
+                let next = name_end(i + 2, &|ch| ch == '-' || in_ident(ch));
+                let name = text[span.end..tokens[next - 1].span.end].to_string();
+                (Token::Atom(name), next)
+            }
+            Token::Symbol(_) => {
+                let rest = &text[span.start..];
+                let op = config.operators.iter().find(|op| {
+                    let len = op.chars().count();
+                    len > 1
+                        && rest.starts_with(op.as_str())
+                        && tokens[i..]
+                            .iter()
+                            .take(len)
+                            .filter(|token| matches!(token.token, Token::Symbol(_)))
+                            .count()
+                            == len
+                });
+                match op {
+                    Some(op) => (Token::Op(op.clone()), i + op.chars().count()),
+                    None => (tokens[i].token.clone(), i + 1),
+                }
+            }
+            token => (token.clone(), i + 1),
+        };
+        out.push(SpannedToken {
+            token,
+            span: Span::new(span.start, tokens[next - 1].span.end),
+        });
+        i = next;
+    }
+    out
+}
+
+fn nest_spanned(tokens: Vec<SpannedToken>) -> Result<Vec<SpannedTree>, LexError> {
     let mut root = Vec::new();
-    let mut stack: Vec<(DelimKind, Vec<Tree>, usize)> = Vec::new();
+    let mut stack: Vec<(DelimKind, Vec<SpannedTree>, usize)> = Vec::new();
 
     for token in tokens {
         match token.token {
             Token::Symbol(ch) => {
                 if let Some(delim) = opening_delim(ch) {
-                    stack.push((delim, Vec::new(), token.byte));
+                    stack.push((delim, Vec::new(), token.span.start));
                     continue;
                 }
 
                 if let Some(found) = closing_delim(ch) {
-                    let Some((expected, child, _open_byte)) = stack.pop() else {
+                    let Some((expected, child, open_byte)) = stack.pop() else {
                         return Err(LexError {
                             message: format!("unexpected closing delimiter '{}'", ch),
-                            byte: token.byte,
+                            byte: token.span.start,
                         });
                     };
 
@@ -140,24 +375,25 @@ fn nest_spanned(tokens: Vec<SpannedToken>) -> Result<Vec<Tree>, LexError> {
                                 opening_char(expected),
                                 ch
                             ),
-                            byte: token.byte,
+                            byte: token.span.start,
                         });
                     }
 
                     push_tree(
                         &mut root,
                         &mut stack,
-                        Tree::Delim {
+                        SpannedTree::Delim {
                             delim: found,
+                            span: Span::new(open_byte, token.span.end),
                             child,
                         },
                     );
                     continue;
                 }
 
-                push_tree(&mut root, &mut stack, Tree::Token(Token::Symbol(ch)));
+                push_tree(&mut root, &mut stack, SpannedTree::Token(token));
             }
-            other => push_tree(&mut root, &mut stack, Tree::Token(other)),
+            _ => push_tree(&mut root, &mut stack, SpannedTree::Token(token)),
         }
     }
 
@@ -171,7 +407,11 @@ fn nest_spanned(tokens: Vec<SpannedToken>) -> Result<Vec<Tree>, LexError> {
     Ok(root)
 }
 
-fn push_tree(root: &mut Vec<Tree>, stack: &mut [(DelimKind, Vec<Tree>, usize)], tree: Tree) {
+fn push_tree(
+    root: &mut Vec<SpannedTree>,
+    stack: &mut [(DelimKind, Vec<SpannedTree>, usize)],
+    tree: SpannedTree,
+) {
     if let Some((_, child, _)) = stack.last_mut() {
         child.push(tree);
     } else {
@@ -266,7 +506,11 @@ fn invalid_character_message(text: &str, byte: usize) -> String {
 
 #[cfg(test)]
 mod tests {
-    use super::{lex, lex_tree, DelimKind, LexError, Token, Tree};
+    use super::{
+        lex, lex_tree, lex_tree_spanned, lex_tree_spanned_with, lex_with, DelimKind, LexError,
+        LexerConfig, SpannedTree, Token, Tree,
+    };
+    use crate::diagnostic::Span;
 
     #[test]
     fn lexes_common_tokens() {
@@ -419,4 +663,95 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn spanned_tree_keeps_byte_ranges() {
+        let tree = lex_tree_spanned("ab (c)").unwrap();
+        let spans: Vec<Span> = tree.iter().map(SpannedTree::span).collect();
+        assert_eq!(
+            spans,
+            vec![Span::new(0, 2), Span::new(2, 3), Span::new(3, 6)]
+        );
+        let SpannedTree::Delim { child, .. } = &tree[2] else {
+            panic!("expected delimiter");
+        };
+        assert_eq!(child[0].span(), Span::new(4, 5));
+    }
+
+    #[test]
+    fn config_takes_the_longest_operator() {
+        let config = LexerConfig::new().operators(&[":", ":=", "<-", "<->"]);
+        let tokens = lex_with("a:=b <-> c : = d", &config).unwrap();
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Op(":=".to_string()),
+                Token::Ident("b".to_string()),
+                Token::Op("<->".to_string()),
+                Token::Ident("c".to_string()),
+                // separated symbols are not an operator
+                Token::Symbol(':'),
+                Token::Symbol('='),
+                Token::Ident("d".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_classifies_names() {
+        let config = LexerConfig::new()
+            .keywords(&["while", "is-atom"])
+            .ident_chars(&['-'])
+            .atoms();
+        let tokens = lex_with("while is-atom x-1 #null-ptr # y", &config).unwrap();
+        let tokens: Vec<_> = tokens
+            .into_iter()
+            .filter(|token| !matches!(token, Token::Whitespace(_)))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword("while".to_string()),
+                Token::Keyword("is-atom".to_string()),
+                Token::Ident("x-1".to_string()),
+                Token::Atom("null-ptr".to_string()),
+                Token::Symbol('#'),
+                Token::Ident("y".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn config_reads_char_literals() {
+        let config = LexerConfig::new().char_literals();
+        let tree = lex_tree_spanned_with(r#"f('(', '"', '\n')"#, &config).unwrap();
+        let SpannedTree::Delim { child, .. } = &tree[1] else {
+            panic!("expected delimiter");
+        };
+        let chars: Vec<_> = child
+            .iter()
+            .filter_map(|tree| match tree {
+                SpannedTree::Token(token) => match token.token {
+                    Token::Char(ch) => Some((ch, token.span)),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            chars,
+            vec![
+                ('(', Span::new(2, 5)),
+                ('"', Span::new(7, 10)),
+                ('\n', Span::new(12, 16)),
+            ]
+        );
+        let err = lex_with("'ab'", &config).unwrap_err();
+        assert_eq!(err.message, "invalid char literal");
+    }
 }
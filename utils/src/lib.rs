@@ -2,8 +2,12 @@
 pub use wit_bindgen;
 
 // module for parsing and encoding text
+pub mod diagnostic;
 pub mod lexer;
 pub mod parse;
+pub mod pretty;
+pub mod tree_parser;
+pub use diagnostic::Diagnostic;
 pub use lexer::*;
 pub use parse::TextCodec;
 
@@ -11,8 +15,27 @@ pub use parse::TextCodec;
 pub mod data;
 pub use data::{bool, identifier, number};
 
+// generic drivers over machines and compilers
+pub mod breakpoint;
+pub mod compose;
+pub mod describe;
+pub mod differential;
+pub mod dynamic;
+pub mod history;
+pub mod metrics;
+pub mod nondet;
+pub mod run;
+pub mod source_map;
+pub use compose::Compose;
+pub use describe::{CompilerDescription, Description};
+pub use source_map::SourceMap;
+// toy machine shared by the driver tests
+#[cfg(test)]
+mod test_machine;
+
 // utilities for web
 pub mod render;
+pub mod render_terminal;
 pub mod wasm_util;
 pub use render::*;
 
@@ -42,17 +65,19 @@ pub trait Machine: Sized {
     type SnapShot: serde::Serialize + serde::de::DeserializeOwned;
 
     // parsing
-    fn parse_code(code: &str) -> Result<Self::Code, String> {
-        Self::Code::parse(code)
+    fn parse_code(code: &str) -> Result<Self::Code, Diagnostic> {
+        Self::Code::parse_spanned(code)
     }
-    fn parse_ainput(ainput: &str) -> Result<Self::AInput, String> {
-        Self::AInput::parse(ainput)
+    fn parse_ainput(ainput: &str) -> Result<Self::AInput, Diagnostic> {
+        Self::AInput::parse_spanned(ainput)
     }
-    fn parse_rinput(rinput: &str) -> Result<Self::RInput, String> {
-        Self::RInput::parse(rinput)
+    fn parse_rinput(rinput: &str) -> Result<Self::RInput, Diagnostic> {
+        Self::RInput::parse_spanned(rinput)
     }
 
     // semantics
+    // errors are plain messages: they are about parsed values, not text, so there is no span to give
+    // (hosts pass them on as span-less error diagnostics, see `Diagnostic::from(String)`)
     fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String>;
     fn step(self, rinput: Self::RInput) -> Result<StepResult<Self>, String>;
 
@@ -62,16 +87,72 @@ pub trait Machine: Sized {
 
     // rendering of Snapshot for web
     fn render(snapshot: Self::SnapShot) -> RenderState;
+
+    // model specific breakpoint condition `@name arg` on the current state (see `breakpoint`)
+    fn hook(&self, name: &str, _arg: &str) -> Result<bool, String> {
+        Err(format!("unknown hook: @{name}"))
+    }
+
+    // name, text formats and examples shown by hosts
+    fn describe() -> Description {
+        Description::new(describe::type_name::<Self>())
+    }
+
+    // location in the code of the current state, as named in source maps of compilers into the model
+    fn location(&self) -> Option<String> {
+        None
+    }
+}
+
+// optional cost model of a machine, used by `metrics::measure` and `metrics::benchmark`
+pub trait Metrics: Machine {
+    // sizes of the current state in the units of the model ("tape", "stack", "heap", ...)
+    fn sizes(&self) -> Vec<(&'static str, usize)>;
+    // model specific events of the next step ("beta", "head-move", "alloc", ...)
+    fn counters(&self, _rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        vec![]
+    }
+}
+
+// models whose step may go to several states, explored by `nondet::explore`
+// a state either halts or has successors; a state with neither is stuck
+pub trait NondetMachine: Sized {
+    type Code: TextCodec;
+    type AInput: TextCodec;
+    type FOutput: TextCodec;
+    // label of a transition (e.g. which redex is reduced)
+    type Choice: TextCodec;
+    type SnapShot: serde::Serialize + serde::de::DeserializeOwned;
+
+    fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String>;
+    fn halted(&self) -> Option<Self::FOutput>;
+    fn successors(&self) -> Result<Vec<(Self::Choice, Self)>, String>;
+
+    fn snapshot(&self) -> Self::SnapShot;
+    fn restore(snapshot: Self::SnapShot) -> Self;
+    fn render(snapshot: Self::SnapShot) -> RenderState;
+
+    // states with the same key are the same state for the explorers
+    fn state_key(&self) -> String {
+        serde_json::to_string(&self.snapshot()).expect("snapshot should serialize")
+    }
 }
 
 pub trait Compiler: Sized {
     type Source: Machine;
     type Target: Machine;
 
-    // compile of code
+    // compile of code, with a plain message as `Machine::make` (the parsed code has no spans)
     fn compile(
         source: <<Self as Compiler>::Source as Machine>::Code,
     ) -> Result<<<Self as Compiler>::Target as Machine>::Code, String>;
+    // `compile` with the source statement of each target location (see `source_map`)
+    // compilers that track locations override this and implement `compile` by it
+    fn compile_mapped(
+        source: <<Self as Compiler>::Source as Machine>::Code,
+    ) -> Result<(<<Self as Compiler>::Target as Machine>::Code, SourceMap), String> {
+        Ok((Self::compile(source)?, SourceMap::default()))
+    }
     // encoding/decoding of inputs and outputs ... how to interpret the source/target code as the same "program"?
     fn encode_ainput(
         ainput: <<Self as Compiler>::Source as Machine>::AInput,
@@ -85,4 +166,33 @@ pub trait Compiler: Sized {
     fn decode_foutput(
         output: <<Self as Compiler>::Target as Machine>::FOutput,
     ) -> Result<<<Self as Compiler>::Source as Machine>::FOutput, String>;
+
+    // names of the compiler and of its source and target models
+    fn describe() -> CompilerDescription {
+        CompilerDescription::of::<Self>()
+    }
+}
+
+// I-diagram: an interpreter for the Guest model, written as a fixed code of the Host model
+// the guest code is not compiled but passed to the interpreter as a part of the host ainput
+pub trait Interpreter: Sized {
+    type Guest: Machine;
+    type Host: Machine;
+
+    // the interpreter itself
+    fn interpreter() -> Result<<<Self as Interpreter>::Host as Machine>::Code, String>;
+    // guest code and guest ainput are encoded together into the host ainput
+    fn encode_ainput(
+        code: <<Self as Interpreter>::Guest as Machine>::Code,
+        ainput: <<Self as Interpreter>::Guest as Machine>::AInput,
+    ) -> Result<<<Self as Interpreter>::Host as Machine>::AInput, String>;
+    fn encode_rinput(
+        rinput: <<Self as Interpreter>::Guest as Machine>::RInput,
+    ) -> Result<<<Self as Interpreter>::Host as Machine>::RInput, String>;
+    fn decode_routput(
+        output: <<Self as Interpreter>::Host as Machine>::ROutput,
+    ) -> Result<<<Self as Interpreter>::Guest as Machine>::ROutput, String>;
+    fn decode_foutput(
+        output: <<Self as Interpreter>::Host as Machine>::FOutput,
+    ) -> Result<<<Self as Interpreter>::Guest as Machine>::FOutput, String>;
 }
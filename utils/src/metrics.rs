@@ -0,0 +1,243 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use crate::run::RunOutcome;
+use crate::{Compiler, Machine, Metrics, StepResult};
+
+// resources used by one run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsReport {
+    // number of successful `step` calls (a halting step is counted)
+    pub steps: usize,
+    pub halted: bool,
+    // maximum of each size over the run, the initial state included
+    pub peaks: BTreeMap<&'static str, usize>,
+    // sum of each counter over the steps
+    pub counters: BTreeMap<&'static str, usize>,
+}
+
+impl MetricsReport {
+    fn record_sizes<M: Metrics>(&mut self, machine: &M) {
+        for (name, size) in machine.sizes() {
+            let peak = self.peaks.entry(name).or_default();
+            *peak = (*peak).max(size);
+        }
+    }
+}
+
+impl Display for MetricsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "steps {}", self.steps)?;
+        if !self.halted {
+            write!(f, " (not halted)")?;
+        }
+        for (name, peak) in &self.peaks {
+            write!(f, ", peak {name} {peak}")?;
+        }
+        for (name, count) in &self.counters {
+            write!(f, ", {name} {count}")?;
+        }
+        Ok(())
+    }
+}
+
+// same as `run::run`, collecting the metrics of every state and step
+pub fn measure<M, I>(machine: M, rinputs: I, fuel: usize) -> (RunOutcome<M>, MetricsReport)
+where
+    M: Metrics,
+    I: IntoIterator<Item = M::RInput>,
+{
+    let mut rinputs = rinputs.into_iter();
+    let mut report = MetricsReport::default();
+    report.record_sizes(&machine);
+
+    let mut machine = machine;
+    let outcome = loop {
+        if report.steps >= fuel {
+            break RunOutcome::OutOfFuel {
+                snapshot: machine.snapshot(),
+            };
+        }
+        let Some(rinput) = rinputs.next() else {
+            break RunOutcome::InputExhausted {
+                snapshot: machine.snapshot(),
+            };
+        };
+        let counters = machine.counters(&rinput);
+        match machine.step(rinput) {
+            Ok(result) => {
+                report.steps += 1;
+                for (name, count) in counters {
+                    *report.counters.entry(name).or_default() += count;
+                }
+                match result {
+                    StepResult::Continue { next, .. } => {
+                        report.record_sizes(&next);
+                        machine = next;
+                    }
+                    StepResult::Halt { output } => {
+                        report.halted = true;
+                        break RunOutcome::Halted { foutput: output };
+                    }
+                }
+            }
+            Err(message) => {
+                break RunOutcome::Error {
+                    step: report.steps,
+                    message,
+                }
+            }
+        }
+    };
+    (outcome, report)
+}
+
+// metrics of the source run and of the compiled run on the same input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkCase {
+    pub ainput: String,
+    pub source: MetricsReport,
+    pub target: MetricsReport,
+}
+
+impl BenchmarkCase {
+    // target steps per source step
+    pub fn step_ratio(&self) -> Option<f64> {
+        (self.source.steps > 0).then(|| self.target.steps as f64 / self.source.steps as f64)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkReport {
+    pub cases: Vec<BenchmarkCase>,
+}
+
+impl Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for case in &self.cases {
+            writeln!(f, "ainput `{}`:", case.ainput)?;
+            writeln!(f, "  source: {}", case.source)?;
+            write!(f, "  target: {}", case.target)?;
+            match case.step_ratio() {
+                Some(ratio) => writeln!(f, " (x{ratio:.2} steps)")?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// run the source code and its compilation on each ainput, feeding `rinput` (source text) at every step
+pub fn benchmark<C>(
+    code: &str,
+    ainputs: &[&str],
+    rinput: &str,
+    fuel: (usize, usize),
+) -> Result<BenchmarkReport, String>
+where
+    C: Compiler,
+    C::Source: Metrics,
+    C::Target: Metrics,
+{
+    C::encode_rinput(<C::Source as Machine>::parse_rinput(rinput)?)?;
+    let source_rinputs =
+        || std::iter::from_fn(|| <C::Source as Machine>::parse_rinput(rinput).ok());
+    let target_rinputs = || {
+        std::iter::from_fn(|| {
+            <C::Source as Machine>::parse_rinput(rinput)
+                .ok()
+                .and_then(|rinput| C::encode_rinput(rinput).ok())
+        })
+    };
+
+    let mut cases = vec![];
+    for ainput in ainputs {
+        // code and inputs are parsed for each run since the types need not be `Clone`
+        let source = <C::Source as Machine>::make(
+            <C::Source as Machine>::parse_code(code)?,
+            <C::Source as Machine>::parse_ainput(ainput)?,
+        )?;
+        let target = <C::Target as Machine>::make(
+            C::compile(<C::Source as Machine>::parse_code(code)?)?,
+            C::encode_ainput(<C::Source as Machine>::parse_ainput(ainput)?)?,
+        )?;
+        let (_, source) = measure(source, source_rinputs(), fuel.0);
+        let (_, target) = measure(target, target_rinputs(), fuel.1);
+        cases.push(BenchmarkCase {
+            ainput: ainput.to_string(),
+            source,
+            target,
+        });
+    }
+    Ok(BenchmarkReport { cases })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_machine::{Amount, Counter};
+
+    // adds one at a time whatever the amount
+    struct Unroll;
+
+    impl Compiler for Unroll {
+        type Source = Counter;
+        type Target = Counter;
+
+        fn compile(source: usize) -> Result<usize, String> {
+            Ok(source)
+        }
+        fn encode_ainput(ainput: usize) -> Result<usize, String> {
+            Ok(ainput)
+        }
+        fn encode_rinput(_rinput: Amount) -> Result<Amount, String> {
+            Ok(Amount(1))
+        }
+        fn decode_routput(output: String) -> Result<String, String> {
+            Ok(output)
+        }
+        fn decode_foutput(output: usize) -> Result<usize, String> {
+            Ok(output)
+        }
+    }
+
+    #[test]
+    fn measure_counts_steps_peaks_and_counters() {
+        let machine = Counter::make(10, 0).unwrap();
+        let (outcome, report) = measure(machine, std::iter::repeat(Amount(3)), 100);
+        assert!(outcome.is_halted());
+        // 0 -> 3 -> 6 -> 9, then the halting step
+        assert_eq!(report.steps, 4);
+        assert!(report.halted);
+        assert_eq!(report.peaks.get("count"), Some(&9));
+        assert_eq!(report.counters.get("add"), Some(&12));
+        assert_eq!(report.to_string(), "steps 4, peak count 9, add 12");
+
+        let machine = Counter::make(10, 0).unwrap();
+        let (outcome, report) = measure(machine, std::iter::repeat(Amount(3)), 2);
+        assert!(matches!(
+            outcome,
+            RunOutcome::OutOfFuel { snapshot: (6, 10) }
+        ));
+        assert!(!report.halted);
+        assert_eq!(
+            report.to_string(),
+            "steps 2 (not halted), peak count 6, add 6"
+        );
+    }
+
+    #[test]
+    fn benchmark_compares_source_and_target() {
+        let report = benchmark::<Unroll>("9", &["0", "8"], "3", (100, 100)).unwrap();
+        let steps = report
+            .cases
+            .iter()
+            .map(|case| (case.source.steps, case.target.steps))
+            .collect::<Vec<_>>();
+        assert_eq!(steps, vec![(3, 9), (1, 1)]);
+        assert_eq!(report.cases[0].step_ratio(), Some(3.0));
+        assert!(report.to_string().contains("(x3.00 steps)"));
+
+        assert!(benchmark::<Unroll>("0", &["1"], "3", (100, 100)).is_err());
+    }
+}
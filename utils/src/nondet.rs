@@ -0,0 +1,415 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{NondetMachine, RenderBlock, TextCodec};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Bfs,
+    Dfs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExploreOptions {
+    pub strategy: Strategy,
+    // maximum number of distinct states
+    pub max_states: usize,
+    // states at this depth are not expanded
+    pub max_depth: Option<usize>,
+}
+
+impl ExploreOptions {
+    pub fn bfs(max_states: usize) -> Self {
+        ExploreOptions {
+            strategy: Strategy::Bfs,
+            max_states,
+            max_depth: None,
+        }
+    }
+    pub fn dfs(max_states: usize) -> Self {
+        ExploreOptions {
+            strategy: Strategy::Dfs,
+            max_states,
+            max_depth: None,
+        }
+    }
+    pub fn with_max_depth(self, max_depth: usize) -> Self {
+        ExploreOptions {
+            max_depth: Some(max_depth),
+            ..self
+        }
+    }
+}
+
+pub enum StateKind<M: NondetMachine> {
+    Halted(M::FOutput),
+    // not halted and without successors
+    Stuck,
+    // all successors are in the graph
+    Expanded,
+    // (some) successors are missing because of `max_states` or `max_depth`
+    Frontier,
+    // `successors` failed
+    Error(String),
+}
+
+pub struct ExploredState<M: NondetMachine> {
+    pub snapshot: M::SnapShot,
+    // depth at which the state was found (the shortest one for BFS)
+    pub depth: usize,
+    pub kind: StateKind<M>,
+}
+
+pub struct ExploredEdge<M: NondetMachine> {
+    pub from: usize,
+    pub to: usize,
+    pub choice: M::Choice,
+}
+
+// explored part of the state graph, states[0] is the initial state
+pub struct Exploration<M: NondetMachine> {
+    pub states: Vec<ExploredState<M>>,
+    pub edges: Vec<ExploredEdge<M>>,
+    // number of transitions into an already found state
+    pub revisits: usize,
+}
+
+// explore the states reachable from `machine`, identifying states by `state_key`
+pub fn explore<M: NondetMachine>(machine: M, options: ExploreOptions) -> Exploration<M> {
+    let mut keys = HashMap::from([(machine.state_key(), 0)]);
+    let mut states = vec![ExploredState {
+        snapshot: machine.snapshot(),
+        depth: 0,
+        kind: StateKind::Frontier,
+    }];
+    let mut edges = vec![];
+    let mut revisits = 0;
+    let mut pending = VecDeque::from([(0, machine)]);
+
+    loop {
+        let next = match options.strategy {
+            Strategy::Bfs => pending.pop_front(),
+            Strategy::Dfs => pending.pop_back(),
+        };
+        let Some((from, machine)) = next else {
+            break;
+        };
+        if let Some(output) = machine.halted() {
+            states[from].kind = StateKind::Halted(output);
+            continue;
+        }
+        let depth = states[from].depth;
+        if options.max_depth.is_some_and(|max| depth >= max) {
+            continue;
+        }
+        let successors = match machine.successors() {
+            Ok(successors) => successors,
+            Err(message) => {
+                states[from].kind = StateKind::Error(message);
+                continue;
+            }
+        };
+
+        let mut kind = if successors.is_empty() {
+            StateKind::Stuck
+        } else {
+            StateKind::Expanded
+        };
+        let mut found = vec![];
+        for (choice, next) in successors {
+            let key = next.state_key();
+            let to = match keys.get(&key) {
+                Some(&to) => {
+                    revisits += 1;
+                    to
+                }
+                None if states.len() >= options.max_states => {
+                    kind = StateKind::Frontier;
+                    continue;
+                }
+                None => {
+                    let to = states.len();
+                    keys.insert(key, to);
+                    states.push(ExploredState {
+                        snapshot: next.snapshot(),
+                        depth: depth + 1,
+                        kind: StateKind::Frontier,
+                    });
+                    found.push((to, next));
+                    to
+                }
+            };
+            edges.push(ExploredEdge { from, to, choice });
+        }
+        states[from].kind = kind;
+        // DFS takes the first choice first
+        match options.strategy {
+            Strategy::Bfs => pending.extend(found),
+            Strategy::Dfs => pending.extend(found.into_iter().rev()),
+        }
+    }
+
+    Exploration {
+        states,
+        edges,
+        revisits,
+    }
+}
+
+impl<M: NondetMachine> Exploration<M> {
+    pub fn halting_outputs(&self) -> impl Iterator<Item = (usize, &M::FOutput)> {
+        self.states
+            .iter()
+            .enumerate()
+            .filter_map(|(i, state)| match &state.kind {
+                StateKind::Halted(output) => Some((i, output)),
+                _ => None,
+            })
+    }
+
+    // every reachable state was expanded
+    pub fn is_complete(&self) -> bool {
+        !self
+            .states
+            .iter()
+            .any(|state| matches!(state.kind, StateKind::Frontier))
+    }
+
+    // states from which a run can go on forever: those on a cycle
+    pub fn cyclic_states(&self) -> Vec<usize> {
+        let on_cycle = on_cycle(self.states.len(), &self.edges);
+        (0..self.states.len()).filter(|&i| on_cycle[i]).collect()
+    }
+
+    // states on a cycle, and states the exploration did not finish
+    pub fn nontermination_candidates(&self) -> Vec<usize> {
+        let on_cycle = on_cycle(self.states.len(), &self.edges);
+        (0..self.states.len())
+            .filter(|&i| on_cycle[i] || matches!(self.states[i].kind, StateKind::Frontier))
+            .collect()
+    }
+
+    // the state graph, with states on a cycle highlighted
+    pub fn render(&self) -> RenderBlock {
+        let on_cycle = on_cycle(self.states.len(), &self.edges);
+        let nodes = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| {
+                let (label, class) = match &state.kind {
+                    StateKind::Halted(output) => (format!("{i}: {}", output.print()), "halted"),
+                    StateKind::Stuck => (format!("{i}: stuck"), "stuck"),
+                    StateKind::Expanded => (i.to_string(), ""),
+                    StateKind::Frontier => (format!("{i}: ..."), "frontier"),
+                    StateKind::Error(message) => (format!("{i}: {message}"), "error"),
+                };
+                let mut node = crate::render_node!(i, inner: crate::render_text!(label));
+                let classes = [class, if on_cycle[i] { "highlight" } else { "" }]
+                    .into_iter()
+                    .filter(|class| !class.is_empty())
+                    .collect::<Vec<_>>();
+                if !classes.is_empty() {
+                    node.class_name = Some(classes.join(" "));
+                }
+                node
+            })
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .map(|edge| crate::render_edge!(edge.from => edge.to, label: edge.choice.print()))
+            .collect();
+        crate::render_graph!(nodes: nodes, edges: edges, title: "state space")
+    }
+}
+
+// membership of a strongly connected component with a cycle (Tarjan's algorithm, without recursion)
+fn on_cycle<M: NondetMachine>(len: usize, edges: &[ExploredEdge<M>]) -> Vec<bool> {
+    let mut successors = vec![vec![]; len];
+    let mut on_cycle = vec![false; len];
+    for edge in edges {
+        successors[edge.from].push(edge.to);
+        if edge.from == edge.to {
+            on_cycle[edge.from] = true;
+        }
+    }
+
+    let mut index = vec![usize::MAX; len];
+    let mut low = vec![0; len];
+    let mut on_stack = vec![false; len];
+    let mut stack = vec![];
+    let mut counter = 0;
+    for root in 0..len {
+        if index[root] != usize::MAX {
+            continue;
+        }
+        index[root] = counter;
+        low[root] = counter;
+        counter += 1;
+        stack.push(root);
+        on_stack[root] = true;
+        // (state, number of its successors already visited)
+        let mut calls = vec![(root, 0)];
+        while let Some(&(v, visited)) = calls.last() {
+            if let Some(&w) = successors[v].get(visited) {
+                calls.last_mut().unwrap().1 += 1;
+                if index[w] == usize::MAX {
+                    index[w] = counter;
+                    low[w] = counter;
+                    counter += 1;
+                    stack.push(w);
+                    on_stack[w] = true;
+                    calls.push((w, 0));
+                } else if on_stack[w] {
+                    low[v] = low[v].min(index[w]);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(u, _)) = calls.last() {
+                low[u] = low[u].min(low[v]);
+            }
+            if low[v] == index[v] {
+                let mut component = vec![];
+                loop {
+                    let w = stack.pop().unwrap();
+                    on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                if component.len() > 1 {
+                    for w in component {
+                        on_cycle[w] = true;
+                    }
+                }
+            }
+        }
+    }
+    on_cycle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderState;
+
+    // x -> x + 1 or x -> 2x (mod `modulo`), halting at 0
+    struct Walk {
+        x: usize,
+        modulo: usize,
+    }
+
+    impl NondetMachine for Walk {
+        type Code = usize;
+        type AInput = usize;
+        type FOutput = usize;
+        type Choice = String;
+        type SnapShot = (usize, usize);
+
+        fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String> {
+            Ok(Walk {
+                x: ainput,
+                modulo: code,
+            })
+        }
+        fn halted(&self) -> Option<Self::FOutput> {
+            (self.x == 0).then_some(0)
+        }
+        fn successors(&self) -> Result<Vec<(Self::Choice, Self)>, String> {
+            let modulo = self.modulo;
+            Ok(vec![
+                (
+                    "inc".to_string(),
+                    Walk {
+                        x: (self.x + 1) % modulo,
+                        modulo,
+                    },
+                ),
+                (
+                    "dbl".to_string(),
+                    Walk {
+                        x: (self.x * 2) % modulo,
+                        modulo,
+                    },
+                ),
+            ])
+        }
+        fn snapshot(&self) -> Self::SnapShot {
+            (self.x, self.modulo)
+        }
+        fn restore((x, modulo): Self::SnapShot) -> Self {
+            Walk { x, modulo }
+        }
+        fn render(_snapshot: Self::SnapShot) -> RenderState {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn bfs_finds_every_state_once() {
+        let result = explore(Walk::make(5, 1).unwrap(), ExploreOptions::bfs(100));
+        let xs = result
+            .states
+            .iter()
+            .map(|s| s.snapshot.0)
+            .collect::<Vec<_>>();
+        assert_eq!(xs, vec![1, 2, 3, 4, 0]);
+        assert!(result.is_complete());
+        assert_eq!(result.halting_outputs().count(), 1);
+        // 1 -> 2 -> 4 -> 3 -> 1 is a cycle
+        assert_eq!(result.cyclic_states(), vec![0, 1, 2, 3]);
+        assert_eq!(result.revisits, result.edges.len() - 4);
+        // depth of 0 is the shortest: 1 -> 2 -> 4 -> 0
+        assert_eq!(result.states[4].depth, 3);
+    }
+
+    #[test]
+    fn dfs_goes_deep_before_wide() {
+        let xs = |options| {
+            let result = explore(Walk::make(100, 1).unwrap(), options);
+            result
+                .states
+                .iter()
+                .map(|s| s.snapshot.0)
+                .collect::<Vec<_>>()
+        };
+        // both find 3 and 4 from 2, then DFS goes on from 3 and BFS turns to 4
+        assert_eq!(xs(ExploreOptions::bfs(6)), vec![1, 2, 3, 4, 6, 5]);
+        assert_eq!(xs(ExploreOptions::dfs(6)), vec![1, 2, 3, 4, 6, 7]);
+    }
+
+    #[test]
+    fn bounds_leave_a_frontier() {
+        let result = explore(
+            Walk::make(100, 1).unwrap(),
+            ExploreOptions::bfs(100).with_max_depth(2),
+        );
+        assert!(!result.is_complete());
+        let candidates = result.nontermination_candidates();
+        let depths = candidates
+            .iter()
+            .map(|&i| result.states[i].depth)
+            .collect::<Vec<_>>();
+        assert!(depths.iter().all(|&d| d == 2));
+
+        let result = explore(Walk::make(100, 1).unwrap(), ExploreOptions::dfs(3));
+        assert_eq!(result.states.len(), 3);
+        assert!(!result.is_complete());
+    }
+
+    #[test]
+    fn render_shows_the_state_graph() {
+        let result = explore(Walk::make(3, 2).unwrap(), ExploreOptions::bfs(100));
+        let RenderBlock::Graph(graph) = result.render() else {
+            panic!("expected a graph block");
+        };
+        assert_eq!(graph.nodes.len(), result.states.len());
+        assert_eq!(graph.edges.len(), result.edges.len());
+        assert!(graph
+            .nodes
+            .iter()
+            .any(|node| node.class_name.as_deref() == Some("halted")));
+    }
+}
@@ -1,13 +1,39 @@
+use crate::diagnostic::{Diagnostic, Span};
+use crate::pretty::Doc;
+
 // parse(print(v)) == v
 // print(parse(s)) ~= s
 pub trait TextCodec: Sized {
     fn parse(text: &str) -> Result<Self, String>;
+    // same as `parse`, but the error may point into `text`
+    // parsers that track positions override this and implement `parse` by it
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        Self::parse(text).map_err(Diagnostic::from)
+    }
+    // every error in `text`, for parsers that recover from errors
+    // such parsers implement `parse_spanned` by `diagnostic::summarize` of this
+    fn parse_all(text: &str) -> Result<Self, Vec<Diagnostic>> {
+        Self::parse_spanned(text).map_err(|diagnostic| vec![diagnostic])
+    }
+    // spans of the nodes of `text` that source maps point to (see `source_map`)
+    // formats without source maps have none
+    fn node_spans(_text: &str) -> Result<Vec<Span>, String> {
+        Ok(vec![])
+    }
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result;
     fn print(&self) -> String {
         let mut s = String::new();
         self.write_fmt(&mut s).unwrap();
         s
     }
+    // layout for `print_pretty`, the default is the one line `print`
+    fn to_doc(&self) -> Doc {
+        Doc::text(self.print())
+    }
+    // `print` for lines of the given width, also parse(print_pretty(v, width)) == v
+    fn print_pretty(&self, width: usize) -> String {
+        self.to_doc().render(width)
+    }
 }
 
 pub trait ParseTextCodec {
@@ -45,9 +71,13 @@ impl TextCodec for usize {
 }
 
 impl TextCodec for crate::data::number::Number {
+    // decimal, or hex with "0x" (of any length)
     fn parse(text: &str) -> Result<Self, String> {
-        let n = text.trim().parse::<usize>().map_err(|e| e.to_string())?;
-        Ok(crate::data::number::Number::from(n))
+        let text = text.trim();
+        match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => crate::data::number::Number::from_hex_str(hex),
+            None => crate::data::number::Number::from_decimal_str(text),
+        }
     }
     fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
         write!(f, "{}", self.to_decimal_string())
@@ -81,28 +111,46 @@ where
     T: TextCodec,
 {
     fn parse(text: &str) -> Result<Self, String> {
+        Self::parse_spanned(text).map_err(String::from)
+    }
+
+    fn parse_spanned(text: &str) -> Result<Self, Diagnostic> {
+        let start = text.len() - text.trim_start().len();
         let trimed = text.trim();
         if !trimed.starts_with('(') || !trimed.ends_with(')') {
-            return Err("Expected to start with '(' and end with ')'".to_string());
+            return Err(Diagnostic::error(
+                Span::new(start, start + trimed.len()),
+                "Expected to start with '(' and end with ')'",
+            ));
         }
+        // byte offset of `inner` in `text`
+        let mut offset = start + 1;
         let mut inner = &trimed[1..trimed.len() - 1];
         let mut v = Vec::new();
 
         loop {
             let until_comma_or_end = inner.find(',').unwrap_or(inner.len());
             let (item_str, rest) = inner.split_at(until_comma_or_end);
+            let item_start = offset + (item_str.len() - item_str.trim_start().len());
             let item_str = item_str.trim();
             if !item_str.is_empty() {
-                let item = T::parse(item_str)?;
+                // an item error without position points to the whole item
+                let item = T::parse_spanned(item_str)
+                    .map_err(|e| e.or_span(Span::new(0, item_str.len())).offset(item_start))?;
                 v.push(item);
             }
+            offset += until_comma_or_end + (rest.len() - rest.trim_start().len());
             inner = rest.trim_start();
             if inner.is_empty() {
                 break;
             }
             if !inner.starts_with(',') {
-                return Err("Expected ',' between items".to_string());
+                return Err(Diagnostic::error(
+                    Span::at(offset),
+                    "Expected ',' between items",
+                ));
             }
+            offset += 1 + (inner.len() - 1 - inner[1..].trim_start().len());
             inner = inner[1..].trim_start();
         }
 
@@ -120,3 +168,19 @@ where
         write!(f, ")")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vec_errors_point_into_the_text() {
+        let text = "( 1, x ,3)";
+        let err = Vec::<usize>::parse_spanned(text).unwrap_err();
+        assert_eq!(err.span, Some(Span::new(5, 6)));
+        // items without their own position are pointed as a whole
+        let err = Vec::<usize>::parse_spanned("(1,  2 3)").unwrap_err();
+        assert_eq!(err.span, Some(Span::new(5, 8)));
+        assert_eq!(Vec::<usize>::parse("(1, 2,)"), Ok(vec![1, 2]));
+    }
+}
@@ -0,0 +1,233 @@
+use std::ops::Add;
+
+use crate::TextCodec;
+
+// document of the pretty printer (Wadler's "prettier printer")
+// a `Group` is laid out on one line if it fits in the width, otherwise its own `Line`s break
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    Nil,
+    Text(String),
+    // a newline, or `flat` when the enclosing group is on one line
+    Line { flat: &'static str },
+    // always a newline, the enclosing groups never fit
+    HardLine,
+    Concat(Vec<Doc>),
+    // the newlines inside are indented by this many more columns
+    Nest(usize, Box<Doc>),
+    Group(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Doc::Text(text.into())
+    }
+
+    // a space or a newline
+    pub fn line() -> Self {
+        Doc::Line { flat: " " }
+    }
+
+    // nothing or a newline
+    pub fn softline() -> Self {
+        Doc::Line { flat: "" }
+    }
+
+    pub fn hardline() -> Self {
+        Doc::HardLine
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Self {
+        Doc::Concat(docs.into_iter().collect())
+    }
+
+    // `docs` separated by `sep`
+    pub fn join(docs: impl IntoIterator<Item = Doc>, sep: Doc) -> Self {
+        let mut out = vec![];
+        for (i, doc) in docs.into_iter().enumerate() {
+            if i > 0 {
+                out.push(sep.clone());
+            }
+            out.push(doc);
+        }
+        Doc::Concat(out)
+    }
+
+    pub fn nest(self, indent: usize) -> Self {
+        Doc::Nest(indent, Box::new(self))
+    }
+
+    pub fn group(self) -> Self {
+        Doc::Group(Box::new(self))
+    }
+
+    // `open` doc `close`, with the inside indented on its own lines when it does not fit
+    pub fn bracket(open: &str, doc: Doc, close: &str, indent: usize) -> Self {
+        Doc::concat([
+            Doc::text(open),
+            Doc::concat([Doc::softline(), doc]).nest(indent),
+            Doc::softline(),
+            Doc::text(close),
+        ])
+        .group()
+    }
+
+    // lay out for lines of at most `width` columns (where possible)
+    pub fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+        let mut column = 0;
+        let mut stack = vec![(0, Mode::Break, self)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Nil => {}
+                Doc::Text(text) => {
+                    out.push_str(text);
+                    column += text.chars().count();
+                }
+                Doc::Line { flat } if mode == Mode::Flat => {
+                    out.push_str(flat);
+                    column += flat.chars().count();
+                }
+                Doc::Line { .. } | Doc::HardLine => {
+                    // no trailing spaces before a newline
+                    out.truncate(out.trim_end_matches(' ').len());
+                    out.push('\n');
+                    out.extend(std::iter::repeat_n(' ', indent));
+                    column = indent;
+                }
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+                Doc::Nest(more, doc) => stack.push((indent + more, mode, doc)),
+                Doc::Group(doc) => {
+                    let fits = mode == Mode::Flat
+                        || fits(
+                            width.saturating_sub(column),
+                            (indent, Mode::Flat, doc),
+                            &stack,
+                        );
+                    let mode = if fits { Mode::Flat } else { Mode::Break };
+                    stack.push((indent, mode, doc));
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Add for Doc {
+    type Output = Doc;
+    fn add(self, rhs: Doc) -> Doc {
+        match self {
+            Doc::Concat(mut docs) => {
+                docs.push(rhs);
+                Doc::Concat(docs)
+            }
+            doc => Doc::Concat(vec![doc, rhs]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+// whether `next` and then `rest` (up to its first newline) fit in `remaining` columns
+fn fits(remaining: usize, next: (usize, Mode, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut remaining = remaining;
+    let mut work = vec![next];
+    let mut rest = rest.iter().rev();
+    while let Some((indent, mode, doc)) = work.pop().or_else(|| rest.next().copied()) {
+        let used = match doc {
+            Doc::Nil => 0,
+            Doc::Text(text) => text.chars().count(),
+            Doc::Line { flat } if mode == Mode::Flat => flat.chars().count(),
+            Doc::Line { .. } => return true,
+            Doc::HardLine => return mode == Mode::Break,
+            Doc::Concat(docs) => {
+                work.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                0
+            }
+            Doc::Nest(more, doc) => {
+                work.push((indent + more, mode, doc));
+                0
+            }
+            Doc::Group(doc) => {
+                work.push((indent, mode, doc));
+                0
+            }
+        };
+        match remaining.checked_sub(used) {
+            Some(left) => remaining = left,
+            None => return false,
+        }
+    }
+    true
+}
+
+// widths used by `check_roundtrip`: everything broken, narrow, usual and one line
+pub const ROUNDTRIP_WIDTHS: [usize; 4] = [0, 20, 80, usize::MAX];
+
+// parse(print(v)) == v and parse(print_pretty(v, w)) == v, compared by their `print` forms
+pub fn check_roundtrip<T: TextCodec>(value: &T) -> Result<(), String> {
+    let expected = value.print();
+    let texts = std::iter::once(expected.clone()).chain(
+        ROUNDTRIP_WIDTHS
+            .iter()
+            .map(|&width| value.print_pretty(width)),
+    );
+    for text in texts {
+        let reparsed = T::parse(&text).map_err(|e| format!("cannot parse\n{text}\n: {e}"))?;
+        let printed = reparsed.print();
+        if printed != expected {
+            return Err(format!(
+                "round trip changed the value\n{text}\n: {printed} != {expected}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // f(a, b, ...) laid out as a call
+    fn call(name: &str, args: Vec<Doc>) -> Doc {
+        Doc::text(name) + Doc::bracket("(", Doc::join(args, Doc::text(",") + Doc::line()), ")", 2)
+    }
+
+    #[test]
+    fn group_breaks_only_when_too_wide() {
+        let doc = call(
+            "f",
+            vec![
+                Doc::text("aaaa"),
+                call("g", vec![Doc::text("b"), Doc::text("c")]),
+            ],
+        );
+        assert_eq!(doc.render(80), "f(aaaa, g(b, c))");
+        assert_eq!(doc.render(12), "f(\n  aaaa,\n  g(b, c)\n)");
+        assert_eq!(doc.render(0), "f(\n  aaaa,\n  g(\n    b,\n    c\n  )\n)");
+    }
+
+    #[test]
+    fn hardline_breaks_the_enclosing_group() {
+        let doc =
+            (Doc::text("a") + Doc::line() + Doc::text("b") + Doc::hardline() + Doc::text("c"))
+                .group();
+        assert_eq!(doc.render(80), "a\nb\nc");
+        let doc = (Doc::text("a") + Doc::line() + Doc::text("b"))
+            .group()
+            .nest(2);
+        assert_eq!(doc.render(usize::MAX), "a b");
+    }
+
+    #[test]
+    fn rest_of_the_line_counts_for_fitting() {
+        // the group itself fits, but not with the text after it
+        let doc = (Doc::text("a") + Doc::line() + Doc::text("b")).group() + Doc::text("cccc");
+        assert_eq!(doc.render(7), "a bcccc");
+        assert_eq!(doc.render(6), "a\nbcccc");
+    }
+}
@@ -12,6 +12,8 @@ pub enum RenderBlock {
     Table(RenderTableBlock),
     #[serde(rename = "container")]
     Container(RenderContainerBlock),
+    #[serde(rename = "graph")]
+    Graph(RenderGraphBlock),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -57,6 +59,65 @@ pub struct RenderContainerBlock {
     pub class_name: Option<String>,
 }
 
+// nodes and edges laid out by the renderer
+// - `orientation` is the direction of edges (vertical: top to bottom, the default)
+// - `groups` are boxes around the nodes naming them, and may be nested by `parent`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderGraphBlock {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub nodes: Vec<RenderGraphNode>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<RenderGraphEdge>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub groups: Vec<RenderGraphGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<RenderOrientation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+}
+
+// a node is shown by `inner`, or by its id if there is none
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderGraphNode {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inner: Option<Box<RenderBlock>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ports: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+}
+
+// `fromPort` / `toPort` name one of the `ports` of the node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderGraphEdge {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "fromPort", skip_serializing_if = "Option::is_none")]
+    pub from_port: Option<String>,
+    #[serde(rename = "toPort", skip_serializing_if = "Option::is_none")]
+    pub to_port: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderGraphGroup {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "className", skip_serializing_if = "Option::is_none")]
+    pub class_name: Option<String>,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum RenderOrientation {
@@ -210,6 +271,137 @@ macro_rules! render_container {
     };
 }
 
+#[macro_export]
+macro_rules! render_node {
+    ($id:expr $(, $key:ident : $value:expr )* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut node = $crate::RenderGraphNode {
+            id: ($id).to_string(),
+            inner: None,
+            ports: vec![],
+            group: None,
+            class_name: None,
+        };
+        $( $crate::render_node!(@set node, $key, $value); )*
+        node
+    }};
+    (@set $node:ident, inner, $value:expr) => {
+        $node.inner = Some(Box::new($value));
+    };
+    (@set $node:ident, ports, $value:expr) => {
+        $node.ports = ($value).into_iter().map(|port| port.to_string()).collect();
+    };
+    (@set $node:ident, group, $value:expr) => {
+        $node.group = Some(($value).to_string());
+    };
+    (@set $node:ident, class, $value:expr) => {
+        $node.class_name = Some(($value).to_string());
+    };
+}
+
+#[macro_export]
+macro_rules! render_edge {
+    ($from:expr => $to:expr $(, $key:ident : $value:expr )* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut edge = $crate::RenderGraphEdge {
+            from: ($from).to_string(),
+            to: ($to).to_string(),
+            from_port: None,
+            to_port: None,
+            label: None,
+            class_name: None,
+        };
+        $( $crate::render_edge!(@set edge, $key, $value); )*
+        edge
+    }};
+    (@set $edge:ident, from_port, $value:expr) => {
+        $edge.from_port = Some(($value).to_string());
+    };
+    (@set $edge:ident, to_port, $value:expr) => {
+        $edge.to_port = Some(($value).to_string());
+    };
+    (@set $edge:ident, label, $value:expr) => {
+        $edge.label = Some(($value).to_string());
+    };
+    (@set $edge:ident, class, $value:expr) => {
+        $edge.class_name = Some(($value).to_string());
+    };
+}
+
+#[macro_export]
+macro_rules! render_group {
+    ($id:expr $(, $key:ident : $value:expr )* $(,)?) => {{
+        #[allow(unused_mut)]
+        let mut group = $crate::RenderGraphGroup {
+            id: ($id).to_string(),
+            parent: None,
+            title: None,
+            class_name: None,
+        };
+        $( $crate::render_group!(@set group, $key, $value); )*
+        group
+    }};
+    (@set $group:ident, parent, $value:expr) => {
+        $group.parent = Some(($value).to_string());
+    };
+    (@set $group:ident, title, $value:expr) => {
+        $group.title = Some(($value).to_string());
+    };
+    (@set $group:ident, class, $value:expr) => {
+        $group.class_name = Some(($value).to_string());
+    };
+}
+
+#[macro_export]
+macro_rules! render_graph {
+    (
+        nodes: [$($node:expr),* $(,)?],
+        edges: [$($edge:expr),* $(,)?]
+        $(, $key:ident : $value:expr )* $(,)?
+    ) => {{
+        #[allow(unused_mut)]
+        let mut block = $crate::RenderGraphBlock {
+            nodes: vec![$($node),*],
+            edges: vec![$($edge),*],
+            groups: vec![],
+            orientation: None,
+            title: None,
+            class_name: None,
+        };
+        $( $crate::render_graph!(@set block, $key, $value); )*
+        $crate::RenderBlock::Graph(block)
+    }};
+    (
+        nodes: $nodes:expr,
+        edges: $edges:expr
+        $(, $key:ident : $value:expr )* $(,)?
+    ) => {{
+        #[allow(unused_mut)]
+        let mut block = $crate::RenderGraphBlock {
+            nodes: $nodes,
+            edges: $edges,
+            groups: vec![],
+            orientation: None,
+            title: None,
+            class_name: None,
+        };
+        $( $crate::render_graph!(@set block, $key, $value); )*
+        $crate::RenderBlock::Graph(block)
+    }};
+    (@set $block:ident, groups, $value:expr) => {
+        $block.groups = $value;
+    };
+    (@set $block:ident, orientation, $value:expr) => {
+        $block.orientation = Some($value);
+    };
+    (@set $block:ident, title, $value:expr) => {
+        $block.title = Some(($value).to_string());
+    };
+    (@set $block:ident, class, $value:expr) => {
+        $block.class_name = Some(($value).to_string());
+    };
+}
+
 #[cfg(test)]
 mod render_schema_tests {
     use super::*;
@@ -252,4 +444,33 @@ mod render_schema_tests {
         let value = serde_json::to_value(state).expect("serialize should succeed");
         assert!(value.is_array());
     }
+
+    #[test]
+    fn render_graph_shape_matches_renderer_contract() {
+        let state = render_state![render_graph!(
+            nodes: [
+                render_node!("q0", ports: ["in", "out"], group: "g", class: "active"),
+                render_node!("q1", inner: render_text!("accept".to_string())),
+            ],
+            edges: [render_edge!("q0" => "q1", from_port: "out", label: "a/b,R")],
+            groups: vec![render_group!("g", title: "states")],
+            orientation: RenderOrientation::Horizontal
+        )];
+        let value = serde_json::to_value(&state).expect("serialize should succeed");
+        assert_eq!(
+            value,
+            json!([{
+                "kind": "graph",
+                "nodes": [
+                    { "id": "q0", "ports": ["in", "out"], "group": "g", "className": "active" },
+                    { "id": "q1", "inner": { "kind": "text", "text": "accept" } }
+                ],
+                "edges": [{ "from": "q0", "to": "q1", "fromPort": "out", "label": "a/b,R" }],
+                "groups": [{ "id": "g", "title": "states" }],
+                "orientation": "horizontal"
+            }])
+        );
+        let back: RenderState = serde_json::from_value(value).expect("deserialize should succeed");
+        assert_eq!(back, state);
+    }
 }
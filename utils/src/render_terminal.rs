@@ -0,0 +1,456 @@
+use crate::render::{
+    RenderBlock, RenderContainerBlock, RenderDisplay, RenderGraphBlock, RenderOrientation,
+    RenderState, RenderTableBlock,
+};
+
+// text version of renderer.js for terminals
+// - tables and `display: block` container items are drawn in boxes, titles are put above the block
+// - the `highlight` class is a coloured background with `Ansi`;
+//   with `Plain` a highlighted text is put in `[ ]` and a highlighted row is marked by `>`
+// - graphs are listed as their nodes and edges
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalStyle {
+    Ansi,
+    Plain,
+}
+
+const HIGHLIGHT: &str = "highlight";
+
+pub fn render_terminal(state: &RenderState, style: TerminalStyle) -> String {
+    let blocks = state
+        .iter()
+        .map(|block| render_block(block, style))
+        .collect();
+    vstack(blocks, 1).print(style)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    None,
+    Highlight,
+    Title,
+}
+
+// '\0' is the second column of a wide character
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    ch: char,
+    mark: Mark,
+}
+
+// lines of cells, not padded to the same width
+#[derive(Debug, Clone, Default)]
+struct Canvas {
+    lines: Vec<Vec<Cell>>,
+}
+
+impl Canvas {
+    fn text(text: &str, mark: Mark) -> Canvas {
+        let lines = text
+            .split('\n')
+            .map(|line| {
+                let mut cells = vec![];
+                for ch in line.chars() {
+                    cells.push(Cell { ch, mark });
+                    if char_width(ch) == 2 {
+                        cells.push(Cell { ch: '\0', mark });
+                    }
+                }
+                cells
+            })
+            .collect();
+        Canvas { lines }
+    }
+
+    fn width(&self) -> usize {
+        self.lines.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    // the line `row` padded to `width`
+    fn line(&self, row: usize, width: usize) -> Vec<Cell> {
+        let mut line = self.lines.get(row).cloned().unwrap_or_default();
+        line.resize(width.max(line.len()), space());
+        line
+    }
+
+    fn mark(mut self, mark: Mark) -> Canvas {
+        let width = self.width();
+        for line in &mut self.lines {
+            line.resize(width, space());
+            for cell in line {
+                cell.mark = mark;
+            }
+        }
+        self
+    }
+
+    fn print(&self, style: TerminalStyle) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            let end = line
+                .iter()
+                .rposition(|cell| cell.ch != ' ' || cell.mark != Mark::None)
+                .map_or(0, |last| last + 1);
+            let mut mark = Mark::None;
+            for cell in &line[..end] {
+                if style == TerminalStyle::Ansi && cell.mark != mark {
+                    out.push_str(match cell.mark {
+                        Mark::None => "\x1b[0m",
+                        Mark::Highlight => "\x1b[0;30;101m",
+                        Mark::Title => "\x1b[0;1m",
+                    });
+                    mark = cell.mark;
+                }
+                if cell.ch != '\0' {
+                    out.push(cell.ch);
+                }
+            }
+            if mark != Mark::None {
+                out.push_str("\x1b[0m");
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+fn space() -> Cell {
+    Cell {
+        ch: ' ',
+        mark: Mark::None,
+    }
+}
+
+// columns of a character on the terminal, 2 for the common wide (CJK, emoji) ranges
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115f
+        | 0x2e80..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+// `gap` empty lines between the parts
+fn vstack(parts: Vec<Canvas>, gap: usize) -> Canvas {
+    let mut lines = vec![];
+    for (i, part) in parts.into_iter().enumerate() {
+        if i > 0 {
+            lines.extend(std::iter::repeat_with(Vec::new).take(gap));
+        }
+        lines.extend(part.lines);
+    }
+    Canvas { lines }
+}
+
+// top aligned, `gap` columns between the parts
+fn hstack(parts: Vec<Canvas>, gap: usize) -> Canvas {
+    let height = parts.iter().map(Canvas::height).max().unwrap_or(0);
+    let lines = (0..height)
+        .map(|row| {
+            let mut line = vec![];
+            for (i, part) in parts.iter().enumerate() {
+                if i > 0 {
+                    line.extend(std::iter::repeat_n(space(), gap));
+                }
+                line.extend(part.line(row, part.width()));
+            }
+            line
+        })
+        .collect();
+    Canvas { lines }
+}
+
+struct Borders {
+    horizontal: char,
+    vertical: char,
+    // top left, top, top right; middle ...; bottom ...
+    corners: [[char; 3]; 3],
+}
+
+fn borders(style: TerminalStyle) -> Borders {
+    match style {
+        TerminalStyle::Ansi => Borders {
+            horizontal: '─',
+            vertical: '│',
+            corners: [['┌', '┬', '┐'], ['├', '┼', '┤'], ['└', '┴', '┘']],
+        },
+        TerminalStyle::Plain => Borders {
+            horizontal: '-',
+            vertical: '|',
+            corners: [['+', '+', '+'], ['+', '+', '+'], ['+', '+', '+']],
+        },
+    }
+}
+
+fn border_cell(ch: char) -> Cell {
+    Cell {
+        ch,
+        mark: Mark::None,
+    }
+}
+
+// `rows` of cells drawn in boxes, with a rule after the first row if `header`
+// rows paired with true are highlighted
+fn grid(rows: Vec<(Vec<Canvas>, bool)>, header: bool, style: TerminalStyle) -> Canvas {
+    if rows.is_empty() {
+        return Canvas::default();
+    }
+    let borders = borders(style);
+    let columns = rows.iter().map(|(cells, _)| cells.len()).max().unwrap_or(0);
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|(cells, _)| cells.get(column))
+                .map(Canvas::width)
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let rule = |[left, middle, right]: [char; 3]| {
+        let mut line = vec![border_cell(left)];
+        for (i, width) in widths.iter().enumerate() {
+            if i > 0 {
+                line.push(border_cell(middle));
+            }
+            line.extend(std::iter::repeat_n(
+                border_cell(borders.horizontal),
+                width + 2,
+            ));
+        }
+        line.push(border_cell(right));
+        line
+    };
+    let mut lines = vec![rule(borders.corners[0])];
+    for (index, (cells, highlighted)) in rows.iter().enumerate() {
+        if index == 1 && header {
+            lines.push(rule(borders.corners[1]));
+        }
+        let height = cells.iter().map(Canvas::height).max().unwrap_or(0).max(1);
+        let padding = Cell {
+            ch: ' ',
+            mark: if *highlighted && style == TerminalStyle::Ansi {
+                Mark::Highlight
+            } else {
+                Mark::None
+            },
+        };
+        for row in 0..height {
+            let left = if *highlighted && style == TerminalStyle::Plain {
+                '>'
+            } else {
+                borders.vertical
+            };
+            let mut line = vec![border_cell(left)];
+            for (column, width) in widths.iter().enumerate() {
+                if column > 0 {
+                    line.push(border_cell(borders.vertical));
+                }
+                let content = match cells.get(column) {
+                    Some(cell) => cell.line(row, *width),
+                    None => vec![space(); *width],
+                };
+                line.push(padding);
+                // a highlighted row colours the unmarked cells
+                line.extend(content.into_iter().map(|cell| match cell.mark {
+                    Mark::None => Cell {
+                        ch: cell.ch,
+                        mark: padding.mark,
+                    },
+                    _ => cell,
+                }));
+                line.push(padding);
+            }
+            line.push(border_cell(borders.vertical));
+            lines.push(line);
+        }
+    }
+    lines.push(rule(borders.corners[2]));
+    Canvas { lines }
+}
+
+fn boxed(canvas: Canvas, style: TerminalStyle) -> Canvas {
+    grid(vec![(vec![canvas], false)], false, style)
+}
+
+fn render_block(block: &RenderBlock, style: TerminalStyle) -> Canvas {
+    let (canvas, title, class) = match block {
+        RenderBlock::Text(text) => {
+            let highlighted = has_highlight(&text.class_name);
+            let canvas = match (highlighted, style) {
+                (true, TerminalStyle::Ansi) => Canvas::text(&text.text, Mark::Highlight),
+                (true, TerminalStyle::Plain) => {
+                    Canvas::text(&format!("[{}]", text.text), Mark::None)
+                }
+                (false, _) => Canvas::text(&text.text, Mark::None),
+            };
+            return titled(canvas, &text.title);
+        }
+        RenderBlock::Table(table) => (render_table(table, style), &table.title, &table.class_name),
+        RenderBlock::Container(container) => (
+            render_container(container, style),
+            &container.title,
+            &container.class_name,
+        ),
+        RenderBlock::Graph(graph) => (render_graph(graph, style), &graph.title, &graph.class_name),
+    };
+    let canvas = if has_highlight(class) && style == TerminalStyle::Ansi {
+        canvas.mark(Mark::Highlight)
+    } else {
+        canvas
+    };
+    titled(canvas, title)
+}
+
+fn has_highlight(class: &Option<String>) -> bool {
+    class
+        .as_deref()
+        .is_some_and(|class| class.split_whitespace().any(|name| name == HIGHLIGHT))
+}
+
+fn titled(canvas: Canvas, title: &Option<String>) -> Canvas {
+    match title {
+        Some(title) => vstack(vec![Canvas::text(title, Mark::Title), canvas], 0),
+        None => canvas,
+    }
+}
+
+fn render_table(table: &RenderTableBlock, style: TerminalStyle) -> Canvas {
+    let cells = |blocks: &[RenderBlock]| {
+        blocks
+            .iter()
+            .map(|block| render_block(block, style))
+            .collect::<Vec<_>>()
+    };
+    let mut rows = vec![];
+    let header = !table.columns.is_empty();
+    if header {
+        rows.push((cells(&table.columns), false));
+    }
+    for row in &table.rows {
+        rows.push((cells(&row.cells), has_highlight(&row.class_name)));
+    }
+    grid(rows, header, style)
+}
+
+fn render_container(container: &RenderContainerBlock, style: TerminalStyle) -> Canvas {
+    let children = container
+        .children
+        .iter()
+        .map(|child| {
+            let canvas = render_block(child, style);
+            match container.display {
+                Some(RenderDisplay::Block) => boxed(canvas, style),
+                _ => canvas,
+            }
+        })
+        .collect();
+    match container.orientation {
+        Some(RenderOrientation::Horizontal) => hstack(children, 1),
+        _ => vstack(children, 0),
+    }
+}
+
+// `id inner` for each node, then `from.port -> to.port label` for each edge
+fn render_graph(graph: &RenderGraphBlock, style: TerminalStyle) -> Canvas {
+    let highlight = |canvas: Canvas, class: &Option<String>| match style {
+        TerminalStyle::Ansi if has_highlight(class) => canvas.mark(Mark::Highlight),
+        _ => canvas,
+    };
+    let mut lines = vec![];
+    for node in &graph.nodes {
+        let mut name = node.id.clone();
+        if let Some(group) = &node.group {
+            name = format!("{group}/{name}");
+        }
+        if style == TerminalStyle::Plain && has_highlight(&node.class_name) {
+            name = format!("[{name}]");
+        }
+        let mut parts = vec![highlight(Canvas::text(&name, Mark::None), &node.class_name)];
+        if let Some(inner) = &node.inner {
+            parts.push(render_block(inner, style));
+        }
+        lines.push(hstack(parts, 1));
+    }
+    for edge in &graph.edges {
+        let end = |node: &str, port: &Option<String>| match port {
+            Some(port) => format!("{node}.{port}"),
+            None => node.to_string(),
+        };
+        let mut text = format!(
+            "{} -> {}",
+            end(&edge.from, &edge.from_port),
+            end(&edge.to, &edge.to_port)
+        );
+        if let Some(label) = &edge.label {
+            text = format!("{text} {label}");
+        }
+        if style == TerminalStyle::Plain && has_highlight(&edge.class_name) {
+            text = format!("[{text}]");
+        }
+        lines.push(highlight(Canvas::text(&text, Mark::None), &edge.class_name));
+    }
+    vstack(lines, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_container, render_row, render_state, render_table, render_text};
+
+    fn tape() -> RenderState {
+        render_state![render_table!(
+            columns: [render_text!("l".to_string()), render_text!("head".to_string())],
+            rows: [
+                render_row!([render_text!("a".to_string()), render_text!("b".to_string(), class: "highlight")]),
+                render_row!([render_text!("q0".to_string()), render_text!("".to_string())], class: "highlight"),
+            ],
+            title: "tape"
+        )]
+    }
+
+    #[test]
+    fn tables_are_boxed_with_marked_highlights() {
+        assert_eq!(
+            render_terminal(&tape(), TerminalStyle::Plain),
+            "tape\n\
+             +----+------+\n\
+             | l  | head |\n\
+             +----+------+\n\
+             | a  | [b]  |\n\
+             > q0 |      |\n\
+             +----+------+\n"
+        );
+        let ansi = render_terminal(&tape(), TerminalStyle::Ansi);
+        assert!(ansi.starts_with("\x1b[0;1mtape\x1b[0m\n┌────┬──────┐\n"));
+        assert!(ansi.contains("│ a  │ \x1b[0;30;101mb\x1b[0m    │"));
+        assert!(ansi.contains("│\x1b[0;30;101m q0 \x1b[0m│\x1b[0;30;101m      \x1b[0m│"));
+    }
+
+    #[test]
+    fn containers_follow_orientation_and_display() {
+        let row = render_state![render_container!(
+            children: [render_text!("x\ny".to_string()), render_text!("z".to_string())],
+            orientation: RenderOrientation::Horizontal
+        )];
+        assert_eq!(render_terminal(&row, TerminalStyle::Plain), "x z\ny\n");
+        let column = render_state![render_container!(
+            children: [render_text!("x".to_string()), render_text!("全角".to_string())],
+            display: RenderDisplay::Block
+        )];
+        assert_eq!(
+            render_terminal(&column, TerminalStyle::Plain),
+            "+---+\n| x |\n+---+\n+------+\n| 全角 |\n+------+\n"
+        );
+    }
+}
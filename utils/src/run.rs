@@ -0,0 +1,189 @@
+use crate::{Machine, StepResult};
+
+// how a fuel-bounded run of a machine ended
+pub enum RunOutcome<M: Machine> {
+    // the machine reached `StepResult::Halt`
+    Halted { foutput: M::FOutput },
+    // the step budget was used up before halting
+    OutOfFuel { snapshot: M::SnapShot },
+    // the rinput iterator ended before halting
+    InputExhausted { snapshot: M::SnapShot },
+    // `step` returned an error at the given (0-indexed) step
+    Error { step: usize, message: String },
+}
+
+impl<M: Machine> RunOutcome<M> {
+    pub fn is_halted(&self) -> bool {
+        matches!(self, RunOutcome::Halted { .. })
+    }
+
+    // the final output, or a message describing why the run did not halt
+    pub fn into_foutput(self) -> Result<M::FOutput, String> {
+        match self {
+            RunOutcome::Halted { foutput } => Ok(foutput),
+            RunOutcome::OutOfFuel { .. } => Err("step limit exceeded".to_string()),
+            RunOutcome::InputExhausted { .. } => Err("rinput exhausted before halt".to_string()),
+            RunOutcome::Error { step, message } => Err(format!("error at step {step}: {message}")),
+        }
+    }
+}
+
+pub struct RunReport<M: Machine> {
+    pub outcome: RunOutcome<M>,
+    // number of successful `step` calls (a halting step is counted)
+    pub steps: usize,
+    // every `ROutput` in order
+    pub routputs: Vec<M::ROutput>,
+    // snapshots of the initial state and of the state after each continuing step
+    // empty unless `RunOptions::trace` is set
+    pub trace: Vec<M::SnapShot>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunOptions {
+    // maximum number of `step` calls
+    pub fuel: usize,
+    // record the snapshot trace
+    pub trace: bool,
+}
+
+impl RunOptions {
+    pub fn fuel(fuel: usize) -> Self {
+        RunOptions { fuel, trace: false }
+    }
+    pub fn with_trace(self) -> Self {
+        RunOptions {
+            trace: true,
+            ..self
+        }
+    }
+}
+
+// call `step` until halt, feeding rinputs from the iterator
+// use `std::iter::repeat(())` for machines without runtime input
+pub fn run<M, I>(machine: M, rinputs: I, options: RunOptions) -> RunReport<M>
+where
+    M: Machine,
+    I: IntoIterator<Item = M::RInput>,
+{
+    let mut rinputs = rinputs.into_iter();
+    let mut routputs = Vec::new();
+    let mut trace = Vec::new();
+    if options.trace {
+        trace.push(machine.snapshot());
+    }
+
+    let mut machine = machine;
+    let mut steps = 0;
+    let outcome = loop {
+        if steps >= options.fuel {
+            break RunOutcome::OutOfFuel {
+                snapshot: machine.snapshot(),
+            };
+        }
+        let Some(rinput) = rinputs.next() else {
+            break RunOutcome::InputExhausted {
+                snapshot: machine.snapshot(),
+            };
+        };
+        match machine.step(rinput) {
+            Ok(StepResult::Continue { next, output }) => {
+                steps += 1;
+                routputs.push(output);
+                if options.trace {
+                    trace.push(next.snapshot());
+                }
+                machine = next;
+            }
+            Ok(StepResult::Halt { output }) => {
+                steps += 1;
+                break RunOutcome::Halted { foutput: output };
+            }
+            Err(message) => {
+                break RunOutcome::Error {
+                    step: steps,
+                    message,
+                }
+            }
+        }
+    };
+
+    RunReport {
+        outcome,
+        steps,
+        routputs,
+        trace,
+    }
+}
+
+// make the machine from code and ainput, then `run` it
+pub fn run_code<M, I>(
+    code: M::Code,
+    ainput: M::AInput,
+    rinputs: I,
+    options: RunOptions,
+) -> Result<RunReport<M>, String>
+where
+    M: Machine,
+    I: IntoIterator<Item = M::RInput>,
+{
+    let machine = M::make(code, ainput)?;
+    Ok(run(machine, rinputs, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_machine::{Amount, Counter};
+
+    fn counter(limit: usize) -> Counter {
+        Counter::make(limit, 0).unwrap()
+    }
+
+    #[test]
+    fn runs_to_halt_and_collects_routputs() {
+        let report = run_code::<Counter, _>(
+            3,
+            0,
+            std::iter::repeat(Amount(1)),
+            RunOptions::fuel(10).with_trace(),
+        )
+        .unwrap();
+        assert_eq!(report.steps, 3);
+        assert_eq!(report.routputs, vec!["1", "2"]);
+        assert_eq!(report.trace, vec![(0, 3), (1, 3), (2, 3)]);
+        assert_eq!(report.outcome.into_foutput().unwrap(), 3);
+    }
+
+    #[test]
+    fn stops_when_fuel_runs_out() {
+        let report = run(
+            counter(10),
+            std::iter::repeat(Amount(1)),
+            RunOptions::fuel(2),
+        );
+        assert_eq!(report.steps, 2);
+        assert!(report.trace.is_empty());
+        assert!(matches!(
+            report.outcome,
+            RunOutcome::OutOfFuel { snapshot: (2, 10) }
+        ));
+    }
+
+    #[test]
+    fn reports_input_exhaustion_and_errors() {
+        let report = run(counter(10), vec![Amount(1)], RunOptions::fuel(10));
+        assert!(matches!(
+            report.outcome,
+            RunOutcome::InputExhausted { snapshot: (1, 10) }
+        ));
+
+        let report = run(
+            counter(10),
+            vec![Amount(1), Amount(2), Amount(0)],
+            RunOptions::fuel(10),
+        );
+        assert_eq!(report.routputs, vec!["1", "3"]);
+        assert!(matches!(report.outcome, RunOutcome::Error { step: 2, .. }));
+    }
+}
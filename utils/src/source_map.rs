@@ -0,0 +1,89 @@
+use crate::diagnostic::{line_col, Span};
+
+// map from locations of a compiled code to the source statements they come from
+// - a location is named by the target model (a flow_ir region label, a TM state, ...), see `Machine::location`
+// - a statement is its index in `TextCodec::node_spans` of the source text (post-order of the parser)
+// locations added by the compiler itself (halt regions, helper states, ...) are not in the map
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    entries: Vec<(String, usize)>,
+}
+
+impl SourceMap {
+    pub fn insert(&mut self, target: impl Into<String>, node: usize) {
+        self.entries.push((target.into(), node));
+    }
+
+    pub fn node(&self, target: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .find(|(name, _)| name == target)
+            .map(|(_, node)| *node)
+    }
+
+    pub fn entries(&self) -> &[(String, usize)] {
+        &self.entries
+    }
+
+    // locations with the spans of their statements, `spans` is `node_spans` of the source text
+    pub fn resolve(&self, spans: &[Span]) -> Result<Vec<SourceMapping>, String> {
+        self.entries
+            .iter()
+            .map(|(target, node)| {
+                let span = spans.get(*node).ok_or_else(|| {
+                    format!(
+                        "source map of {target} points to statement {node}, but the source has {}",
+                        spans.len()
+                    )
+                })?;
+                Ok(SourceMapping {
+                    target: target.clone(),
+                    span: *span,
+                })
+            })
+            .collect()
+    }
+}
+
+// a location of the compiled code and the span of its statement in the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceMapping {
+    pub target: String,
+    pub span: Span,
+}
+
+impl SourceMapping {
+    // 1-indexed line and column, and the text of the line, of the start of the statement
+    pub fn line<'a>(&self, source: &'a str) -> (usize, usize, &'a str) {
+        let (line, column) = line_col(source, self.span.start);
+        (line, column, source.lines().nth(line - 1).unwrap_or(""))
+    }
+}
+
+// the mapping of `target` among `mappings`
+pub fn lookup<'a>(mappings: &'a [SourceMapping], target: &str) -> Option<&'a SourceMapping> {
+    mappings.iter().find(|mapping| mapping.target == target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_points_into_the_source() {
+        let source = "x := 0 ;\nwhile x < 3 [ x := x + 1 ]";
+        let spans = vec![Span::new(0, 6), Span::new(23, 33), Span::new(9, 35)];
+        let mut map = SourceMap::default();
+        map.insert("stmt_assign_1", 1);
+        map.insert("while_head_0", 2);
+        let mappings = map.resolve(&spans).unwrap();
+        let head = lookup(&mappings, "while_head_0").unwrap();
+        assert_eq!(head.line(source), (2, 1, "while x < 3 [ x := x + 1 ]"));
+        assert_eq!(
+            lookup(&mappings, "stmt_assign_1").unwrap().line(source).1,
+            15
+        );
+        map.insert("missing", 3);
+        assert!(map.resolve(&spans).is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{Machine, Metrics, RenderState, StepResult, TextCodec};
+
+// adds the rinput amount to a count starting from the ainput, halting once it reaches the code
+// emits the count after each continuing step and halts with it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter {
+    pub count: usize,
+    pub limit: usize,
+}
+
+// amount added by one step
+// the text form is at most `Amount::MAX` (empty text is 1), so a larger value does not round-trip
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Amount(pub usize);
+
+impl Amount {
+    pub const MAX: usize = 100;
+}
+
+impl TextCodec for Amount {
+    fn parse(text: &str) -> Result<Self, String> {
+        if text.trim().is_empty() {
+            return Ok(Amount(1));
+        }
+        match usize::parse(text)? {
+            n if n > Amount::MAX => Err(format!("amount {n} is over {}", Amount::MAX)),
+            n => Ok(Amount(n)),
+        }
+    }
+    fn write_fmt(&self, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Machine for Counter {
+    type Code = usize;
+    type AInput = usize;
+    type FOutput = usize;
+    type RInput = Amount;
+    type ROutput = String;
+    type SnapShot = (usize, usize);
+
+    fn make(code: Self::Code, ainput: Self::AInput) -> Result<Self, String> {
+        if code == 0 {
+            return Err("zero limit".to_string());
+        }
+        Ok(Counter {
+            count: ainput,
+            limit: code,
+        })
+    }
+    fn step(self, rinput: Self::RInput) -> Result<StepResult<Self>, String> {
+        if rinput.0 == 0 {
+            return Err("zero amount".to_string());
+        }
+        let count = self.count + rinput.0;
+        if count >= self.limit {
+            Ok(StepResult::Halt { output: count })
+        } else {
+            Ok(StepResult::Continue {
+                next: Counter { count, ..self },
+                output: count.to_string(),
+            })
+        }
+    }
+    fn snapshot(&self) -> Self::SnapShot {
+        (self.count, self.limit)
+    }
+    fn restore((count, limit): Self::SnapShot) -> Self {
+        Counter { count, limit }
+    }
+    fn render(_snapshot: Self::SnapShot) -> RenderState {
+        vec![]
+    }
+}
+
+impl Metrics for Counter {
+    fn sizes(&self) -> Vec<(&'static str, usize)> {
+        vec![("count", self.count)]
+    }
+    fn counters(&self, rinput: &Self::RInput) -> Vec<(&'static str, usize)> {
+        vec![("add", rinput.0)]
+    }
+}
@@ -0,0 +1,530 @@
+// parser over token trees shared by the models
+// - whitespace and comments are skipped
+// - a delimited group is parsed by a child parser, so errors never cross the delimiters
+// - `TreeParser` is `Copy`: save it to backtrack (or use `attempt`)
+// - `terminated` / `separated` recover from an error at the next separator,
+//   so one run reports every syntax error in the text
+use std::cell::RefCell;
+
+use crate::diagnostic::{Diagnostic, Span};
+use crate::identifier::Identifier;
+use crate::lexer::{lex_tree_spanned_with, DelimKind, LexerConfig, SpannedTree, Token};
+use crate::number::Number;
+use crate::TextCodec;
+
+pub type PResult<T> = Result<T, Diagnostic>;
+
+// errors reported by recovering combinators, shared by a parser and its children
+#[derive(Debug, Default)]
+pub struct Errors(RefCell<Vec<Diagnostic>>);
+
+impl Errors {
+    pub fn push(&self, diagnostic: Diagnostic) {
+        self.0.borrow_mut().push(diagnostic);
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.borrow().is_empty()
+    }
+
+    fn truncate(&self, len: usize) {
+        self.0.borrow_mut().truncate(len);
+    }
+
+    // all errors (including `last`) in the order of their position
+    pub fn finish<T>(self, last: PResult<T>) -> Result<T, Vec<Diagnostic>> {
+        let mut errors = self.0.into_inner();
+        let value = match last {
+            Ok(value) => Some(value),
+            Err(error) => {
+                errors.push(error);
+                None
+            }
+        };
+        match value {
+            Some(value) if errors.is_empty() => Ok(value),
+            _ => {
+                errors.sort_by_key(|error| error.span.map(|span| span.start));
+                errors.dedup();
+                Err(errors)
+            }
+        }
+    }
+}
+
+// lex `text` and run `f` over the whole of it
+pub fn parse_text<T>(
+    text: &str,
+    f: impl FnOnce(&mut TreeParser<'_>) -> PResult<T>,
+) -> Result<T, Vec<Diagnostic>> {
+    parse_text_with(text, &LexerConfig::default(), f)
+}
+
+// same as `parse_text` with the token classes of the language
+pub fn parse_text_with<T>(
+    text: &str,
+    config: &LexerConfig,
+    f: impl FnOnce(&mut TreeParser<'_>) -> PResult<T>,
+) -> Result<T, Vec<Diagnostic>> {
+    let trees = lex_tree_spanned_with(text, config).map_err(|error| vec![error.into()])?;
+    let errors = Errors::default();
+    let result = {
+        let mut parser = TreeParser::new(&trees, Span::at(text.len()), &errors);
+        f(&mut parser).and_then(|value| {
+            parser.expect_eof()?;
+            Ok(value)
+        })
+    };
+    errors.finish(result)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TreeParser<'a> {
+    items: &'a [SpannedTree],
+    pos: usize,
+    // where errors at the end point to (the closing delimiter or the end of input)
+    end: Span,
+    errors: &'a Errors,
+}
+
+impl<'a> TreeParser<'a> {
+    pub fn new(items: &'a [SpannedTree], end: Span, errors: &'a Errors) -> Self {
+        TreeParser {
+            items,
+            pos: 0,
+            end,
+            errors,
+        }
+    }
+
+    fn skip_trivia(&mut self) {
+        while let Some(SpannedTree::Token(token)) = self.items.get(self.pos) {
+            if !matches!(token.token, Token::Whitespace(_) | Token::Comment(_)) {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&'a SpannedTree> {
+        self.skip_trivia();
+        self.items.get(self.pos)
+    }
+
+    pub fn peek_token(&mut self) -> Option<&'a Token> {
+        match self.peek()? {
+            SpannedTree::Token(token) => Some(&token.token),
+            SpannedTree::Delim { .. } => None,
+        }
+    }
+
+    // the tree after the next one
+    pub fn peek_second(&mut self) -> Option<&'a SpannedTree> {
+        let mut copy = *self;
+        copy.advance()?;
+        copy.peek()
+    }
+
+    pub fn advance(&mut self) -> Option<&'a SpannedTree> {
+        let item = self.peek()?;
+        self.pos += 1;
+        Some(item)
+    }
+
+    pub fn is_eof(&mut self) -> bool {
+        self.peek().is_none()
+    }
+
+    pub fn span_here(&mut self) -> Span {
+        self.peek().map_or(self.end, SpannedTree::span)
+    }
+
+    // span of the last consumed tree
+    pub fn span_prev(&self) -> Span {
+        self.items[..self.pos]
+            .iter()
+            .rev()
+            .find(|item| {
+                !matches!(
+                    item,
+                    SpannedTree::Token(token)
+                        if matches!(token.token, Token::Whitespace(_) | Token::Comment(_))
+                )
+            })
+            .map_or(self.end, SpannedTree::span)
+    }
+
+    pub fn error_here(&mut self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::error(self.span_here(), message)
+    }
+
+    // "expected {what}, found ..."
+    pub fn expected(&mut self, what: &str) -> Diagnostic {
+        let found = match self.peek() {
+            Some(item) => describe(item),
+            None => "end of input".to_string(),
+        };
+        self.error_here(format!("expected {what}, found {found}"))
+    }
+
+    // report an error without stopping the parse
+    pub fn report(&self, diagnostic: Diagnostic) {
+        self.errors.push(diagnostic);
+    }
+
+    pub fn expect_eof(&mut self) -> PResult<()> {
+        if self.is_eof() {
+            Ok(())
+        } else {
+            Err(self.expected("end of input"))
+        }
+    }
+
+    pub fn peek_symbol(&mut self, ch: char) -> bool {
+        matches!(self.peek_token(), Some(Token::Symbol(found)) if *found == ch)
+    }
+
+    pub fn eat_symbol(&mut self, ch: char) -> bool {
+        let found = self.peek_symbol(ch);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_symbol(&mut self, ch: char) -> PResult<()> {
+        if self.eat_symbol(ch) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("symbol '{ch}'")))
+        }
+    }
+
+    // operator token of `LexerConfig::operators`
+    pub fn eat_op(&mut self, op: &str) -> bool {
+        let found = matches!(self.peek_token(), Some(Token::Op(found)) if found == op);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_op(&mut self, op: &str) -> PResult<()> {
+        if self.eat_op(op) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("'{op}'")))
+        }
+    }
+
+    // an operator or a sequence of symbols such as "<-" or "=>"
+    // (without an operator table, whitespace between the symbols is allowed)
+    pub fn eat_symbols(&mut self, symbols: &str) -> bool {
+        if self.eat_op(symbols) {
+            return true;
+        }
+        let mut copy = *self;
+        if symbols.chars().all(|ch| copy.eat_symbol(ch)) {
+            *self = copy;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn expect_symbols(&mut self, symbols: &str) -> PResult<()> {
+        if self.eat_symbols(symbols) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("'{symbols}'")))
+        }
+    }
+
+    // keywords of `LexerConfig::keywords` or plain identifiers
+    pub fn peek_keyword(&mut self, keyword: &str) -> bool {
+        matches!(self.peek_token(), Some(Token::Ident(found) | Token::Keyword(found)) if found == keyword)
+    }
+
+    pub fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_keyword(&mut self, keyword: &str) -> PResult<()> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("keyword '{keyword}'")))
+        }
+    }
+
+    // identifier token as it is (keywords are not rejected)
+    pub fn expect_ident_text(&mut self) -> PResult<&'a str> {
+        match self.peek_token() {
+            Some(Token::Ident(text)) => {
+                self.pos += 1;
+                Ok(text)
+            }
+            _ => Err(self.expected("identifier")),
+        }
+    }
+
+    // `#name` of `LexerConfig::atoms`
+    pub fn eat_atom(&mut self, name: &str) -> bool {
+        let found = matches!(self.peek_token(), Some(Token::Atom(found)) if found == name);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    pub fn expect_atom_text(&mut self) -> PResult<&'a str> {
+        match self.peek_token() {
+            Some(Token::Atom(name)) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.expected("'#' atom")),
+        }
+    }
+
+    pub fn expect_char(&mut self) -> PResult<char> {
+        match self.peek_token() {
+            Some(Token::Char(ch)) => {
+                self.pos += 1;
+                Ok(*ch)
+            }
+            _ => Err(self.expected("char literal")),
+        }
+    }
+
+    pub fn ident(&mut self) -> PResult<Identifier> {
+        let text = self.expect_ident_text()?;
+        Identifier::new(text).map_err(|e| Diagnostic::error(self.span_prev(), e.to_string()))
+    }
+
+    pub fn expect_number_text(&mut self) -> PResult<&'a str> {
+        match self.peek_token() {
+            Some(Token::Number(text)) => {
+                self.pos += 1;
+                Ok(text)
+            }
+            _ => Err(self.expected("number")),
+        }
+    }
+
+    pub fn number(&mut self) -> PResult<Number> {
+        let text = self.expect_number_text()?;
+        Number::parse(text).map_err(|e| Diagnostic::error(self.span_prev(), e))
+    }
+
+    // parser for the inside of the group
+    pub fn eat_delim(&mut self, delim: DelimKind) -> Option<TreeParser<'a>> {
+        match self.peek()? {
+            SpannedTree::Delim {
+                delim: found,
+                span,
+                child,
+            } if *found == delim => {
+                self.pos += 1;
+                let closing = Span::new(span.end.saturating_sub(1), span.end);
+                Some(TreeParser::new(child, closing, self.errors))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn expect_delim(&mut self, delim: DelimKind) -> PResult<TreeParser<'a>> {
+        match self.eat_delim(delim) {
+            Some(inner) => Ok(inner),
+            None => Err(self.expected(open(delim))),
+        }
+    }
+
+    // parse the whole group with `f`
+    pub fn delimited<T>(
+        &mut self,
+        delim: DelimKind,
+        f: impl FnOnce(&mut TreeParser<'a>) -> PResult<T>,
+    ) -> PResult<T> {
+        let mut inner = self.expect_delim(delim)?;
+        let value = f(&mut inner)?;
+        inner.expect_eof()?;
+        Ok(value)
+    }
+
+    // run `f` and rewind if it fails (errors reported inside are dropped too)
+    pub fn attempt<T>(&mut self, f: impl FnOnce(&mut TreeParser<'a>) -> PResult<T>) -> PResult<T> {
+        let saved = *self;
+        let reported = self.errors.len();
+        let result = f(self);
+        if result.is_err() {
+            *self = saved;
+            self.errors.truncate(reported);
+        }
+        result
+    }
+
+    // items each followed by `terminator` up to the end of this parser
+    // a failed item is reported and skipped up to the next `terminator`
+    pub fn terminated<T>(
+        &mut self,
+        terminator: char,
+        mut item: impl FnMut(&mut TreeParser<'a>) -> PResult<T>,
+    ) -> Vec<T> {
+        let mut items = Vec::new();
+        while !self.is_eof() {
+            match item(self).and_then(|value| {
+                self.expect_symbol(terminator)?;
+                Ok(value)
+            }) {
+                Ok(value) => items.push(value),
+                Err(error) => {
+                    self.report(error);
+                    self.skip_past(terminator);
+                }
+            }
+        }
+        items
+    }
+
+    // items separated by `separator` up to the end of this parser (a trailing one is allowed)
+    // a failed item is reported and skipped up to the next `separator`
+    pub fn separated<T>(
+        &mut self,
+        separator: char,
+        mut item: impl FnMut(&mut TreeParser<'a>) -> PResult<T>,
+    ) -> Vec<T> {
+        let mut items = Vec::new();
+        while !self.is_eof() {
+            match item(self).and_then(|value| {
+                if !self.is_eof() {
+                    self.expect_symbol(separator)?;
+                }
+                Ok(value)
+            }) {
+                Ok(value) => items.push(value),
+                Err(error) => {
+                    self.report(error);
+                    self.skip_past(separator);
+                }
+            }
+        }
+        items
+    }
+
+    // skip up to and including the next `ch` at this level (or to the end)
+    pub fn skip_past(&mut self, ch: char) {
+        while !self.is_eof() {
+            if self.eat_symbol(ch) {
+                return;
+            }
+            self.pos += 1;
+        }
+    }
+}
+
+fn open(delim: DelimKind) -> &'static str {
+    match delim {
+        DelimKind::Paren => "'('",
+        DelimKind::Bracket => "'['",
+        DelimKind::Brace => "'{'",
+    }
+}
+
+fn describe(item: &SpannedTree) -> String {
+    match item {
+        SpannedTree::Token(token) => match &token.token {
+            Token::Ident(text)
+            | Token::Number(text)
+            | Token::StringLiteral(text)
+            | Token::Whitespace(text)
+            | Token::Comment(text)
+            | Token::Op(text) => format!("'{text}'"),
+            Token::Keyword(word) => format!("keyword '{word}'"),
+            Token::Atom(name) => format!("'#{name}'"),
+            Token::Symbol(ch) => format!("'{ch}'"),
+            Token::Char(ch) => format!("{ch:?}"),
+        },
+        SpannedTree::Delim { delim, .. } => open(*delim).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // `name = number;` lines
+    fn assignments(text: &str) -> Result<Vec<(String, usize)>, Vec<Diagnostic>> {
+        parse_text(text, |p| {
+            Ok(p.terminated(';', |p| {
+                let name = p.expect_ident_text()?.to_string();
+                p.expect_symbol('=')?;
+                let value = p.expect_number_text()?.parse().unwrap();
+                Ok((name, value))
+            }))
+        })
+    }
+
+    #[test]
+    fn terminated_reports_every_error() {
+        assert_eq!(
+            assignments("x = 1; y = 2;"),
+            Ok(vec![("x".to_string(), 1), ("y".to_string(), 2)])
+        );
+        let text = "x = 1; y = ; z 3; w = 4;";
+        let errors = assignments(text).unwrap_err();
+        let spans: Vec<_> = errors.iter().map(|error| error.span.unwrap()).collect();
+        assert_eq!(spans, vec![Span::new(11, 12), Span::new(15, 16)]);
+        assert_eq!(errors[0].message, "expected number, found ';'");
+        assert_eq!(errors[1].message, "expected symbol '=', found '3'");
+    }
+
+    #[test]
+    fn groups_are_parsed_by_child_parsers() {
+        let list = |text: &str| {
+            parse_text(text, |p| {
+                p.expect_keyword("list")?;
+                p.delimited(DelimKind::Bracket, |inner| {
+                    Ok(inner.separated(',', |p| p.expect_number_text().map(str::to_string)))
+                })
+            })
+        };
+        assert_eq!(
+            list("list [1, 2, 3,]"),
+            Ok(vec!["1".to_string(), "2".to_string(), "3".to_string()])
+        );
+        // errors inside the group do not stop the outer parser
+        let errors = list("list [1, x, 3] 4").unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].span, Some(Span::new(9, 10)));
+        assert_eq!(errors[1].message, "expected end of input, found '4'");
+        // an unclosed group is reported at its opening bracket
+        let errors = list("list [1,").unwrap_err();
+        assert_eq!(errors[0].message, "unclosed delimiter '['");
+        assert_eq!(errors[0].span.map(|span| span.start), Some(5));
+    }
+
+    #[test]
+    fn attempt_rewinds_on_failure() {
+        let result = parse_text("a <- b", |p| {
+            let arrow = p.attempt(|p| {
+                p.expect_ident_text()?;
+                p.expect_symbols("->")
+            });
+            assert!(arrow.is_err());
+            let dst = p.expect_ident_text()?.to_string();
+            p.expect_symbols("<-")?;
+            let src = p.expect_ident_text()?.to_string();
+            Ok((dst, src))
+        });
+        assert_eq!(result, Ok(("a".to_string(), "b".to_string())));
+    }
+}
@@ -1,4 +1,171 @@
-use crate::{Compiler, Machine, StepResult, TextCodec};
+use std::collections::VecDeque;
+
+use crate::diagnostic::{line_col, Diagnostic, Severity};
+use crate::history::{BatchEnd, History, StepOutput};
+use crate::source_map::SourceMapping;
+use crate::{Compiler, Interpreter, Machine, TextCodec};
+
+// error crossing the component boundary (or a `dynamic` object)
+// the span of the diagnostic points into the argument named `origin`
+pub struct BoundaryError {
+    pub diagnostic: Diagnostic,
+    pub origin: &'static str,
+    pub line_col: Option<(usize, usize)>,
+}
+
+impl From<Diagnostic> for BoundaryError {
+    fn from(diagnostic: Diagnostic) -> Self {
+        BoundaryError {
+            diagnostic: Diagnostic {
+                span: None,
+                ..diagnostic
+            },
+            origin: "",
+            line_col: None,
+        }
+    }
+}
+
+impl From<String> for BoundaryError {
+    fn from(message: String) -> Self {
+        Diagnostic::unspanned(message).into()
+    }
+}
+
+// at most this many routputs are returned by one batch, the last ones
+pub const BATCH_ROUTPUTS: usize = 10_000;
+
+// `History::advance` in text form, the wit `batch` record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Batch {
+    pub end: BatchEnd<String>,
+    pub steps: usize,
+    // the last `BATCH_ROUTPUTS` routputs printing as non-empty text, with the 1-indexed step of the batch
+    pub routputs: Vec<(usize, String)>,
+    pub last_routput: Option<String>,
+}
+
+// attach the argument a parse error comes from
+fn located<'a>(origin: &'static str, source: &'a str) -> impl Fn(Diagnostic) -> BoundaryError + 'a {
+    move |diagnostic| BoundaryError {
+        line_col: diagnostic.span.map(|span| line_col(source, span.start)),
+        origin,
+        diagnostic,
+    }
+}
+
+#[doc(hidden)]
+pub use wasm_model::computation::web::types::Diagnostic as WitDiagnostic;
+
+impl From<BoundaryError> for WitDiagnostic {
+    fn from(error: BoundaryError) -> Self {
+        use wasm_model::computation::web::types;
+        let span = error
+            .diagnostic
+            .span
+            .zip(error.line_col)
+            .map(|(span, (line, column))| types::Span {
+                start: span.start as u32,
+                end: span.end as u32,
+                line: line as u32,
+                column: column as u32,
+            });
+        WitDiagnostic {
+            severity: match error.diagnostic.severity {
+                Severity::Error => types::Severity::Error,
+                Severity::Warning => types::Severity::Warning,
+            },
+            message: error.diagnostic.message,
+            origin: if span.is_some() { error.origin } else { "" }.to_string(),
+            span,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub use wasm_model::computation::web::types::{
+    CompilerDescription as WitCompilerDescription, Description as WitDescription,
+};
+
+impl From<crate::Description> for WitDescription {
+    fn from(description: crate::Description) -> Self {
+        use wasm_model::computation::web::types;
+        WitDescription {
+            name: description.name,
+            code_format: description.code_format,
+            ainput_format: description.ainput_format,
+            rinput_format: description.rinput_format,
+            default_rinput: description.default_rinput,
+            examples: description
+                .examples
+                .into_iter()
+                .map(|example| types::Example {
+                    name: example.name,
+                    code: example.code,
+                    ainput: example.ainput,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<crate::CompilerDescription> for WitCompilerDescription {
+    fn from(description: crate::CompilerDescription) -> Self {
+        WitCompilerDescription {
+            name: description.name,
+            source: description.source,
+            target: description.target,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub use wasm_model::computation::web::types::Batch as WitBatch;
+
+impl From<Batch> for WitBatch {
+    fn from(batch: Batch) -> Self {
+        use wasm_model::computation::web::types::BatchEnd as WitBatchEnd;
+        WitBatch {
+            end: match batch.end {
+                BatchEnd::Running => WitBatchEnd::Running,
+                BatchEnd::Halted(foutput) => WitBatchEnd::Halted(foutput),
+                BatchEnd::Failed(message) => {
+                    WitBatchEnd::Failed(BoundaryError::from(message).into())
+                }
+            },
+            steps: batch.steps as u32,
+            routputs: batch
+                .routputs
+                .into_iter()
+                .map(|(step, routput)| (step as u32, routput))
+                .collect(),
+            last_routput: batch.last_routput,
+        }
+    }
+}
+
+#[doc(hidden)]
+pub use wasm_model::computation::web::types::SourceMapping as WitSourceMapping;
+
+// the wit `source-mapping` records, `source` is the code the spans point into
+pub fn wit_source_mappings(mappings: Vec<SourceMapping>, source: &str) -> Vec<WitSourceMapping> {
+    use wasm_model::computation::web::types;
+    mappings
+        .into_iter()
+        .map(|mapping| {
+            let (line, column) = line_col(source, mapping.span.start);
+            WitSourceMapping {
+                target: mapping.target,
+                span: types::Span {
+                    start: mapping.span.start as u32,
+                    end: mapping.span.end as u32,
+                    line: line as u32,
+                    column: column as u32,
+                },
+            }
+        })
+        .collect()
+}
 
 #[doc(hidden)]
 pub mod wasm_model {
@@ -10,69 +177,163 @@ pub mod wasm_model {
         pub_export_macro: true,
     });
 
-    pub fn make_machine_impl<T: Machine>(code: &str, ainput: &str) -> Result<T, String> {
-        let code = T::parse_code(code)?;
-        let ainput = T::parse_ainput(ainput)?;
-        T::make(code, ainput)
+    pub fn make_machine_impl<T: Machine>(
+        code: &str,
+        ainput: &str,
+    ) -> Result<History<T>, BoundaryError> {
+        let code = T::parse_code(code).map_err(located("code", code))?;
+        let ainput = T::parse_ainput(ainput).map_err(located("ainput", ainput))?;
+        Ok(History::new(T::make(code, ainput)?)?)
+    }
+
+    fn initialized<T: Machine>(
+        history: &mut Option<History<T>>,
+    ) -> Result<&mut History<T>, BoundaryError> {
+        Ok(history
+            .as_mut()
+            .ok_or_else(|| "Machine not initialized".to_string())?)
     }
 
-    pub fn step_machine_impl<T>(machine: &mut Option<T>, rinput: &str) -> Result<String, String>
+    pub fn step_machine_impl<T>(
+        history: &mut Option<History<T>>,
+        rinput: &str,
+    ) -> Result<String, BoundaryError>
     where
         T: Machine,
     {
-        let current = machine
-            .take()
-            .ok_or_else(|| "Machine not initialized".to_string())?;
-        let parsed = T::parse_rinput(rinput)?;
-        match current.step(parsed)? {
-            StepResult::Continue {
-                next,
-                output: routput,
-            } => {
-                *machine = Some(next);
-                serde_json::to_string(&serde_json::json!({
-                    "kind": "continue",
-                    "routput": routput.print(),
-                }))
-                .map_err(|e| e.to_string())
-            }
-            StepResult::Halt { output: foutput } => {
-                *machine = None;
-                serde_json::to_string(&serde_json::json!({
-                    "kind": "halt",
-                    "foutput": foutput.print(),
-                }))
-                .map_err(|e| e.to_string())
-            }
+        let history = initialized(history)?;
+        let parsed = T::parse_rinput(rinput).map_err(located("rinput", rinput))?;
+        match history.step(parsed)? {
+            StepOutput::Continue(routput) => Ok(serde_json::to_string(&serde_json::json!({
+                "kind": "continue",
+                "routput": routput.print(),
+            }))
+            .map_err(|e| e.to_string())?),
+            StepOutput::Halt(foutput) => Ok(serde_json::to_string(&serde_json::json!({
+                "kind": "halt",
+                "foutput": foutput.print(),
+            }))
+            .map_err(|e| e.to_string())?),
         }
     }
 
-    pub fn snapshot_machine_impl<T>(machine: &Option<T>) -> Result<String, String>
+    pub fn step_n_machine_impl<T>(
+        history: &mut Option<History<T>>,
+        count: u32,
+        rinput: &str,
+    ) -> Result<super::Batch, BoundaryError>
+    where
+        T: Machine,
+    {
+        let history = initialized(history)?;
+        let parsed = T::parse_rinput(rinput).map_err(located("rinput", rinput))?;
+        let mut routputs = VecDeque::new();
+        let mut last_routput = None;
+        let (steps, end) = history.advance(count as usize, &parsed, |step, routput| {
+            let text = routput.print();
+            if !text.trim().is_empty() {
+                if routputs.len() == BATCH_ROUTPUTS {
+                    routputs.pop_front();
+                }
+                routputs.push_back((step, text.clone()));
+            }
+            last_routput = Some(text);
+        })?;
+        Ok(super::Batch {
+            end: match end {
+                BatchEnd::Running => BatchEnd::Running,
+                BatchEnd::Halted(foutput) => BatchEnd::Halted(foutput.print()),
+                BatchEnd::Failed(message) => BatchEnd::Failed(message),
+            },
+            steps,
+            routputs: routputs.into(),
+            last_routput,
+        })
+    }
+
+    // `step-n` with the default rinput of the model
+    pub fn run_machine_impl<T: Machine>(
+        history: &mut Option<History<T>>,
+        fuel: u32,
+    ) -> Result<super::Batch, BoundaryError> {
+        let description = T::describe();
+        let rinput = description.default_rinput.ok_or_else(|| {
+            format!(
+                "model {} has no default rinput, step it with an rinput ({})",
+                description.name, description.rinput_format
+            )
+        })?;
+        step_n_machine_impl(history, fuel, &rinput)
+    }
+
+    pub fn snapshot_machine_impl<T>(history: &Option<History<T>>) -> Result<String, BoundaryError>
     where
         T: Machine,
     {
-        let machine = machine
+        let history = history
             .as_ref()
             .ok_or_else(|| "Machine not initialized".to_string())?;
-        let snapshot = T::snapshot(machine);
-        serde_json::to_string(&snapshot).map_err(|e| e.to_string())
+        let snapshot = history.snapshot()?;
+        Ok(serde_json::to_string(&snapshot).map_err(|e| e.to_string())?)
     }
 
-    pub fn restore_machine_impl<T>(snapshot: &str) -> Result<T, String>
+    // a restored machine starts a new history
+    pub fn restore_machine_impl<T>(snapshot: &str) -> Result<History<T>, BoundaryError>
     where
         T: Machine,
     {
         let snapshot: T::SnapShot = serde_json::from_str(snapshot).map_err(|e| e.to_string())?;
-        Ok(T::restore(snapshot))
+        Ok(History::new(T::restore(snapshot))?)
     }
 
-    pub fn render_machine_impl<T>(snapshot: &str) -> Result<String, String>
+    pub fn rewind_machine_impl<T>(
+        history: &mut Option<History<T>>,
+        step: u32,
+    ) -> Result<(), BoundaryError>
+    where
+        T: Machine,
+    {
+        Ok(initialized(history)?.jump(step as usize)?)
+    }
+
+    pub fn history_len_impl<T: Machine>(history: &Option<History<T>>) -> u32 {
+        history.as_ref().map_or(0, |history| history.len() as u32)
+    }
+
+    pub fn history_position_impl<T: Machine>(history: &Option<History<T>>) -> u32 {
+        history
+            .as_ref()
+            .map_or(0, |history| history.position() as u32)
+    }
+
+    pub fn hook_machine_impl<T>(
+        history: &Option<History<T>>,
+        name: &str,
+        arg: &str,
+    ) -> Result<bool, BoundaryError>
+    where
+        T: Machine,
+    {
+        let machine = history
+            .as_ref()
+            .ok_or_else(|| "Machine not initialized".to_string())?
+            .machine()
+            .ok_or_else(|| "machine has halted".to_string())?;
+        Ok(machine.hook(name, arg)?)
+    }
+
+    // `None` also before `make` and after the halt
+    pub fn location_machine_impl<T: Machine>(history: &Option<History<T>>) -> Option<String> {
+        history.as_ref()?.machine()?.location()
+    }
+
+    pub fn render_machine_impl<T>(snapshot: &str) -> Result<String, BoundaryError>
     where
         T: Machine,
     {
         let snapshot: T::SnapShot = serde_json::from_str(snapshot).map_err(|e| e.to_string())?;
         let rendered = T::render(snapshot);
-        serde_json::to_string(&rendered).map_err(|e| e.to_string())
+        Ok(serde_json::to_string(&rendered).map_err(|e| e.to_string())?)
     }
 }
 
@@ -84,54 +345,129 @@ pub mod wasm_compiler {
         path: "wit",
         world: "compiler",
         pub_export_macro: true,
+        with: {
+            "computation:web/types": crate::wasm_util::wasm_model::computation::web::types,
+        },
     });
 
-    pub fn compile_code_impl<T: Compiler>(code: &str) -> Result<String, String> {
-        let source_code = <T::Source as Machine>::parse_code(code)?;
+    pub fn compile_code_impl<T: Compiler>(code: &str) -> Result<String, BoundaryError> {
+        let source_code =
+            <T::Source as Machine>::parse_code(code).map_err(located("code", code))?;
         let target_code = T::compile(source_code)?;
         Ok(target_code.print())
     }
 
-    pub fn encode_ainput_impl<T: Compiler>(ainput: &str) -> Result<String, String> {
-        let source_ainput = <T::Source as Machine>::parse_ainput(ainput)?;
+    pub fn source_map_impl<T: Compiler>(
+        code: &str,
+    ) -> Result<Vec<crate::source_map::SourceMapping>, BoundaryError> {
+        let source_code =
+            <T::Source as Machine>::parse_code(code).map_err(located("code", code))?;
+        let (_, map) = T::compile_mapped(source_code)?;
+        let spans = <<T::Source as Machine>::Code as TextCodec>::node_spans(code)?;
+        Ok(map.resolve(&spans)?)
+    }
+
+    pub fn encode_ainput_impl<T: Compiler>(ainput: &str) -> Result<String, BoundaryError> {
+        let source_ainput =
+            <T::Source as Machine>::parse_ainput(ainput).map_err(located("ainput", ainput))?;
         let target_ainput = T::encode_ainput(source_ainput)?;
         Ok(target_ainput.print())
     }
 
-    pub fn encode_rinput_impl<T: Compiler>(rinput: &str) -> Result<String, String> {
-        let source_rinput = <T::Source as Machine>::parse_rinput(rinput)?;
+    pub fn encode_rinput_impl<T: Compiler>(rinput: &str) -> Result<String, BoundaryError> {
+        let source_rinput =
+            <T::Source as Machine>::parse_rinput(rinput).map_err(located("rinput", rinput))?;
         let target_rinput = T::encode_rinput(source_rinput)?;
         Ok(target_rinput.print())
     }
 
-    pub fn decode_routput_impl<T: Compiler>(output: &str) -> Result<String, String> {
+    pub fn decode_routput_impl<T: Compiler>(output: &str) -> Result<String, BoundaryError> {
         let output_target =
-            <<<T as Compiler>::Target as Machine>::ROutput as TextCodec>::parse(output)?;
+            <<<T as Compiler>::Target as Machine>::ROutput as TextCodec>::parse_spanned(output)
+                .map_err(located("output", output))?;
         let output_source = T::decode_routput(output_target)?;
         Ok(output_source.print())
     }
 
-    pub fn decode_foutput_impl<T: Compiler>(output: &str) -> Result<String, String> {
+    pub fn decode_foutput_impl<T: Compiler>(output: &str) -> Result<String, BoundaryError> {
         let output_target =
-            <<<T as Compiler>::Target as Machine>::FOutput as TextCodec>::parse(output)?;
+            <<<T as Compiler>::Target as Machine>::FOutput as TextCodec>::parse_spanned(output)
+                .map_err(located("output", output))?;
         let output_source = T::decode_foutput(output_target)?;
         Ok(output_source.print())
     }
 }
 
+#[doc(hidden)]
+pub mod wasm_interpreter {
+    use super::*;
+
+    wit_bindgen::generate!({
+        path: "wit",
+        world: "interpreter",
+        pub_export_macro: true,
+        with: {
+            "computation:web/types": crate::wasm_util::wasm_model::computation::web::types,
+        },
+    });
+
+    pub fn interpreter_code_impl<T: Interpreter>() -> Result<String, BoundaryError> {
+        Ok(T::interpreter()?.print())
+    }
+
+    pub fn encode_ainput_impl<T: Interpreter>(
+        code: &str,
+        ainput: &str,
+    ) -> Result<String, BoundaryError> {
+        let guest_code = <T::Guest as Machine>::parse_code(code).map_err(located("code", code))?;
+        let guest_ainput =
+            <T::Guest as Machine>::parse_ainput(ainput).map_err(located("ainput", ainput))?;
+        let host_ainput = T::encode_ainput(guest_code, guest_ainput)?;
+        Ok(host_ainput.print())
+    }
+
+    pub fn encode_rinput_impl<T: Interpreter>(rinput: &str) -> Result<String, BoundaryError> {
+        let guest_rinput =
+            <T::Guest as Machine>::parse_rinput(rinput).map_err(located("rinput", rinput))?;
+        let host_rinput = T::encode_rinput(guest_rinput)?;
+        Ok(host_rinput.print())
+    }
+
+    pub fn decode_routput_impl<T: Interpreter>(output: &str) -> Result<String, BoundaryError> {
+        let output_host =
+            <<<T as Interpreter>::Host as Machine>::ROutput as TextCodec>::parse_spanned(output)
+                .map_err(located("output", output))?;
+        let output_guest = T::decode_routput(output_host)?;
+        Ok(output_guest.print())
+    }
+
+    pub fn decode_foutput_impl<T: Interpreter>(output: &str) -> Result<String, BoundaryError> {
+        let output_host =
+            <<<T as Interpreter>::Host as Machine>::FOutput as TextCodec>::parse_spanned(output)
+                .map_err(located("output", output))?;
+        let output_guest = T::decode_foutput(output_host)?;
+        Ok(output_guest.print())
+    }
+}
+
 #[macro_export]
 macro_rules! web_model {
     ($machine:path) => {
         use $crate::wasm_util::wasm_model::Guest;
 
         thread_local! {
-            static MACHINE: std::cell::RefCell<Option<$machine>> = std::cell::RefCell::new(None);
+            static MACHINE: std::cell::RefCell<Option<$crate::history::History<$machine>>> =
+                std::cell::RefCell::new(None);
         }
 
         struct Component;
 
         impl Guest for Component {
-            fn make(code: String, ainput: String) -> Result<(), String> {
+            fn describe() -> $crate::wasm_util::WitDescription {
+                <$machine as $crate::Machine>::describe().into()
+            }
+
+            fn make(code: String, ainput: String) -> Result<(), $crate::wasm_util::WitDiagnostic> {
                 let machine = $crate::wasm_util::wasm_model::make_machine_impl::<$machine>(&code, &ainput)?;
                 MACHINE.with(|state| {
                     *state.borrow_mut() = Some(machine);
@@ -139,21 +475,41 @@ macro_rules! web_model {
                 Ok(())
             }
 
-            fn step(rinput: String) -> Result<String, String> {
+            fn step(rinput: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 MACHINE.with(|machine| {
                     let mut machine = machine.borrow_mut();
                     $crate::wasm_util::wasm_model::step_machine_impl::<$machine>(&mut machine, &rinput)
+                        .map_err(Into::into)
+                })
+            }
+
+            fn step_n(count: u32, rinput: String) -> Result<$crate::wasm_util::WitBatch, $crate::wasm_util::WitDiagnostic> {
+                MACHINE.with(|machine| {
+                    let mut machine = machine.borrow_mut();
+                    $crate::wasm_util::wasm_model::step_n_machine_impl::<$machine>(&mut machine, count, &rinput)
+                        .map(Into::into)
+                        .map_err(Into::into)
+                })
+            }
+
+            fn run(fuel: u32) -> Result<$crate::wasm_util::WitBatch, $crate::wasm_util::WitDiagnostic> {
+                MACHINE.with(|machine| {
+                    let mut machine = machine.borrow_mut();
+                    $crate::wasm_util::wasm_model::run_machine_impl::<$machine>(&mut machine, fuel)
+                        .map(Into::into)
+                        .map_err(Into::into)
                 })
             }
 
-            fn snapshot() -> Result<String, String> {
+            fn snapshot() -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 MACHINE.with(|machine| {
                     let machine = machine.borrow();
                     $crate::wasm_util::wasm_model::snapshot_machine_impl::<$machine>(&machine)
+                        .map_err(Into::into)
                 })
             }
 
-            fn restore(snapshot: String) -> Result<(), String> {
+            fn restore(snapshot: String) -> Result<(), $crate::wasm_util::WitDiagnostic> {
                 let machine = $crate::wasm_util::wasm_model::restore_machine_impl::<$machine>(&snapshot)?;
                 MACHINE.with(|state| {
                     *state.borrow_mut() = Some(machine);
@@ -161,8 +517,42 @@ macro_rules! web_model {
                 Ok(())
             }
 
-            fn render(snapshot: String) -> Result<String, String> {
+            fn render(snapshot: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_model::render_machine_impl::<$machine>(&snapshot)
+                    .map_err(Into::into)
+            }
+
+            fn rewind(step: u32) -> Result<(), $crate::wasm_util::WitDiagnostic> {
+                MACHINE.with(|machine| {
+                    let mut machine = machine.borrow_mut();
+                    $crate::wasm_util::wasm_model::rewind_machine_impl::<$machine>(&mut machine, step)
+                        .map_err(Into::into)
+                })
+            }
+
+            fn history_len() -> u32 {
+                MACHINE.with(|machine| {
+                    $crate::wasm_util::wasm_model::history_len_impl::<$machine>(&machine.borrow())
+                })
+            }
+
+            fn history_position() -> u32 {
+                MACHINE.with(|machine| {
+                    $crate::wasm_util::wasm_model::history_position_impl::<$machine>(&machine.borrow())
+                })
+            }
+
+            fn hook(name: String, arg: String) -> Result<bool, $crate::wasm_util::WitDiagnostic> {
+                MACHINE.with(|machine| {
+                    $crate::wasm_util::wasm_model::hook_machine_impl::<$machine>(&machine.borrow(), &name, &arg)
+                        .map_err(Into::into)
+                })
+            }
+
+            fn location() -> Option<String> {
+                MACHINE.with(|machine| {
+                    $crate::wasm_util::wasm_model::location_machine_impl::<$machine>(&machine.borrow())
+                })
             }
         }
 
@@ -182,24 +572,39 @@ macro_rules! web_compiler {
         struct Component;
 
         impl Guest for Component {
-            fn compile_code(input: String) -> Result<String, String> {
+            fn describe() -> $crate::wasm_util::WitCompilerDescription {
+                <$compiler as $crate::Compiler>::describe().into()
+            }
+
+            fn compile_code(input: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_compiler::compile_code_impl::<$compiler>(&input)
+                    .map_err(Into::into)
             }
 
-            fn encode_ainput(ainput: String) -> Result<String, String> {
+            fn source_map(input: String) -> Result<Vec<$crate::wasm_util::WitSourceMapping>, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_compiler::source_map_impl::<$compiler>(&input)
+                    .map(|mappings| $crate::wasm_util::wit_source_mappings(mappings, &input))
+                    .map_err(Into::into)
+            }
+
+            fn encode_ainput(ainput: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_compiler::encode_ainput_impl::<$compiler>(&ainput)
+                    .map_err(Into::into)
             }
 
-            fn encode_rinput(rinput: String) -> Result<String, String> {
+            fn encode_rinput(rinput: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_compiler::encode_rinput_impl::<$compiler>(&rinput)
+                    .map_err(Into::into)
             }
 
-            fn decode_routput(output: String) -> Result<String, String> {
+            fn decode_routput(output: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_compiler::decode_routput_impl::<$compiler>(&output)
+                    .map_err(Into::into)
             }
 
-            fn decode_foutput(output: String) -> Result<String, String> {
+            fn decode_foutput(output: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
                 $crate::wasm_util::wasm_compiler::decode_foutput_impl::<$compiler>(&output)
+                    .map_err(Into::into)
             }
         }
 
@@ -211,6 +616,48 @@ macro_rules! web_compiler {
     };
 }
 
+#[macro_export]
+macro_rules! web_interpreter {
+    ($interpreter:path) => {
+        use $crate::wasm_util::wasm_interpreter::Guest;
+
+        struct Component;
+
+        impl Guest for Component {
+            fn interpreter_code() -> Result<String, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_interpreter::interpreter_code_impl::<$interpreter>()
+                    .map_err(Into::into)
+            }
+
+            fn encode_ainput(code: String, ainput: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_interpreter::encode_ainput_impl::<$interpreter>(&code, &ainput)
+                    .map_err(Into::into)
+            }
+
+            fn encode_rinput(rinput: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_interpreter::encode_rinput_impl::<$interpreter>(&rinput)
+                    .map_err(Into::into)
+            }
+
+            fn decode_routput(output: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_interpreter::decode_routput_impl::<$interpreter>(&output)
+                    .map_err(Into::into)
+            }
+
+            fn decode_foutput(output: String) -> Result<String, $crate::wasm_util::WitDiagnostic> {
+                $crate::wasm_util::wasm_interpreter::decode_foutput_impl::<$interpreter>(&output)
+                    .map_err(Into::into)
+            }
+        }
+
+        $crate::wasm_util::wasm_interpreter::export!(
+            Component with_types_in $crate::wasm_util::wasm_interpreter
+        );
+
+        fn main() {}
+    };
+}
+
 #[macro_export]
 macro_rules! json_text {
     ($text:expr) => {
@@ -261,3 +708,14 @@ macro_rules! compiler_entry {
         fn main() {}
     };
 }
+
+#[macro_export]
+macro_rules! interpreter_entry {
+    ($interpreter:path) => {
+        #[cfg(target_arch = "wasm32")]
+        $crate::web_interpreter!($interpreter);
+
+        #[cfg(not(target_arch = "wasm32"))]
+        fn main() {}
+    };
+}